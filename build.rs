@@ -0,0 +1,28 @@
+use clap::CommandFactory;
+use std::path::PathBuf;
+
+// the clap definitions of the binary, to render its man pages
+#[allow(dead_code)]
+mod args {
+    include!("src/args.rs");
+}
+
+// man pages of the binary and of every subcommand (unplugged-engine-search.1...),
+// written to the build directory and to UNPLUGGED_MAN_DIR when set, for packagers
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=src/args.rs");
+    println!("cargo:rerun-if-env-changed=UNPLUGGED_MAN_DIR");
+
+    let mut dirs = vec![PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("man")];
+
+    if let Some(dir) = std::env::var_os("UNPLUGGED_MAN_DIR") {
+        dirs.push(PathBuf::from(dir));
+    }
+
+    for dir in dirs {
+        std::fs::create_dir_all(&dir)?;
+        clap_mangen::generate_to(args::Args::command(), &dir)?;
+    }
+
+    Ok(())
+}
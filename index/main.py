@@ -3,8 +3,10 @@ import argparse
 import requests
 import json
 from bs4 import BeautifulSoup as BS
-from dataclasses import dataclass
-from typing import List, Dict, Set
+from dataclasses import dataclass, field
+from typing import List, Dict, Optional, Set
+from urllib.parse import parse_qs, parse_qsl, urlencode, urljoin, urlparse, urlunparse
+from urllib.robotparser import RobotFileParser
 import re
 import os
 
@@ -13,6 +15,15 @@ EPISODES_BY_ID_FILENAME = "episodes_by_id_index.json"
 EPISODES_BY_TAG_FILENAME = "episodes_by_tag_index.json"
 TAGS_BY_TAG = "tags_by_tag_index.json"
 
+# the show notes scraper says who it is, the listing pages keep the browser agent
+NOTES_USER_AGENT = "unplugged-search-indexer (+https://github.com/Gers2017/unplugged-search)"
+NOTES_CACHE_DIR = "notes/"
+# query parameters that only track where a visitor came from
+TRACKING_PARAMS = {"fbclid", "gclid", "mc_cid", "mc_eid", "ref", "source"}
+TRACKING_PREFIXES = ("utm_",)
+# the first match is the notes section of an episode page
+NOTES_SELECTORS = ["div.episode-body", "section.episode-notes", "div.prose"]
+
 
 @dataclass
 class Episode:
@@ -22,6 +33,13 @@ class Episode:
     duration: str  # metadata
     tags: List[str]
     url: str
+    description: str = ""
+    links: List[Dict[str, str]] = field(default_factory=list)
+    video: Optional[str] = None
+    # the number of the episode in its show, id is the key of the index
+    number: Optional[int] = None
+    guests: List[str] = field(default_factory=list)
+    hosts: List[str] = field(default_factory=list)
 
     def toJSON(self) -> str:
         return json.dumps(self.__dict__, indent=2, sort_keys=True)
@@ -99,7 +117,7 @@ def page_content_to_episodes(content: str) -> Episodes:
             ",")) if len(span_elements) > 1 else []
 
         episodes.append(
-            Episode(episode_id, title, date, duration, tags, episode_url))
+            Episode(episode_id, title, date, duration, tags, episode_url, number=episode_id))
 
     return episodes
 
@@ -132,6 +150,178 @@ def get_all_episodes() -> Episodes:
     return all_episodes
 
 
+def normalize_url(url: str) -> str:
+    # https, lowercase host, no tracking parameters, fragment or trailing slash
+    parsed = urlparse(url.strip())
+    query = [
+        (key, value) for key, value in parse_qsl(parsed.query, keep_blank_values=True)
+        if key.lower() not in TRACKING_PARAMS and not key.lower().startswith(TRACKING_PREFIXES)
+    ]
+    path = parsed.path.rstrip("/") or "/"
+
+    return urlunparse(("https", parsed.netloc.lower(), path, "", urlencode(query), ""))
+
+
+def resolve_redirect(url: str) -> str:
+    # follows a single redirect, e.g. an old /episode/512 path to its current page
+    try:
+        response = requests.head(
+            url, headers={"User-Agent": NOTES_USER_AGENT}, allow_redirects=False, timeout=30)
+    except requests.RequestException as err:
+        print(f"Error at resolving {url}: {err}")
+        return url
+
+    location = response.headers.get("Location")
+
+    if response.is_redirect and location:
+        return normalize_url(urljoin(url, location))
+
+    return url
+
+
+def normalize_urls(all_episodes: Episodes, resolve: bool, delay: float) -> None:
+    for episode in all_episodes:
+        url = normalize_url(episode.url)
+
+        if resolve:
+            time.sleep(delay)
+            url = resolve_redirect(url)
+
+        episode.url = url
+
+
+def load_robots() -> RobotFileParser:
+    robots = RobotFileParser(f"{LINUX_UNPLUGGED_URL}/robots.txt")
+
+    try:
+        robots.read()
+    except OSError as err:
+        # without robots.txt every page is allowed
+        print(f"Couldn't read robots.txt: {err}")
+        robots.parse([])
+
+    return robots
+
+
+def get_notes_content(episode: Episode, robots: RobotFileParser, delay: float) -> Optional[str]:
+    cached = f"{NOTES_CACHE_DIR}{episode.id}.html"
+
+    if os.path.isfile(cached):
+        with open(cached, "r") as f:
+            return f.read()
+
+    if not robots.can_fetch(NOTES_USER_AGENT, episode.url):
+        print(f"robots.txt disallows {episode.url}, skipping")
+        return None
+
+    # one request every `delay` seconds, or slower if robots.txt asks for it
+    time.sleep(max(delay, robots.crawl_delay(NOTES_USER_AGENT) or 0))
+
+    response = requests.get(
+        episode.url, headers={"User-Agent": NOTES_USER_AGENT}, timeout=30)
+
+    if not response.ok:
+        print(f"Error at fetching the notes of {episode.id}: {response.status_code}")
+        return None
+
+    with open(cached, "w") as f:
+        f.write(response.text)
+
+    return response.text
+
+
+def page_content_to_notes(content: str, page_url: str) -> tuple[str, List[Dict[str, str]]]:
+    soup = BS(content, "html.parser")
+    notes = next(
+        (soup.select_one(s) for s in NOTES_SELECTORS if soup.select_one(s)), None)
+
+    if notes is None:
+        return "", []
+
+    description = " ".join(notes.get_text(" ").split())
+    links: List[Dict[str, str]] = []
+    seen: Set[str] = set()
+
+    for a_tag in notes.find_all("a", href=True):
+        url = urljoin(page_url, a_tag["href"])
+
+        if not url.startswith("http") or url in seen:
+            continue
+
+        seen.add(url)
+        links.append({"url": url, "label": a_tag.text.strip() or url})
+
+    return description, links
+
+
+def scrape_notes(all_episodes: Episodes, delay: float) -> None:
+    if not os.path.isdir(NOTES_CACHE_DIR):
+        os.mkdir(NOTES_CACHE_DIR)
+
+    robots = load_robots()
+
+    for episode in all_episodes:
+        content = get_notes_content(episode, robots, delay)
+
+        if content is None:
+            continue
+
+        episode.description, episode.links = page_content_to_notes(
+            content, episode.url)
+
+    with_notes = len([ep for ep in all_episodes if ep.description])
+    print(f"Show notes: {with_notes} of {len(all_episodes)} episodes")
+
+
+def youtube_url(url: str) -> Optional[str]:
+    # youtu.be/<id>, youtube.com/watch?v=<id> and youtube.com/live/<id> -> https://www.youtube.com/watch?v=<id>
+    parsed = urlparse(url)
+    host = parsed.netloc.lower().removeprefix("www.").removeprefix("m.")
+    video_id = None
+
+    if host == "youtu.be":
+        video_id = parsed.path.strip("/")
+    elif host == "youtube.com" and parsed.path == "/watch":
+        video_id = parse_qs(parsed.query).get("v", [None])[0]
+    elif host == "youtube.com" and parsed.path.startswith("/live/"):
+        video_id = parsed.path.removeprefix("/live/").strip("/")
+
+    return f"https://www.youtube.com/watch?v={video_id}" if video_id else None
+
+
+def add_videos(all_episodes: Episodes, mapping_file: Optional[str]) -> None:
+    # the mapping file ({"512": "https://youtu.be/..."}) wins over the show notes
+    mapping: Dict[str, str] = dict()
+
+    if mapping_file:
+        with open(mapping_file, "r") as f:
+            mapping = json.load(f)
+
+    for episode in all_episodes:
+        mapped = mapping.get(str(episode.id))
+        from_notes = (youtube_url(link["url"]) for link in episode.links)
+
+        episode.video = youtube_url(mapped) if mapped else next(
+            (url for url in from_notes if url), None)
+
+    with_video = len([ep for ep in all_episodes if ep.video])
+    print(f"Videos: {with_video} of {len(all_episodes)} episodes")
+
+
+def add_people(all_episodes: Episodes, mapping_file: str) -> None:
+    # {"512": {"guests": ["Alan Pope"], "hosts": ["Chris Fisher"]}}
+    with open(mapping_file, "r") as f:
+        mapping: Dict[str, Dict[str, List[str]]] = json.load(f)
+
+    for episode in all_episodes:
+        people = mapping.get(str(episode.id), {})
+        episode.guests = people.get("guests", episode.guests)
+        episode.hosts = people.get("hosts", episode.hosts)
+
+    with_guests = len([ep for ep in all_episodes if ep.guests])
+    print(f"Guests: {with_guests} of {len(all_episodes)} episodes")
+
+
 def index_episodes(all_episodes: Episodes) -> None:
     episodes_by_id: Dict[int, Episode] = dict()
     # Map<tag, episode_id>
@@ -140,7 +330,11 @@ def index_episodes(all_episodes: Episodes) -> None:
     tags_to_list: Dict[str, List[str]] = dict()
 
     for episode in all_episodes:
-        # map id episode to episode
+        # map id episode to episode, with normalized urls a duplicate episode
+        # only differs in its url when the site really changed it
+        if episode.id in episodes_by_id and episodes_by_id[episode.id].url != episode.url:
+            print(f"Episode {episode.id} has two urls: {episodes_by_id[episode.id].url} and {episode.url}")
+
         episodes_by_id[episode.id] = episode
 
         for tag in set(episode.tags):
@@ -191,6 +385,39 @@ def main():
         help="Should the script download new data or use the local data"
     )
 
+    parser.add_argument(
+        "--notes",
+        default=False,
+        action="store_true",
+        help="Scrape the show notes of every episode page into the description and links fields, the pages are cached in notes/"
+    )
+
+    parser.add_argument(
+        "--delay",
+        default=2.0,
+        type=float,
+        help="Seconds between the requests of the show notes scraper"
+    )
+
+    parser.add_argument(
+        "--videos",
+        default=None,
+        help="JSON file mapping episode ids to their YouTube links, the links of the show notes (--notes) are used otherwise"
+    )
+
+    parser.add_argument(
+        "--people",
+        default=None,
+        help="JSON file mapping episode ids to their guests and hosts, searched with guest:"
+    )
+
+    parser.add_argument(
+        "--resolve-redirects",
+        default=False,
+        action="store_true",
+        help="Follow one redirect of every episode url so the index keeps the final url, waits --delay seconds between requests"
+    )
+
     args = parser.parse_args()
     [start, end] = args.range
 
@@ -209,6 +436,16 @@ def main():
         download_pages(start, end)
 
     all_episodes = get_all_episodes()
+    normalize_urls(all_episodes, args.resolve_redirects, args.delay)
+
+    if args.notes:
+        scrape_notes(all_episodes, args.delay)
+
+    if args.notes or args.videos:
+        add_videos(all_episodes, args.videos)
+
+    if args.people:
+        add_people(all_episodes, args.people)
 
     index_episodes(all_episodes)
     end_time = time.time()
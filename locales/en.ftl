@@ -0,0 +1,227 @@
+# English, the default language: every message of the templates must be here,
+# the other catalogs fall back to these messages
+
+## every page
+site-title = Unplugged search
+page-title = Unplugged search engine
+search-placeholder = search here...
+stale-archive =
+    .one = This archive may be out of date, its newest episode is a day old.
+    .other = This archive may be out of date, its newest episode is { $count } days old.
+
+## results.html and partials/results.html
+results-title = search results
+results-of = Search results of:
+results-count =
+    .one = { $count } result in { $ms } ms
+    .other = { $count } results in { $ms } ms
+results-feed = Unplugged search: { $query }
+no-results-for = No episodes found for
+no-results-without = without
+no-results-hint = Try fewer or broader terms.
+feedback-relevant = Relevant for this search
+feedback-irrelevant = Not relevant for this search
+results-copy-link = Copy link
+results-link-copied = Link copied
+results-export = Export:
+results-watch = Watch
+results-details = Details
+results-open-in = Open the show in:
+results-podcast-app = your podcast app
+results-listening-time = about { $duration } of listening
+results-partial = The search took too long, these are the episodes found so far.
+results-year-unknown = Unknown date
+results-group-by-show = Group by show
+results-ungroup = Mix all shows
+results-group-more = All { $count } results in { $show }
+results-previous = Previous
+results-next = Next
+results-page = Page { $page } of { $pages }
+sort-label = Sort the results
+sort-relevance = Most relevant
+sort-newest = Newest first
+sort-oldest = Oldest first
+sort-longest = Longest first
+sort-shortest = Shortest first
+results-year-count =
+    .one = { $count } episode
+    .other = { $count } episodes
+stats-trending = Trending lately
+stats-trending-lift = { $count } of the last { $recent } episodes, { $lift }× as often as before
+
+## episode.html
+episode-listen = Listen on linuxunplugged.com
+episode-watch = Watch the video version
+episode-hosts = Hosts
+episode-guests = Guests
+episode-download = Download the audio
+episode-links = Show notes links
+episode-chapters = Chapters
+episode-related = Related episodes
+playback-other-device = Continue on another device
+tag-category-distro = Distros
+tag-category-app = Apps
+tag-category-topic = Topics
+tag-category-event = Events
+
+## rate_limited.html
+rate-limited-title = slow down
+rate-limited-heading = Whoa, slow down there 🐧
+rate-limited-message = You are searching faster than the archive can keep up with.
+rate-limited-retry =
+    .one = Try again in { $count } second.
+    .other = Try again in { $count } seconds.
+request-id = Request id: { $id }
+
+## help.html
+help-title = search tips
+help-heading = Search tips
+help-link = Search tips
+help-operators = Operators
+help-fields = Fields
+help-term = Episodes with the word in a tag or their title, or said in their transcript
+help-phrase = Searches the words together, as a tag or a part of the title
+help-exclude = Leaves out the episodes with the tag
+help-number = The episode with the number
+help-and = Episodes matching both sides, the terms next to each other match any of them
+help-group = Groups terms, the terms joined by OR match any of them, AND binds tighter than OR
+help-guest = Episodes with the guest
+help-show = Episodes of the show, in an archive of several shows
+help-domain = Episodes linking to the site in their show notes, subdomains too
+help-tag = Episodes with the tag, the titles aren't searched
+help-title = Episodes with the words in their title, the tags aren't searched
+help-id = The episode with the id in the index
+help-after = Episodes published on or after the day, month or year, 2022-01-01, 2023-06 or 2021
+help-before = Episodes published before the day, month or year
+help-longer = Episodes lasting at least the duration, 60m, 1h30m or 90 minutes
+help-shorter = Episodes lasting less than the duration
+
+## stats.html
+stats-title = stats
+stats-heading = { $show } in numbers
+stats-episodes = Episodes
+stats-tags = Tags
+stats-date-span = Date span
+stats-date-span-value = { $first } to { $last }
+stats-average-duration = Average duration
+stats-audio-size = Size of the audio
+stats-updated-at = Index updated
+stats-top-tags = Top tags
+stats-tag-episodes =
+    .one = { $count } episode
+    .other = { $count } episodes
+stats-all-tags = Every tag
+
+## tags.html
+tags-title = tags
+tags-heading =
+    .one = { $count } tag of { $show }
+    .other = The { $count } tags of { $show }
+tags-by-episodes = Most used
+tags-by-name = A to Z
+
+## admin_queries.html
+admin-queries-title = searches
+admin-queries-heading =
+    .one = Searches of the last day
+    .other = Searches of the last { $count } days
+admin-queries-disabled = The queries log is disabled, set analytics.queries_log to record the searches.
+admin-queries-summary = { $count } searches, { $zero } without results
+admin-queries-zero-results = Most frequent queries without results
+admin-queries-top = Most frequent queries
+admin-queries-results =
+    .one = { $count } result
+    .other = { $count } results
+admin-api-keys = Api keys since the last restart
+admin-api-keys-name = Key
+admin-api-keys-today = Today
+admin-api-keys-total = Total
+admin-api-keys-rejected = Rejected
+
+## alert_created.html
+alerts-subscribe = Get new episodes about this search
+alerts-title = saved search
+alerts-heading = Search saved
+alerts-created = Add this feed to your feed reader, it gets the new episodes about "{ $query }" as they are indexed:
+alerts-back = Back to the results
+alerts-email = email for a digest (optional)
+alerts-weekly = weekly
+alerts-daily = daily
+alerts-digest-weekly = You'll also get an email once a week when there are new episodes.
+alerts-digest-daily = You'll also get an email once a day when there are new episodes.
+
+## unsubscribe.html and digest.txt, the emails of the saved searches
+unsubscribe-title = unsubscribe
+unsubscribe-confirm = Stop the emails about "{ $query }"?
+unsubscribe-button = Unsubscribe
+unsubscribe-done = You won't get more emails about "{ $query }".
+digest-subject =
+    .one = { $count } new episode about { $query }
+    .other = { $count } new episodes about { $query }
+digest-intro = New episodes about "{ $query }":
+digest-unsubscribe = Stop these emails:
+
+## favorites.html and the star buttons
+favorites-title = Favorites
+favorites-star = Star or unstar this episode
+favorites-heading =
+    .one = { $count } starred episode
+    .other = { $count } starred episodes
+favorites-empty = Nothing starred yet, star episodes with ☆ to find them here.
+favorites-note = Favorites are kept in a cookie of this browser, no account needed.
+
+## dates and durations, every page
+date-format = { $month } { $day }, { $year }
+month-1 = January
+month-2 = February
+month-3 = March
+month-4 = April
+month-5 = May
+month-6 = June
+month-7 = July
+month-8 = August
+month-9 = September
+month-10 = October
+month-11 = November
+month-12 = December
+duration-hours-minutes = { $hours } hr { $minutes } min
+duration-hours = { $hours } hr
+duration-minutes = { $minutes } min
+
+## archive.html, the pages of export-site
+archive-title =
+    .one = { $count } episode
+    .other = Every episode, { $count } of them
+archive-tag =
+    .one = { $count } episode tagged { $tag }
+    .other = { $count } episodes tagged { $tag }
+archive-tags = Tags
+archive-undated = Undated
+
+## partials/on_this_day.html, the homepage widget
+on-this-day-title = On this day
+on-this-day-today = Today
+on-this-day-years-ago =
+    .one = { $count } year ago
+    .other = { $count } years ago
+
+## partials/popular.html, the suggestions of the homepage
+popular-queries = People are searching for:
+
+## error.html
+status-400 = Bad Request
+status-403 = Forbidden
+status-404 = Not Found
+status-500 = Internal Server Error
+status-503 = Archive being rebuilt
+error-forbidden = The page expired, reload it and try again.
+error-not-found = There is nothing here, try searching the archive instead.
+error-internal = Something went wrong on our side, try again in a moment.
+error-maintenance = The episode archive is being rebuilt, come back in a minute.
+error-unknown-tag = No episode is tagged { $tag }.
+error-tag-suggestions = Did you mean:
+query-empty = The query is empty, type a tag or a title to search
+query-unterminated-quote = The quote at character { $position } is never closed, e.g. "docker compose"
+query-only-exclusions = The query only excludes tags, add a term to search, e.g. linux -nixos
+query-unbalanced-parenthesis = The parenthesis at character { $position } has no match, e.g. (nixos OR fedora) -flatpak
+query-invalid-filter = { $term } isn't a date or a duration, e.g. after:2022-01-01 or longer:60m
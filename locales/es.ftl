@@ -0,0 +1,226 @@
+# Spanish
+
+## every page
+site-title = Buscador de Unplugged
+page-title = Buscador de Unplugged
+search-placeholder = busca aquí...
+stale-archive =
+    .one = Este archivo puede estar desactualizado, su episodio más reciente es de hace un día.
+    .other = Este archivo puede estar desactualizado, su episodio más reciente es de hace { $count } días.
+
+## results.html and partials/results.html
+results-title = resultados de búsqueda
+results-of = Resultados de búsqueda de:
+results-count =
+    .one = { $count } resultado en { $ms } ms
+    .other = { $count } resultados en { $ms } ms
+results-feed = Buscador de Unplugged: { $query }
+no-results-for = No se encontraron episodios para
+no-results-without = sin
+no-results-hint = Prueba con menos términos o con términos más generales.
+feedback-relevant = Relevante para esta búsqueda
+feedback-irrelevant = No es relevante para esta búsqueda
+results-copy-link = Copiar enlace
+results-link-copied = Enlace copiado
+results-listening-time = alrededor de { $duration } de escucha
+results-watch = Ver
+results-details = Detalles
+results-open-in = Abrir el programa en:
+results-podcast-app = tu app de podcasts
+results-export = Exportar:
+results-partial = La búsqueda tardó demasiado, estos son los episodios encontrados hasta ahora.
+results-year-unknown = Fecha desconocida
+results-group-by-show = Agrupar por programa
+results-ungroup = Mezclar todos los programas
+results-group-more = Los { $count } resultados en { $show }
+results-previous = Anterior
+results-next = Siguiente
+results-page = Página { $page } de { $pages }
+sort-label = Ordenar los resultados
+sort-relevance = Más relevantes
+sort-newest = Más recientes primero
+sort-oldest = Más antiguos primero
+sort-longest = Más largos primero
+sort-shortest = Más cortos primero
+results-year-count =
+    .one = { $count } episodio
+    .other = { $count } episodios
+stats-trending = En auge últimamente
+stats-trending-lift = { $count } de los últimos { $recent } episodios, { $lift }× más que antes
+
+## episode.html
+episode-listen = Escúchalo en linuxunplugged.com
+episode-watch = Ver la versión en vídeo
+episode-hosts = Presentadores
+episode-guests = Invitados
+episode-download = Descargar el audio
+episode-links = Enlaces de las notas del episodio
+episode-chapters = Capítulos
+episode-related = Episodios relacionados
+playback-other-device = Continuar en otro dispositivo
+tag-category-distro = Distribuciones
+tag-category-app = Aplicaciones
+tag-category-topic = Temas
+tag-category-event = Eventos
+
+## rate_limited.html
+rate-limited-title = más despacio
+rate-limited-heading = Eh, más despacio 🐧
+rate-limited-message = Estás buscando más rápido de lo que el archivo puede seguirte.
+rate-limited-retry =
+    .one = Inténtalo de nuevo en { $count } segundo.
+    .other = Inténtalo de nuevo en { $count } segundos.
+request-id = Id de la petición: { $id }
+
+## help.html
+help-title = consejos de búsqueda
+help-heading = Consejos de búsqueda
+help-link = Consejos de búsqueda
+help-operators = Operadores
+help-fields = Campos
+help-term = Episodios con la palabra en una etiqueta o en su título, o dicha en su transcripción
+help-phrase = Busca las palabras juntas, como una etiqueta o una parte del título
+help-exclude = Deja fuera los episodios con la etiqueta
+help-number = El episodio con el número
+help-and = Episodios que coinciden con ambos lados, los términos juntos coinciden con cualquiera
+help-group = Agrupa términos, los unidos por OR coinciden con cualquiera, AND se aplica antes que OR
+help-guest = Episodios con el invitado
+help-show = Episodios del programa, en un archivo de varios programas
+help-domain = Episodios que enlazan al sitio en sus notas, subdominios incluidos
+help-tag = Episodios con la etiqueta, sin buscar en los títulos
+help-title = Episodios con las palabras en su título, sin buscar en las etiquetas
+help-id = El episodio con el id en el índice
+help-after = Episodios publicados el día, el mes o el año indicado o después, 2022-01-01, 2023-06 o 2021
+help-before = Episodios publicados antes del día, el mes o el año indicado
+help-longer = Episodios que duran al menos lo indicado, 60m, 1h30m o 90 minutos
+help-shorter = Episodios que duran menos de lo indicado
+
+## stats.html
+stats-title = estadísticas
+stats-heading = { $show } en números
+stats-episodes = Episodios
+stats-tags = Etiquetas
+stats-date-span = Periodo
+stats-date-span-value = del { $first } al { $last }
+stats-average-duration = Duración media
+stats-audio-size = Tamaño del audio
+stats-updated-at = Índice actualizado
+stats-top-tags = Etiquetas más usadas
+stats-tag-episodes =
+    .one = { $count } episodio
+    .other = { $count } episodios
+stats-all-tags = Todas las etiquetas
+
+## tags.html
+tags-title = etiquetas
+tags-heading =
+    .one = { $count } etiqueta de { $show }
+    .other = Las { $count } etiquetas de { $show }
+tags-by-episodes = Más usadas
+tags-by-name = De la A a la Z
+
+## admin_queries.html
+admin-queries-title = búsquedas
+admin-queries-heading =
+    .one = Búsquedas del último día
+    .other = Búsquedas de los últimos { $count } días
+admin-queries-disabled = El registro de búsquedas está desactivado, configura analytics.queries_log para registrarlas.
+admin-queries-summary = { $count } búsquedas, { $zero } sin resultados
+admin-queries-zero-results = Búsquedas más frecuentes sin resultados
+admin-queries-top = Búsquedas más frecuentes
+admin-queries-results =
+    .one = { $count } resultado
+    .other = { $count } resultados
+admin-api-keys = Claves de la api desde el último reinicio
+admin-api-keys-name = Clave
+admin-api-keys-today = Hoy
+admin-api-keys-total = Total
+admin-api-keys-rejected = Rechazadas
+
+## alert_created.html
+alerts-subscribe = Recibir los nuevos episodios de esta búsqueda
+alerts-title = búsqueda guardada
+alerts-heading = Búsqueda guardada
+alerts-created = Añade este feed a tu lector de feeds, recibirá los nuevos episodios sobre "{ $query }" en cuanto se indexen:
+alerts-back = Volver a los resultados
+alerts-email = email para un resumen (opcional)
+alerts-weekly = semanal
+alerts-daily = diario
+alerts-digest-weekly = También recibirás un email una vez a la semana cuando haya episodios nuevos.
+alerts-digest-daily = También recibirás un email una vez al día cuando haya episodios nuevos.
+
+## unsubscribe.html and digest.txt, the emails of the saved searches
+unsubscribe-title = cancelar suscripción
+unsubscribe-confirm = ¿Dejar de recibir emails sobre "{ $query }"?
+unsubscribe-button = Cancelar suscripción
+unsubscribe-done = No recibirás más emails sobre "{ $query }".
+digest-subject =
+    .one = { $count } episodio nuevo sobre { $query }
+    .other = { $count } episodios nuevos sobre { $query }
+digest-intro = Episodios nuevos sobre "{ $query }":
+digest-unsubscribe = Dejar de recibir estos emails:
+
+## favorites.html and the star buttons
+favorites-title = Favoritos
+favorites-star = Marcar o desmarcar este episodio
+favorites-heading =
+    .one = { $count } episodio marcado
+    .other = { $count } episodios marcados
+favorites-empty = Aún no hay nada marcado, marca episodios con ☆ para encontrarlos aquí.
+favorites-note = Los favoritos se guardan en una cookie de este navegador, sin necesidad de cuenta.
+
+## dates and durations, every page
+date-format = { $day } de { $month } de { $year }
+month-1 = enero
+month-2 = febrero
+month-3 = marzo
+month-4 = abril
+month-5 = mayo
+month-6 = junio
+month-7 = julio
+month-8 = agosto
+month-9 = septiembre
+month-10 = octubre
+month-11 = noviembre
+month-12 = diciembre
+duration-hours-minutes = { $hours } h { $minutes } min
+duration-hours = { $hours } h
+duration-minutes = { $minutes } min
+
+## partials/on_this_day.html, the homepage widget
+on-this-day-title = Tal día como hoy
+on-this-day-today = Hoy
+on-this-day-years-ago =
+    .one = Hace { $count } año
+    .other = Hace { $count } años
+
+## archive.html, the pages of export-site
+archive-title =
+    .one = { $count } episodio
+    .other = Todos los episodios, { $count } en total
+archive-tag =
+    .one = { $count } episodio con la etiqueta { $tag }
+    .other = { $count } episodios con la etiqueta { $tag }
+archive-tags = Etiquetas
+archive-undated = Sin fecha
+
+## partials/popular.html, the suggestions of the homepage
+popular-queries = La gente está buscando:
+
+## error.html
+status-400 = Petición incorrecta
+status-403 = Prohibido
+status-404 = No encontrado
+status-500 = Error interno del servidor
+status-503 = Archivo en reconstrucción
+error-forbidden = La página caducó, recárgala e inténtalo de nuevo.
+error-not-found = Aquí no hay nada, prueba a buscar en el archivo.
+error-internal = Algo salió mal de nuestro lado, inténtalo de nuevo en un momento.
+error-maintenance = El archivo de episodios se está reconstruyendo, vuelve en un minuto.
+error-unknown-tag = Ningún episodio tiene la etiqueta { $tag }.
+error-tag-suggestions = Quizás quisiste decir:
+query-empty = La búsqueda está vacía, escribe una etiqueta o un título para buscar
+query-unterminated-quote = La comilla en el carácter { $position } nunca se cierra, p. ej. "docker compose"
+query-only-exclusions = La búsqueda solo excluye etiquetas, añade un término para buscar, p. ej. linux -nixos
+query-unbalanced-parenthesis = El paréntesis en el carácter { $position } no tiene pareja, p. ej. (nixos OR fedora) -flatpak
+query-invalid-filter = { $term } no es una fecha ni una duración, p. ej. after:2022-01-01 o longer:60m
@@ -0,0 +1,160 @@
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use serde::Deserialize;
+use std::path::Path;
+use tera::Context;
+use unplugged_engine::{read_rotated_event_log, QueryEvent, QueryReport};
+
+use crate::error_pages::internal_error_page;
+use crate::shows::Shows;
+
+const REPORT_DAYS: i64 = 7;
+const REPORT_LIMIT: usize = 25;
+const REPORT_MAX_LIMIT: usize = 200;
+
+/// Lets through requests with the admin.token as bearer token, or as basic auth password
+/// (any user name) to read the pages, the pages don't exist without a token
+pub async fn require_admin<B>(
+    State(shows): State<Shows>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let token = &shows[0].config().admin.token;
+
+    if token.is_empty() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    // browsers send basic credentials on their own, a cross-site form would reindex
+    // or reload with them, so the state-changing requests need the bearer token
+    let read_only = matches!(*request.method(), Method::GET | Method::HEAD);
+
+    if is_authorized(request.headers(), token, read_only) {
+        return next.run(request).await;
+    }
+
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Basic realm=\"unplugged admin\"")],
+    )
+        .into_response()
+}
+
+fn is_authorized(headers: &HeaderMap, token: &str, allow_basic: bool) -> bool {
+    let Some(authorization) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    if let Some(bearer) = authorization.strip_prefix("Bearer ") {
+        return constant_time_eq(bearer.trim().as_bytes(), token.as_bytes());
+    }
+
+    if !allow_basic {
+        return false;
+    }
+
+    let password = authorization
+        .strip_prefix("Basic ")
+        .and_then(|credentials| STANDARD.decode(credentials.trim()).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .and_then(|credentials| {
+            credentials
+                .split_once(':')
+                .map(|(_, password)| password.to_string())
+        });
+
+    password.is_some_and(|password| constant_time_eq(password.as_bytes(), token.as_bytes()))
+}
+
+// doesn't leak how much of the token was right through the response time
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    #[serde(default = "default_days")]
+    pub days: i64,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// slug of a show, every show when missing
+    pub show: Option<String>,
+}
+
+fn default_days() -> i64 {
+    REPORT_DAYS
+}
+
+fn default_limit() -> usize {
+    REPORT_LIMIT
+}
+
+async fn query_report(shows: &Shows, report: &ReportQuery) -> QueryReport {
+    let analytics = &shows[0].config().analytics;
+
+    if analytics.queries_log.is_empty() {
+        return QueryReport::default();
+    }
+
+    let events: Vec<QueryEvent> = read_rotated_event_log(
+        Path::new(&analytics.queries_log),
+        analytics.queries_log_keep,
+    )
+    .await;
+
+    let since = Utc::now().timestamp() - report.days.max(0) * 24 * 60 * 60;
+
+    QueryReport::new(
+        &events,
+        since,
+        report.show.as_deref(),
+        report.limit.min(REPORT_MAX_LIMIT),
+    )
+}
+
+// api/admin/queries?days=7, the most frequent and the zero-result queries
+pub async fn handle_admin_queries_json(
+    Query(report): Query<ReportQuery>,
+    State(shows): State<Shows>,
+) -> impl IntoResponse {
+    Json(query_report(&shows, &report).await)
+}
+
+// api/admin/api-keys, the requests of every api key
+pub async fn handle_admin_api_keys_json(State(shows): State<Shows>) -> impl IntoResponse {
+    Json(shows[0].api_keys.usage())
+}
+
+// admin/queries?days=7
+pub async fn handle_admin_queries(
+    Query(report): Query<ReportQuery>,
+    headers: HeaderMap,
+    State(shows): State<Shows>,
+) -> Response {
+    let templates = &shows[0].templates;
+    let base_path = &shows[0].config().server.base_path;
+    let lang = templates.negotiate(&headers);
+
+    let context = Context::from_serialize(serde_json::json!({
+        "report": query_report(&shows, &report).await,
+        "days": report.days,
+        "logging": !shows[0].config().analytics.queries_log.is_empty(),
+        "api_keys": (!shows[0].api_keys.is_empty()).then(|| shows[0].api_keys.usage()),
+        "base_path": base_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| templates.render("admin_queries.html", &context)) {
+        Ok(html) => ([(header::CACHE_CONTROL, "no-store")], Html(html)).into_response(),
+        Err(err) => internal_error_page(templates, base_path, lang, None, &err),
+    }
+}
@@ -0,0 +1,356 @@
+use axum::extract::{Host, Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Form;
+use chrono::Utc;
+use lettre::Address;
+use serde::Deserialize;
+use std::sync::Arc;
+use tera::Context;
+use tracing::error;
+use unplugged_engine::{
+    atom_feed, normalize_query, parse_query_strict, search_permalink, Digest, DigestFrequency,
+    QueryVersion, SaveError, SavedSearch, SearchParams,
+};
+use uuid::Uuid;
+
+use crate::error_pages::{bad_query_page, error_page, internal_error_page};
+use crate::{base_url, search_episodes, AppState};
+
+#[derive(Deserialize)]
+pub struct SaveSearch {
+    pub query: String,
+    /// also emails a digest of the new episodes when set and [digests] is configured
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub frequency: DigestFrequency,
+}
+
+// alerts, form: query=btrfs&email=&frequency=weekly, saves the query and shows the url of its feed
+pub async fn handle_save_search(
+    host: Option<Host>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+    Form(save): Form<SaveSearch>,
+) -> Response {
+    let Some(saved_searches) = &state.saved_searches else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let templates = &state.templates;
+    let lang = templates.negotiate(&headers);
+
+    if let Err(err) = parse_query_strict(&save.query) {
+        return bad_query_page(templates, &state.mount_path, lang, &save.query, &err);
+    }
+
+    let query = normalize_query(&save.query);
+    let email = save.email.trim();
+
+    let digest = if email.is_empty() || !state.config().digests.is_enabled() {
+        None
+    } else {
+        if let Err(err) = email.parse::<Address>() {
+            return error_page(
+                templates,
+                &state.mount_path,
+                lang,
+                StatusCode::BAD_REQUEST,
+                Some(&query),
+                Some(&err),
+            );
+        }
+
+        Some(Digest {
+            email: email.to_string(),
+            frequency: save.frequency,
+            lang: lang.to_string(),
+            unsubscribe: Uuid::new_v4().simple().to_string(),
+            last_sent_at: Utc::now().timestamp(),
+            sent: Vec::new(),
+        })
+    };
+
+    // the episodes matching today aren't news
+    let known = search_episodes(&state, &state.index(), &query, QueryVersion::LATEST)
+        .iter()
+        .map(|(_, episode)| episode.id as usize)
+        .collect();
+
+    let token = Uuid::new_v4().simple().to_string();
+
+    let search = SavedSearch {
+        query: query.clone(),
+        show: state.show.slug.clone(),
+        created_at: Utc::now().timestamp(),
+        known,
+        digest,
+    };
+
+    match saved_searches.insert(token.clone(), search).await {
+        Ok(()) => {}
+        Err(SaveError::Full) => {
+            error!("Error at saving a search: alerts.max saved searches reached");
+            return error_page(
+                templates,
+                &state.mount_path,
+                lang,
+                StatusCode::SERVICE_UNAVAILABLE,
+                Some(&query),
+                None,
+            );
+        }
+        Err(err) => {
+            return internal_error_page(templates, &state.mount_path, lang, Some(&query), &err)
+        }
+    }
+
+    let context = Context::from_serialize(serde_json::json!({
+        "query": query,
+        "digest": search_digest(saved_searches.get(&token)),
+        "feed_url": format!("{}/alerts/{}.atom", base_url(&state, host), token),
+        "permalink": search_permalink(&state.mount_path, &SearchParams::new(&query)),
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| templates.render("alert_created.html", &context)) {
+        Ok(html) => Html(html).into_response(),
+        Err(err) => internal_error_page(templates, &state.mount_path, lang, Some(&query), &err),
+    }
+}
+
+// alerts/<token>.atom, the episodes matching the saved search since it was saved
+pub async fn handle_alert_feed(
+    Path(file): Path<String>,
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let search = state.saved_searches.as_ref().and_then(|saved_searches| {
+        file.strip_suffix(".atom")
+            .and_then(|token| saved_searches.get(token))
+    });
+
+    // tokens of another show are as unknown as made up ones
+    let Some(search) = search.filter(|search| search.show == state.show.slug) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let index = state.index();
+
+    let results_with_score = search_episodes(&state, &index, &search.query, QueryVersion::LATEST);
+    let matches: Vec<_> = results_with_score.iter().map(|(_, ep)| *ep).collect();
+    let episodes = search.new_matches(&matches);
+
+    let feed_url = format!("{}/alerts/{}", base_url(&state, host), file);
+
+    let feed = atom_feed(
+        &format!("Unplugged search: new episodes about {}", search.query),
+        &feed_url,
+        &episodes,
+    );
+
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        feed,
+    )
+        .into_response()
+}
+
+// frequency of the digest of a saved search, for the confirmation page
+fn search_digest(search: Option<SavedSearch>) -> Option<DigestFrequency> {
+    search
+        .and_then(|search| search.digest)
+        .map(|digest| digest.frequency)
+}
+
+// alerts/unsubscribe/<key>, the link of the digests, asks before unsubscribing
+// so link scanners of mail servers don't unsubscribe anyone
+pub async fn handle_unsubscribe_page(
+    Path(key): Path<String>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    match find_digest(&state, &key) {
+        Some((_, search)) => render_unsubscribe(&state, lang, &search.query, false),
+        None => error_page(
+            &state.templates,
+            &state.mount_path,
+            lang,
+            StatusCode::NOT_FOUND,
+            None,
+            None,
+        ),
+    }
+}
+
+// POST alerts/unsubscribe/<key>, from the page or the one click unsubscribe of mail clients,
+// the feed of the search keeps working
+pub async fn handle_unsubscribe(
+    Path(key): Path<String>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let (Some(saved_searches), Some((token, search))) =
+        (&state.saved_searches, find_digest(&state, &key))
+    else {
+        return error_page(
+            &state.templates,
+            &state.mount_path,
+            lang,
+            StatusCode::NOT_FOUND,
+            None,
+            None,
+        );
+    };
+
+    match saved_searches
+        .update(&token, |search| search.digest = None)
+        .await
+    {
+        Ok(_) => render_unsubscribe(&state, lang, &search.query, true),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+// the saved search with the digest of `key`, when it's of this show
+fn find_digest(state: &AppState, key: &str) -> Option<(String, SavedSearch)> {
+    let saved_searches = state.saved_searches.as_ref()?;
+    let token = saved_searches.find_unsubscribe(key)?;
+
+    saved_searches
+        .get(&token)
+        .filter(|search| search.show == state.show.slug)
+        .map(|search| (token, search))
+}
+
+fn render_unsubscribe(state: &AppState, lang: &str, query: &str, done: bool) -> Response {
+    let context = Context::from_serialize(serde_json::json!({
+        "query": query,
+        "done": done,
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| state.templates.render("unsubscribe.html", &context)) {
+        Ok(html) => ([(header::CACHE_CONTROL, "private, no-store")], Html(html)).into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Path, Query, State};
+    use std::sync::Arc;
+    use std::time::Instant;
+    use unplugged_engine::{
+        parse_event_log, popular_queries, Config, Episode, EpisodesById, EpisodesByTag, EventLog,
+        IndexHandle, PageCache, QueryEvent, SavedSearch, SavedSearches, ShowIndex, Tag,
+    };
+
+    use super::handle_alert_feed;
+    use crate::api_keys::ApiKeys;
+    use crate::assets::{bundled_translations, Templates};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::IpRateLimiter;
+    use crate::reload::ConfigHandle;
+    use crate::{favorites_signer, handle_search_api, AppState};
+
+    #[tokio::test]
+    async fn test_alert_feed_isnt_a_popular_query() {
+        let dir = std::env::temp_dir().join(format!("unplugged-alerts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let episode = Episode {
+            id: 512,
+            title: String::from("512: NixOS Everywhere"),
+            tags: vec![Tag::from("nixos")],
+            ..Default::default()
+        };
+        let index = ShowIndex::new(
+            EpisodesById::from([(512, episode)]),
+            EpisodesByTag::from([(String::from("nixos"), vec![512])]),
+            None,
+        );
+
+        let saved_searches = SavedSearches::load(&dir.join("alerts.json"), 10)
+            .await
+            .unwrap();
+        let saved = SavedSearch {
+            query: String::from("nixos"),
+            show: String::new(),
+            created_at: 0,
+            known: Vec::new(),
+            digest: None,
+        };
+        saved_searches
+            .insert(String::from("token"), saved)
+            .await
+            .unwrap();
+
+        let queries_log = dir.join("queries.jsonl");
+        let config = Arc::new(Config::default());
+
+        let state = Arc::new(AppState {
+            config: Arc::new(ConfigHandle::new(
+                config.clone(),
+                dir.join("config.toml"),
+                false,
+                String::new(),
+            )),
+            show: config.show_configs().remove(0),
+            mount_path: String::new(),
+            index: IndexHandle::ready(index),
+            index_upload: tokio::sync::Mutex::new(()),
+            discord: None,
+            templates: Arc::new(Templates::new(
+                tera::Tera::default(),
+                Arc::new(bundled_translations()),
+                false,
+            )),
+            saved_searches: Some(Arc::new(saved_searches)),
+            playback: None,
+            query_log: Some(Arc::new(EventLog::open(&queries_log).await.unwrap())),
+            click_log: None,
+            feedback_log: None,
+            popular_queries: tokio::sync::Mutex::new(None),
+            favorites: favorites_signer("secret"),
+            page_cache: PageCache::new(1),
+            rate_limiter: Arc::new(IpRateLimiter::new(10, 10)),
+            api_keys: Arc::new(ApiKeys::new(&config.api)),
+            metrics: Arc::new(Metrics::new()),
+            telemetry: None,
+            started_at: Instant::now(),
+        });
+
+        // feed readers poll the alert twice, a person searches once
+        for _ in 0..2 {
+            let feed =
+                handle_alert_feed(Path(String::from("token.atom")), None, State(state.clone()))
+                    .await;
+            assert!(feed.status().is_success());
+        }
+
+        let search = serde_urlencoded::from_str("query=everywhere").unwrap();
+        assert!(handle_search_api(Ok(Query(search)), State(state.clone()))
+            .await
+            .status()
+            .is_success());
+
+        // the log is appended in the background
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let contents = tokio::fs::read_to_string(&queries_log).await.unwrap();
+        let events: Vec<QueryEvent> = parse_event_log(&contents);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(popular_queries(&events, 0, None, 1, 5), vec!["everywhere"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,23 @@
+use crate::Episode;
+use serde_json::{json, Value};
+
+/// Serializes the episodes in the Alfred/Raycast script filter format,
+/// actioning an item opens the episode url
+pub fn alfred_items(episodes: &[&Episode]) -> Value {
+    let items: Vec<_> = episodes
+        .iter()
+        .map(|episode| {
+            json!({
+                "uid": episode.id.to_string(),
+                "title": episode.title,
+                "subtitle": format!("{} | {} | {}", episode.date, episode.duration, episode.tag_names().join(", ")),
+                "arg": episode.url,
+                "quicklookurl": episode.url,
+                "icon": { "path": "icon.png" },
+                "text": { "copy": episode.url },
+            })
+        })
+        .collect();
+
+    json!({ "items": items })
+}
@@ -0,0 +1,458 @@
+use crate::normalize_query;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+// longer queries are pasted text rather than something to suggest
+const POPULAR_QUERY_MAX_CHARS: usize = 30;
+
+/// A click on a search result, one line of the clicks log
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ClickEvent {
+    /// unix seconds
+    pub timestamp: i64,
+    /// slug of the show, empty in a single show archive
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub show: String,
+    /// normalized query of the results page
+    pub query: String,
+    pub episode_id: usize,
+    /// 1-based position of the episode in the results
+    pub rank: usize,
+    /// only recorded with analytics.store_ip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+}
+
+/// Whether a result is relevant for the query, one line of the feedback log
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeedbackEvent {
+    /// unix seconds
+    pub timestamp: i64,
+    /// slug of the show, empty in a single show archive
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub show: String,
+    /// normalized query of the results page
+    pub query: String,
+    pub episode_id: usize,
+    pub relevant: bool,
+    /// only recorded with analytics.store_ip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+}
+
+/// A search of the search page or /api/search, one line of the queries log
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueryEvent {
+    /// unix seconds
+    pub timestamp: i64,
+    /// slug of the show, empty in a single show archive
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub show: String,
+    /// normalized query
+    pub query: String,
+    pub results: usize,
+    pub zero_results: bool,
+    pub took_ms: f64,
+    /// served from the page cache, `took_ms` is the lookup
+    #[serde(default)]
+    pub cached: bool,
+}
+
+/// Starts a new file once the log reaches `max_bytes`, the previous files
+/// are kept as `<path>.1` (newest) to `<path>.<keep>`
+#[derive(Clone, Copy, Debug)]
+pub struct Rotation {
+    pub max_bytes: u64,
+    pub keep: usize,
+}
+
+/// Append-only json lines file of events (clicks, feedback, queries)
+pub struct EventLog {
+    path: PathBuf,
+    rotation: Option<Rotation>,
+    /// the file and its size
+    file: Mutex<(File, u64)>,
+}
+
+impl EventLog {
+    pub async fn open(path: &Path) -> std::io::Result<Self> {
+        Self::open_rotating(path, None).await
+    }
+
+    pub async fn open_rotating(path: &Path, rotation: Option<Rotation>) -> std::io::Result<Self> {
+        let file = open_append(path).await?;
+        let size = file.metadata().await?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            file: Mutex::new((file, size)),
+        })
+    }
+
+    pub async fn append<T: Serialize>(&self, event: &T) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        // a single write per line so lines of concurrent events never interleave
+        let mut guard = self.file.lock().await;
+        let (file, size) = &mut *guard;
+
+        if let Some(rotation) = self.rotation {
+            if *size > 0 && *size + line.len() as u64 > rotation.max_bytes {
+                file.flush().await?;
+                rotate(&self.path, rotation.keep).await?;
+                *file = open_append(&self.path).await?;
+                *size = 0;
+            }
+        }
+
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        *size += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// The current file of the log, without the rotated ones
+    pub async fn contents(&self) -> std::io::Result<Vec<u8>> {
+        // no append halfway through the read
+        let _guard = self.file.lock().await;
+        tokio::fs::read(&self.path).await
+    }
+
+    /// Replaces the current file of the log with `contents`, e.g. of a backup
+    pub async fn replace(&self, contents: &[u8]) -> std::io::Result<()> {
+        let mut guard = self.file.lock().await;
+        let (file, size) = &mut *guard;
+
+        let tmp = PathBuf::from(format!("{}.tmp", self.path.display()));
+        tokio::fs::write(&tmp, contents).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+
+        *file = open_append(&self.path).await?;
+        *size = contents.len() as u64;
+
+        Ok(())
+    }
+}
+
+async fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
+
+// <path>.<keep - 1> -> <path>.<keep>, ..., <path> -> <path>.1
+async fn rotate(path: &Path, keep: usize) -> std::io::Result<()> {
+    let rotated = |index: usize| PathBuf::from(format!("{}.{}", path.display(), index));
+
+    if keep == 0 {
+        return tokio::fs::remove_file(path).await;
+    }
+
+    for index in (1..keep).rev() {
+        let from = rotated(index);
+
+        if tokio::fs::try_exists(&from).await? {
+            tokio::fs::rename(&from, rotated(index + 1)).await?;
+        }
+    }
+
+    tokio::fs::rename(path, rotated(1)).await
+}
+
+/// Events of a log, lines that can't be parsed (e.g. cut by a crash) are skipped
+pub fn parse_event_log<T: DeserializeOwned>(contents: &str) -> Vec<T> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+pub async fn read_event_log<T: DeserializeOwned>(path: &Path) -> std::io::Result<Vec<T>> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    Ok(parse_event_log(&contents))
+}
+
+/// Events of a rotated log and of its `keep` previous files, oldest first,
+/// missing files are skipped
+pub async fn read_rotated_event_log<T: DeserializeOwned>(path: &Path, keep: usize) -> Vec<T> {
+    let mut files: Vec<_> = (1..=keep)
+        .rev()
+        .map(|index| PathBuf::from(format!("{}.{}", path.display(), index)))
+        .collect();
+    files.push(path.to_path_buf());
+
+    let mut events = Vec::new();
+
+    for file in files {
+        if let Ok(contents) = tokio::fs::read_to_string(&file).await {
+            events.extend(parse_event_log(&contents));
+        }
+    }
+
+    events
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct QueryCount {
+    pub query: String,
+    pub searches: usize,
+    /// results of the latest search of the query
+    pub results: usize,
+}
+
+/// The most frequent queries and zero-result queries of the queries log
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct QueryReport {
+    pub searches: usize,
+    pub zero_result_searches: usize,
+    pub top: Vec<QueryCount>,
+    pub zero_results: Vec<QueryCount>,
+}
+
+impl QueryReport {
+    /// Counts the searches since the unix timestamp `since`, only of `show` when set
+    pub fn new(events: &[QueryEvent], since: i64, show: Option<&str>, limit: usize) -> Self {
+        let events: Vec<_> = events
+            .iter()
+            .filter(|event| event.timestamp >= since)
+            .filter(|event| show.is_none_or(|show| event.show == show))
+            .filter(|event| !event.query.is_empty())
+            .collect();
+
+        let mut counts: HashMap<String, QueryCount> = HashMap::new();
+
+        // oldest first, so the results of the latest search win
+        for event in &events {
+            // counted by the canonical form, shown as first searched, `ZFS backup` is `backup zfs`
+            let count = counts
+                .entry(normalize_query(&event.query))
+                .or_insert_with(|| QueryCount {
+                    query: event.query.split_whitespace().collect::<Vec<_>>().join(" "),
+                    searches: 0,
+                    results: 0,
+                });
+
+            count.searches += 1;
+            count.results = event.results;
+        }
+
+        let mut top: Vec<_> = counts.into_values().collect();
+        top.sort_by(|a, b| b.searches.cmp(&a.searches).then(a.query.cmp(&b.query)));
+
+        let zero_results = top
+            .iter()
+            .filter(|count| count.results == 0)
+            .take(limit)
+            .cloned()
+            .collect();
+
+        top.truncate(limit);
+
+        Self {
+            searches: events.len(),
+            zero_result_searches: events.iter().filter(|event| event.zero_results).count(),
+            top,
+            zero_results,
+        }
+    }
+}
+
+/// The most frequent queries worth suggesting on the homepage: the ones finding episodes,
+/// searched at least `min_searches` times, without operators nor fields. The log only has the
+/// searches of people, the alert feeds and digests searching their saved queries aren't in it
+pub fn popular_queries(
+    events: &[QueryEvent],
+    since: i64,
+    show: Option<&str>,
+    min_searches: usize,
+    limit: usize,
+) -> Vec<String> {
+    QueryReport::new(events, since, show, usize::MAX)
+        .top
+        .into_iter()
+        .filter(|count| count.results > 0 && count.searches >= min_searches.max(1))
+        .filter(|count| is_suggestable(&count.query))
+        .take(limit)
+        .map(|count| count.query)
+        .collect()
+}
+
+fn is_suggestable(query: &str) -> bool {
+    query.chars().count() <= POPULAR_QUERY_MAX_CHARS
+        && query
+            .split_whitespace()
+            .all(|word| !word.starts_with(['-', '#']) && !word.contains(':'))
+        && query
+            .chars()
+            .all(|c| c.is_alphanumeric() || c.is_whitespace() || "\"-.+'".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_event_log, popular_queries, ClickEvent, EventLog, FeedbackEvent, QueryEvent,
+        QueryReport, Rotation,
+    };
+
+    #[test]
+    fn test_parse_event_log() {
+        let event = ClickEvent {
+            timestamp: 1680000000,
+            show: String::new(),
+            query: String::from("nixos"),
+            episode_id: 512,
+            rank: 1,
+            ip: None,
+        };
+
+        let line = serde_json::to_string(&event).unwrap();
+
+        // no ip nor show unless they are set
+        assert_eq!(
+            line,
+            r#"{"timestamp":1680000000,"query":"nixos","episode_id":512,"rank":1}"#
+        );
+
+        let log = format!("{}\n{{\"timestamp\":16800\n{}\n", line, line);
+        assert_eq!(
+            parse_event_log::<ClickEvent>(&log),
+            vec![event.clone(), event]
+        );
+
+        // lines of another kind of event are skipped too
+        assert!(parse_event_log::<FeedbackEvent>(&log).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("unplugged-rotation-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("queries.jsonl");
+
+        let rotation = Rotation {
+            max_bytes: 15,
+            keep: 2,
+        };
+        let log = EventLog::open_rotating(&path, Some(rotation))
+            .await
+            .unwrap();
+
+        // 10 bytes per line, a file holds a single line
+        for index in 0..4 {
+            log.append(&format!("event {}", index)).await.unwrap();
+        }
+
+        let lines = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+
+        assert_eq!(lines("queries.jsonl"), "\"event 3\"\n");
+        assert_eq!(lines("queries.jsonl.1"), "\"event 2\"\n");
+        assert_eq!(lines("queries.jsonl.2"), "\"event 1\"\n");
+        assert!(!dir.join("queries.jsonl.3").exists());
+
+        // a replaced file is appended to and rotated like the previous one
+        log.replace(b"\"event 9\"\n").await.unwrap();
+        assert_eq!(log.contents().await.unwrap(), b"\"event 9\"\n");
+
+        log.append(&"event 4").await.unwrap();
+        assert_eq!(lines("queries.jsonl"), "\"event 4\"\n");
+        assert_eq!(lines("queries.jsonl.1"), "\"event 9\"\n");
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_query_report() {
+        let event = |timestamp, show: &str, query: &str, results| QueryEvent {
+            timestamp,
+            show: show.to_string(),
+            query: query.to_string(),
+            results,
+            zero_results: results == 0,
+            took_ms: 0.5,
+            cached: false,
+        };
+
+        let events = [
+            event(10, "", "nixos", 9),
+            event(100, "", "nixos", 9),
+            event(100, "", "btrfs raid", 0),
+            event(101, "", "fedora", 5),
+            event(102, "", "nixos", 9),
+            event(103, "", "raid  Btrfs", 0),
+            event(104, "lup", "arch", 0),
+        ];
+
+        let report = QueryReport::new(&events, 100, None, 2);
+
+        assert_eq!(report.searches, 6);
+        assert_eq!(report.zero_result_searches, 3);
+        assert_eq!(
+            report
+                .top
+                .iter()
+                .map(|count| (count.query.as_str(), count.searches))
+                .collect::<Vec<_>>(),
+            vec![("btrfs raid", 2), ("nixos", 2)]
+        );
+        assert_eq!(
+            report
+                .zero_results
+                .iter()
+                .map(|count| count.query.as_str())
+                .collect::<Vec<_>>(),
+            vec!["btrfs raid", "arch"]
+        );
+
+        let lup = QueryReport::new(&events, 0, Some("lup"), 10);
+        assert_eq!(lup.searches, 1);
+        assert_eq!(lup.zero_results[0].query, "arch");
+    }
+
+    #[test]
+    fn test_popular_queries() {
+        let event = |query: &str, results| QueryEvent {
+            timestamp: 100,
+            show: String::new(),
+            query: query.to_string(),
+            results,
+            zero_results: results == 0,
+            took_ms: 0.5,
+            cached: false,
+        };
+
+        let mut events = Vec::new();
+
+        for (query, searches, results) in [
+            ("nixos", 5, 9),
+            ("steam deck", 3, 4),
+            ("btrfs raid", 6, 0),
+            ("linux -nixos", 6, 20),
+            ("guest:wendell", 6, 2),
+            ("<script>alert(1)</script>", 6, 1),
+            ("proxmox", 1, 3),
+        ] {
+            events.extend((0..searches).map(|_| event(query, results)));
+        }
+
+        assert_eq!(
+            popular_queries(&events, 0, None, 2, 5),
+            vec!["nixos", "steam deck"]
+        );
+        assert_eq!(popular_queries(&events, 0, None, 1, 1), vec!["nixos"]);
+        assert_eq!(
+            popular_queries(&events, 0, None, 1, 5),
+            vec!["nixos", "steam deck", "proxmox"]
+        );
+        assert!(popular_queries(&events, 101, None, 1, 5).is_empty());
+    }
+}
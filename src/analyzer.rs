@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use unicode_normalization::UnicodeNormalization;
+
+/// Codes of the languages with a bundled stopword list, `[analyzer] language`
+pub const ANALYZER_LANGUAGES: [&str; 3] = ["en", "es", "de"];
+
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "the", "be", "is", "are", "to", "of", "and", "a", "an", "in", "that", "have", "i", "it", "for",
+    "not", "on", "with", "he", "as", "you", "do", "at", "this", "but", "his", "by", "from", "they",
+    "we", "say", "her", "she", "or", "will", "my", "one", "all", "would", "there", "their", "what",
+    "so", "up", "out", "if", "about", "who", "get", "which", "me", "when", "make", "can", "like",
+    "time", "no", "just", "him", "know", "take", "people", "into", "year", "your", "some", "could",
+    "them", "see", "other", "than", "then", "now", "look", "only", "come", "its", "it's", "over",
+    "think", "also", "back", "after", "use", "two", "how", "our", "work", "first", "well", "way",
+    "even", "new", "want", "because", "any", "these", "give", "day", "most", "us", "was",
+];
+
+const SPANISH_STOPWORDS: &[&str] = &[
+    "de", "la", "que", "el", "en", "y", "a", "los", "del", "se", "las", "por", "un", "para", "con",
+    "no", "una", "su", "al", "lo", "como", "más", "mas", "pero", "sus", "le", "ya", "o", "u",
+    "este", "sí", "porque", "esta", "entre", "cuando", "muy", "sin", "sobre", "también", "me",
+    "hasta", "hay", "donde", "quien", "desde", "todo", "nos", "durante", "todos", "uno", "les",
+    "ni", "contra", "otros", "ese", "eso", "ante", "ellos", "e", "esto", "mí", "antes", "algunos",
+    "qué", "unos", "yo", "otro", "otras", "otra", "él", "tanto", "esa", "estos", "mucho",
+    "quienes", "nada", "muchos", "cual", "poco", "ella", "estar", "estas", "algunas", "algo",
+    "nosotros", "mi", "mis", "tú", "te", "ti", "tu", "tus", "ellas", "es", "son", "fue", "ser",
+    "era", "ha", "han", "hemos", "está", "están", "cómo", "vez", "año", "día",
+];
+
+const GERMAN_STOPWORDS: &[&str] = &[
+    "der", "die", "das", "und", "in", "zu", "den", "von", "ist", "mit", "sich", "des", "auf",
+    "für", "nicht", "im", "dem", "ein", "eine", "als", "auch", "es", "an", "werden", "aus", "er",
+    "hat", "dass", "daß", "sie", "nach", "wird", "bei", "einer", "um", "am", "sind", "noch", "wie",
+    "einem", "über", "einen", "so", "zum", "war", "haben", "nur", "oder", "aber", "vor", "zur",
+    "bis", "mehr", "durch", "man", "sein", "wurde", "sei", "ich", "du", "wir", "ihr", "mein",
+    "dein", "unser", "euer", "was", "wer", "wo", "wenn", "dann", "doch", "schon", "hier", "da",
+    "ja", "nein", "kein", "keine", "mal", "ob", "weil", "diese", "dieser", "dieses", "ins", "vom",
+    "jetzt", "neue", "neuen",
+];
+
+// characters joining the words of a compound term, `low-memory-monitor`, `gnu/linux`
+const COMPOUND_SEPARATORS: [char; 3] = ['-', '_', '/'];
+
+// shorter parts of a compound match nearly every title
+const COMPOUND_PART_MIN_CHARS: usize = 2;
+
+// shorter words are left alone, "news" isn't the plural of "new"
+const STEM_MIN_CHARS: usize = 5;
+// the stems are matched inside the words, shorter ones inside too many
+const STEM_MIN_STEM_CHARS: usize = 4;
+
+/// The text as the search compares it: compatibility normalized (NFKC, the ligatures,
+/// full width letters and accents written as two characters read like the plain ones)
+/// and lowercase, the same for the indexed texts and the terms of the queries
+pub fn normalize_text(text: &str) -> String {
+    text.nfkc().collect::<String>().to_lowercase()
+}
+
+/// The stem of an english word, its inflections stripped (`containers` -> `container`,
+/// `updated` -> `updat`), none when it's left as is. The stems are searched inside
+/// the words like every term, `updat` finds "update", "updates" and "updated"
+pub fn stem(word: &str) -> Option<String> {
+    if word.chars().count() < STEM_MIN_CHARS || !word.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+
+    let stem = if let Some(stem) = word.strip_suffix("ies") {
+        stem
+    } else if let Some(stem) = word.strip_suffix("ing").or_else(|| word.strip_suffix("ed")) {
+        // `shipped` -> `ship`
+        match stem.as_bytes() {
+            [.., a, b] if a == b && !b"aeiouls".contains(b) => &stem[..stem.len() - 1],
+            _ => stem,
+        }
+    } else if ["sses", "shes", "ches", "xes", "zes"]
+        .iter()
+        .any(|suffix| word.ends_with(suffix))
+    {
+        &word[..word.len() - 2]
+    } else if word.ends_with('s') && !["ss", "us", "is"].iter().any(|end| word.ends_with(end)) {
+        &word[..word.len() - 1]
+    } else {
+        return None;
+    };
+
+    (stem.len() >= STEM_MIN_STEM_CHARS && stem.chars().any(|c| "aeiouy".contains(c)))
+        .then(|| stem.to_string())
+}
+
+/// The parts of a compound term, none when it's a single word, `dnf-fedora` -> `dnf`, `fedora`,
+/// quoted phrases (with spaces) are searched as written
+pub fn compound_parts(term: &str) -> Vec<&str> {
+    if term.contains(char::is_whitespace) || !term.contains(COMPOUND_SEPARATORS) {
+        return Vec::new();
+    }
+
+    term.split(COMPOUND_SEPARATORS)
+        .filter(|part| part.chars().count() >= COMPOUND_PART_MIN_CHARS)
+        .collect()
+}
+
+/// What the search matches of a term of a query: the lowercase term and the parts
+/// of a compound, without the stopwords
+pub fn analyze_term(term: &str, stopwords: &HashSet<String>) -> Vec<String> {
+    let term = normalize_text(term);
+    let mut analyzed: Vec<_> = compound_parts(&term)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    analyzed.insert(0, term);
+    analyzed.retain(|term| !stopwords.contains(term));
+    analyzed
+}
+
+/// The bundled stopwords of a language, the words dropped from the queries
+pub fn bundled_stopwords(language: &str) -> Option<&'static [&'static str]> {
+    match language {
+        "en" => Some(ENGLISH_STOPWORDS),
+        "es" => Some(SPANISH_STOPWORDS),
+        "de" => Some(GERMAN_STOPWORDS),
+        _ => None,
+    }
+}
+
+/// The words of a stopwords file, one per line, without the blank lines and the `#` comments
+pub fn parse_stopwords(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|word| !word.is_empty())
+        .map(normalize_text)
+        .collect()
+}
+
+/// The stopwords of `language` and the `extra` ones, lowercase like the search terms,
+/// none for a language without a bundled list
+pub fn stopwords(language: &str, extra: &[String]) -> HashSet<String> {
+    bundled_stopwords(language)
+        .unwrap_or_default()
+        .iter()
+        .map(|word| word.to_string())
+        .chain(extra.iter().map(|word| word.trim().to_lowercase()))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        analyze_term, bundled_stopwords, compound_parts, normalize_text, parse_stopwords, stem,
+        stopwords, ANALYZER_LANGUAGES,
+    };
+
+    #[test]
+    fn test_stopwords() {
+        for language in ANALYZER_LANGUAGES {
+            let words = bundled_stopwords(language).unwrap();
+
+            assert!(words.iter().all(|word| *word == word.to_lowercase()));
+        }
+
+        let spanish = stopwords("es", &[String::from(" Episodio ")]);
+
+        assert!(spanish.contains("más"));
+        assert!(spanish.contains("episodio"));
+        assert!(!spanish.contains("the"));
+
+        assert!(stopwords("de", &[]).contains("über"));
+        assert!(stopwords("en", &[]).contains("it's"));
+        assert!(stopwords("fr", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_stopwords() {
+        let contents = "# the show\nLinux Unplugged\n\n  jupiter  # the network\nÉpisode\n";
+
+        assert_eq!(
+            parse_stopwords(contents),
+            vec!["linux unplugged", "jupiter", "épisode"]
+        );
+    }
+
+    #[test]
+    fn test_compound_parts() {
+        assert_eq!(
+            compound_parts("low-memory-monitor"),
+            vec!["low", "memory", "monitor"]
+        );
+        assert_eq!(compound_parts("gnu/linux"), vec!["gnu", "linux"]);
+        assert_eq!(compound_parts("e-mail"), vec!["mail"]);
+        assert!(compound_parts("fedora").is_empty());
+        assert!(compound_parts("dnf-fedora vs apt").is_empty());
+
+        let stopwords = stopwords("en", &[]);
+
+        assert_eq!(
+            analyze_term("DNF-Fedora", &stopwords),
+            vec!["dnf-fedora", "dnf", "fedora"]
+        );
+        assert_eq!(analyze_term("the-zfs", &stopwords), vec!["the-zfs", "zfs"]);
+        assert!(analyze_term("The", &stopwords).is_empty());
+        assert_eq!(analyze_term("Ｚｆｓ", &stopwords), vec!["zfs"]);
+    }
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(normalize_text("Caf\u{65}\u{301} ﬁle"), "café file");
+        assert_eq!(normalize_text("ＮｉｘＯＳ"), "nixos");
+    }
+
+    #[test]
+    fn test_stem() {
+        let stems = [
+            ("containers", Some("container")),
+            ("libraries", Some("librar")),
+            ("patches", Some("patch")),
+            ("updated", Some("updat")),
+            ("shipped", Some("ship")),
+            ("hosting", Some("host")),
+            ("installed", Some("install")),
+            ("games", Some("game")),
+            // too short, not an inflection or not a word
+            ("news", None),
+            ("address", None),
+            ("linux", None),
+            ("virus", None),
+            ("gaming", None),
+            ("zfs-tools", None),
+        ];
+
+        for (word, expected) in stems {
+            assert_eq!(stem(word).as_deref(), expected, "{}", word);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use axum::extract::State;
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{NaiveDate, Utc};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use serde::Serialize;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use tracing::{warn, Span};
+use unplugged_engine::ApiConfig;
+
+use crate::admin::constant_time_eq;
+use crate::AppState;
+
+/// Marks the api requests with a valid key, the ip rate limit leaves them
+/// to the rate limit of the key
+#[derive(Clone, Copy)]
+pub struct ApiClient;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ApiKeyUsage {
+    pub name: String,
+    /// requests since 00:00 UTC
+    pub today: u64,
+    pub daily_quota: u64,
+    /// requests since the server started
+    pub total: u64,
+    /// requests rejected by the rate limit or the quota
+    pub rejected: u64,
+}
+
+struct ApiKey {
+    name: String,
+    key: String,
+    daily_quota: u64,
+    limiter: DefaultDirectRateLimiter,
+    usage: Mutex<KeyUsage>,
+}
+
+#[derive(Default)]
+struct KeyUsage {
+    day: Option<NaiveDate>,
+    today: u64,
+    total: u64,
+    rejected: u64,
+}
+
+/// The keys of `[[api.keys]]` with their rate limits and usage counts,
+/// the counts live in memory and start over on restarts
+pub struct ApiKeys {
+    require_key: bool,
+    keys: Vec<ApiKey>,
+}
+
+enum Rejection {
+    /// seconds until the next request is allowed
+    RateLimited(u64),
+    QuotaExceeded,
+}
+
+impl ApiKeys {
+    pub fn new(config: &ApiConfig) -> Self {
+        let keys = config
+            .keys
+            .iter()
+            .map(|key| {
+                let per_second = NonZeroU32::new(key.per_second).unwrap_or(NonZeroU32::MIN);
+                let burst = NonZeroU32::new(key.burst).unwrap_or(per_second);
+
+                ApiKey {
+                    name: key.name.clone(),
+                    key: key.key.clone(),
+                    daily_quota: key.daily_quota,
+                    limiter: RateLimiter::direct(Quota::per_second(per_second).allow_burst(burst)),
+                    usage: Mutex::new(KeyUsage::default()),
+                }
+            })
+            .collect();
+
+        Self {
+            require_key: config.require_key,
+            keys,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Requests of every key, in the order of the config
+    pub fn usage(&self) -> Vec<ApiKeyUsage> {
+        let today = Utc::now().date_naive();
+
+        self.keys
+            .iter()
+            .map(|key| {
+                let usage = key.usage.lock().unwrap();
+
+                ApiKeyUsage {
+                    name: key.name.clone(),
+                    today: if usage.day == Some(today) {
+                        usage.today
+                    } else {
+                        0
+                    },
+                    daily_quota: key.daily_quota,
+                    total: usage.total,
+                    rejected: usage.rejected,
+                }
+            })
+            .collect()
+    }
+
+    fn find(&self, key: &str) -> Option<&ApiKey> {
+        self.keys
+            .iter()
+            .find(|api_key| constant_time_eq(api_key.key.as_bytes(), key.as_bytes()))
+    }
+}
+
+impl ApiKey {
+    // counts the request against the rate limit and the quota of the day
+    fn check(&self) -> Result<(), Rejection> {
+        let today = Utc::now().date_naive();
+        let mut usage = self.usage.lock().unwrap();
+
+        if usage.day != Some(today) {
+            usage.day = Some(today);
+            usage.today = 0;
+        }
+
+        let result = if self.daily_quota > 0 && usage.today >= self.daily_quota {
+            Err(Rejection::QuotaExceeded)
+        } else {
+            self.limiter.check().map_err(|not_until| {
+                let wait = not_until.wait_time_from(DefaultClock::default().now());
+                Rejection::RateLimited(wait.as_secs().max(1))
+            })
+        };
+
+        match result {
+            Ok(()) => {
+                usage.today += 1;
+                usage.total += 1;
+            }
+            Err(_) => usage.rejected += 1,
+        }
+
+        result
+    }
+}
+
+// Authorization: Bearer <key>, X-API-Key: <key> or ?api_key=<key>
+fn request_key<B>(request: &Request<B>) -> Option<String> {
+    let headers = request.headers();
+
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    let header_key = headers
+        .get("X-API-Key")
+        .and_then(|value| value.to_str().ok());
+
+    if let Some(key) = bearer.or(header_key) {
+        return Some(key.trim().to_string());
+    }
+
+    request.uri().query().and_then(|query| {
+        serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+            .ok()?
+            .into_iter()
+            .find(|(name, _)| name == "api_key")
+            .map(|(_, key)| key)
+    })
+}
+
+/// Checks the key of `/api/*` requests, anonymous requests go through to the
+/// ip rate limit unless `api.require_key` is set
+pub async fn require_api_key<B>(
+    State(state): State<Arc<AppState>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if !request.uri().path().starts_with("/api/") {
+        return next.run(request).await;
+    }
+
+    let api_keys = &state.api_keys;
+
+    let Some(key) = request_key(&request) else {
+        if api_keys.require_key {
+            return unauthorized("an api key is required");
+        }
+
+        return next.run(request).await;
+    };
+
+    let Some(api_key) = api_keys.find(&key) else {
+        return unauthorized("unknown api key");
+    };
+
+    match api_key.check() {
+        Ok(()) => {
+            Span::current().record("api_key", api_key.name.as_str());
+            request.extensions_mut().insert(ApiClient);
+            next.run(request).await
+        }
+        Err(Rejection::RateLimited(retry_after)) => {
+            warn!("Rate limited the api key {}", api_key.name);
+
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "too many requests for this api key",
+            )
+                .into_response()
+        }
+        Err(Rejection::QuotaExceeded) => {
+            warn!("The api key {} used up its daily quota", api_key.name);
+
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, seconds_until_midnight().to_string())],
+                "the daily quota of this api key is used up",
+            )
+                .into_response()
+        }
+    }
+}
+
+fn unauthorized(message: &'static str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        message,
+    )
+        .into_response()
+}
+
+// quotas start over at 00:00 UTC
+fn seconds_until_midnight() -> i64 {
+    let now = Utc::now();
+    let midnight = (now.date_naive() + chrono::Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("Error at computing midnight");
+
+    (midnight - now.naive_utc()).num_seconds().max(1)
+}
@@ -0,0 +1,275 @@
+// the command line of the binary, only depends on clap and std
+// so build.rs can include it to generate the man pages
+
+use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Local first search engine for the linux unplugged show"
+)]
+pub struct Args {
+    /// Path of the config file, defaults to config.toml when it exists
+    #[arg(long, env = "UNPLUGGED_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Options of serve, the command run without a subcommand
+    /// (`unplugged-engine --dev` is `unplugged-engine serve --dev`)
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Runs the web server, the default command
+    Serve(ServeArgs),
+    /// Searches the index from the terminal, `search "zfs -btrfs"`
+    Search(SearchArgs),
+    /// Builds, validates and summarizes the index files
+    #[command(subcommand)]
+    Index(IndexCommand),
+    /// Searches as you type in the terminal, enter opens the selected episode
+    Tui(ShowArg),
+    /// Debugs queries and their ranking
+    #[command(subcommand)]
+    Query(QueryCommand),
+    /// Replays a query log against the engine and reports latencies and throughput
+    Bench(BenchArgs),
+    /// Renders the episode pages, a page per tag and the archive into static html,
+    /// `export-site --out dist/`
+    ExportSite(ExportSiteArgs),
+    /// Prints the completion script of a shell, `completions bash`
+    Completions(CompletionsArgs),
+    /// Checks the config, index files, templates and address before starting the server
+    Doctor,
+}
+
+#[derive(clap::Args)]
+pub struct ExportSiteArgs {
+    /// Directory to write the site to, a directory per show in a multi-show archive
+    #[arg(long)]
+    pub out: PathBuf,
+
+    /// Slug of the show in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+
+    /// Path the site is hosted under, e.g. "/archive", the root by default
+    #[arg(long, default_value = "")]
+    pub base_path: String,
+
+    /// Language of the pages, a catalog of locales/
+    #[arg(long, default_value = "en")]
+    pub lang: String,
+}
+
+#[derive(clap::Args)]
+pub struct CompletionsArgs {
+    pub shell: Shell,
+}
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    /// The queries to replay, analytics.queries_log (json lines) or one query per line,
+    /// analytics.queries_log by default
+    #[arg(long)]
+    pub log: Option<PathBuf>,
+
+    /// Queries searched at once
+    #[arg(long, default_value_t = 8)]
+    pub concurrency: usize,
+
+    /// Times the whole log is replayed
+    #[arg(long, default_value_t = 1)]
+    pub repeat: usize,
+
+    /// Prints the report as json
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Subcommand)]
+pub enum QueryCommand {
+    /// Prints the tokens, terms and rewrites of a query and the score of its best results per field
+    Explain(ExplainArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ExplainArgs {
+    pub query: String,
+
+    /// Slug of the show in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+
+    /// Results explained per show
+    #[arg(long, default_value_t = 5)]
+    pub limit: usize,
+}
+
+#[derive(clap::Args)]
+pub struct ServeArgs {
+    /// Address to listen on, `0.0.0.0:8080`, overrides server.address of the config
+    #[arg(long)]
+    pub address: Option<SocketAddr>,
+
+    /// Format of the log lines, json is meant for log collectors (Loki, ELK)
+    /// overrides logging.format of the config
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// File to log to instead of stderr, rotated past logging.file_max_bytes,
+    /// overrides logging.file of the config
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// File to write the pid of the server to, for init scripts (rc.d, sysvinit),
+    /// removed when the server stops
+    #[arg(long)]
+    pub pid_file: Option<PathBuf>,
+
+    /// Development mode, recompiles the templates when they change
+    /// and renders template errors as error pages
+    #[arg(long)]
+    pub dev: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct SearchArgs {
+    /// Same syntax as the search box, tags, titles, ids, "quotes" and -excluded tags
+    pub query: String,
+
+    /// Slug of the show to search in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+
+    /// Results listed per show
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+
+    /// table for reading, json for scripts, plain for tab separated lines (id, date, title, url)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Order of the results, the same as `&sort=` of the search page
+    #[arg(long, value_enum, default_value_t = SortArg::Relevance)]
+    pub sort: SortArg,
+
+    /// Searches a packed index (`index pack`) instead of the index files of the config,
+    /// ranked like the web search
+    #[arg(long, conflicts_with = "show")]
+    pub packed: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Plain,
+}
+
+/// The sort orders of the search page, the episodes without a date or a duration go last
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortArg {
+    Relevance,
+    Newest,
+    Oldest,
+    Longest,
+    Shortest,
+}
+
+#[derive(Subcommand)]
+pub enum IndexCommand {
+    /// Rebuilds the tag index (episodes_by_tag) and the related episodes of the episodes
+    /// (episodes_by_id), or both files from the RSS feed of the show with --feed
+    Build(BuildArgs),
+    /// Checks the index files can be served
+    Validate(ShowArg),
+    /// Prints the episodes, tags, dates and average duration of the index
+    Stats(ShowArg),
+    /// Writes the packed index, a single file `search --packed` reads
+    Pack(PackArgs),
+    /// Writes the rkyv archive of the index files, loaded instead of them when `archive` is set
+    Archive(ArchiveArgs),
+    /// Writes the index files into a sqlite database, loaded instead of them when `database`
+    /// is set and updated in place by `build --feed --update`
+    Database(DatabaseArgs),
+}
+
+#[derive(clap::Args)]
+pub struct PackArgs {
+    /// Slug of the show in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+
+    /// Path of the packed index, index.upidx next to the episodes_by_id file by default
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct ArchiveArgs {
+    /// Slug of the show in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+
+    /// Path of the archive, the `archive` of the show by default
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct DatabaseArgs {
+    /// Slug of the show in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+
+    /// Path of the database, the `database` of the show by default
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct BuildArgs {
+    /// Slug of the show in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+
+    /// Url or path of the RSS feed to build both index files from
+    #[arg(long)]
+    pub feed: Option<String>,
+
+    /// Directory to write the index files built from the feed to,
+    /// the paths of the config by default
+    #[arg(long, requires = "feed")]
+    pub out: Option<PathBuf>,
+
+    /// JSON file mapping episode ids to their guests and hosts,
+    /// `{"512": {"guests": ["Alan Pope"]}}`, replacing the people of the feed
+    #[arg(long, requires = "feed")]
+    pub people: Option<PathBuf>,
+
+    /// Updates the index instead of replacing it, the episodes missing from the feed
+    /// (feeds often list only the newest ones) and the fields the feed doesn't have are kept
+    #[arg(long, requires = "feed")]
+    pub update: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ShowArg {
+    /// Slug of the show in a multi-show archive, every show by default
+    #[arg(long)]
+    pub show: Option<String>,
+}
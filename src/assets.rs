@@ -0,0 +1,393 @@
+use axum::handler::HandlerWithoutStateExt;
+use axum::http::{header, HeaderMap, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, get_service, MethodRouter};
+use axum::Router;
+use futures::future::BoxFuture;
+use rust_embed::RustEmbed;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tera::{Context, Tera};
+use tower_http::services::ServeDir;
+use tracing::{info, warn};
+use unplugged_engine::{parse_date, parse_duration, Catalog, Translations};
+
+use crate::error_pages::not_found_page;
+use crate::shows::Shows;
+
+#[derive(RustEmbed)]
+#[folder = "templates/"]
+struct EmbeddedTemplates;
+
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct EmbeddedStatic;
+
+#[derive(RustEmbed)]
+#[folder = "locales/"]
+struct EmbeddedLocales;
+
+// the catalog every other catalog falls back to
+const DEFAULT_LANG: &str = "en";
+
+/// The bundled catalogs, locales/<lang>.ftl
+pub fn bundled_translations() -> Translations {
+    let catalog = |lang: &str| {
+        let file = EmbeddedLocales::get(&format!("{}.ftl", lang))
+            .expect("Error at reading bundled catalog");
+        let source = std::str::from_utf8(&file.data).expect("Error at reading bundled catalog");
+
+        Catalog::parse(source)
+            .unwrap_or_else(|err| panic!("Error at parsing locales/{}.ftl: {}", lang, err))
+    };
+
+    let mut translations = Translations::new(DEFAULT_LANG, catalog(DEFAULT_LANG));
+
+    for name in EmbeddedLocales::iter() {
+        let Some(lang) = name.strip_suffix(".ftl") else {
+            continue;
+        };
+
+        if lang != DEFAULT_LANG {
+            translations.add(lang, catalog(lang));
+        }
+    }
+
+    translations
+}
+
+/// Compiles the bundled templates, overridden file by file by the templates of `dir`
+/// (`dir/partials/results.html` replaces the bundled `partials/results.html`)
+pub fn compile_templates(dir: &str, translations: &Arc<Translations>) -> Result<Tera, tera::Error> {
+    let overrides: Vec<(PathBuf, Option<String>)> = if dir.is_empty() {
+        Vec::new()
+    } else {
+        let dir = Path::new(dir);
+
+        files_in(dir)
+            .into_iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+            .map(|path| {
+                let name = template_name(dir, &path);
+                (path, Some(name))
+            })
+            .collect()
+    };
+
+    let bundled: Vec<_> = EmbeddedTemplates::iter()
+        .filter(|name| {
+            !overrides
+                .iter()
+                .any(|(_, overridden)| overridden.as_deref() == Some(name.as_ref()))
+        })
+        .map(|name| {
+            let file = EmbeddedTemplates::get(&name).expect("Error at reading bundled template");
+            let content = String::from_utf8(file.data.into_owned())
+                .expect("Error at reading bundled template");
+            (name.into_owned(), content)
+        })
+        .collect();
+
+    let mut tera = Tera::default();
+    tera.register_function("t", translate_function(translations.clone()));
+    tera.register_filter(
+        "humanize_duration",
+        humanize_duration_filter(translations.clone()),
+    );
+    tera.register_filter("localize_date", localize_date_filter(translations.clone()));
+    tera.add_raw_templates(bundled)?;
+    tera.add_template_files(overrides)?;
+    Ok(tera)
+}
+
+// {{ t(key="results-count", lang=lang, count=total_results) }}, the other
+// arguments fill the `{ $name }` placeholders of the message
+fn translate_function(translations: Arc<Translations>) -> impl tera::Function {
+    move |args: &HashMap<String, tera::Value>| {
+        let key = args
+            .get("key")
+            .and_then(tera::Value::as_str)
+            .ok_or_else(|| tera::Error::msg("t() needs a `key` string argument"))?;
+
+        let lang = args
+            .get("lang")
+            .and_then(tera::Value::as_str)
+            .unwrap_or_else(|| translations.default_lang());
+
+        let values: Vec<(&str, String)> = args
+            .iter()
+            .filter(|(name, _)| *name != "key" && *name != "lang")
+            .map(|(name, value)| {
+                let value = match value {
+                    tera::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (name.as_str(), value)
+            })
+            .collect();
+
+        Ok(tera::Value::String(
+            translations.translate(lang, key, &values),
+        ))
+    }
+}
+
+// the `lang` argument of a filter, the default language when it's missing
+fn filter_lang<'a>(
+    translations: &'a Translations,
+    args: &'a HashMap<String, tera::Value>,
+) -> &'a str {
+    args.get("lang")
+        .and_then(tera::Value::as_str)
+        .unwrap_or_else(|| translations.default_lang())
+}
+
+// {{ episode.duration | humanize_duration(lang=lang) }}, "01:32:07" -> "1 hr 32 min",
+// numbers are seconds, durations that don't parse are printed as they are
+fn humanize_duration_filter(translations: Arc<Translations>) -> impl tera::Filter {
+    move |value: &tera::Value, args: &HashMap<String, tera::Value>| {
+        let seconds = value
+            .as_u64()
+            .or_else(|| parse_duration(value.as_str().unwrap_or_default()));
+
+        Ok(match seconds {
+            Some(seconds) => tera::Value::String(
+                translations.humanize_duration(filter_lang(&translations, args), seconds),
+            ),
+            None => value.clone(),
+        })
+    }
+}
+
+// {{ episode.date | localize_date(lang=lang) }}, "May 16th, 2023" -> "16 de mayo de 2023",
+// dates that don't parse are printed as they are
+fn localize_date_filter(translations: Arc<Translations>) -> impl tera::Filter {
+    move |value: &tera::Value, args: &HashMap<String, tera::Value>| {
+        let raw = value.as_str().unwrap_or_default();
+
+        Ok(match parse_date(raw) {
+            Some(date) => tera::Value::String(
+                translations.localize_date(filter_lang(&translations, args), date),
+            ),
+            None => value.clone(),
+        })
+    }
+}
+
+// name of a template relative to the templates directory, with forward slashes
+fn template_name(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The files of `dir` and its subdirectories
+pub fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+/// The compiled templates, replaced when they are recompiled in dev mode
+pub struct Templates {
+    tera: RwLock<Tera>,
+    translations: Arc<Translations>,
+    /// last failed recompilation, shown instead of the pages until it's fixed
+    compile_error: RwLock<Option<String>>,
+    dev: bool,
+}
+
+impl Templates {
+    pub fn new(tera: Tera, translations: Arc<Translations>, dev: bool) -> Self {
+        Self {
+            tera: RwLock::new(tera),
+            translations,
+            compile_error: RwLock::new(None),
+            dev,
+        }
+    }
+
+    pub fn render(&self, name: &str, context: &Context) -> Result<String, tera::Error> {
+        if let Some(message) = self.compile_error.read().unwrap().as_deref() {
+            return Err(tera::Error::msg(message));
+        }
+
+        self.tera.read().unwrap().render(name, context)
+    }
+
+    pub fn translations(&self) -> &Arc<Translations> {
+        &self.translations
+    }
+
+    /// The language of the pages for the `Accept-Language` of the request
+    pub fn negotiate(&self, headers: &HeaderMap) -> &str {
+        let accept_language = headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        self.translations.negotiate(accept_language)
+    }
+
+    /// error pages show the details of the error in dev mode
+    pub fn is_dev(&self) -> bool {
+        self.dev
+    }
+
+    pub fn count(&self) -> usize {
+        self.tera.read().unwrap().get_template_names().count()
+    }
+
+    pub fn replace(&self, tera: Result<Tera, tera::Error>) {
+        match tera {
+            Ok(tera) => {
+                *self.tera.write().unwrap() = tera;
+                *self.compile_error.write().unwrap() = None;
+            }
+            Err(err) => *self.compile_error.write().unwrap() = Some(error_chain(&err)),
+        }
+    }
+}
+
+// tera errors wrap the useful message (the line of the template) in their source
+pub fn error_chain(err: &dyn Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+
+    while let Some(err) = source {
+        message.push_str(&format!("\n  caused by: {}", err));
+        source = err.source();
+    }
+
+    message
+}
+
+/// Recompiles the templates of `dir` (dev mode), the page caches are cleared
+/// so the next request renders with the new templates
+pub fn recompile_templates(dir: &str, shows: &Shows) {
+    let tera = compile_templates(dir, shows[0].templates.translations());
+
+    match &tera {
+        Ok(_) => info!(dir, "Recompiled templates"),
+        Err(err) => warn!(dir, "Error at compiling templates: {}", error_chain(err)),
+    }
+
+    shows[0].templates.replace(tera);
+
+    for show in shows.iter() {
+        show.page_cache.clear();
+    }
+}
+
+/// A static file of `dir`, or the bundled one when `dir` is empty or doesn't have it
+pub fn read_static_file(dir: &str, name: &str) -> Option<Vec<u8>> {
+    if !dir.is_empty() {
+        if let Ok(contents) = std::fs::read(Path::new(dir).join(name)) {
+            return Some(contents);
+        }
+    }
+
+    EmbeddedStatic::get(name).map(|file| file.data.into_owned())
+}
+
+/// Where the static files (index.html, css) are served from,
+/// the files of `dir` override the ones bundled in the binary
+#[derive(Clone)]
+pub struct StaticFiles {
+    dir: Option<PathBuf>,
+    /// renders the 404 page
+    templates: Arc<Templates>,
+}
+
+impl StaticFiles {
+    pub fn new(dir: &str, templates: Arc<Templates>) -> Self {
+        let dir = (!dir.is_empty()).then(|| PathBuf::from(dir));
+        Self { dir, templates }
+    }
+
+    /// Serves index.html
+    pub fn index<S>(&self, base_path: &str) -> MethodRouter<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let embedded = self.embedded(base_path);
+
+        match &self.dir {
+            None => get(embedded),
+            Some(dir) => get_service(ServeDir::new(dir).fallback(embedded.into_service())),
+        }
+    }
+
+    /// Serves the files as fallback of the `router`, the 404 page when there's no such file
+    pub fn fallback<S>(&self, router: Router<S>, base_path: &str) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let embedded = self.embedded(base_path);
+
+        match &self.dir {
+            None => router.fallback(embedded),
+            Some(dir) => {
+                router.fallback_service(ServeDir::new(dir).fallback(embedded.into_service()))
+            }
+        }
+    }
+
+    fn embedded(
+        &self,
+        base_path: &str,
+    ) -> impl Fn(Uri, HeaderMap) -> BoxFuture<'static, Response> + Clone {
+        let templates = self.templates.clone();
+        let base_path = base_path.to_string();
+
+        move |uri: Uri, headers: HeaderMap| {
+            let templates = templates.clone();
+            let base_path = base_path.clone();
+            Box::pin(async move { serve_embedded(&uri, &headers, &templates, &base_path) })
+        }
+    }
+}
+
+fn serve_embedded(
+    uri: &Uri,
+    headers: &HeaderMap,
+    templates: &Templates,
+    base_path: &str,
+) -> Response {
+    let mut path = uri.path().trim_start_matches('/').to_string();
+
+    if path.is_empty() || path.ends_with('/') {
+        path.push_str("index.html");
+    }
+
+    match EmbeddedStatic::get(&path) {
+        Some(file) => (
+            [(header::CONTENT_TYPE, file.metadata.mimetype().to_string())],
+            file.data,
+        )
+            .into_response(),
+        None => not_found_page(templates, base_path, templates.negotiate(headers), uri),
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{edit_distance, max_tag_distance, tag_key, EpisodesById, EpisodesByTag};
+use std::collections::HashSet;
+
+// shorter words of the titles aren't worth completing
+const MIN_TITLE_WORD_LEN: usize = 3;
+
+/// Prefix completions of the known tags and of the words of the titles, kept sorted
+/// so a lookup is a binary search
+#[derive(Clone, Debug, Default)]
+pub struct Autocomplete {
+    /// (term, number of episodes using the term) sorted by term
+    terms: Vec<(String, usize)>,
+    /// (word, number of titles saying it) sorted by word, only completed, never
+    /// offered for a misspelled tag
+    title_words: Vec<(String, usize)>,
+}
+
+impl Autocomplete {
+    pub fn new(by_tag: &EpisodesByTag) -> Self {
+        let terms = by_tag
+            .iter()
+            .map(|(tag, ids)| (tag.to_lowercase(), ids.len()))
+            .collect();
+
+        Self {
+            terms: sorted_counts(terms),
+            title_words: Vec::new(),
+        }
+    }
+
+    /// Also completes the words of the titles, `kube` -> `kubernetes` without the tag
+    pub fn with_titles(mut self, episodes_by_id: &EpisodesById) -> Self {
+        let words = episodes_by_id
+            .values()
+            .flat_map(|episode| {
+                let title = episode.title.to_lowercase();
+                let words: HashSet<_> = title
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| word.chars().count() >= MIN_TITLE_WORD_LEN)
+                    .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+                    .map(|word| (word.to_string(), 1))
+                    .collect();
+                words
+            })
+            .collect();
+
+        self.title_words = sorted_counts(words);
+        self
+    }
+
+    /// Terms starting with `prefix`, the tags first then the words of the titles,
+    /// most used first
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<&str> {
+        let prefix = prefix.trim_start_matches(['-', '"']).to_lowercase();
+
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let mut suggestions = starting_with(&self.terms, &prefix);
+
+        for word in starting_with(&self.title_words, &prefix) {
+            if !suggestions.contains(&word) {
+                suggestions.push(word);
+            }
+        }
+
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// Known tags a few edits away from a misspelled one (`nix-os` -> `nixos`),
+    /// closest first, then most used
+    pub fn nearest(&self, tag: &str, limit: usize) -> Vec<&str> {
+        let key = tag_key(tag);
+
+        if key.is_empty() {
+            return Vec::new();
+        }
+
+        let max_distance = max_tag_distance(&key);
+
+        let mut matches: Vec<_> = self
+            .terms
+            .iter()
+            .map(|(term, count)| (edit_distance(&key, &tag_key(term)), count, term))
+            .filter(|(distance, ..)| *distance <= max_distance)
+            .collect();
+
+        matches.sort_by(
+            |(a_distance, a_count, a_term), (b_distance, b_count, b_term)| {
+                a_distance
+                    .cmp(b_distance)
+                    .then(b_count.cmp(a_count))
+                    .then(a_term.cmp(b_term))
+            },
+        );
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(.., term)| term.as_str())
+            .collect()
+    }
+}
+
+// sorted by term, the counts of the duplicated terms added up
+fn sorted_counts(mut terms: Vec<(String, usize)>) -> Vec<(String, usize)> {
+    terms.sort();
+    terms.dedup_by(|(a_term, a_count), (b_term, b_count)| {
+        // keep the first of the duplicated terms, adding up the counts
+        a_term == b_term && {
+            *b_count += *a_count;
+            true
+        }
+    });
+    terms
+}
+
+// the terms starting with `prefix`, most used first
+fn starting_with<'a>(terms: &'a [(String, usize)], prefix: &str) -> Vec<&'a str> {
+    let start = terms.partition_point(|(term, _)| term.as_str() < prefix);
+
+    let mut matches: Vec<_> = terms[start..]
+        .iter()
+        .take_while(|(term, _)| term.starts_with(prefix))
+        .collect();
+
+    matches.sort_by(|(a_term, a_count), (b_term, b_count)| {
+        b_count.cmp(a_count).then(a_term.cmp(b_term))
+    });
+
+    matches.into_iter().map(|(term, _)| term.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Autocomplete;
+    use crate::{Episode, EpisodesById, EpisodesByTag};
+
+    #[test]
+    fn test_suggest() {
+        let by_tag = EpisodesByTag::from([
+            (String::from("nixos"), vec![1, 2, 3]),
+            (String::from("nixpkgs"), vec![1]),
+            (String::from("nix"), vec![2, 3]),
+            (String::from("fedora"), vec![4]),
+        ]);
+
+        let autocomplete = Autocomplete::new(&by_tag);
+
+        assert_eq!(
+            autocomplete.suggest("nix", 10),
+            vec!["nixos", "nix", "nixpkgs"]
+        );
+        assert_eq!(autocomplete.suggest("NIX", 1), vec!["nixos"]);
+        assert_eq!(autocomplete.suggest("-fed", 10), vec!["fedora"]);
+        assert!(autocomplete.suggest("arch", 10).is_empty());
+        assert!(autocomplete.suggest("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_suggest_titles() {
+        let by_tag = EpisodesByTag::from([(String::from("nixos"), vec![1, 2])]);
+        let episode = |title: &str| Episode {
+            title: String::from(title),
+            ..Default::default()
+        };
+        let episodes_by_id = EpisodesById::from([
+            (1, episode("512: NixOS on the Steam Deck")),
+            (2, episode("513: Nixpkgs, Nixpkgs Everywhere")),
+            (3, episode("514: Nixpkgs Unstable")),
+        ]);
+
+        let autocomplete = Autocomplete::new(&by_tag).with_titles(&episodes_by_id);
+
+        // the tags first, the words of the titles once per title
+        assert_eq!(autocomplete.suggest("nix", 10), vec!["nixos", "nixpkgs"]);
+        assert_eq!(autocomplete.suggest("STE", 10), vec!["steam"]);
+        // numbers and short words aren't completed
+        assert!(autocomplete.suggest("51", 10).is_empty());
+        assert!(autocomplete.suggest("on", 10).is_empty());
+        // misspelled tags are only corrected to tags
+        assert!(autocomplete.nearest("stem", 3).is_empty());
+    }
+
+    #[test]
+    fn test_nearest() {
+        let by_tag = EpisodesByTag::from([
+            (String::from("nixos"), vec![1, 2, 3]),
+            (String::from("nix"), vec![2, 3]),
+            (String::from("docker compose"), vec![4]),
+            (String::from("fedora"), vec![4]),
+        ]);
+
+        let autocomplete = Autocomplete::new(&by_tag);
+
+        assert_eq!(autocomplete.nearest("nix-os", 3), vec!["nixos"]);
+        assert_eq!(autocomplete.nearest("nixo", 3), vec!["nixos", "nix"]);
+        assert_eq!(
+            autocomplete.nearest("docker-compse", 3),
+            vec!["docker compose"]
+        );
+        assert!(autocomplete.nearest("arch", 3).is_empty());
+    }
+}
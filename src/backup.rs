@@ -0,0 +1,404 @@
+use axum::body::{Bytes, StreamBody};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::Utc;
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info};
+use unplugged_engine::{
+    read_tarball, tar_end, tar_entry, EventLog, PlaybackPosition, SavedSearch, Transcripts,
+};
+
+use crate::index_upload::{install_index, parse_index, ParsedIndex};
+use crate::shows::Shows;
+
+/// Largest upload of /admin/restore
+pub const RESTORE_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+// bumped when the layout of the tarball changes
+const BACKUP_FORMAT: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const ALERTS: &str = "alerts.json";
+const PLAYBACK: &str = "playback.json";
+const CONFIG: &str = "config.toml";
+
+/// What the manifest.json of a backup records
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    /// version of the engine that wrote it
+    version: String,
+    /// unix seconds
+    created_at: i64,
+    /// slugs of the shows of the backup
+    shows: Vec<String>,
+}
+
+/// A file of the backup, read when the stream gets to it
+enum BackupFile {
+    Manifest,
+    /// the index files of the show at that position
+    Index(usize),
+    Log(&'static str),
+    Alerts,
+    Playback,
+    Config,
+}
+
+// admin/backup, the index of every show, the analytics logs, the saved searches, the playback
+// positions and a snapshot of the config as a tarball, the config has the secrets of the instance.
+// the entries are streamed as they are read, an error past the first one aborts the download
+pub async fn handle_backup(State(shows): State<Shows>) -> Response {
+    let state = &shows[0];
+    let created_at = Utc::now().timestamp();
+
+    let mut files = vec![BackupFile::Manifest];
+    files.extend((0..shows.len()).map(BackupFile::Index));
+    files.extend(
+        event_logs(state)
+            .into_iter()
+            .map(|(name, _)| BackupFile::Log(name)),
+    );
+
+    if state.saved_searches.is_some() {
+        files.push(BackupFile::Alerts);
+    }
+
+    if state.playback.is_some() {
+        files.push(BackupFile::Playback);
+    }
+
+    files.push(BackupFile::Config);
+
+    info!(files = files.len(), "Streaming a backup");
+
+    let body = stream::iter(files)
+        .then(move |file| {
+            let shows = shows.clone();
+            async move { backup_entries(&shows, file, created_at).await }
+        })
+        .map(move |entries| {
+            entries
+                .map(|entries| {
+                    let tar: Vec<u8> = entries
+                        .iter()
+                        .flat_map(|(name, contents)| tar_entry(name, contents, created_at))
+                        .collect();
+                    Bytes::from(tar)
+                })
+                .map_err(std::io::Error::other)
+        })
+        .chain(stream::once(async { Ok(Bytes::from(tar_end())) }));
+
+    let filename = format!(
+        "unplugged-backup-{}.tar",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    );
+
+    (
+        [
+            (header::CONTENT_TYPE, String::from("application/x-tar")),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        StreamBody::new(body),
+    )
+        .into_response()
+}
+
+// the tar entries of a file of the backup, by their name in the tarball
+async fn backup_entries(
+    shows: &Shows,
+    file: BackupFile,
+    created_at: i64,
+) -> Result<Vec<(String, Vec<u8>)>, &'static str> {
+    let state = &shows[0];
+
+    match file {
+        BackupFile::Manifest => {
+            let manifest = Manifest {
+                format: BACKUP_FORMAT,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                created_at,
+                shows: shows.iter().map(|show| show.show.slug.clone()).collect(),
+            };
+
+            let manifest = serde_json::to_vec_pretty(&manifest).unwrap_or_default();
+            Ok(vec![(MANIFEST.to_string(), manifest)])
+        }
+        BackupFile::Index(position) => {
+            let show = &shows[position];
+            let index = show.index();
+            let dir = index_dir(&show.show.slug);
+
+            let by_id = serde_json::to_vec(&index.episodes_by_id)
+                .map_err(|_| backup_error("Error at serializing the index"))?;
+            let by_tag = serde_json::to_vec(&index.episodes_by_tag)
+                .map_err(|_| backup_error("Error at serializing the index"))?;
+
+            let mut entries = vec![
+                (format!("{}episodes_by_id.json", dir), by_id),
+                (format!("{}episodes_by_tag.json", dir), by_tag),
+            ];
+
+            if !show.show.transcripts.is_empty() {
+                let transcripts = serde_json::to_vec(&*index.transcripts)
+                    .map_err(|_| backup_error("Error at serializing the transcripts"))?;
+                entries.push((format!("{}transcripts.json", dir), transcripts));
+            }
+
+            Ok(entries)
+        }
+        BackupFile::Log(name) => {
+            let logs = event_logs(state);
+            let Some((_, log)) = logs.iter().find(|(log_name, _)| *log_name == name) else {
+                return Ok(Vec::new());
+            };
+
+            match log.contents().await {
+                Ok(contents) => Ok(vec![(name.to_string(), contents)]),
+                Err(err) => {
+                    error!("Error at reading the {} log: {}", name, err);
+                    Err(backup_error("Error at reading the analytics logs"))
+                }
+            }
+        }
+        BackupFile::Alerts => match &state.saved_searches {
+            Some(saved_searches) => saved_searches
+                .to_json()
+                .map(|json| vec![(ALERTS.to_string(), json.into_bytes())])
+                .map_err(|_| backup_error("Error at serializing the saved searches")),
+            None => Ok(Vec::new()),
+        },
+        BackupFile::Playback => match &state.playback {
+            Some(playback) => playback
+                .to_json()
+                .map(|json| vec![(PLAYBACK.to_string(), json.into_bytes())])
+                .map_err(|_| backup_error("Error at serializing the playback positions")),
+            None => Ok(Vec::new()),
+        },
+        BackupFile::Config => toml::to_string(&*state.config())
+            .map(|config| vec![(CONFIG.to_string(), config.into_bytes())])
+            .map_err(|_| backup_error("Error at serializing the config")),
+    }
+}
+
+// admin/restore, PUT with a tarball of admin/backup, replaces the index of the shows of
+// the backup, the analytics logs, the saved searches and the playback positions, nothing changes unless every
+// file is valid, config.toml isn't restored
+pub async fn handle_restore(State(shows): State<Shows>, body: Bytes) -> Response {
+    let state = &shows[0];
+
+    let entries = match read_tarball(&body) {
+        Ok(entries) => entries,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let mut files: HashMap<String, Vec<u8>> = entries
+        .into_iter()
+        .map(|entry| (entry.name, entry.contents))
+        .collect();
+
+    let manifest = files
+        .remove(MANIFEST)
+        .ok_or_else(|| String::from("Error at reading the backup: no manifest.json"))
+        .and_then(|manifest| {
+            serde_json::from_slice::<Manifest>(&manifest)
+                .map_err(|err| format!("Error at parsing manifest.json: {}", err))
+        })
+        .and_then(|manifest| match manifest.format {
+            BACKUP_FORMAT => Ok(manifest),
+            format => Err(format!(
+                "Error at reading the backup: unsupported format {}",
+                format
+            )),
+        });
+
+    let manifest = match manifest {
+        Ok(manifest) => manifest,
+        Err(message) => return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response(),
+    };
+
+    // everything is parsed before anything is replaced
+    let mut indexes = Vec::new();
+
+    for slug in manifest.shows.iter() {
+        let Some(show) = shows.iter().find(|show| &show.show.slug == slug) else {
+            let message = format!("Error at restoring the show \"{}\": not configured", slug);
+            return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response();
+        };
+
+        match parse_show_files(show, &mut files) {
+            Ok(index) => indexes.push((show, index)),
+            Err(message) => return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response(),
+        }
+    }
+
+    let saved_searches = match files.remove(ALERTS) {
+        Some(contents) if state.saved_searches.is_some() => {
+            match serde_json::from_slice::<HashMap<String, SavedSearch>>(&contents) {
+                Ok(searches) => Some(searches),
+                Err(err) => {
+                    let message = format!("Error at parsing {}: {}", ALERTS, err);
+                    return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response();
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let playback = match files.remove(PLAYBACK) {
+        Some(contents) if state.playback.is_some() => {
+            match serde_json::from_slice::<HashMap<String, HashMap<String, PlaybackPosition>>>(
+                &contents,
+            ) {
+                Ok(listeners) => Some(listeners),
+                Err(err) => {
+                    let message = format!("Error at parsing {}: {}", PLAYBACK, err);
+                    return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response();
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let mut summary =
+        serde_json::json!({ "shows": [], "logs": [], "alerts": null, "playback": null });
+
+    for (show, index) in indexes {
+        match install_index(&shows, show, index).await {
+            Ok(installed) => summary["shows"].as_array_mut().unwrap().push(installed),
+            Err(message) => return (StatusCode::INTERNAL_SERVER_ERROR, message).into_response(),
+        }
+    }
+
+    for (name, log) in event_logs(state) {
+        let Some(contents) = files.remove(name) else {
+            continue;
+        };
+
+        if let Err(err) = log.replace(&contents).await {
+            error!("Error at restoring the {} log: {}", name, err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error at restoring the analytics logs",
+            )
+                .into_response();
+        }
+
+        summary["logs"].as_array_mut().unwrap().push(name.into());
+    }
+
+    if let (Some(saved), Some(searches)) = (&state.saved_searches, saved_searches) {
+        let count = searches.len();
+
+        if let Err(err) = saved.replace_all(searches).await {
+            error!("Error at restoring the saved searches: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error at restoring the saved searches",
+            )
+                .into_response();
+        }
+
+        summary["alerts"] = count.into();
+    }
+
+    if let (Some(positions), Some(listeners)) = (&state.playback, playback) {
+        let count = listeners.len();
+
+        if let Err(err) = positions.replace_all(listeners).await {
+            error!("Error at restoring the playback positions: {}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error at restoring the playback positions",
+            )
+                .into_response();
+        }
+
+        summary["playback"] = count.into();
+    }
+
+    info!(
+        created_at = manifest.created_at,
+        version = manifest.version,
+        "Restored a backup"
+    );
+
+    Json(summary).into_response()
+}
+
+// the index files of a show in the backup, as an upload of them
+fn parse_show_files(
+    show: &crate::AppState,
+    files: &mut HashMap<String, Vec<u8>>,
+) -> Result<ParsedIndex, String> {
+    let dir = index_dir(&show.show.slug);
+    let mut take = |name: &str| {
+        files
+            .remove(&format!("{}{}", dir, name))
+            .ok_or_else(|| format!("Error at reading the backup: no {}{}", dir, name))
+    };
+
+    let by_id = take("episodes_by_id.json")?;
+    let by_tag = take("episodes_by_tag.json")?;
+    let transcripts = take("transcripts.json").ok();
+
+    let (episodes_by_id, episodes_by_tag) = parse_index(&by_id, &by_tag)?;
+
+    let mut index_files = vec![
+        (show.show.episodes_by_id.clone(), Bytes::from(by_id)),
+        (show.show.episodes_by_tag.clone(), Bytes::from(by_tag)),
+    ];
+
+    // transcripts only replace the loaded ones where the show has a transcripts file
+    let transcripts = match transcripts {
+        Some(contents) if !show.show.transcripts.is_empty() => {
+            let parsed = serde_json::from_slice::<Transcripts>(&contents)
+                .map_err(|err| format!("Error at parsing {}transcripts.json: {}", dir, err))?;
+            index_files.push((show.show.transcripts.clone(), Bytes::from(contents)));
+            Some(parsed)
+        }
+        _ => None,
+    };
+
+    Ok(ParsedIndex {
+        episodes_by_id,
+        episodes_by_tag,
+        files: index_files,
+        transcripts,
+    })
+}
+
+// index/ in a single show archive, index/<slug>/ otherwise
+fn index_dir(slug: &str) -> String {
+    if slug.is_empty() {
+        String::from("index/")
+    } else {
+        format!("index/{}/", slug)
+    }
+}
+
+// the analytics logs that are configured, by their name in the backup
+fn event_logs(state: &crate::AppState) -> Vec<(&'static str, &Arc<EventLog>)> {
+    [
+        ("analytics/queries.jsonl", &state.query_log),
+        ("analytics/clicks.jsonl", &state.click_log),
+        ("analytics/feedback.jsonl", &state.feedback_log),
+    ]
+    .into_iter()
+    .filter_map(|(name, log)| log.as_ref().map(|log| (name, log)))
+    .collect()
+}
+
+fn backup_error(message: &'static str) -> &'static str {
+    error!("{}", message);
+    message
+}
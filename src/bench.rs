@@ -0,0 +1,127 @@
+use crate::QueryEvent;
+use serde::Serialize;
+use std::time::Duration;
+
+/// A query to replay, with the slug of the show it was searched in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayQuery {
+    /// empty in a single show archive and for plain text logs
+    pub show: String,
+    pub query: String,
+}
+
+/// The queries of a log to replay, either the json lines of `analytics.queries_log`
+/// or one plain query per line, blank lines are skipped
+pub fn parse_replay_log(contents: &str) -> Vec<ReplayQuery> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match serde_json::from_str::<QueryEvent>(line) {
+            Ok(event) => ReplayQuery {
+                show: event.show,
+                query: event.query,
+            },
+            Err(_) => ReplayQuery {
+                show: String::new(),
+                query: line.trim().to_string(),
+            },
+        })
+        .collect()
+}
+
+/// Latencies and throughput of a replay
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BenchReport {
+    pub queries: usize,
+    pub zero_results: usize,
+    pub elapsed_ms: f64,
+    pub queries_per_second: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl BenchReport {
+    /// `searches` holds the latency and the number of results of every query
+    pub fn new(searches: &[(Duration, usize)], elapsed: Duration) -> Self {
+        let mut latencies: Vec<_> = searches.iter().map(|(latency, _)| *latency).collect();
+        latencies.sort();
+
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let seconds = elapsed.as_secs_f64();
+
+        Self {
+            queries: searches.len(),
+            zero_results: searches.iter().filter(|(_, results)| *results == 0).count(),
+            elapsed_ms: ms(elapsed),
+            queries_per_second: if seconds > 0.0 {
+                searches.len() as f64 / seconds
+            } else {
+                0.0
+            },
+            p50_ms: ms(percentile(&latencies, 50.0)),
+            p90_ms: ms(percentile(&latencies, 90.0)),
+            p99_ms: ms(percentile(&latencies, 99.0)),
+            max_ms: ms(latencies.last().copied().unwrap_or_default()),
+        }
+    }
+}
+
+/// Nearest rank percentile of sorted latencies, zero without latencies
+pub fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_replay_log, percentile, BenchReport, ReplayQuery};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_replay_log() {
+        let log = r#"{"timestamp":1680000000,"show":"lup","query":"nixos","results":9,"zero_results":false,"took_ms":0.5}
+  zfs -btrfs
+
+{"timestamp":1680000000,"query":"docker","results":3,"zero_results":false,"took_ms":0.5}"#;
+
+        let query = |show: &str, query: &str| ReplayQuery {
+            show: String::from(show),
+            query: String::from(query),
+        };
+
+        assert_eq!(
+            parse_replay_log(log),
+            vec![
+                query("lup", "nixos"),
+                query("", "zfs -btrfs"),
+                query("", "docker")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bench_report() {
+        let ms = Duration::from_millis;
+        let latencies: Vec<_> = (1..=100).map(ms).collect();
+
+        assert_eq!(percentile(&latencies, 50.0), ms(50));
+        assert_eq!(percentile(&latencies, 99.0), ms(99));
+        assert_eq!(percentile(&latencies[..1], 90.0), ms(1));
+        assert_eq!(percentile(&[], 90.0), Duration::ZERO);
+
+        let searches = [(ms(4), 2), (ms(1), 0), (ms(2), 5), (ms(3), 1)];
+        let report = BenchReport::new(&searches, Duration::from_secs(2));
+
+        assert_eq!(report.queries, 4);
+        assert_eq!(report.zero_results, 1);
+        assert_eq!(report.queries_per_second, 2.0);
+        assert_eq!(report.p50_ms, 2.0);
+        assert_eq!(report.max_ms, 4.0);
+    }
+}
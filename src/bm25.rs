@@ -0,0 +1,175 @@
+use crate::{SearchFields, Symbol};
+use roaring::RoaringBitmap;
+use std::collections::{HashMap, HashSet};
+
+// how quickly the points of a term repeated in a title level off
+const K1: f64 = 1.2;
+// how much the long titles are penalized, 0 ignores the length
+const B: f64 = 0.75;
+
+/// The document frequencies of the words of the titles and of the tags, computed once
+/// per index, BM25 weighs the points of a term by how rare it is: a tag of most episodes
+/// is worth little, a word of a single title a lot
+#[derive(Clone, Debug, Default)]
+pub struct Bm25 {
+    /// episodes of the index, 0 for the episodes scored without an index
+    documents: u32,
+    /// mean number of words of the titles
+    average_title_words: f64,
+    /// the episodes of every word of the titles
+    title_words: HashMap<String, RoaringBitmap>,
+    /// episodes with the tag
+    tags: HashMap<Symbol, u32>,
+}
+
+impl Bm25 {
+    pub fn new(search_fields: &HashMap<usize, SearchFields>) -> Self {
+        let mut bm25 = Self {
+            documents: search_fields.len() as u32,
+            ..Default::default()
+        };
+        let mut title_words = 0;
+
+        for (id, fields) in search_fields.iter() {
+            let Ok(posting) = u32::try_from(*id) else {
+                continue;
+            };
+
+            for word in fields.title.split_whitespace() {
+                bm25.title_words
+                    .entry(word.to_string())
+                    .or_default()
+                    .insert(posting);
+                title_words += 1;
+            }
+
+            for (tag, _) in fields.tags.iter() {
+                *bm25.tags.entry(*tag).or_default() += 1;
+            }
+        }
+
+        if !search_fields.is_empty() {
+            bm25.average_title_words = title_words as f64 / search_fields.len() as f64;
+        }
+
+        bm25
+    }
+
+    /// The weights of the terms of a query, their frequencies are looked up once per query
+    /// rather than once per result
+    pub fn weights(&self, terms: &HashSet<String>) -> TermWeights<'_> {
+        TermWeights {
+            bm25: self,
+            title_idf: terms
+                .iter()
+                .map(|term| (term.clone(), self.idf(self.title_frequency(term))))
+                .collect(),
+        }
+    }
+
+    // the titles with a word containing the term, a phrase is as rare as its rarest word
+    fn title_frequency(&self, term: &str) -> u32 {
+        term.split_whitespace()
+            .map(|part| {
+                self.title_words
+                    .iter()
+                    .filter(|(word, _)| word.contains(part))
+                    .fold(RoaringBitmap::new(), |ids, (_, posting)| ids | posting)
+                    .len() as u32
+            })
+            .min()
+            .unwrap_or_default()
+    }
+
+    // the inverse document frequency of BM25, never negative
+    fn idf(&self, frequency: u32) -> f64 {
+        if self.documents == 0 {
+            return 1.0;
+        }
+
+        let documents = self.documents as f64;
+        let frequency = frequency as f64;
+
+        (1.0 + (documents - frequency + 0.5) / (frequency + 0.5)).ln()
+    }
+}
+
+/// The weights of the points of the terms of a query
+#[derive(Clone, Debug)]
+pub struct TermWeights<'a> {
+    bm25: &'a Bm25,
+    title_idf: HashMap<String, f64>,
+}
+
+impl TermWeights<'_> {
+    /// Weight of a tag of an episode, 1 without an index
+    pub fn tag(&self, tag: Symbol) -> f64 {
+        self.bm25
+            .idf(self.bm25.tags.get(&tag).copied().unwrap_or_default())
+    }
+
+    /// Weight of a term said `count` times in a title of `words` words, 1 without an index
+    pub fn title(&self, term: &str, count: usize, words: usize) -> f64 {
+        let Some(idf) = self.title_idf.get(term) else {
+            return 1.0;
+        };
+
+        if self.bm25.documents == 0 || count == 0 {
+            return 1.0;
+        }
+
+        let count = count as f64;
+        let length = words as f64 / self.bm25.average_title_words.max(1.0);
+        let saturation = count * (K1 + 1.0) / (count + K1 * (1.0 - B + B * length));
+
+        idf * saturation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bm25;
+    use crate::SearchFields;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_bm25() {
+        let fields = |title: &str, tags: &[u32]| SearchFields {
+            title: String::from(title),
+            tags: tags.iter().map(|tag| (*tag, 100)).collect(),
+            ..Default::default()
+        };
+
+        let search_fields = HashMap::from([
+            (1, fields("linux on the steam deck", &[0, 1])),
+            (2, fields("linux gaming", &[0])),
+            (3, fields("the zfs episode", &[0])),
+            (4, fields("zfs on linux, zfs everywhere", &[0, 2])),
+        ]);
+        let bm25 = Bm25::new(&search_fields);
+        let terms = ["steam", "linux", "zfs", "steam deck"]
+            .map(String::from)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let weights = bm25.weights(&terms);
+
+        // the tag of every episode is worth less than a rare one
+        assert!(weights.tag(0) < weights.tag(2));
+        assert!(weights.tag(0) > 0.0);
+
+        // rarer words weigh more, repeating them levels off, longer titles weigh less
+        let steam = weights.title("steam", 1, 5);
+        let linux = weights.title("linux", 1, 5);
+        assert!(steam > linux);
+        assert!(weights.title("zfs", 2, 5) > weights.title("zfs", 1, 5));
+        assert!(weights.title("zfs", 2, 5) < 2.0 * weights.title("zfs", 1, 5));
+        assert!(weights.title("linux", 1, 2) > linux);
+        assert_eq!(weights.title("steam deck", 1, 5), steam);
+
+        // without an index every match is worth its flat points
+        let none = Bm25::default();
+        let weights = none.weights(&terms);
+        assert_eq!(weights.tag(0), 1.0);
+        assert_eq!(weights.title("linux", 1, 5), 1.0);
+    }
+}
@@ -0,0 +1,131 @@
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use std::sync::Arc;
+use tera::Context;
+use unplugged_engine::Favorites;
+
+use crate::error_pages::internal_error_page;
+use crate::{cookie_attributes, AppState};
+
+// a year, favorites are meant to stay
+const FAVORITES_MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// Name of the favorites cookie of a show, every show of an archive has its own
+/// so the stars of the combined results can tell them apart
+pub fn favorites_cookie(state: &AppState) -> String {
+    if state.show.slug.is_empty() {
+        String::from("favorites")
+    } else {
+        format!("favorites-{}", state.show.slug)
+    }
+}
+
+// the favorites of the cookie, none when it's missing or its signature doesn't match
+fn read_favorites(state: &AppState, headers: &HeaderMap) -> Favorites {
+    let name = favorites_cookie(state);
+
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie, _)| *cookie == name)
+        .and_then(|(_, value)| state.favorites.decode(value))
+        .unwrap_or_default()
+}
+
+// readable by scripts, which fill the stars of cached pages from it
+fn set_favorites_cookie(state: &AppState, favorites: &Favorites) -> HeaderValue {
+    let (value, max_age) = if favorites.is_empty() {
+        (String::new(), 0)
+    } else {
+        (state.favorites.encode(favorites), FAVORITES_MAX_AGE)
+    };
+
+    let cookie = format!(
+        "{}={}; {}; Max-Age={}; SameSite=Lax",
+        favorites_cookie(state),
+        value,
+        cookie_attributes(&state.config()),
+        max_age
+    );
+
+    HeaderValue::from_str(&cookie).expect("Error at building the favorites cookie")
+}
+
+// favorites/<id>, POST stars the episode
+pub async fn handle_star(
+    Path(id): Path<usize>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    update_favorites(&state, &headers, id, true)
+}
+
+// favorites/<id>, DELETE unstars the episode
+pub async fn handle_unstar(
+    Path(id): Path<usize>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    update_favorites(&state, &headers, id, false)
+}
+
+fn update_favorites(state: &AppState, headers: &HeaderMap, id: usize, star: bool) -> Response {
+    if !state.index().episodes_by_id.contains_key(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let mut favorites = read_favorites(state, headers);
+
+    if star {
+        favorites.add(id);
+    } else {
+        favorites.remove(id);
+    }
+
+    (
+        StatusCode::NO_CONTENT,
+        [(header::SET_COOKIE, set_favorites_cookie(state, &favorites))],
+    )
+        .into_response()
+}
+
+// favorites, the starred episodes of the cookie, newest first
+pub async fn handle_favorites(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let favorites = read_favorites(&state, &headers);
+    let index = state.index();
+    let episodes: Vec<_> = favorites
+        .ids()
+        .iter()
+        .rev()
+        .filter_map(|id| {
+            let mut episode = serde_json::to_value(index.episodes_by_id.get(id)?).ok()?;
+            episode["slug"] = index.slugs.slug(*id).into();
+            Some(episode)
+        })
+        .collect();
+
+    let context = Context::from_serialize(serde_json::json!({
+        "episodes": episodes,
+        "favorites_cookie": favorites_cookie(&state),
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| state.templates.render("favorites.html", &context)) {
+        Ok(html) => (
+            [
+                (header::CACHE_CONTROL, "private, no-store"),
+                (header::VARY, "Cookie, Accept-Language"),
+            ],
+            Html(html),
+        )
+            .into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
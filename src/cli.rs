@@ -0,0 +1,898 @@
+use axum::body::Bytes;
+use clap::CommandFactory;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use unplugged_engine::{
+    add_related, build_episodes_by_tag, compound_parts, index_changes, index_store, ingest_feed,
+    parse_query, parse_query_strict, parse_replay_log, parse_rss_feed, score_episode,
+    score_indexed_episode, search_index, write_packed_index, ArchiveIndexStore, BenchReport,
+    Config, EpisodesById, EpisodesByTag, IndexStore, Ingest, PackedIndex, ParseResult, QueryParser,
+    ReplayQuery, SearchEngine, ShowConfig, ShowIndex, SortOrder,
+};
+
+use crate::args::{
+    ArchiveArgs, Args, BenchArgs, BuildArgs, Command, DatabaseArgs, ExplainArgs, IndexCommand,
+    OutputFormat, PackArgs, QueryCommand, SearchArgs, ShowArg, SortArg,
+};
+use crate::index_upload::replace_files;
+use crate::static_site::export_site;
+use crate::tui::{run_tui, TuiShows};
+
+// names of the index files written to --out
+const EPISODES_BY_ID_FILE: &str = "episodes_by_id_index.json";
+const EPISODES_BY_TAG_FILE: &str = "episodes_by_tag_index.json";
+// name of the packed index written next to the episodes_by_id file
+const PACKED_INDEX_FILE: &str = "index.upidx";
+
+/// Runs a command other than serve, returns the exit code
+pub async fn run(config: Config, command: Command) -> i32 {
+    let result = match command {
+        Command::Serve(_) | Command::Doctor => unreachable!("run by main"),
+        Command::Search(args) => search(&config, args).await,
+        Command::Index(IndexCommand::Build(args)) => build_index(&config, args).await,
+        Command::Index(IndexCommand::Validate(args)) => check_index(&config, args).await,
+        Command::Index(IndexCommand::Stats(args)) => index_stats(&config, args).await,
+        Command::Index(IndexCommand::Pack(args)) => pack_index(&config, args).await,
+        Command::Index(IndexCommand::Archive(args)) => archive_index(&config, args).await,
+        Command::Index(IndexCommand::Database(args)) => database_index(&config, args).await,
+        Command::Tui(args) => tui(config, args).await,
+        Command::Query(QueryCommand::Explain(args)) => explain_query(&config, args).await,
+        Command::Bench(args) => bench(config, args).await,
+        Command::ExportSite(args) => export_site(&config, args).await,
+        Command::Completions(args) => {
+            let mut command = Args::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(args.shell, &mut command, name, &mut std::io::stdout());
+            Ok(())
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+pub fn select_shows(config: &Config, slug: Option<&str>) -> Result<Vec<ShowConfig>, String> {
+    let shows = config.show_configs();
+
+    let Some(slug) = slug else {
+        return Ok(shows);
+    };
+
+    let selected: Vec<_> = shows.into_iter().filter(|show| show.slug == slug).collect();
+
+    if selected.is_empty() {
+        return Err(format!("Error at finding the show {}", slug));
+    }
+
+    Ok(selected)
+}
+
+// the slug of a show in messages, empty for a single show archive
+fn show_label(show: &ShowConfig) -> String {
+    if show.slug.is_empty() {
+        String::new()
+    } else {
+        format!("{}: ", show.slug)
+    }
+}
+
+// args.rs only depends on clap, the orders are mapped to the ones of the library here
+fn sort_order(sort: SortArg) -> SortOrder {
+    match sort {
+        SortArg::Relevance => SortOrder::Relevance,
+        SortArg::Newest => SortOrder::Newest,
+        SortArg::Oldest => SortOrder::Oldest,
+        SortArg::Longest => SortOrder::Longest,
+        SortArg::Shortest => SortOrder::Shortest,
+    }
+}
+
+async fn search(config: &Config, args: SearchArgs) -> Result<(), String> {
+    // the same mistakes the search box reports
+    parse_query_strict(&args.query).map_err(|err| err.to_string())?;
+
+    let shows = match &args.packed {
+        Some(_) => Vec::new(),
+        None => select_shows(config, args.show.as_deref())?,
+    };
+    let several_shows = shows.len() > 1;
+
+    let mut rows = Vec::new();
+    let mut total = 0;
+
+    // the packed index is decoded and searched like the index files, the same results as the web
+    if let Some(path) = &args.packed {
+        let (by_id, by_tag) = PackedIndex::open(path)?.read_index();
+        let engine = SearchEngine::new(ShowIndex::new(by_id, by_tag, None), config);
+        let results = engine.search_sorted(&args.query, sort_order(args.sort));
+
+        total += results.len();
+        rows.extend(
+            results
+                .into_iter()
+                .take(args.limit)
+                .map(|result| (String::new(), result.score, result.episode.clone())),
+        );
+    }
+
+    for show in shows {
+        let engine = SearchEngine::load(&show, config)
+            .await
+            .map_err(|err| err.to_string())?;
+        let results = engine.search_sorted(&args.query, sort_order(args.sort));
+
+        total += results.len();
+        rows.extend(
+            results
+                .into_iter()
+                .take(args.limit)
+                .map(|result| (show.slug.clone(), result.score, result.episode.clone())),
+        );
+    }
+
+    match args.format {
+        OutputFormat::Json => {
+            let results: Vec<_> = rows
+                .iter()
+                .map(|(show, score, episode)| {
+                    serde_json::json!({ "show": show, "score": score, "episode": episode })
+                })
+                .collect();
+            let json = serde_json::to_string_pretty(&results)
+                .map_err(|err| format!("Error at serializing the results: {}", err))?;
+            println!("{}", json);
+        }
+        OutputFormat::Plain => {
+            for (_, _, episode) in rows.iter() {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    episode.id, episode.date, episode.title, episode.url
+                );
+            }
+        }
+        OutputFormat::Table => {
+            let mut header = vec!["SCORE", "ID", "DATE", "DURATION", "TITLE"];
+
+            if several_shows {
+                header.insert(0, "SHOW");
+            }
+
+            let lines: Vec<Vec<String>> = rows
+                .iter()
+                .map(|(show, score, episode)| {
+                    let mut line = vec![
+                        score.to_string(),
+                        episode.id.to_string(),
+                        episode.date.clone(),
+                        episode.duration.clone(),
+                        episode.title.clone(),
+                    ];
+
+                    if several_shows {
+                        line.insert(0, show.clone());
+                    }
+
+                    line
+                })
+                .collect();
+
+            let header: Vec<_> = header.into_iter().map(String::from).collect();
+            print_table(&header, &lines);
+            println!("\n{} of {} results", rows.len(), total);
+        }
+    }
+
+    Ok(())
+}
+
+// columns padded to their widest cell, the last one is left as is
+fn print_table(header: &[String], lines: &[Vec<String>]) {
+    let mut widths: Vec<_> = header.iter().map(|cell| cell.chars().count()).collect();
+
+    for line in lines {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for line in std::iter::once(header).chain(lines.iter().map(Vec::as_slice)) {
+        let last = line.len().saturating_sub(1);
+        let cells: Vec<_> = line
+            .iter()
+            .enumerate()
+            .map(|(column, cell)| {
+                if column == last {
+                    cell.clone()
+                } else {
+                    format!("{:<width$}", cell, width = widths[column])
+                }
+            })
+            .collect();
+
+        println!("{}", cells.join("  "));
+    }
+}
+
+async fn explain_query(config: &Config, args: ExplainArgs) -> Result<(), String> {
+    let mut parser = QueryParser::new(&args.query);
+    let tokens: Vec<_> = std::iter::from_fn(|| parser.get_token())
+        .map(|token| format!("{:?}", token))
+        .collect();
+    println!("tokens    {}", tokens.join(" "));
+
+    let parsed = parse_query(&args.query);
+    let scoped: Vec<_> = parsed
+        .scoped_terms()
+        .into_iter()
+        .filter_map(|(field, value)| Some(format!("{}{:?}", field?.prefix(), value)))
+        .collect();
+
+    let ParseResult { terms, exclude } = parsed;
+    println!("terms     {:?}", terms);
+    println!("exclude   {:?}", exclude);
+
+    if !scoped.is_empty() {
+        println!("fields    {}", scoped.join(", "));
+    }
+
+    if let Err(err) = parse_query_strict(&args.query) {
+        println!("warning   {}", err);
+    }
+
+    // what the search does to the terms before matching them
+    let common_words = config.analyzer.stopwords();
+    let mut rewrites = Vec::new();
+    let mut searched: Vec<String> = Vec::new();
+
+    for term in terms.iter() {
+        let lowercase = term.to_lowercase();
+
+        if lowercase != *term {
+            rewrites.push(format!("{:?} lowercased to {:?}", term, lowercase));
+        }
+
+        let parts = compound_parts(&lowercase);
+
+        if !parts.is_empty() {
+            rewrites.push(format!("{:?} also searched as {:?}", lowercase, parts));
+        }
+
+        for word in std::iter::once(lowercase.as_str()).chain(parts) {
+            let word = word.to_string();
+
+            if common_words.contains(&word) {
+                rewrites.push(format!("{:?} dropped, it's a common word", word));
+            } else if searched.contains(&word) {
+                rewrites.push(format!("{:?} dropped, it's repeated", word));
+            } else {
+                searched.push(word);
+            }
+        }
+    }
+
+    if rewrites.is_empty() {
+        rewrites.push(String::from("none"));
+    }
+
+    println!("rewrites  {}", rewrites.join(", "));
+    println!("searched  {:?}", searched);
+    println!(
+        "scoring   {} per matching tag, {} per term in the title",
+        config.scoring.tag, config.scoring.title
+    );
+
+    for show in select_shows(config, args.show.as_deref())? {
+        let index = ShowIndex::load(&show).await?;
+        let results = search_index(&index, &args.query, &common_words, &config.scoring, || {
+            false
+        });
+
+        println!(
+            "\n{}{} of {} results",
+            show_label(&show),
+            results.episodes.len().min(args.limit),
+            results.episodes.len()
+        );
+
+        for (score, episode) in results.episodes.iter().take(args.limit) {
+            println!("{:>5}  {}", score, episode.title);
+
+            let breakdown = usize::try_from(episode.id)
+                .ok()
+                .and_then(|id| score_indexed_episode(&index, id, &results.terms, &config.scoring))
+                .unwrap_or_else(|| score_episode(episode, &results.terms, &config.scoring));
+            let fields = [
+                ("tag", &breakdown.tags),
+                ("title", &breakdown.title),
+                ("chapter", &breakdown.chapters),
+                ("notes", &breakdown.description),
+                ("said", &breakdown.transcript),
+            ];
+
+            for (field, matches) in fields {
+                for (matched, points) in matches {
+                    println!("       {:<7} {:<24} +{}", field, matched, points);
+                }
+            }
+
+            // episodes found by id score nothing
+            if breakdown.total == 0 {
+                println!("       id      {:<24} +0", episode.id);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn bench(config: Config, args: BenchArgs) -> Result<(), String> {
+    let log = match &args.log {
+        Some(log) => log.clone(),
+        None if !config.analytics.queries_log.is_empty() => {
+            PathBuf::from(&config.analytics.queries_log)
+        }
+        None => {
+            return Err(String::from(
+                "Error at finding the queries, pass --log or set analytics.queries_log",
+            ))
+        }
+    };
+
+    let contents = tokio::fs::read_to_string(&log)
+        .await
+        .map_err(|err| format!("Error at reading {}: {}", log.display(), err))?;
+
+    let queries = parse_replay_log(&contents);
+
+    if queries.is_empty() {
+        return Err(format!("Error at reading {}: no queries", log.display()));
+    }
+
+    let mut indices = HashMap::new();
+
+    for show in config.show_configs() {
+        let index = ShowIndex::load(&show).await?;
+        indices.insert(show.slug, index);
+    }
+
+    // queries of removed shows, and of a log recorded before the archive had
+    // several shows, go to the first show
+    let first = config.show_configs()[0].slug.clone();
+    let queries: Vec<_> = std::iter::repeat_n(queries, args.repeat.max(1))
+        .flatten()
+        .map(|query| {
+            if indices.contains_key(&query.show) {
+                query
+            } else {
+                ReplayQuery {
+                    show: first.clone(),
+                    ..query
+                }
+            }
+        })
+        .collect();
+
+    eprintln!(
+        "Replaying {} queries with {} workers",
+        queries.len(),
+        args.concurrency.max(1)
+    );
+
+    let indices = Arc::new(indices);
+    let queries = Arc::new(queries);
+    let next = Arc::new(AtomicUsize::new(0));
+    let common_words = Arc::new(config.analyzer.stopwords());
+    let scoring = Arc::new(config.scoring.clone());
+    let started = Instant::now();
+
+    // every worker takes the next query until none are left
+    let workers: Vec<_> = (0..args.concurrency.max(1))
+        .map(|_| {
+            let (indices, queries, next) = (indices.clone(), queries.clone(), next.clone());
+            let (common_words, scoring) = (common_words.clone(), scoring.clone());
+
+            tokio::task::spawn_blocking(move || {
+                let mut searches = Vec::new();
+
+                while let Some(query) = queries.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let start = Instant::now();
+                    let results = search_index(
+                        &indices[&query.show],
+                        &query.query,
+                        &common_words,
+                        &scoring,
+                        || false,
+                    );
+                    searches.push((start.elapsed(), results.episodes.len()));
+                }
+
+                searches
+            })
+        })
+        .collect();
+
+    let mut searches = Vec::new();
+
+    for worker in workers {
+        searches.extend(
+            worker
+                .await
+                .map_err(|err| format!("Error at replaying the queries: {}", err))?,
+        );
+    }
+
+    let report = BenchReport::new(&searches, started.elapsed());
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|err| format!("Error at serializing the report: {}", err))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    println!(
+        "queries      {} ({} without results)",
+        report.queries, report.zero_results
+    );
+    println!("elapsed      {:.1} ms", report.elapsed_ms);
+    println!("throughput   {:.0} queries/s", report.queries_per_second);
+    println!(
+        "latency      p50 {:.3} ms, p90 {:.3} ms, p99 {:.3} ms, max {:.3} ms",
+        report.p50_ms, report.p90_ms, report.p99_ms, report.max_ms
+    );
+
+    Ok(())
+}
+
+async fn tui(config: Config, args: ShowArg) -> Result<(), String> {
+    let mut shows = Vec::new();
+
+    for show in select_shows(&config, args.show.as_deref())? {
+        let index = ShowIndex::load(&show).await?;
+        shows.push((show, index));
+    }
+
+    let shows = TuiShows {
+        shows,
+        stopwords: config.analyzer.stopwords(),
+        scoring: config.scoring,
+    };
+
+    // the terminal is read and drawn in a blocking loop
+    tokio::task::spawn_blocking(move || run_tui(shows))
+        .await
+        .map_err(|err| format!("Error at running the tui: {}", err))?
+        .map_err(|err| format!("Error at running the tui: {}", err))
+}
+
+async fn build_index(config: &Config, args: BuildArgs) -> Result<(), String> {
+    if let Some(feed) = &args.feed {
+        return build_index_from_feed(config, feed, &args).await;
+    }
+
+    for show in select_shows(config, args.show.as_deref())? {
+        // a database is rebuilt in place from its own episodes
+        if !show.database.is_empty() {
+            let store = index_store(&show)?;
+            let (mut by_id, _) = store.read()?;
+
+            let by_tag = build_episodes_by_tag(&by_id);
+            add_related(&mut by_id, &by_tag);
+            store.replace(&by_id, &by_tag)?;
+
+            println!(
+                "{}wrote {} ({} episodes and {} tags)",
+                show_label(&show),
+                show.database,
+                by_id.len(),
+                by_tag.len()
+            );
+            continue;
+        }
+
+        let contents = tokio::fs::read_to_string(&show.episodes_by_id)
+            .await
+            .map_err(|err| format!("Error at reading {}: {}", show.episodes_by_id, err))?;
+        let mut by_id: EpisodesById = serde_json::from_str(&contents)
+            .map_err(|err| format!("Error at parsing {}: {}", show.episodes_by_id, err))?;
+
+        let by_tag = build_episodes_by_tag(&by_id);
+        add_related(&mut by_id, &by_tag);
+
+        // sorted keys, so rebuilding an unchanged index leaves the files unchanged
+        let by_id: BTreeMap<_, _> = by_id.into_iter().collect();
+        let by_tag: BTreeMap<_, _> = by_tag.into_iter().collect();
+
+        let to_json = |value: serde_json::Result<String>| {
+            value
+                .map(Bytes::from)
+                .map_err(|err| format!("Error at serializing the index: {}", err))
+        };
+        let by_id_json = to_json(serde_json::to_string_pretty(&by_id))?;
+        let by_tag_json = to_json(serde_json::to_string_pretty(&by_tag))?;
+
+        replace_files(&[
+            (&show.episodes_by_id, by_id_json),
+            (&show.episodes_by_tag, by_tag_json),
+        ])
+        .await
+        .map_err(|err| format!("Error at writing the index files: {}", err))?;
+
+        println!(
+            "{}wrote {} ({} episodes and their related episodes)",
+            show_label(&show),
+            show.episodes_by_id,
+            by_id.len()
+        );
+        println!(
+            "{}wrote {} ({} tags)",
+            show_label(&show),
+            show.episodes_by_tag,
+            by_tag.len()
+        );
+    }
+
+    Ok(())
+}
+
+async fn build_index_from_feed(
+    config: &Config,
+    feed: &str,
+    args: &BuildArgs,
+) -> Result<(), String> {
+    let show = match &args.out {
+        Some(dir) => {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|err| format!("Error at creating {}: {}", dir.display(), err))?;
+
+            let file = |name: &str| dir.join(name).to_string_lossy().into_owned();
+            ShowConfig {
+                episodes_by_id: file(EPISODES_BY_ID_FILE),
+                episodes_by_tag: file(EPISODES_BY_TAG_FILE),
+                ..Default::default()
+            }
+        }
+        None => {
+            let shows = select_shows(config, args.show.as_deref())?;
+
+            let [show] = shows.as_slice() else {
+                return Err(String::from(
+                    "Error at picking the show, the archive has several shows, pick one with --show",
+                ));
+            };
+
+            // the json files or the database, the archive is rewritten by `index archive`
+            ShowConfig {
+                archive: String::new(),
+                ..show.clone()
+            }
+        }
+    };
+    let store = index_store(&show)?;
+    let target = show.index_files().join(" and ");
+
+    // the episodes of the current index missing from the feed are kept with --update
+    let (previous_by_id, previous_by_tag) = if args.update {
+        store.read()?
+    } else {
+        (EpisodesById::new(), EpisodesByTag::new())
+    };
+
+    let xml = read_feed(feed).await?;
+    let feed = parse_rss_feed(&xml)?;
+
+    eprintln!("Parsed {} episodes", feed.episodes.len());
+
+    let Ingest {
+        episodes_by_id: mut by_id,
+        episodes_by_tag: by_tag,
+        added,
+        skipped,
+        duplicates,
+    } = ingest_feed(feed, &previous_by_id)?;
+
+    for title in skipped {
+        eprintln!("Skipped \"{}\", it has no episode number", title);
+    }
+
+    for title in duplicates {
+        eprintln!("Skipped a second version of an episode: {}", title);
+    }
+
+    if args.update {
+        eprintln!("Added {} episodes", added.len());
+    }
+
+    if let Some(path) = &args.people {
+        add_people(&mut by_id, path).await?;
+    }
+
+    // an update only writes the episodes and tags that changed, in place in a database
+    if args.update {
+        let (by_id, by_tag) = index_changes(&previous_by_id, &previous_by_tag, &by_id, &by_tag);
+        store.upsert(&by_id, &by_tag)?;
+
+        println!(
+            "updated {} ({} episodes and {} tags changed)",
+            target,
+            by_id.len(),
+            by_tag.len()
+        );
+    } else {
+        store.replace(&by_id, &by_tag)?;
+
+        println!(
+            "wrote {} ({} episodes and {} tags)",
+            target,
+            by_id.len(),
+            by_tag.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct EpisodePeople {
+    guests: Option<Vec<String>>,
+    hosts: Option<Vec<String>>,
+}
+
+// the mapping file wins over the podcast:person tags of the feed
+async fn add_people(by_id: &mut EpisodesById, path: &Path) -> Result<(), String> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|err| format!("Error at reading {}: {}", path.display(), err))?;
+    let people: HashMap<usize, EpisodePeople> = serde_json::from_str(&contents)
+        .map_err(|err| format!("Error at parsing {}: {}", path.display(), err))?;
+
+    for (id, people) in people {
+        let Some(episode) = by_id.get_mut(&id) else {
+            eprintln!("Skipped the people of episode {}, it isn't in the feed", id);
+            continue;
+        };
+
+        if let Some(guests) = people.guests {
+            episode.guests = guests;
+        }
+
+        if let Some(hosts) = people.hosts {
+            episode.hosts = hosts;
+        }
+    }
+
+    Ok(())
+}
+
+// downloads the feed showing how much arrived so far, a path is read as is
+async fn read_feed(feed: &str) -> Result<String, String> {
+    if !feed.starts_with("http://") && !feed.starts_with("https://") {
+        eprintln!("Reading {}", feed);
+
+        return tokio::fs::read_to_string(feed)
+            .await
+            .map_err(|err| format!("Error at reading {}: {}", feed, err));
+    }
+
+    eprintln!("Downloading {}", feed);
+
+    let mut response = reqwest::get(feed)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Error at downloading the feed: {}", err))?;
+
+    let mut body = Vec::new();
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|err| format!("Error at downloading the feed: {}", err))?
+    {
+        body.extend_from_slice(&chunk);
+        eprint!("\r{} KiB", body.len() / 1024);
+    }
+
+    eprintln!();
+
+    String::from_utf8(body).map_err(|err| format!("Error at reading the feed: {}", err))
+}
+
+async fn check_index(config: &Config, args: ShowArg) -> Result<(), String> {
+    let mut failed = false;
+
+    for show in select_shows(config, args.show.as_deref())? {
+        match ShowIndex::load(&show).await {
+            Ok(index) => println!(
+                "{}ok, {} episodes and {} tags",
+                show_label(&show),
+                index.episodes_by_id.len(),
+                index.episodes_by_tag.len()
+            ),
+            Err(err) => {
+                eprintln!("{}{}", show_label(&show), err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        return Err(String::from("Error at validating the index"));
+    }
+
+    Ok(())
+}
+
+async fn index_stats(config: &Config, args: ShowArg) -> Result<(), String> {
+    for show in select_shows(config, args.show.as_deref())? {
+        let index = ShowIndex::load(&show).await?;
+        let stats = &index.stats;
+        let unknown = || String::from("-");
+
+        println!("{}{}", show_label(&show), show.name);
+        println!("  episodes          {}", stats.episodes);
+        println!("  tags              {}", stats.tags);
+        println!(
+            "  first episode     {}",
+            stats.first_date.clone().unwrap_or_else(unknown)
+        );
+        println!(
+            "  last episode      {}",
+            stats.last_date.clone().unwrap_or_else(unknown)
+        );
+        println!(
+            "  average duration  {}",
+            stats.average_duration.clone().unwrap_or_else(unknown)
+        );
+        println!(
+            "  audio             {} of {} episodes, {}",
+            stats.audio_episodes,
+            stats.episodes,
+            stats
+                .audio_bytes
+                .map(|bytes| format!("{:.1} GiB", bytes as f64 / (1024.0 * 1024.0 * 1024.0)))
+                .unwrap_or_else(unknown)
+        );
+
+        let top_tags: Vec<_> = stats
+            .top_tags
+            .iter()
+            .map(|tag| format!("{} ({})", tag.tag, tag.episodes))
+            .collect();
+        println!("  top tags          {}", top_tags.join(", "));
+    }
+
+    Ok(())
+}
+
+async fn archive_index(config: &Config, args: ArchiveArgs) -> Result<(), String> {
+    let shows = select_shows(config, args.show.as_deref())?;
+
+    if args.out.is_some() && shows.len() > 1 {
+        return Err(String::from(
+            "Error at archiving the index: --out needs a single show, pick it with --show",
+        ));
+    }
+
+    for mut show in shows {
+        let path = match (&args.out, show.archive.as_str()) {
+            (Some(out), _) => out.clone(),
+            (None, "") => {
+                return Err(format!(
+                    "Error at archiving the index: {}no archive path, set `archive` or pass --out",
+                    show_label(&show)
+                ))
+            }
+            (None, archive) => PathBuf::from(archive),
+        };
+
+        // always from the json files, not the archive being replaced
+        show.archive = String::new();
+        let index = ShowIndex::load(&show).await?;
+
+        ArchiveIndexStore::new(&path).replace(&index.episodes_by_id, &index.episodes_by_tag)?;
+
+        let bytes = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        println!(
+            "{}wrote {} ({} KiB, {} episodes and {} tags)",
+            show_label(&show),
+            path.display(),
+            bytes / 1024,
+            index.episodes_by_id.len(),
+            index.episodes_by_tag.len()
+        );
+    }
+
+    Ok(())
+}
+
+async fn database_index(config: &Config, args: DatabaseArgs) -> Result<(), String> {
+    let shows = select_shows(config, args.show.as_deref())?;
+
+    if args.out.is_some() && shows.len() > 1 {
+        return Err(String::from(
+            "Error at writing the index database: --out needs a single show, pick it with --show",
+        ));
+    }
+
+    for show in shows {
+        let path = match (&args.out, show.database.as_str()) {
+            (Some(out), _) => out.to_string_lossy().into_owned(),
+            (None, "") => {
+                return Err(format!(
+                    "Error at writing the index database: {}no database path, set `database` or pass --out",
+                    show_label(&show)
+                ))
+            }
+            (None, database) => database.to_string(),
+        };
+
+        // always from the json files, not the database being replaced
+        let files = ShowConfig {
+            archive: String::new(),
+            database: String::new(),
+            ..show.clone()
+        };
+        let index = ShowIndex::load(&files).await?;
+
+        let database = ShowConfig {
+            database: path.clone(),
+            ..show.clone()
+        };
+        index_store(&database)?.replace(&index.episodes_by_id, &index.episodes_by_tag)?;
+
+        let bytes = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        println!(
+            "{}wrote {} ({} KiB, {} episodes and {} tags)",
+            show_label(&show),
+            path,
+            bytes / 1024,
+            index.episodes_by_id.len(),
+            index.episodes_by_tag.len()
+        );
+    }
+
+    Ok(())
+}
+
+async fn pack_index(config: &Config, args: PackArgs) -> Result<(), String> {
+    let shows = select_shows(config, args.show.as_deref())?;
+
+    if args.out.is_some() && shows.len() > 1 {
+        return Err(String::from(
+            "Error at packing the index: --out needs a single show, pick it with --show",
+        ));
+    }
+
+    for show in shows {
+        let index = ShowIndex::load(&show).await?;
+
+        let path = args.out.clone().unwrap_or_else(|| {
+            let by_id = PathBuf::from(&show.episodes_by_id);
+            by_id.with_file_name(PACKED_INDEX_FILE)
+        });
+
+        write_packed_index(&index.episodes_by_id, &index.episodes_by_tag, &path)?;
+
+        let bytes = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        println!(
+            "{}wrote {} ({} KiB, {} episodes and {} tags)",
+            show_label(&show),
+            path.display(),
+            bytes / 1024,
+            index.episodes_by_id.len(),
+            index.episodes_by_tag.len()
+        );
+    }
+
+    Ok(())
+}
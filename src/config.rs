@@ -0,0 +1,1253 @@
+use crate::{parse_stopwords, stopwords, ANALYZER_LANGUAGES};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the environment variables overriding config keys,
+/// `rate_limit.per_second` is overridden by `UNPLUGGED_RATE_LIMIT_PER_SECOND`
+pub const ENV_PREFIX: &str = "UNPLUGGED_";
+
+const DEFAULT_SHOW_NAME: &str = "Linux Unplugged";
+
+// shorter api keys are easy to guess
+const API_KEY_MIN_LEN: usize = 16;
+
+// file names in a certbot live directory
+const TLS_DIR_CERT: &str = "fullchain.pem";
+const TLS_DIR_KEY: &str = "privkey.pem";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub tls: TlsConfig,
+    pub paths: PathsConfig,
+    pub podcast: PodcastConfig,
+    pub scoring: ScoringConfig,
+    pub analyzer: AnalyzerConfig,
+    pub cache: CacheConfig,
+    pub rate_limit: RateLimitConfig,
+    pub logging: LoggingConfig,
+    pub discord: DiscordConfig,
+    pub matrix: MatrixConfig,
+    pub analytics: AnalyticsConfig,
+    pub admin: AdminConfig,
+    pub alerts: AlertsConfig,
+    pub playback: PlaybackConfig,
+    pub digests: DigestsConfig,
+    pub favorites: FavoritesConfig,
+    pub api: ApiConfig,
+    pub timeouts: TimeoutsConfig,
+    pub telemetry: TelemetryConfig,
+    pub staleness: StalenessConfig,
+    /// shows served under their own prefix, the episodes of `paths` are served
+    /// at the root when empty
+    pub shows: Vec<ShowConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: String,
+    /// url prefix when served from a subdirectory behind a reverse proxy, e.g. "/search"
+    pub base_path: String,
+    /// absolute url of the instance (including the base path) used in feeds,
+    /// sitemaps and OpenSearch, derived from the Host header when empty
+    pub public_url: String,
+    /// requests handled at once, the ones over it are answered with a 503, 0 for no cap
+    pub max_concurrent_requests: usize,
+}
+
+/// Serves https directly when set, either with `cert` and `key`
+/// or with a `dir` holding `fullchain.pem` and `privkey.pem`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// pem encoded certificate chain
+    pub cert: String,
+    /// pem encoded private key
+    pub key: String,
+    /// certificates directory (e.g. /etc/letsencrypt/live/<domain>),
+    /// reloaded when the files change on renewal
+    pub dir: String,
+}
+
+impl TlsConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.dir.is_empty() || !self.cert.is_empty()
+    }
+
+    /// Certificate and key paths, `None` when tls is disabled
+    pub fn files(&self) -> Option<(PathBuf, PathBuf)> {
+        if !self.dir.is_empty() {
+            let dir = Path::new(&self.dir);
+            return Some((dir.join(TLS_DIR_CERT), dir.join(TLS_DIR_KEY)));
+        }
+
+        if !self.cert.is_empty() {
+            return Some((PathBuf::from(&self.cert), PathBuf::from(&self.key)));
+        }
+
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    pub episodes_by_id: String,
+    pub episodes_by_tag: String,
+    /// rkyv archive of both index files (`index archive`), loaded instead of them when set
+    pub archive: String,
+    /// sqlite database of the index (`index database`), loaded instead of the files when set
+    /// and updated in place by `index build --feed --update`
+    pub database: String,
+    /// transcripts of the episodes by id, none when empty
+    pub transcripts: String,
+    /// templates overriding the ones bundled in the binary file by file
+    pub templates: String,
+    /// static files overriding the ones bundled in the binary file by file
+    pub static_dir: String,
+    /// reloads the index of a show when its files change, always on with --dev
+    pub watch: bool,
+}
+
+/// A show of a combined archive, served under `/<slug>`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShowConfig {
+    pub slug: String,
+    pub name: String,
+    pub episodes_by_id: String,
+    pub episodes_by_tag: String,
+    /// rkyv archive of both index files, loaded instead of them when set
+    pub archive: String,
+    /// sqlite database of the index, loaded instead of the files when set
+    pub database: String,
+    /// transcripts of the episodes by id, none when empty
+    pub transcripts: String,
+    pub podcast: PodcastConfig,
+}
+
+impl ShowConfig {
+    /// The files the index of the show is loaded from, its archive, its database
+    /// or both json files
+    pub fn index_files(&self) -> Vec<&str> {
+        if !self.archive.is_empty() {
+            vec![&self.archive]
+        } else if !self.database.is_empty() {
+            vec![&self.database]
+        } else {
+            vec![&self.episodes_by_id, &self.episodes_by_tag]
+        }
+    }
+}
+
+/// Where the show is listened to, for the "open in app" links of the results
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PodcastConfig {
+    /// rss feed of the podcast
+    pub feed_url: String,
+    /// the number of the podcasts.apple.com/.../id<number> url of the podcast
+    pub apple_podcasts_id: String,
+    /// square cover of the podcast, the thumbnail of the oEmbed cards
+    pub artwork_url: String,
+    /// width and height of the cover in pixels
+    pub artwork_size: u32,
+}
+
+impl Default for PodcastConfig {
+    fn default() -> Self {
+        Self {
+            feed_url: String::new(),
+            apple_podcasts_id: String::new(),
+            artwork_url: String::new(),
+            artwork_size: 1400,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringConfig {
+    /// "bm25" weighs the points of the tags and titles by how rare the terms are in the index,
+    /// "flat" gives every match the same points
+    pub model: String,
+    /// score per tag matching a search term
+    pub tag: i32,
+    /// score per search term found in the title
+    pub title: i32,
+    /// score per search term found in the chapter titles
+    pub chapter: i32,
+    /// score per search term found in the show notes
+    pub description: i32,
+    /// score per search term said in the transcript
+    pub transcript: i32,
+    /// percent of the points of a misspelled term matched with the closest words of the index,
+    /// "kuberntes" with "kubernetes", 0 turns off the typo tolerance
+    pub fuzzy: i32,
+    /// percent of the points of an inflected term matched by its stem where the term itself
+    /// isn't found, "containers" with the tag "container", 0 turns off the stemming
+    pub stem: i32,
+}
+
+impl ScoringConfig {
+    /// Whether the points are weighed by the document frequencies of the terms
+    pub fn is_bm25(&self) -> bool {
+        self.model == "bm25"
+    }
+}
+
+/// How the terms of the queries are analyzed, the language of the archive
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyzerConfig {
+    /// code of the language whose stopwords are dropped from the queries, "en", "es" or "de"
+    pub language: String,
+    /// words dropped on top of the bundled ones, e.g. the name of the show
+    pub stopwords: Vec<String>,
+    /// file of stopwords, one per line with `#` comments, replacing the bundled ones of
+    /// `language`, they're used when it's empty or can't be read
+    pub stopwords_file: String,
+}
+
+impl AnalyzerConfig {
+    /// The words the search ignores
+    pub fn stopwords(&self) -> HashSet<String> {
+        let file = match self.stopwords_file.as_str() {
+            "" => None,
+            path => std::fs::read_to_string(path).ok(),
+        };
+
+        match file {
+            Some(contents) => parse_stopwords(&contents)
+                .into_iter()
+                .chain(stopwords("", &self.stopwords))
+                .collect(),
+            None => stopwords(&self.language, &self.stopwords),
+        }
+    }
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            language: String::from("en"),
+            stopwords: Vec::new(),
+            stopwords_file: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// rendered results pages kept in memory
+    pub pages: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub per_second: u32,
+    pub burst: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// "text" or "json"
+    pub format: String,
+    /// file the server logs to instead of stderr, empty logs to stderr
+    pub file: String,
+    /// the log file starts a new file past this size
+    pub file_max_bytes: u64,
+    /// rotated log files kept, `<file>.1` being the newest
+    pub file_keep: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    /// hex encoded public key of the Discord application, empty disables the endpoint
+    pub public_key: String,
+}
+
+/// Bot answering `!search <query>` in Matrix rooms with the top results of the first show
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatrixConfig {
+    /// url of the homeserver of the bot account, empty disables the bot
+    pub homeserver: String,
+    /// the bot account, e.g. "@unplugged:example.org"
+    pub user: String,
+    /// logs in with the password when empty, which adds a device on every start
+    pub access_token: String,
+    pub password: String,
+    /// ids of the rooms the bot answers in, joined at startup
+    pub rooms: Vec<String>,
+}
+
+impl MatrixConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.homeserver.is_empty()
+    }
+}
+
+/// Searches, clicks on search results and relevance feedback,
+/// recorded only when their log is set
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    /// json lines file of every search (normalized query, results, latency), empty disables it
+    pub queries_log: String,
+    /// the queries log starts a new file past this size
+    pub queries_log_max_bytes: u64,
+    /// rotated queries logs kept, `<queries_log>.1` being the newest
+    pub queries_log_keep: usize,
+    /// append-only json lines file of the clicks, empty disables /track/click
+    pub clicks_log: String,
+    /// append-only json lines file of the relevant/irrelevant votes, empty disables /feedback
+    pub feedback_log: String,
+    /// also record the ip of the client, off so the log can't identify anyone
+    pub store_ip: bool,
+    /// most searched queries of the queries log suggested on the homepage, 0 disables them
+    pub popular_queries: usize,
+    /// days of the queries log the popular queries are counted over
+    pub popular_days: i64,
+    /// searches a query needs to be suggested, so a single person can't put one on the homepage
+    pub popular_min_searches: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    /// password of the /admin pages (any user name), empty disables them
+    pub token: String,
+}
+
+/// Saved searches with a feed of the episodes matching them after they were saved
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// json file of the saved searches, empty disables /alerts
+    pub file: String,
+    /// saved searches kept at most
+    pub max: usize,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            file: String::new(),
+            max: 10_000,
+        }
+    }
+}
+
+/// Listening progress of the episode pages' player, kept by an anonymous token
+/// the listener can share between devices
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaybackConfig {
+    /// json file of the playback positions, empty disables /playback
+    pub file: String,
+    /// listeners kept at most
+    pub max_listeners: usize,
+}
+
+impl PlaybackConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.file.is_empty()
+    }
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            file: String::new(),
+            max_listeners: 10_000,
+        }
+    }
+}
+
+/// Keys of the json apis (`/api/*`), which stay open to everyone unless `require_key` is set
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// rejects the requests without a valid key instead of rate limiting them by ip
+    pub require_key: bool,
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+/// A key sent as `Authorization: Bearer <key>`, `X-API-Key` or `?api_key=`,
+/// its requests are rate limited by key rather than by ip
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// who the key belongs to, shown in the usage report of /admin/queries
+    pub name: String,
+    pub key: String,
+    pub per_second: u32,
+    pub burst: u32,
+    /// requests per day (UTC), 0 for no quota
+    pub daily_quota: u64,
+}
+
+impl Default for ApiKeyConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            key: String::new(),
+            per_second: 10,
+            burst: 20,
+            daily_quota: 0,
+        }
+    }
+}
+
+/// Starred episodes kept in a signed cookie, no accounts involved
+/// Email digests of the saved searches, sent over smtp
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigestsConfig {
+    /// smtp server, empty disables the digests
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    /// "starttls", "tls" or "none"
+    pub smtp_security: String,
+    pub smtp_username: String,
+    pub smtp_password: String,
+    /// sender of the digests, e.g. "Unplugged search <alerts@example.com>"
+    pub from: String,
+}
+
+impl DigestsConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.smtp_host.is_empty()
+    }
+}
+
+impl Default for DigestsConfig {
+    fn default() -> Self {
+        Self {
+            smtp_host: String::new(),
+            smtp_port: 587,
+            smtp_security: String::from("starttls"),
+            smtp_username: String::new(),
+            smtp_password: String::new(),
+            from: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FavoritesConfig {
+    /// key signing the favorites cookie, a random one is generated at startup
+    /// when empty (the favorites are then lost on restarts)
+    pub secret: String,
+}
+
+/// Time a request may take before it's answered with a 503, searches return the
+/// episodes found so far when they use up most of it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    pub default_ms: u64,
+    /// /suggest, typed as you go so a late answer is useless anyway
+    pub suggest_ms: u64,
+    /// /search/export, which renders every matching episode
+    pub export_ms: u64,
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            default_ms: 5_000,
+            suggest_ms: 500,
+            export_ms: 30_000,
+        }
+    }
+}
+
+/// Aggregate counters of the searches (volume, zero result rate, latency percentiles)
+/// posted to an endpoint, opt-in and without any query
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// url the json reports are posted to, empty disables the telemetry
+    pub endpoint: String,
+    /// minutes between two reports
+    pub interval_minutes: u64,
+}
+
+impl TelemetryConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.endpoint.is_empty()
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            interval_minutes: 60,
+        }
+    }
+}
+
+/// Flags the archive as out of date (a banner on the pages, `/readyz` and `/metrics`)
+/// when the newest episode of a show is older than `max_days`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct StalenessConfig {
+    /// days without a new episode, 0 never flags the archive
+    pub max_days: u32,
+}
+
+impl StalenessConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.max_days > 0
+    }
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            queries_log: String::new(),
+            queries_log_max_bytes: 10 * 1024 * 1024,
+            queries_log_keep: 5,
+            clicks_log: String::new(),
+            feedback_log: String::new(),
+            store_ip: false,
+            popular_queries: 5,
+            popular_days: 7,
+            popular_min_searches: 3,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: String::from("127.0.0.1:3000"),
+            base_path: String::new(),
+            public_url: String::new(),
+            max_concurrent_requests: 256,
+        }
+    }
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            episodes_by_id: String::from("episodes_by_id_index.json"),
+            episodes_by_tag: String::from("episodes_by_tag_index.json"),
+            archive: String::new(),
+            database: String::new(),
+            transcripts: String::new(),
+            templates: String::new(),
+            static_dir: String::new(),
+            watch: false,
+        }
+    }
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            model: String::from("bm25"),
+            tag: 50,
+            title: 100,
+            chapter: 25,
+            description: 15,
+            transcript: 10,
+            fuzzy: 50,
+            stem: 100,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { pages: 256 }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_second: 5,
+            burst: 20,
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: String::from("text"),
+            file: String::new(),
+            file_max_bytes: 10 * 1024 * 1024,
+            file_keep: 5,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: String,
+        message: String,
+    },
+    Parse {
+        message: String,
+    },
+    /// `key` is the dotted path of the offending key, e.g. "rate_limit.burst"
+    Invalid {
+        key: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, message } => {
+                write!(f, "Error at reading config file {}: {}", path, message)
+            }
+            ConfigError::Parse { message } => write!(f, "Error at parsing config: {}", message),
+            ConfigError::Invalid { key, message } => {
+                write!(f, "Invalid config key `{}`: {}", key, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Sections applied by a reload (SIGHUP or /admin/reload), the others are only read at start
+pub const RELOADABLE_SECTIONS: [&str; 6] = [
+    "scoring",
+    "analyzer",
+    "rate_limit",
+    "timeouts",
+    "staleness",
+    "admin",
+];
+
+/// The config to run with after reading the file again
+#[derive(Clone, Debug)]
+pub struct ConfigReload {
+    /// the new file, with the sections only read at start kept as they were
+    pub config: Config,
+    /// reloadable sections that changed
+    pub applied: Vec<String>,
+    /// sections that changed but are only read at start
+    pub restart_required: Vec<String>,
+}
+
+fn config_sections(config: &Config) -> serde_json::Map<String, serde_json::Value> {
+    match serde_json::to_value(config) {
+        Ok(serde_json::Value::Object(sections)) => sections,
+        _ => unreachable!("the config serializes to a table"),
+    }
+}
+
+fn invalid(key: &str, message: &str) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.to_string(),
+        message: message.to_string(),
+    }
+}
+
+impl Config {
+    /// Applies the reloadable sections of `new` to this config, the changes of the other
+    /// sections are reported instead, they need a restart
+    pub fn reload(&self, new: Config) -> ConfigReload {
+        let current = config_sections(self);
+        let mut sections = config_sections(&new);
+        let mut applied = Vec::new();
+        let mut restart_required = Vec::new();
+
+        for (section, value) in sections.iter_mut() {
+            if *value == current[section] {
+                continue;
+            }
+
+            if RELOADABLE_SECTIONS.contains(&section.as_str()) {
+                applied.push(section.clone());
+            } else {
+                restart_required.push(section.clone());
+                *value = current[section].clone();
+            }
+        }
+
+        let config = serde_json::from_value(serde_json::Value::Object(sections))
+            .expect("Error at reading back the reloaded config");
+
+        ConfigReload {
+            config,
+            applied,
+            restart_required,
+        }
+    }
+
+    /// Loads the config file (defaults when it doesn't exist and `required` is false),
+    /// applies the `UNPLUGGED_*` environment overrides and validates the result
+    pub fn load(path: &Path, required: bool) -> Result<Self, ConfigError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(ConfigError::Read {
+                    path: path.display().to_string(),
+                    message: err.to_string(),
+                })
+            }
+        };
+
+        Self::from_toml(&contents, |name| std::env::var(name).ok())
+    }
+
+    /// Parses the config from toml, `env` looks up the environment overrides
+    pub fn from_toml<F>(contents: &str, env: F) -> Result<Self, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut table: toml::Table =
+            toml::from_str(contents).map_err(|err| ConfigError::Parse {
+                message: err.to_string(),
+            })?;
+
+        apply_env_overrides(&mut table, env)?;
+
+        let config: Config =
+            serde_path_to_error::deserialize(toml::Value::Table(table)).map_err(|err| {
+                let key = err.path().to_string();
+                invalid(&key, &err.into_inner().to_string())
+            })?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.address.parse::<SocketAddr>().is_err() {
+            return Err(invalid(
+                "server.address",
+                "expected an ip address and port, e.g. \"0.0.0.0:3000\"",
+            ));
+        }
+
+        let base_path = &self.server.base_path;
+
+        if !base_path.is_empty() && (!base_path.starts_with('/') || base_path.ends_with('/')) {
+            return Err(invalid(
+                "server.base_path",
+                "expected a path starting with a slash and without a trailing one, e.g. \"/search\"",
+            ));
+        }
+
+        let public_url = &self.server.public_url;
+        let is_absolute = public_url.starts_with("http://") || public_url.starts_with("https://");
+
+        if !public_url.is_empty() && !is_absolute {
+            return Err(invalid(
+                "server.public_url",
+                "expected an absolute http(s) url",
+            ));
+        }
+
+        let tls = &self.tls;
+
+        if tls.cert.is_empty() != tls.key.is_empty() {
+            let key = if tls.cert.is_empty() {
+                "tls.cert"
+            } else {
+                "tls.key"
+            };
+            return Err(invalid(key, "tls.cert and tls.key must be set together"));
+        }
+
+        if !tls.dir.is_empty() && !tls.cert.is_empty() {
+            return Err(invalid(
+                "tls.dir",
+                "expected either tls.dir or tls.cert and tls.key",
+            ));
+        }
+
+        if self.cache.pages == 0 {
+            return Err(invalid("cache.pages", "must be greater than zero"));
+        }
+
+        if self.rate_limit.per_second == 0 {
+            return Err(invalid(
+                "rate_limit.per_second",
+                "must be greater than zero",
+            ));
+        }
+
+        if self.rate_limit.burst == 0 {
+            return Err(invalid("rate_limit.burst", "must be greater than zero"));
+        }
+
+        if !["bm25", "flat"].contains(&self.scoring.model.as_str()) {
+            return Err(invalid("scoring.model", "expected \"bm25\" or \"flat\""));
+        }
+
+        if !(0..=100).contains(&self.scoring.fuzzy) {
+            return Err(invalid("scoring.fuzzy", "expected a percent from 0 to 100"));
+        }
+
+        if !(0..=100).contains(&self.scoring.stem) {
+            return Err(invalid("scoring.stem", "expected a percent from 0 to 100"));
+        }
+
+        if self.analytics.queries_log_max_bytes == 0 {
+            return Err(invalid(
+                "analytics.queries_log_max_bytes",
+                "must be greater than zero",
+            ));
+        }
+
+        let timeouts = [
+            ("timeouts.default_ms", self.timeouts.default_ms),
+            ("timeouts.suggest_ms", self.timeouts.suggest_ms),
+            ("timeouts.export_ms", self.timeouts.export_ms),
+        ];
+
+        if let Some((key, _)) = timeouts.iter().find(|(_, millis)| *millis == 0) {
+            return Err(invalid(key, "must be greater than zero"));
+        }
+
+        let matrix = &self.matrix;
+
+        if matrix.is_enabled() {
+            if !matrix.homeserver.starts_with("http://")
+                && !matrix.homeserver.starts_with("https://")
+            {
+                return Err(invalid(
+                    "matrix.homeserver",
+                    "expected an absolute http(s) url",
+                ));
+            }
+
+            if !matrix.user.starts_with('@') || !matrix.user.contains(':') {
+                return Err(invalid(
+                    "matrix.user",
+                    "expected a user id, e.g. \"@unplugged:example.org\"",
+                ));
+            }
+
+            if matrix.access_token.is_empty() && matrix.password.is_empty() {
+                return Err(invalid(
+                    "matrix.access_token",
+                    "expected an access token or a password",
+                ));
+            }
+
+            if matrix.rooms.is_empty() || matrix.rooms.iter().any(|room| !room.starts_with('!')) {
+                return Err(invalid(
+                    "matrix.rooms",
+                    "expected room ids, e.g. [\"!abc:example.org\"]",
+                ));
+            }
+        }
+
+        let telemetry = &self.telemetry;
+        let is_absolute =
+            telemetry.endpoint.starts_with("http://") || telemetry.endpoint.starts_with("https://");
+
+        if telemetry.is_enabled() && !is_absolute {
+            return Err(invalid(
+                "telemetry.endpoint",
+                "expected an absolute http(s) url",
+            ));
+        }
+
+        if telemetry.interval_minutes == 0 {
+            return Err(invalid(
+                "telemetry.interval_minutes",
+                "must be greater than zero",
+            ));
+        }
+
+        if self.playback.is_enabled() && self.playback.max_listeners == 0 {
+            return Err(invalid(
+                "playback.max_listeners",
+                "must be greater than zero",
+            ));
+        }
+
+        let digests = &self.digests;
+
+        if digests.is_enabled() {
+            if !["starttls", "tls", "none"].contains(&digests.smtp_security.as_str()) {
+                return Err(invalid(
+                    "digests.smtp_security",
+                    "expected \"starttls\", \"tls\" or \"none\"",
+                ));
+            }
+
+            if !digests.from.contains('@') {
+                return Err(invalid(
+                    "digests.from",
+                    "expected the sender address, e.g. \"Unplugged search <alerts@example.com>\"",
+                ));
+            }
+
+            if self.alerts.file.is_empty() {
+                return Err(invalid(
+                    "alerts.file",
+                    "the digests are sent for the saved searches, set alerts.file",
+                ));
+            }
+
+            // the emails link to the episodes without a request to take the host from
+            if self.server.public_url.is_empty() {
+                return Err(invalid(
+                    "server.public_url",
+                    "the links of the digests need the public url of the instance",
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+        let mut keys = HashSet::new();
+
+        for (i, api_key) in self.api.keys.iter().enumerate() {
+            if api_key.name.is_empty() || !names.insert(api_key.name.as_str()) {
+                return Err(invalid(
+                    &format!("api.keys[{}].name", i),
+                    "expected a name no other key uses",
+                ));
+            }
+
+            if api_key.key.len() < API_KEY_MIN_LEN || !keys.insert(api_key.key.as_str()) {
+                return Err(invalid(
+                    &format!("api.keys[{}].key", i),
+                    &format!(
+                        "expected a key of at least {} characters no other key uses",
+                        API_KEY_MIN_LEN
+                    ),
+                ));
+            }
+
+            if api_key.per_second == 0 || api_key.burst == 0 {
+                return Err(invalid(
+                    &format!("api.keys[{}]", i),
+                    "per_second and burst must be greater than zero",
+                ));
+            }
+        }
+
+        validate_podcast("podcast", &self.podcast)?;
+
+        let mut slugs = HashSet::new();
+
+        for (i, show) in self.shows.iter().enumerate() {
+            let is_slug = show
+                .slug
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+            if show.slug.is_empty() || !is_slug {
+                return Err(invalid(
+                    &format!("shows[{}].slug", i),
+                    "expected lowercase letters, digits and dashes, e.g. \"lup\"",
+                ));
+            }
+
+            if !slugs.insert(show.slug.as_str()) {
+                return Err(invalid(
+                    &format!("shows[{}].slug", i),
+                    "is used by another show",
+                ));
+            }
+
+            if show.episodes_by_id.is_empty() || show.episodes_by_tag.is_empty() {
+                return Err(invalid(
+                    &format!("shows[{}]", i),
+                    "expected the episodes_by_id and episodes_by_tag index files",
+                ));
+            }
+
+            validate_podcast(&format!("shows[{}].podcast", i), &show.podcast)?;
+        }
+
+        for (i, show) in self.show_configs().iter().enumerate() {
+            let key = if self.shows.is_empty() {
+                String::from("paths.database")
+            } else {
+                format!("shows[{}].database", i)
+            };
+
+            if show.database.is_empty() {
+                continue;
+            }
+
+            if !show.archive.is_empty() {
+                return Err(invalid(
+                    &key,
+                    "expected either an archive or a database, not both",
+                ));
+            }
+
+            if !cfg!(feature = "sqlite") {
+                return Err(invalid(&key, "expected a build with the sqlite feature"));
+            }
+        }
+
+        if !ANALYZER_LANGUAGES.contains(&self.analyzer.language.as_str()) {
+            return Err(invalid(
+                "analyzer.language",
+                &format!("expected one of {}", ANALYZER_LANGUAGES.join(", ")),
+            ));
+        }
+
+        if !self.analyzer.stopwords_file.is_empty()
+            && std::fs::read_to_string(&self.analyzer.stopwords_file).is_err()
+        {
+            return Err(invalid(
+                "analyzer.stopwords_file",
+                "expected a readable text file of stopwords",
+            ));
+        }
+
+        if !["text", "json"].contains(&self.logging.format.as_str()) {
+            return Err(invalid("logging.format", "expected \"text\" or \"json\""));
+        }
+
+        Ok(())
+    }
+
+    /// The configured shows, or a single show with an empty slug
+    /// built from the `paths` section
+    pub fn show_configs(&self) -> Vec<ShowConfig> {
+        if !self.shows.is_empty() {
+            return self.shows.clone();
+        }
+
+        vec![ShowConfig {
+            slug: String::new(),
+            name: String::from(DEFAULT_SHOW_NAME),
+            episodes_by_id: self.paths.episodes_by_id.clone(),
+            episodes_by_tag: self.paths.episodes_by_tag.clone(),
+            archive: self.paths.archive.clone(),
+            database: self.paths.database.clone(),
+            transcripts: self.paths.transcripts.clone(),
+            podcast: self.podcast.clone(),
+        }]
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        self.server
+            .address
+            .parse()
+            .expect("Error at parsing server.address")
+    }
+}
+
+fn validate_podcast(section: &str, podcast: &PodcastConfig) -> Result<(), ConfigError> {
+    let feed_url = &podcast.feed_url;
+
+    if !feed_url.is_empty() && !feed_url.starts_with("http://") && !feed_url.starts_with("https://")
+    {
+        return Err(invalid(
+            &format!("{}.feed_url", section),
+            "expected an absolute http(s) url",
+        ));
+    }
+
+    let artwork_url = &podcast.artwork_url;
+
+    if !artwork_url.is_empty()
+        && !artwork_url.starts_with("http://")
+        && !artwork_url.starts_with("https://")
+    {
+        return Err(invalid(
+            &format!("{}.artwork_url", section),
+            "expected an absolute http(s) url",
+        ));
+    }
+
+    if !podcast
+        .apple_podcasts_id
+        .chars()
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid(
+            &format!("{}.apple_podcasts_id", section),
+            "expected the number of the Apple Podcasts url, e.g. \"1234567890\"",
+        ));
+    }
+
+    Ok(())
+}
+
+// every key of the default config can be overridden, the value is parsed
+// as a toml value (numbers, booleans, arrays) falling back to a plain string,
+// string keys take it as it is so numeric ids and tokens stay strings
+fn apply_env_overrides<F>(table: &mut toml::Table, env: F) -> Result<(), ConfigError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let defaults = toml::Table::try_from(Config::default()).expect("Error at serializing config");
+
+    for (section, keys) in defaults.iter() {
+        let Some(keys) = keys.as_table() else {
+            continue;
+        };
+
+        for (key, default) in keys.iter() {
+            let name = format!("{}{}_{}", ENV_PREFIX, section, key).to_uppercase();
+
+            let Some(raw) = env(&name) else {
+                continue;
+            };
+
+            let value = if default.is_str() {
+                toml::Value::String(raw)
+            } else {
+                toml::from_str::<toml::Table>(&format!("value = {}", raw))
+                    .ok()
+                    .and_then(|mut parsed| parsed.remove("value"))
+                    .unwrap_or(toml::Value::String(raw))
+            };
+
+            let section_table = table
+                .entry(section.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+
+            let Some(section_table) = section_table.as_table_mut() else {
+                return Err(invalid(section, "expected a table"));
+            };
+
+            section_table.insert(key.clone(), value);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ConfigError};
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::from_toml("", |_| None).unwrap();
+
+        assert_eq!(config.server.address, "127.0.0.1:3000");
+        assert_eq!(config.scoring.title, 100);
+        assert!(!config.tls.is_enabled());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let contents = "[rate_limit]\nper_second = 2\n";
+        let config = Config::from_toml(contents, |name| match name {
+            "UNPLUGGED_RATE_LIMIT_PER_SECOND" => Some(String::from("9")),
+            "UNPLUGGED_SERVER_ADDRESS" => Some(String::from("0.0.0.0:8080")),
+            "UNPLUGGED_PODCAST_APPLE_PODCASTS_ID" => Some(String::from("1234567890")),
+            _ => None,
+        })
+        .unwrap();
+
+        assert_eq!(config.rate_limit.per_second, 9);
+        assert_eq!(config.server.address, "0.0.0.0:8080");
+        assert_eq!(config.podcast.apple_podcasts_id, "1234567890");
+    }
+
+    #[test]
+    fn test_reload() {
+        let current = Config::from_toml("[scoring]\ntag = 5\n", |_| None).unwrap();
+        let contents =
+            "[scoring]\ntag = 7\n[server]\naddress = \"0.0.0.0:8080\"\n[rate_limit]\nburst = 3\n";
+        let new = Config::from_toml(contents, |_| None).unwrap();
+
+        let reload = current.reload(new);
+
+        assert_eq!(reload.applied, vec!["rate_limit", "scoring"]);
+        assert_eq!(reload.restart_required, vec!["server"]);
+        assert_eq!(reload.config.scoring.tag, 7);
+        assert_eq!(reload.config.rate_limit.burst, 3);
+        assert_eq!(reload.config.server.address, "127.0.0.1:3000");
+
+        let unchanged = current.reload(current.clone());
+
+        assert!(unchanged.applied.is_empty() && unchanged.restart_required.is_empty());
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        let error_key = |contents: &str| match Config::from_toml(contents, |_| None) {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            other => panic!("unexpected result {:?}", other),
+        };
+
+        assert_eq!(
+            error_key("[rate_limit]\nburst = \"many\""),
+            "rate_limit.burst"
+        );
+        assert_eq!(error_key("[rate_limit]\nburst = 0"), "rate_limit.burst");
+        assert_eq!(
+            error_key("[server]\naddress = \"localhost\""),
+            "server.address"
+        );
+        assert_eq!(error_key("[cache]\nsizes = 1"), "cache.sizes");
+        assert_eq!(
+            error_key("[[shows]]\nslug = \"Self Hosted\""),
+            "shows[0].slug"
+        );
+        assert_eq!(
+            error_key("[server]\nbase_path = \"search/\""),
+            "server.base_path"
+        );
+        assert_eq!(error_key("[tls]\ncert = \"cert.pem\""), "tls.key");
+        assert_eq!(
+            error_key("[[api.keys]]\nname = \"alfred\"\nkey = \"short\""),
+            "api.keys[0].key"
+        );
+        assert_eq!(
+            error_key("[timeouts]\nsuggest_ms = 0"),
+            "timeouts.suggest_ms"
+        );
+        assert_eq!(
+            error_key("[digests]\nsmtp_host = \"smtp.example.com\"\nfrom = \"a@example.com\""),
+            "alerts.file"
+        );
+        assert_eq!(
+            error_key("[playback]\nfile = \"playback.json\"\nmax_listeners = 0"),
+            "playback.max_listeners"
+        );
+        assert_eq!(error_key("[scoring]\nfuzzy = 150"), "scoring.fuzzy");
+        assert_eq!(error_key("[scoring]\nstem = -1"), "scoring.stem");
+        assert_eq!(error_key("[scoring]\nmodel = \"tfidf\""), "scoring.model");
+        assert_eq!(
+            error_key("[telemetry]\nendpoint = \"stats.example.com\""),
+            "telemetry.endpoint"
+        );
+        assert_eq!(
+            error_key("[paths]\narchive = \"index.rkyv\"\ndatabase = \"index.sqlite\""),
+            "paths.database"
+        );
+        assert_eq!(
+            error_key("[analyzer]\nlanguage = \"klingon\""),
+            "analyzer.language"
+        );
+        assert_eq!(
+            error_key("[analyzer]\nstopwords_file = \"/nonexistent/stopwords.txt\""),
+            "analyzer.stopwords_file"
+        );
+        assert_eq!(
+            error_key("[matrix]\nhomeserver = \"https://example.org\"\nuser = \"@bot:example.org\"\naccess_token = \"secret\""),
+            "matrix.rooms"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRequest, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::admin::constant_time_eq;
+use crate::error_pages::error_page;
+use crate::{cookie_attributes, AppState};
+
+const CSRF_COOKIE: &str = "csrf";
+const CSRF_HEADER: &str = "X-CSRF-Token";
+const CSRF_FIELD: &str = "csrf_token";
+
+// called by servers, scripts and mail clients rather than browsers, authenticated by other means
+fn is_exempt(path: &str) -> bool {
+    path == "/discord/interactions"
+        || path.starts_with("/api/")
+        || path.starts_with("/alerts/unsubscribe/")
+}
+
+/// Double-submit cookie: html pages set a random `csrf` cookie, the scripts of the pages
+/// send it back in the `X-CSRF-Token` header or the `csrf_token` field of the forms,
+/// state-changing requests without a matching token are rejected with a 403
+pub async fn csrf_protect(
+    State(state): State<Arc<AppState>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let cookie = csrf_cookie(request.headers());
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+
+    if safe || is_exempt(request.uri().path()) {
+        let mut response = next.run(request).await;
+
+        let is_html = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|value| value.as_bytes().starts_with(b"text/html"));
+
+        // pages are cached for everyone, the token is a cookie rather than part of the html
+        if safe && cookie.is_none() && is_html {
+            let cookie = format!(
+                "{}={}; {}; SameSite=Strict",
+                CSRF_COOKIE,
+                Uuid::new_v4().simple(),
+                cookie_attributes(&state.config())
+            );
+
+            response.headers_mut().append(
+                header::SET_COOKIE,
+                HeaderValue::from_str(&cookie).expect("Error at building the csrf cookie"),
+            );
+        }
+
+        return response;
+    }
+
+    let (request, token) = submitted_token(request).await;
+
+    let valid = match (&cookie, &token) {
+        (Some(cookie), Some(token)) => constant_time_eq(cookie.as_bytes(), token.as_bytes()),
+        _ => false,
+    };
+
+    if valid {
+        return next.run(request).await;
+    }
+
+    warn!(
+        "Rejected {} {} without a valid csrf token",
+        request.method(),
+        request.uri()
+    );
+
+    let lang = state.templates.negotiate(request.headers());
+
+    error_page(
+        &state.templates,
+        &state.mount_path,
+        lang,
+        StatusCode::FORBIDDEN,
+        None,
+        None,
+    )
+}
+
+fn csrf_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == CSRF_COOKIE)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// the token of the header, or of the field of a form, which means reading the body
+// and putting it back for the handler
+async fn submitted_token(request: Request<Body>) -> (Request<Body>, Option<String>) {
+    if let Some(token) = request
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        let token = token.to_string();
+        return (request, Some(token));
+    }
+
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| {
+            value
+                .as_bytes()
+                .starts_with(b"application/x-www-form-urlencoded")
+        });
+
+    if !is_form {
+        return (request, None);
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = Bytes::from_request(Request::new(body), &())
+        .await
+        .unwrap_or_default();
+
+    let token = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+        .ok()
+        .and_then(|fields| {
+            fields
+                .into_iter()
+                .find(|(name, _)| name == CSRF_FIELD)
+                .map(|(_, value)| value)
+        });
+
+    (Request::from_parts(parts, Body::from(bytes)), token)
+}
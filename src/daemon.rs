@@ -0,0 +1,134 @@
+use std::net::TcpListener;
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, warn};
+
+// the first socket passed with LISTEN_FDS, what systemd::listener takes
+const LISTEN_FD: i32 = 3;
+
+/// Why the server stopped accepting connections
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// SIGTERM or SIGINT
+    Exit,
+    /// SIGUSR2, the binary is started again on the same socket
+    Reexec,
+}
+
+/// Resolves on the first SIGTERM, SIGINT or SIGUSR2, SIGHUP reloads the config instead
+pub async fn stop_signal() -> Stop {
+    let mut terminate = signal(SignalKind::terminate()).expect("Error at listening to SIGTERM");
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Error at listening to SIGINT");
+    let mut upgrade = signal(SignalKind::user_defined2()).expect("Error at listening to SIGUSR2");
+
+    let stop = tokio::select! {
+        _ = terminate.recv() => Stop::Exit,
+        _ = interrupt.recv() => Stop::Exit,
+        _ = upgrade.recv() => Stop::Reexec,
+    };
+
+    match stop {
+        Stop::Exit => info!("Stopping, waiting for the requests in flight"),
+        Stop::Reexec => {
+            info!("Restarting, waiting for the requests in flight before starting again");
+            if let Err(err) = sd_notify::notify(false, &[sd_notify::NotifyState::Reloading]) {
+                warn!("Error at notifying systemd: {}", err);
+            }
+        }
+    }
+
+    stop
+}
+
+/// Starts the binary again in this process with the same arguments, which reads the config,
+/// index and templates again and picks up an upgraded binary, the socket is passed like systemd
+/// does (`LISTEN_FDS`) so connections queue in the meantime instead of being refused
+pub fn reexec(socket: TcpListener) -> ! {
+    let mut args = std::env::args_os();
+    let program = args.next().expect("Error at reading the program name");
+
+    // dup2 clears close-on-exec, the fcntl covers the socket already being fd 3
+    let fd = socket.as_raw_fd();
+    let passed = unsafe {
+        (fd == LISTEN_FD || libc::dup2(fd, LISTEN_FD) == LISTEN_FD)
+            && libc::fcntl(LISTEN_FD, libc::F_SETFD, 0) == 0
+    };
+
+    if !passed {
+        error!(
+            "Error at passing the socket to the new process: {}",
+            std::io::Error::last_os_error()
+        );
+        std::process::exit(1);
+    }
+
+    let err = std::process::Command::new(&program)
+        .args(args)
+        .env("LISTEN_FDS", "1")
+        .env("LISTEN_PID", std::process::id().to_string())
+        .env_remove("LISTEN_FDNAMES")
+        .exec();
+
+    error!(
+        "Error at starting {} again: {}",
+        program.to_string_lossy(),
+        err
+    );
+    std::process::exit(1);
+}
+
+/// The pid of the server written to `--pid-file` for init scripts, removed on exit
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Fails when the file holds the pid of another running process,
+    /// a stale file left by a crash is overwritten
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let pid = std::process::id();
+
+        if let Ok(contents) = std::fs::read_to_string(path) {
+            if let Ok(running) = contents.trim().parse::<u32>() {
+                if running != pid && is_running(running) {
+                    return Err(format!(
+                        "Error at creating the pid file {}: the server is already running with pid {}",
+                        path.display(),
+                        running
+                    ));
+                }
+            }
+        }
+
+        std::fs::write(path, format!("{}\n", pid))
+            .map_err(|err| format!("Error at creating the pid file {}: {}", path.display(), err))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Error at removing the pid file {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // signal 0 only checks the process exists, EPERM means it's owned by another user
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
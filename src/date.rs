@@ -0,0 +1,100 @@
+use chrono::{Datelike, NaiveDate};
+
+const DATE_FORMATS: [&str; 3] = ["%B %d, %Y", "%A, %B %d, %Y", "%Y-%m-%d"];
+
+// removes the ordinal suffix of the day, "March 19th, 2023" -> "March 19, 2023"
+fn strip_ordinals(date: &str) -> String {
+    date.split_whitespace()
+        .map(|word| {
+            let digits = word.trim_end_matches(|ch: char| ch.is_ascii_alphabetic() || ch == ',');
+            let suffix = &word[digits.len()..];
+            let is_ordinal = ["st", "nd", "rd", "th"]
+                .iter()
+                .any(|s| suffix.trim_end_matches(',') == *s);
+
+            if !digits.is_empty() && digits.chars().all(|ch| ch.is_ascii_digit()) && is_ordinal {
+                format!("{}{}", digits, &suffix[2..])
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses the human readable date of an episode ("March 19th, 2023")
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    let date = strip_ordinals(date.trim());
+
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(&date, format).ok())
+}
+
+/// Formats a date like the episode dates of the index, "March 19th, 2023"
+pub fn format_date(date: NaiveDate) -> String {
+    let day = date.day();
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+
+    format!("{} {}{}, {}", date.format("%B"), day, suffix, date.year())
+}
+
+/// The first day of a year, a month or a day of the `after:` and `before:` filters,
+/// "2023", "2023-06" or "2023-06-15"
+pub fn parse_date_bound(value: &str) -> Option<NaiveDate> {
+    let mut parts = value.trim().splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next().map_or(Some(1), |month| month.parse().ok())?;
+    let day = parts.next().map_or(Some(1), |day| day.parse().ok())?;
+
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_date, parse_date, parse_date_bound};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_date() {
+        let expected = NaiveDate::from_ymd_opt(2023, 3, 19);
+
+        assert_eq!(parse_date("March 19th, 2023"), expected);
+        assert_eq!(parse_date("Sunday, March 19th, 2023"), expected);
+        assert_eq!(parse_date(" 2023-03-19 "), expected);
+        assert_eq!(
+            parse_date("March 1st, 2023"),
+            NaiveDate::from_ymd_opt(2023, 3, 1)
+        );
+        assert_eq!(parse_date("not a date"), None);
+        assert_eq!(parse_date(""), None);
+    }
+
+    #[test]
+    fn test_format_date() {
+        let date = |day| NaiveDate::from_ymd_opt(2023, 3, day).unwrap();
+
+        assert_eq!(format_date(date(19)), "March 19th, 2023");
+        assert_eq!(format_date(date(1)), "March 1st, 2023");
+        assert_eq!(format_date(date(12)), "March 12th, 2023");
+        assert_eq!(format_date(date(22)), "March 22nd, 2023");
+        assert_eq!(parse_date(&format_date(date(23))), Some(date(23)));
+    }
+
+    #[test]
+    fn test_parse_date_bound() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day);
+
+        assert_eq!(parse_date_bound("2023"), date(2023, 1, 1));
+        assert_eq!(parse_date_bound("2023-06"), date(2023, 6, 1));
+        assert_eq!(parse_date_bound("2022-01-15"), date(2022, 1, 15));
+        assert_eq!(parse_date_bound("2023-13"), None);
+        assert_eq!(parse_date_bound("yesterday"), None);
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+use unplugged_engine::ShowIndex;
+
+use crate::assets::{files_in, recompile_templates};
+use crate::maintenance::set_index_metrics;
+use crate::shows::Shows;
+
+const DEV_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+// paths.watch, the indexer rewrites the files at most a few times a day
+const INDEX_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The directories watched by dev mode besides the index files of the shows
+pub struct DevWatch {
+    pub templates_dir: String,
+    /// served from disk, a change only needs a reload of the page
+    pub static_dir: String,
+}
+
+// modification time of every file, to tell which ones changed
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+fn snapshot<I>(files: I) -> Snapshot
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    files
+        .into_iter()
+        .map(|path| {
+            let modified = path.metadata().and_then(|meta| meta.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
+
+// the files added, removed or modified since `before`, relative to `dir`
+fn changed_files(before: &Snapshot, after: &Snapshot, dir: &Path) -> Vec<String> {
+    before
+        .keys()
+        .chain(after.keys().filter(|path| !before.contains_key(*path)))
+        .filter(|path| before.get(*path) != after.get(*path))
+        .map(|path| path.strip_prefix(dir).unwrap_or(path).display().to_string())
+        .collect()
+}
+
+/// The index files of every show and their modification times, to reload the index
+/// of a show when its files change
+pub struct IndexWatch {
+    files: Vec<Vec<PathBuf>>,
+    snapshots: Vec<Snapshot>,
+}
+
+impl IndexWatch {
+    pub fn new(shows: &Shows) -> Self {
+        let files: Vec<Vec<PathBuf>> = shows
+            .iter()
+            .map(|show| {
+                show.show
+                    .index_files()
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .collect();
+        let snapshots = files.iter().map(|files| snapshot(files.clone())).collect();
+
+        Self { files, snapshots }
+    }
+
+    /// Reloads the index of the shows whose files changed since the last check
+    pub async fn reload_changed(&mut self, shows: &Shows) {
+        for (i, show) in shows.iter().enumerate() {
+            let current = snapshot(self.files[i].clone());
+            let changed = changed_files(&self.snapshots[i], &current, Path::new(""));
+            self.snapshots[i] = current;
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            // a file still being written fails to parse, it's loaded once it's complete
+            match ShowIndex::load(&show.show).await {
+                Ok(index) => {
+                    info!(
+                        show = show.show.slug,
+                        files = changed.join(", "),
+                        episodes = index.episodes_by_id.len(),
+                        "Index changed, reloaded it"
+                    );
+
+                    show.index.swap(index);
+                    show.page_cache.clear();
+                    set_index_metrics(shows);
+                }
+                Err(reason) => warn!(
+                    show = show.show.slug,
+                    files = changed.join(", "),
+                    "Index changed, keeping the previous one: {}",
+                    reason
+                ),
+            }
+        }
+    }
+}
+
+/// The loop of paths.watch outside dev mode, reloads the index of a show when
+/// its files are rewritten, the requests keep the index they started with
+pub async fn watch_indices(shows: Shows) {
+    let mut indices = IndexWatch::new(&shows);
+
+    info!("Watching the index files");
+
+    let mut interval = tokio::time::interval(INDEX_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        indices.reload_changed(&shows).await;
+    }
+}
+
+/// The dev mode loop, recompiles the templates, reloads the index of a show when its
+/// files change and logs which files changed, static files are served from disk as they are
+pub async fn watch_files(watch: DevWatch, shows: Shows) {
+    let templates_dir = PathBuf::from(&watch.templates_dir);
+    let static_dir = PathBuf::from(&watch.static_dir);
+    let mut templates = snapshot(files_in(&templates_dir));
+    let mut static_files = snapshot(files_in(&static_dir));
+    let mut indices = IndexWatch::new(&shows);
+
+    info!(
+        templates = watch.templates_dir,
+        static_dir = watch.static_dir,
+        "Development mode, watching the templates, static files and index files"
+    );
+
+    let mut interval = tokio::time::interval(DEV_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let current = snapshot(files_in(&templates_dir));
+        let changed = changed_files(&templates, &current, &templates_dir);
+        templates = current;
+
+        if !changed.is_empty() {
+            info!(files = changed.join(", "), "Templates changed");
+            recompile_templates(&watch.templates_dir, &shows);
+        }
+
+        let current = snapshot(files_in(&static_dir));
+        let changed = changed_files(&static_files, &current, &static_dir);
+        static_files = current;
+
+        if !changed.is_empty() {
+            info!(
+                files = changed.join(", "),
+                "Static files changed, reload the page"
+            );
+        }
+
+        indices.reload_changed(&shows).await;
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::Utc;
+use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+use tera::Context;
+use tracing::{error, info, warn};
+use unplugged_engine::{Digest, DigestsConfig, Episode, QueryVersion, SavedSearch};
+
+use crate::shows::Shows;
+use crate::{base_url, search_episodes, AppState};
+
+// how often the saved searches are checked for due digests
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The smtp connection of the digests
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &DigestsConfig) -> Result<Self, String> {
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .map_err(|err| format!("Error at parsing digests.from: {}", err))?;
+
+        let builder = match config.smtp_security.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host),
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host),
+            _ => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.smtp_host,
+            )),
+        }
+        .map_err(|err| format!("Error at setting up digests.smtp_host: {}", err))?;
+
+        let mut builder = builder.port(config.smtp_port);
+
+        if !config.smtp_username.is_empty() {
+            builder = builder.credentials(Credentials::new(
+                config.smtp_username.clone(),
+                config.smtp_password.clone(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+/// Emails the new matches of the saved searches whose digest is due, every hour,
+/// the searches without news wait for their next period
+pub async fn send_digests(shows: Shows, mailer: Mailer) {
+    let Some(saved_searches) = shows[0].saved_searches.clone() else {
+        return;
+    };
+
+    let mut interval = tokio::time::interval(DIGEST_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now().timestamp();
+
+        for (token, search) in saved_searches.with_digest() {
+            let Some(digest) = search.digest.as_ref().filter(|digest| digest.is_due(now)) else {
+                continue;
+            };
+
+            let state = shows.iter().find(|show| show.show.slug == search.show);
+
+            // a show in maintenance can't tell what's new
+            let Some(state) = state.filter(|state| state.index.is_ready()) else {
+                continue;
+            };
+
+            let index = state.index();
+
+            let results_with_score =
+                search_episodes(state, &index, &search.query, QueryVersion::LATEST);
+            let matches: Vec<_> = results_with_score.iter().map(|(_, ep)| *ep).collect();
+            let episodes = digest.unsent(search.new_matches(&matches));
+
+            if !episodes.is_empty() {
+                // retried on the next check
+                if let Err(err) = send_digest(state, &mailer, &search, digest, &episodes).await {
+                    warn!("Error at emailing a digest: {}", err);
+                    continue;
+                }
+
+                info!(episodes = episodes.len(), "Emailed a digest");
+            }
+
+            let sent: Vec<_> = episodes.iter().map(|episode| episode.id as usize).collect();
+
+            let updated = saved_searches
+                .update(&token, |search| {
+                    if let Some(digest) = search.digest.as_mut() {
+                        digest.last_sent_at = now;
+                        digest.sent.extend(sent);
+                    }
+                })
+                .await;
+
+            if let Err(err) = updated {
+                error!("Error at saving alerts.file: {}", err);
+            }
+        }
+    }
+}
+
+async fn send_digest(
+    state: &AppState,
+    mailer: &Mailer,
+    search: &SavedSearch,
+    digest: &Digest,
+    episodes: &[&Episode],
+) -> Result<(), String> {
+    let base_url = base_url(state, None);
+    let index = state.index();
+    let unsubscribe_url = format!("{}/alerts/unsubscribe/{}", base_url, digest.unsubscribe);
+
+    let episodes: Vec<_> = episodes
+        .iter()
+        .map(|episode| {
+            let page = match index.slugs.slug(episode.id as usize) {
+                Some(slug) => format!("{}/episode/{}", base_url, slug),
+                None => episode.url.clone(),
+            };
+
+            serde_json::json!({ "title": episode.title, "date": episode.date, "page": page })
+        })
+        .collect();
+
+    let subject = state.templates.translations().translate(
+        &digest.lang,
+        "digest-subject",
+        &[
+            ("count", episodes.len().to_string()),
+            ("query", search.query.clone()),
+        ],
+    );
+
+    let body = Context::from_serialize(serde_json::json!({
+        "query": search.query,
+        "episodes": episodes,
+        "unsubscribe_url": unsubscribe_url,
+        "lang": digest.lang,
+    }))
+    .and_then(|context| state.templates.render("digest.txt", &context))
+    .map_err(|err| format!("Error at rendering digest.txt: {}", err))?;
+
+    let to = digest
+        .email
+        .parse::<Mailbox>()
+        .map_err(|err| format!("Error at parsing the email: {}", err))?;
+
+    let mut message = Message::builder()
+        .from(mailer.from.clone())
+        .to(to)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body)
+        .map_err(|err| format!("Error at building the email: {}", err))?;
+
+    // one click unsubscribe of the mail clients (RFC 8058)
+    let headers = message.headers_mut();
+    headers.insert_raw(HeaderValue::new(
+        HeaderName::new_from_ascii_str("List-Unsubscribe"),
+        format!("<{}>", unsubscribe_url),
+    ));
+    headers.insert_raw(HeaderValue::new(
+        HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+        String::from("List-Unsubscribe=One-Click"),
+    ));
+
+    mailer
+        .transport
+        .send(message)
+        .await
+        .map(|_| ())
+        .map_err(|err| format!("Error at sending the email: {}", err))
+}
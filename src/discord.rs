@@ -0,0 +1,143 @@
+use crate::Episode;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// https://discord.com/developers/docs/interactions/receiving-and-responding
+const INTERACTION_PING: u8 = 1;
+const INTERACTION_APPLICATION_COMMAND: u8 = 2;
+const RESPONSE_PONG: u8 = 1;
+const RESPONSE_CHANNEL_MESSAGE: u8 = 4;
+const EMBED_COLOR: u32 = 0x5393F2;
+
+/// Verifies that interaction payloads were signed by Discord
+#[derive(Clone)]
+pub struct DiscordVerifier {
+    key: VerifyingKey,
+}
+
+impl DiscordVerifier {
+    /// `public_key` is the hex encoded public key of the Discord application
+    pub fn new(public_key: &str) -> Option<Self> {
+        let bytes: [u8; 32] = hex::decode(public_key.trim()).ok()?.try_into().ok()?;
+        let key = VerifyingKey::from_bytes(&bytes).ok()?;
+
+        Some(Self { key })
+    }
+
+    /// Checks the `X-Signature-Ed25519` signature of `timestamp + body`
+    pub fn verify(&self, signature: &str, timestamp: &str, body: &[u8]) -> bool {
+        let Some(signature) = hex::decode(signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        else {
+            return false;
+        };
+
+        let message = [timestamp.as_bytes(), body].concat();
+        self.key.verify(&message, &signature).is_ok()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Interaction {
+    #[serde(rename = "type")]
+    pub kind: u8,
+    pub data: Option<InteractionData>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InteractionData {
+    #[serde(default)]
+    pub options: Vec<InteractionOption>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InteractionOption {
+    pub name: String,
+    pub value: Value,
+}
+
+pub enum InteractionRequest {
+    Ping,
+    /// `/unplugged <query>`
+    Search(String),
+    Unsupported,
+}
+
+impl Interaction {
+    pub fn request(&self) -> InteractionRequest {
+        match self.kind {
+            INTERACTION_PING => InteractionRequest::Ping,
+            INTERACTION_APPLICATION_COMMAND => {
+                let query = self
+                    .data
+                    .iter()
+                    .flat_map(|data| data.options.iter())
+                    .find(|option| option.name == "query")
+                    .and_then(|option| option.value.as_str())
+                    .unwrap_or_default();
+
+                InteractionRequest::Search(query.to_string())
+            }
+            _ => InteractionRequest::Unsupported,
+        }
+    }
+}
+
+pub fn discord_pong_response() -> Value {
+    json!({ "type": RESPONSE_PONG })
+}
+
+/// Replies to the slash command with an embed per episode
+pub fn discord_search_response(query: &str, episodes: &[&Episode]) -> Value {
+    if episodes.is_empty() {
+        return json!({
+            "type": RESPONSE_CHANNEL_MESSAGE,
+            "data": { "content": format!("No episodes found for `{}`", query) },
+        });
+    }
+
+    let embeds: Vec<_> = episodes
+        .iter()
+        .map(|episode| {
+            json!({
+                "title": episode.title,
+                "url": episode.url,
+                "description": episode.tag_names().join(", "),
+                "color": EMBED_COLOR,
+                "footer": { "text": format!("{} | {}", episode.date, episode.duration) },
+            })
+        })
+        .collect();
+
+    json!({
+        "type": RESPONSE_CHANNEL_MESSAGE,
+        "data": { "content": format!("Top results for `{}`", query), "embeds": embeds },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiscordVerifier;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn test_verify() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let verifier = DiscordVerifier::new(&public_key).unwrap();
+
+        let body = br#"{"type":1}"#;
+        let signature = hex::encode(
+            signing_key
+                .sign(&[b"1680000000", &body[..]].concat())
+                .to_bytes(),
+        );
+
+        assert!(verifier.verify(&signature, "1680000000", body));
+        assert!(!verifier.verify(&signature, "1680000001", body));
+        assert!(!verifier.verify("not hex", "1680000000", body));
+        assert!(DiscordVerifier::new("abc").is_none());
+    }
+}
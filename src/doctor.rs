@@ -0,0 +1,172 @@
+use axum_server::tls_rustls::RustlsConfig;
+use listenfd::ListenFd;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::Arc;
+use unplugged_engine::{Config, ConfigError, DiscordVerifier, ShowIndex};
+
+use crate::assets::{bundled_translations, compile_templates, error_chain};
+use crate::digests::Mailer;
+
+#[derive(Default)]
+struct Report {
+    failed: usize,
+}
+
+impl Report {
+    fn ok(&self, check: &str, detail: &str) {
+        println!("ok    {:<10} {}", check, detail);
+    }
+
+    fn warn(&self, check: &str, detail: &str) {
+        println!("warn  {:<10} {}", check, detail);
+    }
+
+    fn fail(&mut self, check: &str, detail: &str) {
+        println!("FAIL  {:<10} {}", check, detail);
+        self.failed += 1;
+    }
+}
+
+/// Checks the config, the index files, the templates and the listening address
+/// the way the server would use them, returns the exit code (1 when a check failed)
+pub async fn run_doctor(path: &Path, config: Result<Config, ConfigError>) -> i32 {
+    let mut report = Report::default();
+
+    let config = match config {
+        Ok(config) => config,
+        Err(err) => {
+            report.fail("config", &err.to_string());
+            return 1;
+        }
+    };
+
+    if path.exists() {
+        report.ok("config", &path.display().to_string());
+    } else {
+        report.warn(
+            "config",
+            &format!("{} not found, using the defaults", path.display()),
+        );
+    }
+
+    for show in config.show_configs() {
+        let name = if show.slug.is_empty() {
+            show.name.clone()
+        } else {
+            show.slug.clone()
+        };
+
+        match ShowIndex::load(&show).await {
+            Ok(index) => report.ok(
+                "index",
+                &format!(
+                    "{}: {} episodes and {} tags",
+                    name,
+                    index.episodes_by_id.len(),
+                    index.episodes_by_tag.len()
+                ),
+            ),
+            Err(err) => report.fail(
+                "index",
+                &format!(
+                    "{}: {}, build it with `index build --feed <url>` or the indexer",
+                    name, err
+                ),
+            ),
+        }
+    }
+
+    let translations = Arc::new(bundled_translations());
+
+    match compile_templates(&config.paths.templates, &translations) {
+        Ok(tera) => {
+            let source = if config.paths.templates.is_empty() {
+                String::from("bundled")
+            } else {
+                format!("bundled and {}", config.paths.templates)
+            };
+            report.ok(
+                "templates",
+                &format!(
+                    "{} templates, {}",
+                    tera.get_template_names().count(),
+                    source
+                ),
+            );
+        }
+        Err(err) => report.fail("templates", &error_chain(&err)),
+    }
+
+    let static_dir = &config.paths.static_dir;
+
+    if !static_dir.is_empty() && !Path::new(static_dir).is_dir() {
+        report.fail(
+            "static",
+            &format!("paths.static_dir {} is not a directory", static_dir),
+        );
+    }
+
+    if let Some((cert, key)) = config.tls.files() {
+        match RustlsConfig::from_pem_file(&cert, &key).await {
+            Ok(_) => report.ok("tls", &cert.display().to_string()),
+            Err(err) => report.fail(
+                "tls",
+                &format!(
+                    "Error at loading certificate {} and key {}: {}",
+                    cert.display(),
+                    key.display(),
+                    err
+                ),
+            ),
+        }
+    }
+
+    if config.digests.is_enabled() {
+        match Mailer::new(&config.digests) {
+            Ok(_) => report.ok("digests", &config.digests.smtp_host),
+            Err(err) => report.fail("digests", &err),
+        }
+    }
+
+    let public_key = &config.discord.public_key;
+
+    if !public_key.is_empty() && DiscordVerifier::new(public_key).is_none() {
+        report.fail(
+            "discord",
+            "discord.public_key is not a valid ed25519 public key",
+        );
+    }
+
+    if config.favorites.secret.is_empty() {
+        report.warn(
+            "favorites",
+            "favorites.secret is empty, the favorites are lost on every restart",
+        );
+    }
+
+    let addr = config.socket_addr();
+
+    if ListenFd::from_env().len() > 0 {
+        report.ok("address", "the socket is passed by systemd");
+    } else {
+        // dropped right away, the server binds it again
+        match TcpListener::bind(addr) {
+            Ok(_) => report.ok("address", &format!("{} is free", addr)),
+            Err(err) => report.fail(
+                "address",
+                &format!(
+                    "Error at binding {}: {}, stop what listens on it or change server.address",
+                    addr, err
+                ),
+            ),
+        }
+    }
+
+    if report.failed > 0 {
+        println!("\n{} failed checks", report.failed);
+        return 1;
+    }
+
+    0
+}
@@ -0,0 +1,162 @@
+use crate::Episode;
+
+/// Parses the duration of an episode ("01:08:28", "68:28" or "4108") into seconds
+pub fn parse_duration(duration: &str) -> Option<u64> {
+    let duration = duration.trim();
+
+    if duration.is_empty() {
+        return None;
+    }
+
+    let parts: Vec<_> = duration.split(':').collect();
+
+    if parts.len() > 3 {
+        return None;
+    }
+
+    parts.iter().try_fold(0, |acc, part| {
+        part.trim()
+            .parse::<u64>()
+            .ok()
+            .map(|value| acc * 60 + value)
+    })
+}
+
+/// Parses the duration of the `longer:` and `shorter:` filters into seconds,
+/// "45m", "1h", "1h30m" or "90" (minutes)
+pub fn parse_duration_bound(value: &str) -> Option<u64> {
+    let value = value.trim().to_lowercase();
+
+    if let Ok(minutes) = value.parse::<u64>() {
+        return Some(minutes * 60);
+    }
+
+    let (hours, minutes) = match value.split_once('h') {
+        Some((hours, minutes)) => (hours, minutes),
+        None => ("0", value.as_str()),
+    };
+
+    let minutes = minutes.strip_suffix("min").unwrap_or(minutes);
+    let minutes = minutes.strip_suffix('m').unwrap_or(minutes);
+    let minutes = if minutes.is_empty() { "0" } else { minutes };
+
+    let seconds = hours.parse::<u64>().ok()? * 3600 + minutes.parse::<u64>().ok()? * 60;
+
+    (seconds > 0 || value.starts_with('0')).then_some(seconds)
+}
+
+/// Total seconds of listening of a result set, the episodes whose duration
+/// doesn't parse count as zero
+pub fn listening_time<'a, I>(episodes: I) -> u64
+where
+    I: IntoIterator<Item = &'a Episode>,
+{
+    episodes
+        .into_iter()
+        .filter_map(Episode::duration_seconds)
+        .sum()
+}
+
+/// Rounds a listening time for "about 37 hours of listening",
+/// to the hour from an hour up, to the minute below
+pub fn approximate_listening_time(seconds: u64) -> u64 {
+    if seconds >= 3600 {
+        (seconds + 1800) / 3600 * 3600
+    } else {
+        (seconds + 30) / 60 * 60
+    }
+}
+
+/// Formats seconds as an ISO 8601 duration, 4108 -> "PT1H8M28S"
+pub fn to_iso8601_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    let mut iso = String::from("PT");
+
+    if hours > 0 {
+        iso.push_str(&format!("{}H", hours));
+    }
+
+    if minutes > 0 {
+        iso.push_str(&format!("{}M", minutes));
+    }
+
+    if seconds > 0 || (hours == 0 && minutes == 0) {
+        iso.push_str(&format!("{}S", seconds));
+    }
+
+    iso
+}
+
+/// Formats seconds like the episode durations, 4108 -> "01:08:28"
+pub fn format_duration(seconds: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        approximate_listening_time, format_duration, listening_time, parse_duration,
+        parse_duration_bound, to_iso8601_duration,
+    };
+    use crate::Episode;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("01:08:28"), Some(4108));
+        assert_eq!(parse_duration("68:28"), Some(4108));
+        assert_eq!(parse_duration(" 4108 "), Some(4108));
+        assert_eq!(parse_duration("1 hr 8 mins"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn test_parse_duration_bound() {
+        assert_eq!(parse_duration_bound("45m"), Some(2700));
+        assert_eq!(parse_duration_bound("45min"), Some(2700));
+        assert_eq!(parse_duration_bound("1h"), Some(3600));
+        assert_eq!(parse_duration_bound("1H30m"), Some(5400));
+        assert_eq!(parse_duration_bound("90"), Some(5400));
+        assert_eq!(parse_duration_bound("m"), None);
+        assert_eq!(parse_duration_bound("long"), None);
+    }
+
+    #[test]
+    fn test_listening_time() {
+        let episode = |duration: &str| Episode {
+            id: 0,
+            title: String::new(),
+            date: String::new(),
+            duration: String::from(duration),
+            tags: vec![],
+            url: String::new(),
+            ..Default::default()
+        };
+
+        let episodes = [episode("01:08:28"), episode("45:00"), episode("unknown")];
+        assert_eq!(listening_time(&episodes), 4108 + 2700);
+        assert_eq!(listening_time([]), 0);
+
+        assert_eq!(approximate_listening_time(4108 + 2700), 7200);
+        assert_eq!(approximate_listening_time(5399), 3600);
+        assert_eq!(approximate_listening_time(2729), 2700);
+    }
+
+    #[test]
+    fn test_to_iso8601_duration() {
+        assert_eq!(to_iso8601_duration(4108), "PT1H8M28S");
+        assert_eq!(to_iso8601_duration(3600), "PT1H");
+        assert_eq!(to_iso8601_duration(0), "PT0S");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(4108), "01:08:28");
+        assert_eq!(format_duration(59), "00:00:59");
+        assert_eq!(format_duration(36000), "10:00:00");
+    }
+}
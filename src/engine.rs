@@ -0,0 +1,174 @@
+use crate::{
+    parse_query_strict, search_index, Config, Episode, QueryError, ScoringConfig, ShowConfig,
+    ShowIndex, SortOrder,
+};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Why the engine can't load an index or search a query
+#[derive(Debug)]
+pub enum EngineError {
+    /// a file can't be read
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    /// a file isn't json of the expected shape
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+    /// the index can't be searched, e.g. a tag lists an unknown episode
+    Index(String),
+    /// the query has a mistake, only reported by `search_strict`
+    Query(QueryError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Io { path, source } => write!(f, "Error at reading {}: {}", path, source),
+            EngineError::Parse { path, source } => {
+                write!(f, "Error at parsing {}: {}", path, source)
+            }
+            EngineError::Index(message) => write!(f, "{}", message),
+            EngineError::Query(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::Io { source, .. } => Some(source),
+            EngineError::Parse { source, .. } => Some(source),
+            EngineError::Index(_) => None,
+            EngineError::Query(err) => Some(err),
+        }
+    }
+}
+
+impl From<QueryError> for EngineError {
+    fn from(err: QueryError) -> Self {
+        EngineError::Query(err)
+    }
+}
+
+/// An episode of the results and its score
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoredEpisode<'a> {
+    pub score: i32,
+    pub episode: &'a Episode,
+}
+
+/// The search of a show without a server: the index, the stopwords of `[analyzer]`
+/// and the weights of `[scoring]`, for the cli, the tests and other frontends
+pub struct SearchEngine {
+    index: ShowIndex,
+    stopwords: HashSet<String>,
+    scoring: ScoringConfig,
+}
+
+impl SearchEngine {
+    pub fn new(index: ShowIndex, config: &Config) -> Self {
+        Self {
+            index,
+            stopwords: config.analyzer.stopwords(),
+            scoring: config.scoring.clone(),
+        }
+    }
+
+    /// Loads the index of a show, one of `config.show_configs()`
+    pub async fn load(show: &ShowConfig, config: &Config) -> Result<Self, EngineError> {
+        let index = ShowIndex::load(show).await.map_err(EngineError::Index)?;
+
+        Ok(Self::new(index, config))
+    }
+
+    pub fn index(&self) -> &ShowIndex {
+        &self.index
+    }
+
+    /// The episodes matching the query, best first, mistakes in the query are worked around
+    pub fn search(&self, query: &str) -> Vec<ScoredEpisode<'_>> {
+        search_index(&self.index, query, &self.stopwords, &self.scoring, || false)
+            .episodes
+            .into_iter()
+            .map(|(score, episode)| ScoredEpisode { score, episode })
+            .collect()
+    }
+
+    /// `search` in another order than the relevance, `SortOrder::Newest`
+    pub fn search_sorted(&self, query: &str, sort: SortOrder) -> Vec<ScoredEpisode<'_>> {
+        let mut results = self.search(query);
+        sort.sort(&mut results, |result| {
+            self.index.search_fields.get(&(result.episode.id as usize))
+        });
+        results
+    }
+
+    /// `search` reporting the mistakes of the query, e.g. an unterminated quote
+    pub fn search_strict(&self, query: &str) -> Result<Vec<ScoredEpisode<'_>>, EngineError> {
+        parse_query_strict(query)?;
+
+        Ok(self.search(query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EngineError, SearchEngine};
+    use crate::{
+        Config, Episode, EpisodesById, EpisodesByTag, QueryError, ShowIndex, SortOrder, Tag,
+    };
+
+    #[test]
+    fn test_search_engine() {
+        let episode = |id: i64, title: &str, tag: &str, date: &str| Episode {
+            id,
+            title: String::from(title),
+            tags: vec![Tag::from(tag)],
+            date: String::from(date),
+            ..Default::default()
+        };
+        let by_id = EpisodesById::from([
+            (
+                490,
+                episode(490, "490: The Arch Disaster", "arch", "January 10th, 2023"),
+            ),
+            (
+                512,
+                episode(512, "512: Unity Again", "ubuntu", "March 2nd, 2023"),
+            ),
+        ]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("ubuntu"), vec![512]),
+        ]);
+        let engine = SearchEngine::new(ShowIndex::new(by_id, by_tag, None), &Config::default());
+
+        let ids: Vec<_> = engine
+            .search("the arch")
+            .iter()
+            .map(|result| result.episode.id)
+            .collect();
+
+        assert_eq!(ids, vec![490]);
+        assert!(engine.search("-ubuntu").is_empty());
+        assert!(matches!(
+            engine.search_strict("\"arch"),
+            Err(EngineError::Query(QueryError::UnterminatedQuote(0)))
+        ));
+        assert_eq!(engine.search_strict("unity").unwrap().len(), 1);
+
+        let oldest: Vec<_> = engine
+            .search_sorted("arch OR unity", SortOrder::Oldest)
+            .iter()
+            .map(|result| result.episode.id)
+            .collect();
+        assert_eq!(oldest, vec![490, 512]);
+        let newest = engine.search_sorted("arch OR unity", SortOrder::Newest);
+        assert_eq!(newest[0].episode.id, 512);
+    }
+}
@@ -0,0 +1,231 @@
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{Html, IntoResponse, Response};
+use serde::Deserialize;
+use std::any::Any;
+use std::error::Error;
+use tera::Context;
+use tracing::error;
+use unplugged_engine::QueryError;
+
+use crate::assets::{error_chain, Templates};
+
+// served when error.html itself can't be rendered
+const FALLBACK_ERROR_PAGE: &str = "<!DOCTYPE html>\n<html lang=\"en\">\n<head><title>Unplugged search engine | error</title></head>\n<body>\n<h1>Something went wrong</h1>\n</body>\n</html>\n";
+
+/// Renders templates/error.html in `lang`, `query` fills the search box, `err` is
+/// the details of 500 pages in dev mode
+pub fn error_page(
+    templates: &Templates,
+    base_path: &str,
+    lang: &str,
+    status: StatusCode,
+    query: Option<&str>,
+    err: Option<&dyn Error>,
+) -> Response {
+    let translations = templates.translations();
+
+    let message = match (status, err) {
+        (StatusCode::BAD_REQUEST, Some(err)) => err.to_string(),
+        (StatusCode::FORBIDDEN, _) => translations.translate(lang, "error-forbidden", &[]),
+        (StatusCode::NOT_FOUND, _) => translations.translate(lang, "error-not-found", &[]),
+        (StatusCode::SERVICE_UNAVAILABLE, _) => {
+            translations.translate(lang, "error-maintenance", &[])
+        }
+        _ => translations.translate(lang, "error-internal", &[]),
+    };
+
+    let details = err
+        .filter(|_| status.is_server_error() && templates.is_dev())
+        .map(error_chain);
+
+    render_error_page(
+        templates,
+        base_path,
+        lang,
+        status,
+        query,
+        ErrorBody {
+            message: &message,
+            details,
+            suggestions: &[],
+        },
+    )
+}
+
+// what error.html shows below the title
+struct ErrorBody<'a> {
+    message: &'a str,
+    /// the error chain, dev mode only
+    details: Option<String>,
+    /// tags to link instead, /tag/<tag> 404s
+    suggestions: &'a [&'a str],
+}
+
+fn render_error_page(
+    templates: &Templates,
+    base_path: &str,
+    lang: &str,
+    status: StatusCode,
+    query: Option<&str>,
+    body: ErrorBody,
+) -> Response {
+    let title = templates
+        .translations()
+        .get(lang, &format!("status-{}", status.as_u16()), &[])
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or("Error").to_string());
+
+    let context = Context::from_serialize(serde_json::json!({
+        "status": status.as_u16(),
+        "title": title,
+        "message": body.message,
+        "query": query.unwrap_or_default(),
+        "details": body.details,
+        "suggestions": body.suggestions,
+        "base_path": base_path,
+        "lang": lang,
+    }));
+
+    let html = context
+        .and_then(|context| templates.render("error.html", &context))
+        .unwrap_or_else(|err| {
+            error!("Error at rendering the error page: {}", error_chain(&err));
+            String::from(FALLBACK_ERROR_PAGE)
+        });
+
+    (status, [(header::VARY, "Accept-Language")], Html(html)).into_response()
+}
+
+/// A 400 page explaining what's wrong with the query
+pub fn bad_query_page(
+    templates: &Templates,
+    base_path: &str,
+    lang: &str,
+    query: &str,
+    err: &QueryError,
+) -> Response {
+    let (key, args) = match err {
+        QueryError::Empty => ("query-empty", vec![]),
+        QueryError::UnterminatedQuote(index) => (
+            "query-unterminated-quote",
+            vec![("position", (index + 1).to_string())],
+        ),
+        QueryError::OnlyExclusions => ("query-only-exclusions", vec![]),
+        QueryError::UnbalancedParenthesis(index) => (
+            "query-unbalanced-parenthesis",
+            vec![("position", (index + 1).to_string())],
+        ),
+        QueryError::InvalidFilter(term) => {
+            ("query-invalid-filter", vec![("term", term.to_string())])
+        }
+    };
+
+    let message = templates
+        .translations()
+        .get(lang, key, &args)
+        .unwrap_or_else(|| err.to_string());
+
+    render_error_page(
+        templates,
+        base_path,
+        lang,
+        StatusCode::BAD_REQUEST,
+        Some(query),
+        ErrorBody {
+            message: &message,
+            details: None,
+            suggestions: &[],
+        },
+    )
+}
+
+/// 404 page of /tag/<tag> for tags the archive doesn't have, linking the closest ones
+pub fn unknown_tag_page(
+    templates: &Templates,
+    base_path: &str,
+    lang: &str,
+    tag: &str,
+    suggestions: &[&str],
+) -> Response {
+    let message =
+        templates
+            .translations()
+            .translate(lang, "error-unknown-tag", &[("tag", tag.to_string())]);
+
+    render_error_page(
+        templates,
+        base_path,
+        lang,
+        StatusCode::NOT_FOUND,
+        None,
+        ErrorBody {
+            message: &message,
+            details: None,
+            suggestions,
+        },
+    )
+}
+
+/// A 500 page for a failed render, logging the error
+pub fn internal_error_page(
+    templates: &Templates,
+    base_path: &str,
+    lang: &str,
+    query: Option<&str>,
+    err: &dyn Error,
+) -> Response {
+    error!("Error at rendering page: {}", error_chain(err));
+    error_page(
+        templates,
+        base_path,
+        lang,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        query,
+        Some(err),
+    )
+}
+
+#[derive(Deserialize)]
+struct QueryParam {
+    query: Option<String>,
+}
+
+/// 404 page of unknown paths, keeping the query of urls like /serch?query=nixos
+pub fn not_found_page(templates: &Templates, base_path: &str, lang: &str, uri: &Uri) -> Response {
+    let query = uri
+        .query()
+        .and_then(|query| serde_urlencoded::from_str::<QueryParam>(query).ok())
+        .and_then(|param| param.query);
+
+    error_page(
+        templates,
+        base_path,
+        lang,
+        StatusCode::NOT_FOUND,
+        query.as_deref(),
+        None,
+    )
+}
+
+/// The 500 page of handlers that panicked, so the connection still gets a response,
+/// in the default language since the request is gone
+pub fn panic_page(
+    templates: &Templates,
+    base_path: &str,
+    panic: Box<dyn Any + Send + 'static>,
+) -> Response {
+    let message = panic
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| panic.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic");
+
+    error!("Handler panicked: {}", message);
+    error_page(
+        templates,
+        base_path,
+        templates.translations().default_lang(),
+        StatusCode::INTERNAL_SERVER_ERROR,
+        None,
+        None,
+    )
+}
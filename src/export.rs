@@ -0,0 +1,138 @@
+use crate::Episode;
+use serde::Deserialize;
+
+/// Formats of /search/export, one line per episode after a header
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    /// a markdown table
+    Md,
+}
+
+const COLUMNS: [&str; 6] = ["title", "date", "duration", "tags", "url", "links"];
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Md => "text/markdown; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Md => "md",
+        }
+    }
+
+    /// The column names, ending with a newline
+    pub fn header(self) -> String {
+        match self {
+            ExportFormat::Csv => format!("{}\r\n", COLUMNS.join(",")),
+            ExportFormat::Md => format!(
+                "| {} |\n|{}\n",
+                COLUMNS.join(" | "),
+                "---|".repeat(COLUMNS.len())
+            ),
+        }
+    }
+
+    /// The line of an episode, ending with a newline
+    pub fn row(self, episode: &Episode) -> String {
+        let tags = episode.tag_names().join(", ");
+        // the urls of the show notes, one cell
+        let links = episode
+            .links
+            .iter()
+            .map(|link| link.url.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let fields = [
+            episode.title.as_str(),
+            &episode.date,
+            &episode.duration,
+            &tags,
+            &episode.url,
+            &links,
+        ];
+
+        match self {
+            ExportFormat::Csv => {
+                let fields: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+                format!("{}\r\n", fields.join(","))
+            }
+            ExportFormat::Md => {
+                let fields: Vec<_> = fields.iter().map(|field| markdown_cell(field)).collect();
+                format!("| {} |\n", fields.join(" | "))
+            }
+        }
+    }
+}
+
+// quoted when it holds a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// a pipe would end the cell and a line break the row
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExportFormat;
+    use crate::{Episode, Link, Tag};
+
+    fn episode() -> Episode {
+        Episode {
+            id: 512,
+            title: String::from("512: Unity, \"Again\" | Ubuntu"),
+            date: String::from("May 16th, 2023"),
+            duration: String::from("01:08:28"),
+            tags: vec![Tag::from("ubuntu"), Tag::from("unity")],
+            url: String::from("https://linuxunplugged.com/512"),
+            links: vec![
+                Link {
+                    url: String::from("https://ubuntuunity.org"),
+                    label: String::from("Ubuntu Unity"),
+                },
+                Link {
+                    url: String::from("https://github.com/ubuntu"),
+                    label: String::new(),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_csv() {
+        assert_eq!(
+            ExportFormat::Csv.header(),
+            "title,date,duration,tags,url,links\r\n"
+        );
+        assert_eq!(
+            ExportFormat::Csv.row(&episode()),
+            "\"512: Unity, \"\"Again\"\" | Ubuntu\",\"May 16th, 2023\",01:08:28,\"ubuntu, unity\",https://linuxunplugged.com/512,https://ubuntuunity.org https://github.com/ubuntu\r\n"
+        );
+    }
+
+    #[test]
+    fn test_export_markdown() {
+        assert_eq!(
+            ExportFormat::Md.header(),
+            "| title | date | duration | tags | url | links |\n|---|---|---|---|---|---|\n"
+        );
+        assert_eq!(
+            ExportFormat::Md.row(&episode()),
+            "| 512: Unity, \"Again\" \\| Ubuntu | May 16th, 2023 | 01:08:28 | ubuntu, unity | https://linuxunplugged.com/512 | https://ubuntuunity.org https://github.com/ubuntu |\n"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Episodes kept at most, the oldest favorite is dropped to make room
+/// so the cookie stays far below the 4KB browsers accept
+pub const FAVORITES_MAX: usize = 100;
+
+/// Ids of the starred episodes, oldest first
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Favorites {
+    ids: Vec<usize>,
+}
+
+impl Favorites {
+    pub fn ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.ids.contains(&id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Stars `id` again as the newest favorite
+    pub fn add(&mut self, id: usize) {
+        self.remove(id);
+
+        if self.ids.len() >= FAVORITES_MAX {
+            self.ids.remove(0);
+        }
+
+        self.ids.push(id);
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        self.ids.retain(|favorite| *favorite != id);
+    }
+}
+
+/// Signs the favorites cookie with HMAC-SHA256 so clients can read it
+/// but not fill it with made up values
+#[derive(Clone)]
+pub struct FavoritesSigner {
+    mac: Hmac<Sha256>,
+}
+
+impl FavoritesSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            mac: Hmac::new_from_slice(secret).expect("Error at creating the hmac, any key fits"),
+        }
+    }
+
+    /// The value of the cookie, `12-512-7.<hex signature>`
+    pub fn encode(&self, favorites: &Favorites) -> String {
+        let ids: Vec<_> = favorites.ids.iter().map(usize::to_string).collect();
+        let ids = ids.join("-");
+
+        let mut mac = self.mac.clone();
+        mac.update(ids.as_bytes());
+
+        format!("{}.{}", ids, hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// The favorites of a cookie value, `None` when the signature doesn't match
+    pub fn decode(&self, value: &str) -> Option<Favorites> {
+        let (ids, signature) = value.rsplit_once('.')?;
+        let signature = hex::decode(signature).ok()?;
+
+        let mut mac = self.mac.clone();
+        mac.update(ids.as_bytes());
+        mac.verify_slice(&signature).ok()?;
+
+        if ids.is_empty() {
+            return Some(Favorites::default());
+        }
+
+        let ids = ids
+            .split('-')
+            .map(|id| id.parse().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Favorites { ids })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Favorites, FavoritesSigner, FAVORITES_MAX};
+
+    #[test]
+    fn test_add_remove() {
+        let mut favorites = Favorites::default();
+        favorites.add(512);
+        favorites.add(7);
+        favorites.add(512);
+
+        assert_eq!(favorites.ids(), &[7, 512]);
+
+        favorites.remove(7);
+        assert_eq!(favorites.ids(), &[512]);
+
+        for id in 0..FAVORITES_MAX {
+            favorites.add(id + 1000);
+        }
+
+        assert_eq!(favorites.ids().len(), FAVORITES_MAX);
+        assert!(!favorites.contains(512));
+    }
+
+    #[test]
+    fn test_signed_cookie() {
+        let signer = FavoritesSigner::new(b"secret");
+
+        let mut favorites = Favorites::default();
+        favorites.add(12);
+        favorites.add(512);
+
+        let value = signer.encode(&favorites);
+        assert!(value.starts_with("12-512."));
+        assert_eq!(signer.decode(&value), Some(favorites));
+
+        let empty = signer.encode(&Favorites::default());
+        assert_eq!(signer.decode(&empty), Some(Favorites::default()));
+
+        // edited ids, another key and garbage are all rejected
+        let signature = value.split_once('.').unwrap().1;
+        assert!(signer.decode(&format!("12-513.{}", signature)).is_none());
+        assert!(FavoritesSigner::new(b"other").decode(&value).is_none());
+        assert!(signer.decode("12-512").is_none());
+        assert!(signer.decode("").is_none());
+    }
+}
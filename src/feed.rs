@@ -0,0 +1,218 @@
+use crate::{parse_date, parse_duration, Episode};
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+
+const FALLBACK_DATE: &str = "1970-01-01T00:00:00Z";
+
+const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+fn to_rfc3339(date: NaiveDate) -> String {
+    format!("{}T00:00:00Z", date.format("%Y-%m-%d"))
+}
+
+/// Serializes the episodes into an Atom 1.0 feed.
+/// `feed_url` is the absolute url of the feed itself and doubles as the feed id,
+/// each entry uses the episode url as its stable id.
+pub fn atom_feed(title: &str, feed_url: &str, episodes: &[&Episode]) -> String {
+    let updated = episodes
+        .iter()
+        .filter_map(|episode| parse_date(&episode.date))
+        .max()
+        .map(to_rfc3339)
+        .unwrap_or_else(|| FALLBACK_DATE.to_string());
+
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape_xml(title)));
+    xml.push_str(&format!("  <id>{}</id>\n", escape_xml(feed_url)));
+    xml.push_str(&format!(
+        "  <link rel=\"self\" href=\"{}\"/>\n",
+        escape_xml(feed_url)
+    ));
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated));
+    xml.push_str("  <author><name>Unplugged search</name></author>\n");
+
+    for episode in episodes {
+        let updated = parse_date(&episode.date)
+            .map(to_rfc3339)
+            .unwrap_or_else(|| FALLBACK_DATE.to_string());
+
+        let url = escape_xml(&episode.url);
+
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_xml(&episode.title)
+        ));
+        xml.push_str(&format!("    <id>{}</id>\n", url));
+        xml.push_str(&format!("    <link href=\"{}\"/>\n", url));
+        xml.push_str(&format!("    <updated>{}</updated>\n", updated));
+
+        for tag in &episode.tags {
+            xml.push_str(&format!(
+                "    <category term=\"{}\"/>\n",
+                escape_xml(&tag.name)
+            ));
+        }
+
+        xml.push_str(&format!(
+            "    <summary>{} | {}</summary>\n",
+            escape_xml(&episode.date),
+            escape_xml(&episode.duration)
+        ));
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Serializes the episodes into a JSON Feed 1.1, the same entries as `atom_feed`
+/// plus the audio files as attachments. `home_page_url` is the html page of the feed.
+pub fn json_feed(title: &str, home_page_url: &str, feed_url: &str, episodes: &[&Episode]) -> Value {
+    let items: Vec<_> = episodes
+        .iter()
+        .map(|episode| {
+            let mut item = json!({
+                "id": episode.url,
+                "url": episode.url,
+                "title": episode.title,
+                "content_text": format!("{} | {}", episode.date, episode.duration),
+                "tags": episode.tag_names(),
+            });
+
+            if let Some(summary) = &episode.description {
+                item["summary"] = json!(summary);
+            }
+
+            if let Some(date) = parse_date(&episode.date) {
+                item["date_published"] = json!(to_rfc3339(date));
+            }
+
+            if let Some(audio) = &episode.audio {
+                let mut attachment = json!({
+                    "url": audio.url,
+                    "mime_type": audio.mime_type.as_deref().unwrap_or("audio/mpeg"),
+                });
+
+                if let Some(bytes) = audio.bytes {
+                    attachment["size_in_bytes"] = json!(bytes);
+                }
+
+                if let Some(seconds) = parse_duration(&episode.duration) {
+                    attachment["duration_in_seconds"] = json!(seconds);
+                }
+
+                item["attachments"] = json!([attachment]);
+            }
+
+            item
+        })
+        .collect();
+
+    json!({
+        "version": JSON_FEED_VERSION,
+        "title": title,
+        "home_page_url": home_page_url,
+        "feed_url": feed_url,
+        "authors": [{ "name": "Unplugged search" }],
+        "items": items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{atom_feed, escape_xml, json_feed};
+    use crate::{Enclosure, Episode, Tag};
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("<rust & \"linux\">"),
+            "&lt;rust &amp; &quot;linux&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn test_atom_feed() {
+        let episode = Episode {
+            id: 512,
+            title: String::from("512: Docker & Friends"),
+            date: String::from("March 19th, 2023"),
+            duration: String::from("01:08:28"),
+            tags: vec![Tag::from("docker")],
+            url: String::from("https://linuxunplugged.com/512"),
+            ..Default::default()
+        };
+
+        let feed = atom_feed(
+            "nixos",
+            "http://localhost:3000/search.atom?query=nixos",
+            &[&episode],
+        );
+
+        assert!(feed.contains("<id>https://linuxunplugged.com/512</id>"));
+        assert!(feed.contains("<title>512: Docker &amp; Friends</title>"));
+        assert!(feed.contains("<updated>2023-03-19T00:00:00Z</updated>"));
+        assert!(feed.contains("<category term=\"docker\"/>"));
+    }
+
+    #[test]
+    fn test_json_feed() {
+        let episode = Episode {
+            id: 512,
+            title: String::from("512: Docker & Friends"),
+            date: String::from("March 19th, 2023"),
+            duration: String::from("01:08:28"),
+            tags: vec![Tag::from("docker")],
+            url: String::from("https://linuxunplugged.com/512"),
+            audio: Some(Enclosure {
+                url: String::from("https://example.com/512.mp3"),
+                bytes: Some(65_000_000),
+                mime_type: None,
+            }),
+            ..Default::default()
+        };
+
+        let feed = json_feed(
+            "nixos",
+            "http://localhost:3000/search?query=nixos",
+            "http://localhost:3000/search.json?query=nixos",
+            &[&episode],
+        );
+
+        assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(
+            feed["feed_url"],
+            "http://localhost:3000/search.json?query=nixos"
+        );
+
+        let item = &feed["items"][0];
+
+        assert_eq!(item["id"], "https://linuxunplugged.com/512");
+        assert_eq!(item["date_published"], "2023-03-19T00:00:00Z");
+        assert_eq!(item["tags"][0], "docker");
+        assert!(item.get("summary").is_none());
+        assert_eq!(item["attachments"][0]["mime_type"], "audio/mpeg");
+        assert_eq!(item["attachments"][0]["duration_in_seconds"], 4108);
+    }
+}
@@ -0,0 +1,101 @@
+/// Levenshtein distance between two strings, in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// The letters and digits of a tag, so "nix-os", "Nix OS" and "nixos" are the same tag
+pub fn tag_key(tag: &str) -> String {
+    tag.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Edits allowed between a misspelled tag and a known one, a third of its length
+pub fn max_tag_distance(tag: &str) -> usize {
+    (tag.chars().count() / 3).max(1)
+}
+
+/// The words of `vocabulary` closest to a misspelled word, within `max_tag_distance` edits,
+/// none when it is spelled right
+pub fn closest_words<'a, I>(word: &str, vocabulary: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let max_distance = max_tag_distance(word);
+    let length = word.chars().count();
+
+    let mut closest = Vec::new();
+    let mut best = max_distance;
+
+    for candidate in vocabulary {
+        // a difference in length takes as many edits
+        if candidate.chars().count().abs_diff(length) > best {
+            continue;
+        }
+
+        let distance = edit_distance(word, candidate);
+
+        if distance == 0 {
+            return Vec::new();
+        }
+
+        if distance < best {
+            best = distance;
+            closest.clear();
+        }
+
+        if distance == best && !closest.contains(&candidate) {
+            closest.push(candidate);
+        }
+    }
+
+    closest.sort();
+    closest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest_words, edit_distance, max_tag_distance, tag_key};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("nixos", "nixos"), 0);
+        assert_eq!(edit_distance("nixso", "nixos"), 2);
+        assert_eq!(edit_distance("fedra", "fedora"), 1);
+        assert_eq!(edit_distance("", "arch"), 4);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_tag_key() {
+        assert_eq!(tag_key("nix-os"), "nixos");
+        assert_eq!(tag_key("Docker Compose"), "dockercompose");
+        assert_eq!(max_tag_distance("nix"), 1);
+        assert_eq!(max_tag_distance("docker compose"), 4);
+    }
+
+    #[test]
+    fn test_closest_words() {
+        let vocabulary = ["kubernetes", "docker", "dock", "nixos", "nixpkgs", "fedora"];
+
+        assert_eq!(closest_words("kuberntes", vocabulary), vec!["kubernetes"]);
+        assert_eq!(closest_words("dockr", vocabulary), vec!["dock", "docker"]);
+        assert_eq!(closest_words("nixso", vocabulary), Vec::<&str>::new());
+        assert_eq!(closest_words("fedora", vocabulary), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::parse_date;
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How the results of a combined search are split, `group_by=show`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Show,
+}
+
+/// A section of the results page, the episodes keep the order of the results
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ResultGroup<T> {
+    /// heading of the section, none for the episodes without a year
+    pub name: Option<String>,
+    pub count: usize,
+    /// episodes listed before the section, ranks continue across sections
+    pub offset: usize,
+    pub episodes: Vec<T>,
+}
+
+impl<T> ResultGroup<T> {
+    /// Every result in a single section without heading
+    pub fn ungrouped(episodes: Vec<T>) -> Vec<Self> {
+        vec![ResultGroup {
+            name: None,
+            count: episodes.len(),
+            offset: 0,
+            episodes,
+        }]
+    }
+}
+
+/// Splits the results by the year of their date, newest year first,
+/// the episodes whose date doesn't parse go last
+pub fn group_by_year<T, F>(episodes: Vec<T>, date: F) -> Vec<ResultGroup<T>>
+where
+    F: Fn(&T) -> &str,
+{
+    let mut years: BTreeMap<Option<i32>, Vec<T>> = BTreeMap::new();
+
+    for episode in episodes {
+        let year = parse_date(date(&episode)).map(|date| date.year());
+        years.entry(year).or_default().push(episode);
+    }
+
+    // None sorts first, so it's last once reversed
+    let mut groups = Vec::new();
+    let mut offset = 0;
+
+    for (year, episodes) in years.into_iter().rev() {
+        let count = episodes.len();
+
+        groups.push(ResultGroup {
+            name: year.map(|year| year.to_string()),
+            count,
+            offset,
+            episodes,
+        });
+
+        offset += count;
+    }
+
+    groups
+}
+
+/// Splits the results by `name` (e.g. the show), the section of the best result first,
+/// each section keeps its first `limit` episodes and counts all of them
+pub fn group_by_name<T, F>(episodes: Vec<T>, name: F, limit: usize) -> Vec<ResultGroup<T>>
+where
+    F: Fn(&T) -> String,
+{
+    let mut groups: Vec<ResultGroup<T>> = Vec::new();
+
+    for episode in episodes {
+        let episode_name = name(&episode);
+
+        let index = match groups
+            .iter()
+            .position(|group| group.name.as_deref() == Some(episode_name.as_str()))
+        {
+            Some(index) => index,
+            None => {
+                groups.push(ResultGroup {
+                    name: Some(episode_name),
+                    count: 0,
+                    offset: 0,
+                    episodes: Vec::new(),
+                });
+                groups.len() - 1
+            }
+        };
+
+        let group = &mut groups[index];
+        group.count += 1;
+
+        if group.episodes.len() < limit {
+            group.episodes.push(episode);
+        }
+    }
+
+    let mut offset = 0;
+
+    for group in groups.iter_mut() {
+        group.offset = offset;
+        offset += group.episodes.len();
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_by_name, group_by_year, ResultGroup};
+
+    #[test]
+    fn test_group_by_year() {
+        let episodes = vec![
+            ("491", "February 4th, 2022"),
+            ("512", "May 16th, 2023"),
+            ("000", "someday"),
+            ("490", "January 1st, 2022"),
+        ];
+
+        let groups = group_by_year(episodes, |(_, date)| date);
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                let ids: Vec<_> = group.episodes.iter().map(|(id, _)| *id).collect();
+                (group.name.as_deref(), group.count, group.offset, ids)
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (Some("2023"), 1, 0, vec!["512"]),
+                (Some("2022"), 2, 1, vec!["491", "490"]),
+                (None, 1, 3, vec!["000"]),
+            ]
+        );
+
+        assert_eq!(ResultGroup::ungrouped(vec![1, 2])[0].count, 2);
+    }
+
+    #[test]
+    fn test_group_by_name() {
+        let episodes = vec![("ssh", 1), ("lup", 2), ("ssh", 3), ("ssh", 4), ("lup", 5)];
+
+        let groups = group_by_name(episodes, |(show, _)| show.to_string(), 2);
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                let ids: Vec<_> = group.episodes.iter().map(|(_, id)| *id).collect();
+                (group.name.as_deref(), group.count, group.offset, ids)
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (Some("ssh"), 3, 0, vec![1, 3]),
+                (Some("lup"), 2, 2, vec![2, 5]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::{SecondsFormat, Utc};
+use serde_json::json;
+use unplugged_engine::{IndexStaleness, IndexStatus, ShowIndex};
+
+use crate::shows::Shows;
+use crate::AppState;
+
+/// Days since the newest episode when the archive is out of date, for the banner of the pages
+pub fn stale_days(state: &AppState, index: &ShowIndex) -> Option<i64> {
+    let staleness = IndexStaleness::new(index, Utc::now(), state.config().staleness.max_days);
+    staleness.stale.then_some(staleness.age_days).flatten()
+}
+
+// healthz, the process is up, always 200 while it serves: a show in maintenance or swapping its index
+// isn't a reason to restart it, /readyz fails then
+pub async fn handle_healthz(State(shows): State<Shows>) -> impl IntoResponse {
+    let indices: Vec<_> = shows
+        .iter()
+        .map(|show| {
+            let episodes = show.index().episodes_by_id.len();
+            json!({
+                "show": show.show.slug,
+                "loaded": show.index.is_ready(),
+                "episodes": episodes,
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "status": "ok",
+        "uptime_seconds": shows[0].started_at.elapsed().as_secs(),
+        "indices": indices,
+    }))
+}
+
+// readyz, the index of every show is loaded with episodes and the templates are compiled
+pub async fn handle_readyz(State(shows): State<Shows>) -> impl IntoResponse {
+    let indices: Vec<_> = shows.iter().map(|show| show.index()).collect();
+    let episodes = indices
+        .iter()
+        .map(|index| index.episodes_by_id.len())
+        .sum::<usize>();
+    let tags = indices
+        .iter()
+        .map(|index| index.episodes_by_tag.len())
+        .sum::<usize>();
+    let templates = shows[0].templates.count();
+
+    // the shows in maintenance or without episodes and why
+    let unavailable: Vec<_> = shows
+        .iter()
+        .zip(indices.iter())
+        .filter_map(|(show, index)| match show.index.status() {
+            IndexStatus::Ready if index.episodes_by_id.is_empty() => {
+                Some((show, String::from("the index has no episodes")))
+            }
+            IndexStatus::Ready => None,
+            IndexStatus::Unavailable(reason) => Some((show, reason)),
+        })
+        .map(|(show, reason)| json!({ "show": show.show.slug, "reason": reason }))
+        .collect();
+
+    // an out of date archive is still served, it's reported without failing the check
+    let now = Utc::now();
+    let max_days = shows[0].config().staleness.max_days;
+    let staleness: Vec<_> = shows
+        .iter()
+        .zip(indices.iter())
+        .map(|(show, index)| {
+            let staleness = IndexStaleness::new(index, now, max_days);
+            json!({
+                "show": show.show.slug,
+                "newest_episode": staleness.newest_episode.map(|date| date.to_string()),
+                "age_days": staleness.age_days,
+                "refreshed_at": staleness.refreshed_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                "stale": staleness.stale,
+            })
+        })
+        .collect();
+    let stale = staleness.iter().any(|show| show["stale"] == true);
+
+    let index_ready = unavailable.is_empty();
+    let templates_ready = templates > 0;
+    let ready = index_ready && templates_ready;
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let body = json!({
+        "status": if ready { "ready" } else { "not ready" },
+        "checks": {
+            "index": {
+                "ready": index_ready,
+                "episodes": episodes,
+                "tags": tags,
+                "unavailable": unavailable,
+            },
+            "templates": { "ready": templates_ready, "count": templates },
+            "staleness": { "stale": stale, "max_days": max_days, "shows": staleness },
+        },
+    });
+
+    (status, Json(body))
+}
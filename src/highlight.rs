@@ -0,0 +1,291 @@
+use crate::{analyze_term, stem, Episode, ParseResult, QueryField};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A part of a text, `matched` when it's a term of the query
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Highlight {
+    pub text: String,
+    pub matched: bool,
+}
+
+/// The byte ranges of `text` matching one of the lowercase `terms`, case insensitive,
+/// the overlapping ones merged
+pub fn match_ranges(text: &str, terms: &[String]) -> Vec<Range<usize>> {
+    // the lowercase text and the byte of `text` every one of its bytes comes from
+    let mut lower = String::with_capacity(text.len());
+    let mut origin = Vec::with_capacity(text.len() + 1);
+
+    for (start, ch) in text.char_indices() {
+        for lower_ch in ch.to_lowercase() {
+            lower.push(lower_ch);
+            origin.extend(std::iter::repeat_n(start, lower_ch.len_utf8()));
+        }
+    }
+    origin.push(text.len());
+
+    let mut ranges: Vec<Range<usize>> = terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .flat_map(|term| {
+            lower
+                .match_indices(term.as_str())
+                .map(|(start, term)| origin[start]..origin[start + term.len()])
+                .collect::<Vec<_>>()
+        })
+        .filter(|range| !range.is_empty())
+        .collect();
+
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::new();
+
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+/// `text` split into the parts matching a term and the others, the templates wrap
+/// the matches in `<mark>` and escape every part
+pub fn highlight(text: &str, terms: &[String]) -> Vec<Highlight> {
+    let mut parts = Vec::new();
+    let mut end = 0;
+
+    let mut push = |range: Range<usize>, matched| {
+        if !range.is_empty() {
+            parts.push(Highlight {
+                text: text[range].to_string(),
+                matched,
+            });
+        }
+    };
+
+    for range in match_ranges(text, terms) {
+        push(end..range.start, false);
+        end = range.end;
+        push(range, true);
+    }
+
+    push(end..text.len(), false);
+
+    parts
+}
+
+// characters of the show notes quoted around their first match
+const SNIPPET_CHARS: usize = 200;
+
+/// The part of `text` around its first match, at most `chars` characters cut at
+/// whitespace with an ellipsis where it's cut, highlighted, empty without a match
+pub fn snippet(text: &str, terms: &[String], chars: usize) -> Vec<Highlight> {
+    let Some(first) = match_ranges(text, terms).first().cloned() else {
+        return Vec::new();
+    };
+
+    // a third of the snippet before the match, the rest after it
+    let before = text[..first.start]
+        .char_indices()
+        .rev()
+        .nth(chars / 3)
+        .map(|(start, _)| start);
+    let start = match before {
+        Some(start) => text[start..first.start]
+            .find(char::is_whitespace)
+            .map_or(first.start, |space| start + space + 1),
+        None => 0,
+    };
+
+    let end = match text[start..].char_indices().nth(chars) {
+        Some((end, _)) if start + end > first.end => text[..start + end]
+            .rfind(char::is_whitespace)
+            .filter(|space| *space >= first.end)
+            .unwrap_or(start + end),
+        Some(_) => first.end,
+        None => text.len(),
+    };
+
+    let mut parts = highlight(&text[start..end], terms);
+
+    if start > 0 {
+        parts.insert(
+            0,
+            Highlight {
+                text: String::from("…"),
+                matched: false,
+            },
+        );
+    }
+
+    if end < text.len() {
+        parts.push(Highlight {
+            text: String::from("…"),
+            matched: false,
+        });
+    }
+
+    parts
+}
+
+/// Why an episode is in the results: the terms of the query found in its title,
+/// its tags or its show notes, the title with them highlighted, the tags matching
+/// them and a snippet of the show notes
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Matches {
+    pub terms: Vec<String>,
+    pub title: Vec<Highlight>,
+    /// lowercase names of the tags
+    pub tags: Vec<String>,
+    /// the show notes around their first match, empty when they don't match
+    pub description: Vec<Highlight>,
+}
+
+impl Matches {
+    /// `terms` are the lowercase terms of `highlight_terms`, a tag matches a term
+    /// like in the search, when either contains the other
+    pub fn new(episode: &Episode, terms: &[String]) -> Self {
+        let title = episode.title.to_lowercase();
+        let description = episode.description.as_deref().unwrap_or_default();
+        let notes = description.to_lowercase();
+        let tags: Vec<_> = episode
+            .tags
+            .iter()
+            .map(|tag| tag.name.to_lowercase())
+            .filter(|tag| {
+                terms
+                    .iter()
+                    .any(|term| tag.contains(term.as_str()) || term.contains(tag.as_str()))
+            })
+            .collect();
+
+        let mut matched = Vec::new();
+
+        for term in terms {
+            let in_tags = tags
+                .iter()
+                .any(|tag| tag.contains(term.as_str()) || term.contains(tag.as_str()));
+
+            let in_text = title.contains(term.as_str()) || notes.contains(term.as_str());
+
+            if (in_text || in_tags) && !matched.contains(term) {
+                matched.push(term.clone());
+            }
+        }
+
+        Self {
+            terms: matched,
+            title: highlight(&episode.title, terms),
+            tags,
+            description: snippet(description, terms, SNIPPET_CHARS),
+        }
+    }
+}
+
+/// The lowercase terms of a query the titles and the tags are highlighted with: the plain
+/// terms and the values of `tag:` and `title:`, without the stopwords, with the parts
+/// of the compounds and the stems of the inflected terms
+pub fn highlight_terms(query: &ParseResult, stopwords: &HashSet<String>) -> Vec<String> {
+    let mut terms = Vec::new();
+
+    for (field, value) in query.scoped_terms() {
+        if matches!(field, None | Some(QueryField::Tag | QueryField::Title)) {
+            for term in analyze_term(value, stopwords) {
+                let stem = stem(&term);
+
+                for term in std::iter::once(term).chain(stem) {
+                    if !term.is_empty() && !terms.contains(&term) {
+                        terms.push(term);
+                    }
+                }
+            }
+        }
+    }
+
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlight, highlight_terms, match_ranges, snippet, Highlight, Matches};
+    use crate::{parse_query, Episode, Tag};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_highlight() {
+        let terms =
+            |terms: &[&str]| -> Vec<String> { terms.iter().map(|t| t.to_string()).collect() };
+        let part = |text: &str, matched| Highlight {
+            text: String::from(text),
+            matched,
+        };
+
+        assert_eq!(
+            highlight("Docker on NixOS", &terms(&["nixos", "dock"])),
+            vec![
+                part("Dock", true),
+                part("er on ", false),
+                part("NixOS", true)
+            ]
+        );
+
+        // overlapping matches are merged, the ranges are bytes of the original text
+        assert_eq!(
+            match_ranges("ZFS ZFS", &terms(&["zfs z", "s zf"])),
+            vec![0..6]
+        );
+        assert_eq!(match_ranges("Ça marche", &terms(&["ça"])), vec![0..3]);
+        assert_eq!(
+            highlight("Linux", &terms(&["bsd"])),
+            vec![part("Linux", false)]
+        );
+        assert!(highlight("", &terms(&["bsd"])).is_empty());
+    }
+
+    #[test]
+    fn test_snippet() {
+        let terms = vec![String::from("send")];
+        let text = |parts: Vec<Highlight>| -> String {
+            parts.iter().map(|part| part.text.as_str()).collect()
+        };
+
+        let notes = "We take ZFS snapshots every hour and send them to the NAS overnight";
+
+        // cut at whitespace around the first match
+        let parts = snippet(notes, &terms, 30);
+        assert_eq!(text(parts.clone()), "…hour and send them to the NAS…");
+        assert!(parts.iter().any(|part| part.matched && part.text == "send"));
+
+        // short notes are quoted whole
+        assert_eq!(text(snippet(notes, &terms, 200)), notes);
+        assert!(snippet(notes, &[String::from("btrfs")], 200).is_empty());
+    }
+
+    #[test]
+    fn test_matches() {
+        let stopwords = HashSet::from([String::from("the")]);
+        let query = parse_query("the Steam-Deck title:review guest:wendell -gnome");
+        let terms = highlight_terms(&query, &stopwords);
+
+        assert_eq!(terms, vec!["steam-deck", "steam", "deck", "review"]);
+        assert_eq!(
+            highlight_terms(&parse_query("containers"), &stopwords),
+            vec!["containers", "container"]
+        );
+
+        let episode = Episode {
+            title: String::from("Steam Deck Review"),
+            tags: vec![Tag::from("Steam Deck"), Tag::from("gaming")],
+            ..Default::default()
+        };
+        let matches = Matches::new(&episode, &terms);
+
+        assert_eq!(matches.terms, vec!["steam", "deck", "review"]);
+        assert_eq!(matches.tags, vec!["steam deck"]);
+        assert_eq!(matches.title.len(), 5);
+        assert!(matches.title[0].matched);
+    }
+}
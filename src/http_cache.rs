@@ -0,0 +1,186 @@
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use unplugged_engine::normalize_query;
+
+use crate::AppState;
+
+const DYNAMIC_CACHE_CONTROL: &str = "public, max-age=300, must-revalidate";
+const STATIC_CACHE_CONTROL: &str = "public, max-age=3600";
+// responses setting a cookie (the csrf token) are only kept by the browser
+const PRIVATE_CACHE_CONTROL: &str = "private, no-cache";
+
+// responses derived from the index, they only change with the index or the query
+const DYNAMIC_PATHS: [&str; 11] = [
+    "/search",
+    "/search.atom",
+    "/search/export",
+    "/suggest",
+    "/api/alfred",
+    "/opensearch.xml",
+    "/sitemap.xml",
+    "/episode/",
+    "/tags",
+    "/api/tags",
+    "/related/",
+];
+
+// long lived connections, live data and per-client pages, never cached,
+// the ones ending with a slash are prefixes
+const UNCACHED_PATHS: [&str; 9] = [
+    "/search/stream",
+    "/ws",
+    "/metrics",
+    "/healthz",
+    "/readyz",
+    "/favorites",
+    "/playback/",
+    "/alerts/",
+    "/api/",
+];
+
+// `/episode/` matches the pages under it, `/tags` only itself
+fn matches_path(patterns: &[&str], path: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('/') {
+            Some(prefix) => path.starts_with(prefix) && path.len() > pattern.len(),
+            None => path == *pattern,
+        })
+}
+
+fn is_dynamic(path: &str) -> bool {
+    matches_path(&DYNAMIC_PATHS, path)
+}
+
+// the Cache-Control of a response, the one the handler set is kept
+fn set_cache_control(response: &mut Response, cache_control: &'static str) {
+    let headers = response.headers_mut();
+
+    if headers.contains_key(header::CACHE_CONTROL) {
+        return;
+    }
+
+    let cache_control = if headers.contains_key(header::SET_COOKIE) {
+        PRIVATE_CACHE_CONTROL
+    } else {
+        cache_control
+    };
+
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+}
+
+// the index and config versions and everything the response depends on,
+// query parameters are normalized so "NixOS  fedora" and "nixos fedora" share the tag
+fn compute_etag(
+    index_version: u64,
+    config_version: u64,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+) -> String {
+    let mut params: Vec<(String, String)> = query
+        .and_then(|query| serde_urlencoded::from_str(query).ok())
+        .unwrap_or_default();
+
+    for (_, value) in params.iter_mut() {
+        *value = normalize_query(value);
+    }
+
+    params.sort();
+
+    let mut hasher = DefaultHasher::new();
+
+    index_version.hash(&mut hasher);
+    config_version.hash(&mut hasher);
+    path.hash(&mut hasher);
+    params.hash(&mut hasher);
+
+    // the language of the page is negotiated from Accept-Language
+    for name in [
+        header::HOST.as_str(),
+        header::ACCEPT_LANGUAGE.as_str(),
+        "HX-Request",
+    ] {
+        headers
+            .get(name)
+            .map(|value| value.as_bytes())
+            .hash(&mut hasher);
+    }
+
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+fn matches_etag(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
+
+/// Sets Cache-Control on successful GET responses without one, private when they set
+/// a cookie and none for the per-client and live routes, and answers
+/// `304 Not Modified` for the dynamic routes when If-None-Match matches the ETag
+pub async fn conditional_get<B>(
+    State(state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_string();
+
+    if !is_dynamic(&path) {
+        let mut response = next.run(request).await;
+
+        if response.status().is_success() && !matches_path(&UNCACHED_PATHS, &path) {
+            set_cache_control(&mut response, STATIC_CACHE_CONTROL);
+        }
+
+        return response;
+    }
+
+    let etag = compute_etag(
+        state.index().version,
+        state.config.version(),
+        &path,
+        request.uri().query(),
+        request.headers(),
+    );
+    let etag_value = HeaderValue::from_str(&etag).expect("Error at building etag header");
+
+    if matches_etag(request.headers(), &etag) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag_value),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_static(DYNAMIC_CACHE_CONTROL),
+                ),
+            ],
+        )
+            .into_response();
+    }
+
+    let mut response = next.run(request).await;
+
+    if response.status() == StatusCode::OK {
+        response.headers_mut().insert(header::ETAG, etag_value);
+        set_cache_control(&mut response, DYNAMIC_CACHE_CONTROL);
+    }
+
+    response
+}
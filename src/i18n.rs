@@ -0,0 +1,383 @@
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Error at parsing a catalog, with the 1-based line of the catalog
+#[derive(Debug, PartialEq, Eq)]
+pub struct CatalogError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// The messages of one language, parsed from a subset of the fluent syntax:
+///
+/// ```text
+/// # comment
+/// search-placeholder = search here...
+/// results-count =
+///     .one = { $count } result
+///     .other = { $count } results
+/// ```
+///
+/// `{ $name }` is replaced by the argument `name`, the `.one` and `.other`
+/// variants are picked by the `count` argument
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(source: &str) -> Result<Self, CatalogError> {
+        let mut messages = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: &str| CatalogError {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, value)) = trimmed.split_once('=') else {
+                return Err(error("expected `key = value`"));
+            };
+
+            let (name, value) = (name.trim(), value.trim());
+
+            // indented `.variant = value` lines belong to the message above
+            if let Some(variant) = name.strip_prefix('.') {
+                let Some(key) = current
+                    .as_ref()
+                    .filter(|_| line.starts_with(char::is_whitespace))
+                else {
+                    return Err(error("variant outside of a message"));
+                };
+
+                messages.insert(format!("{}.{}", key, variant), value.to_string());
+                continue;
+            }
+
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+            if !valid {
+                return Err(error("invalid message key"));
+            }
+
+            if !value.is_empty() {
+                messages.insert(name.to_string(), value.to_string());
+            }
+
+            current = Some(name.to_string());
+        }
+
+        Ok(Self { messages })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // the plural variant of `key` for `count`, english and spanish share the rule
+    fn lookup(&self, key: &str, count: Option<i64>) -> Option<&str> {
+        let variant = count.map(|count| if count == 1 { "one" } else { "other" });
+
+        variant
+            .and_then(|variant| self.get(&format!("{}.{}", key, variant)))
+            .or_else(|| self.get(key))
+    }
+}
+
+/// The catalogs of every supported language, the first one is the default
+/// and fills the messages missing from the others
+#[derive(Clone, Debug)]
+pub struct Translations {
+    catalogs: Vec<(String, Catalog)>,
+}
+
+impl Translations {
+    pub fn new(default_lang: &str, default: Catalog) -> Self {
+        Self {
+            catalogs: vec![(default_lang.to_lowercase(), default)],
+        }
+    }
+
+    pub fn add(&mut self, lang: &str, catalog: Catalog) {
+        self.catalogs.push((lang.to_lowercase(), catalog));
+    }
+
+    pub fn default_lang(&self) -> &str {
+        &self.catalogs[0].0
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.catalogs.iter().map(|(lang, _)| lang.as_str())
+    }
+
+    /// The best supported language of an `Accept-Language` header,
+    /// `es-MX` matches `es`, the default language when none matches
+    pub fn negotiate(&self, accept_language: &str) -> &str {
+        let supported = |tag: &str| {
+            let primary = tag.split('-').next().unwrap_or(tag);
+
+            self.languages()
+                .find(|lang| *lang == tag)
+                .or_else(|| self.languages().find(|lang| *lang == primary))
+        };
+
+        parse_accept_language(accept_language)
+            .iter()
+            .find_map(|tag| supported(tag))
+            .unwrap_or_else(|| self.default_lang())
+    }
+
+    /// The message `key` of `lang`, the key itself when no catalog has it
+    pub fn translate(&self, lang: &str, key: &str, args: &[(&str, String)]) -> String {
+        self.get(lang, key, args).unwrap_or_else(|| key.to_string())
+    }
+
+    /// The message `key` of `lang` with its `{ $name }` placeholders replaced
+    pub fn get(&self, lang: &str, key: &str, args: &[(&str, String)]) -> Option<String> {
+        let count = args
+            .iter()
+            .find(|(name, _)| *name == "count")
+            .and_then(|(_, value)| value.parse::<f64>().ok())
+            .map(|count| count as i64);
+
+        let catalog = self
+            .catalogs
+            .iter()
+            .find(|(catalog_lang, _)| catalog_lang == lang);
+
+        let message = catalog
+            .and_then(|(_, catalog)| catalog.lookup(key, count))
+            .or_else(|| self.catalogs[0].1.lookup(key, count));
+
+        message.map(|message| interpolate(message, args))
+    }
+
+    /// The length of an episode in `lang` rounded to the minute, 5527 -> "1 hr 32 min"
+    pub fn humanize_duration(&self, lang: &str, seconds: u64) -> String {
+        let minutes = ((seconds + 30) / 60).max(1);
+        let (hours, minutes) = (minutes / 60, minutes % 60);
+
+        let key = match (hours, minutes) {
+            (0, _) => "duration-minutes",
+            (_, 0) => "duration-hours",
+            _ => "duration-hours-minutes",
+        };
+
+        self.translate(
+            lang,
+            key,
+            &[
+                ("hours", hours.to_string()),
+                ("minutes", minutes.to_string()),
+            ],
+        )
+    }
+
+    /// The date in the format of `lang`, "May 16, 2023" or "16 de mayo de 2023"
+    pub fn localize_date(&self, lang: &str, date: NaiveDate) -> String {
+        let month = self.translate(lang, &format!("month-{}", date.month()), &[]);
+
+        self.translate(
+            lang,
+            "date-format",
+            &[
+                ("day", date.day().to_string()),
+                ("month", month),
+                ("year", date.year().to_string()),
+            ],
+        )
+    }
+}
+
+fn interpolate(message: &str, args: &[(&str, String)]) -> String {
+    let mut result = String::with_capacity(message.len());
+    let mut rest = message;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+
+        result.push_str(&rest[..start]);
+
+        let placeholder = rest[start + 1..start + end].trim();
+        let value = placeholder.strip_prefix('$').and_then(|name| {
+            args.iter()
+                .find(|(arg, _)| *arg == name)
+                .map(|(_, value)| value.as_str())
+        });
+
+        match value {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[start..start + end + 1]),
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Language tags of an `Accept-Language` header, most preferred first
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let tag = params.next()?.trim().to_lowercase();
+
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|quality| quality.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+
+    // stable, tags with the same quality keep the order of the header
+    tags.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_accept_language, Catalog, Translations};
+    use chrono::NaiveDate;
+
+    const EN: &str = "
+# results page
+search-placeholder = search here...
+results-count =
+    .one = { $count } result in { $ms } ms
+    .other = { $count } results in { $ms } ms
+only-english = only in english
+";
+
+    const ES: &str = "
+search-placeholder = busca aquí...
+results-count =
+    .one = { $count } resultado en { $ms } ms
+    .other = { $count } resultados en { $ms } ms
+";
+
+    fn translations() -> Translations {
+        let mut translations = Translations::new("en", Catalog::parse(EN).unwrap());
+        translations.add("es", Catalog::parse(ES).unwrap());
+        translations
+    }
+
+    #[test]
+    fn test_parse_catalog() {
+        let catalog = Catalog::parse(EN).unwrap();
+
+        assert_eq!(catalog.len(), 4);
+        assert_eq!(catalog.get("search-placeholder"), Some("search here..."));
+        assert_eq!(
+            catalog.get("results-count.one"),
+            Some("{ $count } result in { $ms } ms")
+        );
+
+        assert_eq!(Catalog::parse("a = b\nnot a message").unwrap_err().line, 2);
+        assert_eq!(Catalog::parse(".one = b").unwrap_err().line, 1);
+        assert!(Catalog::parse("bad key = b").is_err());
+    }
+
+    #[test]
+    fn test_translate() {
+        let translations = translations();
+        let args = |count: &str| [("count", count.to_string()), ("ms", String::from("0.5"))];
+
+        assert_eq!(
+            translations.translate("es", "results-count", &args("1")),
+            "1 resultado en 0.5 ms"
+        );
+        assert_eq!(
+            translations.translate("es", "results-count", &args("0")),
+            "0 resultados en 0.5 ms"
+        );
+        assert_eq!(
+            translations.translate("en", "results-count", &args("9")),
+            "9 results in 0.5 ms"
+        );
+
+        // missing messages fall back to the default language, then to the key
+        assert_eq!(
+            translations.translate("es", "only-english", &[]),
+            "only in english"
+        );
+        assert_eq!(
+            translations.translate("fr", "missing-key", &[]),
+            "missing-key"
+        );
+        assert!(translations.get("es", "missing-key", &[]).is_none());
+    }
+
+    #[test]
+    fn test_humanize_and_localize() {
+        let mut translations = Translations::new(
+            "en",
+            Catalog::parse(
+                "duration-hours-minutes = { $hours } hr { $minutes } min\nduration-hours = { $hours } hr\nduration-minutes = { $minutes } min\ndate-format = { $month } { $day }, { $year }\nmonth-5 = May",
+            )
+            .unwrap(),
+        );
+        translations.add(
+            "es",
+            Catalog::parse("date-format = { $day } de { $month } de { $year }\nmonth-5 = mayo")
+                .unwrap(),
+        );
+
+        assert_eq!(translations.humanize_duration("en", 5527), "1 hr 32 min");
+        assert_eq!(translations.humanize_duration("en", 3590), "1 hr");
+        assert_eq!(translations.humanize_duration("es", 10), "1 min");
+
+        let date = NaiveDate::from_ymd_opt(2023, 5, 16).unwrap();
+        assert_eq!(translations.localize_date("en", date), "May 16, 2023");
+        assert_eq!(translations.localize_date("es", date), "16 de mayo de 2023");
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let translations = translations();
+
+        assert_eq!(
+            parse_accept_language("es-MX,es;q=0.9,en;q=0.8"),
+            vec!["es-mx", "es", "en"]
+        );
+        assert_eq!(parse_accept_language("en;q=0.5, es"), vec!["es", "en"]);
+
+        assert_eq!(translations.negotiate("es-MX,en;q=0.8"), "es");
+        assert_eq!(translations.negotiate("fr, en;q=0.5, es;q=0.4"), "en");
+        assert_eq!(translations.negotiate("fr, es;q=0"), "en");
+        assert_eq!(translations.negotiate(""), "en");
+    }
+}
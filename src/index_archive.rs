@@ -0,0 +1,251 @@
+use crate::{Episode, EpisodesById, EpisodesByTag, IndexStore};
+use memmap2::Mmap;
+use rkyv::primitive::{ArchivedUsize, FixedUsize};
+use std::path::{Path, PathBuf};
+
+/// Both index files in a single rkyv archive, validated and read in place without parsing any json
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, Default, PartialEq)]
+pub struct IndexArchive {
+    pub episodes_by_id: EpisodesById,
+    pub episodes_by_tag: EpisodesByTag,
+}
+
+/// Writes the archive next to `path` then renames it, a server never reads half of it
+pub fn write_index_archive(archive: &IndexArchive, path: &Path) -> Result<(), String> {
+    let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(archive)
+        .map_err(|err| format!("Error at archiving the index: {}", err))?;
+
+    let tmp = PathBuf::from(format!("{}.tmp", path.display()));
+
+    std::fs::write(&tmp, &bytes)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|err| format!("Error at writing {}: {}", path.display(), err))
+}
+
+/// An archive written by `write_index_archive`, memory mapped and read in place,
+/// only the episodes looked up are copied out of it
+pub struct MappedIndexArchive {
+    path: PathBuf,
+    mmap: Mmap,
+}
+
+impl MappedIndexArchive {
+    /// Maps the archive and checks its bytes once, so a truncated or foreign file
+    /// is an error instead of undefined behavior
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|err| format!("Error at reading {}: {}", path.display(), err))?;
+
+        // the mapping is page aligned, as rkyv needs
+        let mmap = unsafe { Mmap::map(&file) }
+            .map_err(|err| format!("Error at mapping {}: {}", path.display(), err))?;
+
+        rkyv::access::<ArchivedIndexArchive, rkyv::rancor::Error>(&mmap)
+            .map_err(|err| format!("Error at reading {}: {}", path.display(), err))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            mmap,
+        })
+    }
+
+    fn archived(&self) -> &ArchivedIndexArchive {
+        // checked by `open`, the mapping isn't written after
+        unsafe { rkyv::access_unchecked::<ArchivedIndexArchive>(&self.mmap) }
+    }
+
+    pub fn episodes(&self) -> usize {
+        self.archived().episodes_by_id.len()
+    }
+
+    pub fn tags(&self) -> usize {
+        self.archived().episodes_by_tag.len()
+    }
+
+    /// The episode of `id`, the only one copied out of the archive
+    pub fn episode(&self, id: usize) -> Result<Option<Episode>, String> {
+        let Ok(id) = FixedUsize::try_from(id) else {
+            return Ok(None);
+        };
+
+        self.archived()
+            .episodes_by_id
+            .get(&ArchivedUsize::from_native(id))
+            .map(|episode| self.deserialize(episode))
+            .transpose()
+    }
+
+    /// The episodes of `tag` in the order of the tag index
+    pub fn posting(&self, tag: &str) -> Option<Vec<usize>> {
+        self.archived()
+            .episodes_by_tag
+            .get(tag)
+            .map(|ids| ids.iter().map(|id| id.to_native() as usize).collect())
+    }
+
+    /// Every episode and tag, what the search indices are built from
+    pub fn read(&self) -> Result<(EpisodesById, EpisodesByTag), String> {
+        let archived = self.archived();
+
+        let by_id = archived
+            .episodes_by_id
+            .iter()
+            .map(|(id, episode)| Ok((id.to_native() as usize, self.deserialize(episode)?)))
+            .collect::<Result<EpisodesById, String>>()?;
+
+        let by_tag = archived
+            .episodes_by_tag
+            .iter()
+            .map(|(tag, ids)| {
+                let ids = ids.iter().map(|id| id.to_native() as usize).collect();
+                (tag.to_string(), ids)
+            })
+            .collect();
+
+        Ok((by_id, by_tag))
+    }
+
+    fn deserialize(&self, episode: &rkyv::Archived<Episode>) -> Result<Episode, String> {
+        rkyv::deserialize::<Episode, rkyv::rancor::Error>(episode)
+            .map_err(|err| format!("Error at reading {}: {}", self.path.display(), err))
+    }
+}
+
+/// The archive of a show as its store, replaced whole like the json files
+pub struct ArchiveIndexStore {
+    path: PathBuf,
+}
+
+impl ArchiveIndexStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl IndexStore for ArchiveIndexStore {
+    fn read(&self) -> Result<(EpisodesById, EpisodesByTag), String> {
+        MappedIndexArchive::open(&self.path)?.read()
+    }
+
+    fn episode(&self, id: usize) -> Result<Option<Episode>, String> {
+        MappedIndexArchive::open(&self.path)?.episode(id)
+    }
+
+    fn posting(&self, tag: &str) -> Result<Option<Vec<usize>>, String> {
+        Ok(MappedIndexArchive::open(&self.path)?.posting(tag))
+    }
+
+    fn replace(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String> {
+        let archive = IndexArchive {
+            episodes_by_id: by_id.clone(),
+            episodes_by_tag: by_tag.clone(),
+        };
+
+        write_index_archive(&archive, &self.path)
+    }
+
+    fn upsert(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String> {
+        let (mut stored_by_id, mut stored_by_tag) = self.read()?;
+
+        stored_by_id.extend(by_id.iter().map(|(id, episode)| (*id, episode.clone())));
+
+        for (tag, ids) in by_tag.iter() {
+            if ids.is_empty() {
+                stored_by_tag.remove(tag);
+            } else {
+                stored_by_tag.insert(tag.clone(), ids.clone());
+            }
+        }
+
+        self.replace(&stored_by_id, &stored_by_tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_index_archive, ArchiveIndexStore, IndexArchive, MappedIndexArchive};
+    use crate::{Episode, EpisodesById, EpisodesByTag, IndexStore, Tag};
+
+    #[test]
+    fn test_index_archive() {
+        let episode = Episode {
+            id: 512,
+            title: String::from("512: Ubuntu Unity"),
+            tags: vec![Tag::from("ubuntu")],
+            video: Some(String::from("https://youtube.com/watch?v=512")),
+            ..Default::default()
+        };
+
+        let archive = IndexArchive {
+            episodes_by_id: EpisodesById::from([(512, episode.clone())]),
+            episodes_by_tag: EpisodesByTag::from([(String::from("ubuntu"), vec![512])]),
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("unplugged-archive-{}.rkyv", std::process::id()));
+        write_index_archive(&archive, &path).unwrap();
+
+        // looked up in place
+        let mapped = MappedIndexArchive::open(&path).unwrap();
+        assert_eq!((mapped.episodes(), mapped.tags()), (1, 1));
+        assert_eq!(mapped.episode(512).unwrap(), Some(episode));
+        assert_eq!(mapped.episode(511).unwrap(), None);
+        assert_eq!(mapped.posting("ubuntu"), Some(vec![512]));
+        assert_eq!(mapped.posting("arch"), None);
+        assert_eq!(
+            mapped.read().unwrap(),
+            (
+                archive.episodes_by_id.clone(),
+                archive.episodes_by_tag.clone()
+            )
+        );
+
+        std::fs::write(&path, b"not an archive").unwrap();
+        assert!(MappedIndexArchive::open(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_archive_index_store() {
+        let path = std::env::temp_dir().join(format!(
+            "unplugged-archive-store-{}.rkyv",
+            std::process::id()
+        ));
+        let store = ArchiveIndexStore::new(&path);
+        assert!(store.read().is_err());
+
+        let episode = |id: i64| Episode {
+            id,
+            ..Default::default()
+        };
+        let by_id = EpisodesById::from([(490, episode(490)), (500, episode(500))]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("zfs"), vec![500]),
+        ]);
+        store.replace(&by_id, &by_tag).unwrap();
+        assert_eq!(store.read().unwrap(), (by_id, by_tag));
+        assert_eq!(store.episode(500).unwrap(), Some(episode(500)));
+        assert_eq!(store.posting("arch").unwrap(), Some(vec![490]));
+
+        store
+            .upsert(
+                &EpisodesById::from([(501, episode(501))]),
+                &EpisodesByTag::from([
+                    (String::from("btrfs"), vec![501]),
+                    (String::from("zfs"), vec![]),
+                ]),
+            )
+            .unwrap();
+
+        let (by_id, by_tag) = store.read().unwrap();
+        assert_eq!(by_id.len(), 3);
+        assert_eq!(by_tag["btrfs"], vec![501]);
+        assert!(!by_tag.contains_key("zfs"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,748 @@
+use crate::{
+    index_store, index_version, normalize_text, parse_date, read_json_file, transcript_text,
+    Autocomplete, Bm25, Episode, EpisodeSlugs, EpisodesById, EpisodesByTag, IndexStats, Link,
+    ShowConfig, Sitemap, Symbol, Symbols, Transcripts,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use roaring::RoaringBitmap;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+// tags listed by the stats page
+const STATS_TOP_TAGS: usize = 20;
+
+/// The index of a show and everything derived from it, built together
+/// so swapping the index swaps all of it at once
+pub struct ShowIndex {
+    /// fingerprint of the index, part of every ETag
+    pub version: u64,
+    pub episodes_by_id: EpisodesById,
+    pub episodes_by_tag: EpisodesByTag,
+    /// the episodes of every tag, what the search matches the terms against
+    pub tag_join: TagJoin,
+    /// lowercase title and interned tags of every episode by id
+    pub search_fields: HashMap<usize, SearchFields>,
+    /// the episodes of every word of the titles, chapters and transcripts
+    pub word_postings: WordPostings,
+    /// document frequencies of the words of the titles and of the tags, weighing the scores
+    pub bm25: Bm25,
+    pub sitemap: Sitemap,
+    pub stats: IndexStats,
+    pub autocomplete: Autocomplete,
+    /// url slugs of the episode pages
+    pub slugs: EpisodeSlugs,
+    /// transcripts of the episodes, shared with the index replacing this one on uploads
+    pub transcripts: Arc<Transcripts>,
+    /// newest modification time of the index files, when they were last rebuilt
+    pub updated_at: Option<DateTime<Utc>>,
+    /// when the index was loaded, uploaded or reloaded, its last successful refresh
+    pub loaded_at: DateTime<Utc>,
+}
+
+impl ShowIndex {
+    pub fn new(
+        episodes_by_id: EpisodesById,
+        episodes_by_tag: EpisodesByTag,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        let slugs = EpisodeSlugs::new(&episodes_by_id);
+        let tag_join = TagJoin::new(&episodes_by_id, &episodes_by_tag);
+        let search_fields = episodes_by_id
+            .iter()
+            .map(|(id, episode)| (*id, SearchFields::new(episode, tag_join.symbols())))
+            .collect();
+        let word_postings = WordPostings::new(&search_fields);
+        let bm25 = Bm25::new(&search_fields);
+
+        Self {
+            version: index_version(&episodes_by_id, &episodes_by_tag),
+            sitemap: Sitemap::new(&episodes_by_id, &episodes_by_tag, &slugs),
+            stats: IndexStats::new(&episodes_by_id, &episodes_by_tag, STATS_TOP_TAGS),
+            autocomplete: Autocomplete::new(&episodes_by_tag).with_titles(&episodes_by_id),
+            tag_join,
+            search_fields,
+            word_postings,
+            bm25,
+            slugs,
+            transcripts: Arc::default(),
+            episodes_by_id,
+            episodes_by_tag,
+            updated_at,
+            loaded_at: Utc::now(),
+        }
+    }
+
+    /// Adds the transcripts of the episodes, searched along with their titles
+    pub fn with_transcripts(mut self, transcripts: Arc<Transcripts>) -> Self {
+        for (id, fields) in self.search_fields.iter_mut() {
+            fields.transcript = transcripts
+                .get(id)
+                .map(|segments| transcript_text(segments))
+                .unwrap_or_default();
+        }
+
+        self.word_postings = WordPostings::new(&self.search_fields);
+        self.transcripts = transcripts;
+        self
+    }
+
+    pub fn empty() -> Self {
+        Self::new(EpisodesById::new(), EpisodesByTag::new(), None)
+    }
+
+    /// Reads and validates the index of a show from its store, both json files at once,
+    /// its archive or its database
+    pub async fn load(show: &ShowConfig) -> Result<Self, String> {
+        let store = index_store(show)?;
+        let (mut episodes_by_id, episodes_by_tag) =
+            tokio::task::spawn_blocking(move || store.read())
+                .await
+                .map_err(|err| format!("Error at reading the index: {}", err))??;
+
+        validate_index(&episodes_by_id, &episodes_by_tag)?;
+        default_show(&mut episodes_by_id, &show.slug);
+
+        let transcripts = if show.transcripts.is_empty() {
+            Transcripts::new()
+        } else {
+            read_index_file::<Transcripts>(&show.transcripts).await?
+        };
+
+        let mut updated_at = None;
+
+        for file in show.index_files() {
+            let modified = tokio::fs::metadata(file)
+                .await
+                .and_then(|meta| meta.modified())
+                .ok();
+
+            updated_at = updated_at.max(modified);
+        }
+
+        // the derived indices take a while for a large archive
+        tokio::task::spawn_blocking(move || {
+            Self::new(
+                episodes_by_id,
+                episodes_by_tag,
+                updated_at.map(DateTime::<Utc>::from),
+            )
+            .with_transcripts(Arc::new(transcripts))
+        })
+        .await
+        .map_err(|err| format!("Error at building the index: {}", err))
+    }
+}
+
+/// Episodes without a show belong to the show of their index, `show:lup` finds them
+pub fn default_show(episodes_by_id: &mut EpisodesById, slug: &str) {
+    for episode in episodes_by_id.values_mut() {
+        if episode.show.is_empty() {
+            episode.show = slug.to_string();
+        }
+    }
+}
+
+// deserialized while it's read instead of reading the whole file into memory first
+async fn read_index_file<T>(file: &str) -> Result<T, String>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let path = PathBuf::from(file);
+
+    tokio::task::spawn_blocking(move || read_json_file(&path))
+        .await
+        .map_err(|err| format!("Error at reading {}: {}", file, err))?
+}
+
+/// Checks the index can be searched, it has episodes and every tag
+/// points to episodes of the index
+pub fn validate_index(by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String> {
+    if by_id.is_empty() {
+        return Err(String::from("the index has no episodes"));
+    }
+
+    for (tag, ids) in by_tag.iter() {
+        if let Some(id) = ids.iter().find(|id| !by_id.contains_key(id)) {
+            return Err(format!("the tag {} lists the unknown episode {}", tag, id));
+        }
+    }
+
+    Ok(())
+}
+
+/// The tag index of the episodes, lowercase tags listing the ids of their episodes in order,
+/// as the indexer writes it
+pub fn build_episodes_by_tag(by_id: &EpisodesById) -> EpisodesByTag {
+    let mut ids: Vec<_> = by_id.keys().copied().collect();
+    ids.sort();
+
+    let mut by_tag = EpisodesByTag::new();
+
+    for id in ids {
+        let mut tags: Vec<_> = by_id[&id]
+            .tags
+            .iter()
+            .map(|tag| tag.name.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+
+        for tag in tags {
+            by_tag.entry(tag).or_default().push(id);
+        }
+    }
+
+    by_tag
+}
+
+/// The posting list of every tag, the ids of its episodes as a bitmap, built once per index
+/// so a search combines them with set operations (`|` for matching tags, `-` for excluded ones)
+#[derive(Clone, Debug, Default)]
+pub struct TagJoin {
+    /// the tags of the tag index in alphabetical order, then the other tags of the episodes
+    symbols: Symbols,
+    /// by symbol
+    postings: Vec<RoaringBitmap>,
+}
+
+impl TagJoin {
+    /// Ids missing from `by_id` are skipped, a loaded index has none
+    pub fn new(by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Self {
+        let mut symbols = Symbols::default();
+        let mut tags: Vec<_> = by_tag.keys().collect();
+        tags.sort();
+
+        for tag in tags {
+            symbols.intern(tag);
+        }
+
+        for episode in by_id.values() {
+            for tag in episode.tags.iter() {
+                symbols.intern(&tag.name.to_lowercase());
+            }
+        }
+
+        let mut postings = vec![RoaringBitmap::new(); symbols.len()];
+
+        for (tag, ids) in by_tag.iter() {
+            let Some(symbol) = symbols.get(tag) else {
+                continue;
+            };
+
+            postings[symbol as usize] = ids
+                .iter()
+                .filter(|id| by_id.contains_key(id))
+                .filter_map(|id| u32::try_from(*id).ok())
+                .collect();
+        }
+
+        Self { symbols, postings }
+    }
+
+    /// The interned tags, shared by the `SearchFields` of the index
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    /// Tags with their posting list
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RoaringBitmap)> {
+        self.symbols
+            .iter()
+            .map(|(symbol, tag)| (tag, &self.postings[symbol as usize]))
+    }
+
+    /// The episodes of `tag`, looked up without reading the other tags
+    pub fn posting(&self, tag: &str) -> Option<&RoaringBitmap> {
+        self.symbols
+            .get(tag)
+            .map(|symbol| &self.postings[symbol as usize])
+    }
+
+    /// The episodes of the tags matching `predicate`
+    pub fn union<F>(&self, predicate: F) -> RoaringBitmap
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.iter()
+            .filter(|(tag, _)| predicate(tag))
+            .map(|(_, ids)| ids)
+            .fold(RoaringBitmap::new(), |acc, ids| acc | ids)
+    }
+}
+
+/// The posting list of every word of the titles, chapter titles, show notes and transcripts, built once
+/// per index so a search looks the terms up instead of reading every episode: a term without
+/// whitespace is in a text when it's in one of its words, a phrase when its words follow each other
+#[derive(Clone, Debug, Default)]
+pub struct WordPostings {
+    /// the sorted vocabulary, a word id is its index
+    words: Vec<String>,
+    /// the episodes of every word by id
+    episodes: Vec<RoaringBitmap>,
+    /// the positions of every word by id in the texts of its episodes, what the phrases are matched with
+    positions: Vec<HashMap<u32, Vec<u32>>>,
+    /// the ids of the words of every substring of up to `GRAM_CHARS` chars
+    grams: HashMap<String, RoaringBitmap>,
+    /// the episodes by number
+    numbers: HashMap<u32, RoaringBitmap>,
+}
+
+// the longer terms are looked up by their substrings of this many chars
+const GRAM_CHARS: usize = 3;
+
+impl WordPostings {
+    pub fn new(search_fields: &HashMap<usize, SearchFields>) -> Self {
+        let mut postings = Self::default();
+        let mut positions: HashMap<&str, HashMap<u32, Vec<u32>>> = HashMap::new();
+
+        for (id, fields) in search_fields.iter() {
+            let Ok(posting) = u32::try_from(*id) else {
+                continue;
+            };
+
+            let texts = [&fields.title, &fields.description, &fields.transcript]
+                .into_iter()
+                .chain(fields.chapters.iter());
+            let mut position = 0;
+
+            for text in texts {
+                for word in text.split_whitespace() {
+                    positions
+                        .entry(word)
+                        .or_default()
+                        .entry(posting)
+                        .or_default()
+                        .push(position);
+                    position += 1;
+                }
+
+                // a phrase doesn't run from a text into the next one
+                position += 1;
+            }
+
+            if let Some(number) = fields.number {
+                postings.numbers.entry(number).or_default().insert(posting);
+            }
+        }
+
+        let mut positions: Vec<_> = positions.into_iter().collect();
+        positions.sort_unstable_by_key(|(word, _)| *word);
+
+        for (id, (word, word_positions)) in positions.into_iter().enumerate() {
+            let chars: Vec<_> = word.chars().collect();
+
+            for len in 1..=GRAM_CHARS.min(chars.len()) {
+                for gram in chars.windows(len) {
+                    let gram: String = gram.iter().collect();
+                    postings.grams.entry(gram).or_default().insert(id as u32);
+                }
+            }
+
+            postings.words.push(word.to_string());
+            postings
+                .episodes
+                .push(word_positions.keys().copied().collect());
+            postings.positions.push(word_positions);
+        }
+
+        postings
+    }
+
+    /// The episodes whose title, chapter titles, show notes or transcript contain the lowercase `term`,
+    /// a word or a phrase
+    pub fn matching(&self, term: &str) -> RoaringBitmap {
+        let words: Vec<_> = term.split_whitespace().collect();
+
+        match words.as_slice() {
+            [] => RoaringBitmap::new(),
+            [word] => self.episodes_of(&self.containing(word)),
+            _ => self.phrase(&words),
+        }
+    }
+
+    // the ids of the words containing `term`, its substrings narrow them down
+    // before they're compared
+    fn containing(&self, term: &str) -> RoaringBitmap {
+        let chars: Vec<_> = term.chars().collect();
+
+        if chars.len() <= GRAM_CHARS {
+            return self.grams.get(term).cloned().unwrap_or_default();
+        }
+
+        let mut ids: Option<RoaringBitmap> = None;
+
+        for gram in chars.windows(GRAM_CHARS) {
+            let gram: String = gram.iter().collect();
+            let Some(gram_ids) = self.grams.get(&gram) else {
+                return RoaringBitmap::new();
+            };
+
+            ids = Some(match ids {
+                Some(ids) => ids & gram_ids,
+                None => gram_ids.clone(),
+            });
+        }
+
+        ids.unwrap_or_default()
+            .into_iter()
+            .filter(|id| self.words[*id as usize].contains(term))
+            .collect()
+    }
+
+    // the ids of the words starting with `prefix`, a range of the sorted vocabulary
+    fn starting_with(&self, prefix: &str) -> RoaringBitmap {
+        let start = self.words.partition_point(|word| word.as_str() < prefix);
+
+        self.words[start..]
+            .iter()
+            .take_while(|word| word.starts_with(prefix))
+            .enumerate()
+            .map(|(offset, _)| (start + offset) as u32)
+            .collect()
+    }
+
+    fn episodes_of(&self, ids: &RoaringBitmap) -> RoaringBitmap {
+        ids.iter().fold(RoaringBitmap::new(), |acc, id| {
+            acc | &self.episodes[id as usize]
+        })
+    }
+
+    // the first word of a phrase ends a word of the text, the last one starts one
+    // and the ones in between are whole words, at consecutive positions
+    fn phrase(&self, words: &[&str]) -> RoaringBitmap {
+        let last = words.len() - 1;
+
+        let slots: Vec<RoaringBitmap> = words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| match index {
+                0 => self
+                    .containing(word)
+                    .into_iter()
+                    .filter(|id| self.words[*id as usize].ends_with(word))
+                    .collect(),
+                index if index == last => self.starting_with(word),
+                _ => self
+                    .words
+                    .binary_search_by(|vocabulary| vocabulary.as_str().cmp(word))
+                    .map(|id| RoaringBitmap::from_iter([id as u32]))
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        let candidates = slots
+            .iter()
+            .map(|ids| self.episodes_of(ids))
+            .reduce(|acc, episodes| acc & episodes)
+            .unwrap_or_default();
+
+        candidates
+            .into_iter()
+            .filter(|episode| {
+                let positions: Vec<HashSet<u32>> = slots
+                    .iter()
+                    .map(|ids| {
+                        ids.iter()
+                            .filter_map(|id| self.positions[id as usize].get(episode))
+                            .flatten()
+                            .copied()
+                            .collect()
+                    })
+                    .collect();
+
+                positions[0].iter().any(|start| {
+                    (1..positions.len())
+                        .all(|offset| positions[offset].contains(&(start + offset as u32)))
+                })
+            })
+            .collect()
+    }
+
+    /// Every word of the titles, chapter titles, show notes and transcripts
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.words.iter().map(String::as_str)
+    }
+
+    /// The episodes numbered `number`
+    pub fn numbered(&self, number: u32) -> Option<&RoaringBitmap> {
+        self.numbers.get(&number)
+    }
+}
+
+/// The normalized texts (`normalize_text`) and interned tags of an episode, computed once
+/// per index so matching a query against every episode doesn't allocate
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchFields {
+    pub title: String,
+    pub number: Option<u32>,
+    /// interned tags with their weight in percent
+    pub tags: Vec<(Symbol, u32)>,
+    /// lowercase guests
+    pub guests: Vec<String>,
+    /// lowercase slug of the show
+    pub show: String,
+    /// domains of the links of the show notes
+    pub domains: Vec<String>,
+    /// lowercase chapter titles
+    pub chapters: Vec<String>,
+    /// lowercase show notes, empty without a description
+    pub description: String,
+    /// lowercase transcript, empty without one
+    pub transcript: String,
+    /// publication day, none when the date doesn't parse
+    pub date: Option<NaiveDate>,
+    /// duration in seconds, none when it doesn't parse
+    pub duration: Option<u64>,
+}
+
+impl SearchFields {
+    /// `symbols` holds the lowercase tags of the episode
+    pub fn new(episode: &Episode, symbols: &Symbols) -> Self {
+        Self {
+            title: normalize_text(&episode.title),
+            number: episode.number(),
+            tags: episode
+                .tags
+                .iter()
+                .filter_map(|tag| Some((symbols.get(&tag.name.to_lowercase())?, tag.weight())))
+                .collect(),
+            guests: episode
+                .guests
+                .iter()
+                .map(|guest| normalize_text(guest))
+                .collect(),
+            show: episode.show.to_lowercase(),
+            domains: episode.links.iter().filter_map(Link::domain).collect(),
+            chapters: episode
+                .chapters
+                .iter()
+                .map(|chapter| normalize_text(&chapter.title))
+                .collect(),
+            description: normalize_text(episode.description.as_deref().unwrap_or_default()),
+            transcript: String::new(),
+            date: parse_date(&episode.date),
+            duration: episode.duration_seconds(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IndexStatus {
+    Ready,
+    /// the index is missing, broken or being rebuilt, the pages of the show
+    /// are replaced by a maintenance page until it loads
+    Unavailable(String),
+}
+
+/// The current index of a show, swapped as a whole when it's reloaded,
+/// searches keep the index they started with
+pub struct IndexHandle {
+    state: RwLock<(IndexStatus, Arc<ShowIndex>)>,
+}
+
+impl IndexHandle {
+    pub fn ready(index: ShowIndex) -> Self {
+        Self {
+            state: RwLock::new((IndexStatus::Ready, Arc::new(index))),
+        }
+    }
+
+    pub fn unavailable(reason: &str) -> Self {
+        Self {
+            state: RwLock::new((
+                IndexStatus::Unavailable(reason.to_string()),
+                Arc::new(ShowIndex::empty()),
+            )),
+        }
+    }
+
+    /// The current index, an empty one while it's unavailable
+    pub fn get(&self) -> Arc<ShowIndex> {
+        self.state
+            .read()
+            .expect("Error at reading the index state")
+            .1
+            .clone()
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        self.state
+            .read()
+            .expect("Error at reading the index state")
+            .0
+            .clone()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status() == IndexStatus::Ready
+    }
+
+    pub fn swap(&self, index: ShowIndex) {
+        *self
+            .state
+            .write()
+            .expect("Error at writing the index state") = (IndexStatus::Ready, Arc::new(index));
+    }
+
+    /// Puts the show in maintenance, the previous index stays around for the
+    /// requests still using it
+    pub fn set_unavailable(&self, reason: &str) {
+        self.state
+            .write()
+            .expect("Error at writing the index state")
+            .0 = IndexStatus::Unavailable(reason.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_episodes_by_tag, validate_index, IndexHandle, IndexStatus, ShowIndex, TagJoin,
+    };
+    use crate::{Chapter, EpisodesById, EpisodesByTag, Tag, TranscriptSegment, Transcripts};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn index() -> (EpisodesById, EpisodesByTag) {
+        let by_id: EpisodesById =
+            serde_json::from_str(r#"{"512": {"id": 512, "title": "512: Unity Again", "date": "May 16th, 2023", "duration": "01:08:28", "tags": ["ubuntu"], "url": "https://linuxunplugged.com/512"}}"#)
+                .unwrap();
+        let by_tag = HashMap::from([(String::from("ubuntu"), vec![512])]);
+
+        (by_id, by_tag)
+    }
+
+    #[test]
+    fn test_validate_index() {
+        let (by_id, mut by_tag) = index();
+        assert_eq!(validate_index(&by_id, &by_tag), Ok(()));
+
+        by_tag.insert(String::from("unity"), vec![513]);
+        assert_eq!(
+            validate_index(&by_id, &by_tag),
+            Err(String::from("the tag unity lists the unknown episode 513"))
+        );
+
+        assert!(validate_index(&EpisodesById::new(), &EpisodesByTag::new()).is_err());
+    }
+
+    #[test]
+    fn test_tag_join() {
+        let (by_id, mut by_tag) = index();
+        by_tag.insert(String::from("gnome"), vec![512, 513]);
+
+        by_tag.insert(String::from("gnome desktop"), vec![512]);
+
+        let join = TagJoin::new(&by_id, &by_tag);
+        let tags: Vec<_> = join
+            .iter()
+            .map(|(tag, ids)| (tag, ids.iter().collect::<Vec<_>>()))
+            .collect();
+
+        assert_eq!(
+            tags,
+            vec![
+                ("gnome", vec![512]),
+                ("gnome desktop", vec![512]),
+                ("ubuntu", vec![512])
+            ]
+        );
+
+        let gnome = join.union(|tag| tag.contains("gnome"));
+        assert_eq!(gnome.iter().collect::<Vec<_>>(), vec![512]);
+        assert!(join.union(|tag| tag == "kde").is_empty());
+
+        // a posting is the whole tag, not the tags containing it
+        let posting = |tag| join.posting(tag).map(|ids| ids.iter().collect::<Vec<_>>());
+        assert_eq!(posting("gnome desktop"), Some(vec![512]));
+        assert_eq!(posting("desktop"), None);
+    }
+
+    #[test]
+    fn test_word_postings() {
+        let (mut by_id, by_tag) = index();
+        let mut episode = by_id[&512].clone();
+        episode.id = 490;
+        episode.title = String::from("490: The Arch Disaster");
+        episode.chapters = vec![Chapter {
+            title: String::from("Unity on Arch"),
+            start_secs: 60,
+        }];
+        by_id.insert(490, episode);
+
+        let transcripts = Transcripts::from([(
+            512,
+            vec![TranscriptSegment {
+                start_secs: 0,
+                text: String::from("Canonical's\nsnaps"),
+            }],
+        )]);
+        let index = ShowIndex::new(by_id, by_tag, None).with_transcripts(Arc::new(transcripts));
+        let postings = &index.word_postings;
+
+        let ids = |term: &str| postings.matching(term).iter().collect::<Vec<_>>();
+
+        assert_eq!(ids("unity"), vec![490, 512]);
+        assert_eq!(ids("disas"), vec![490]);
+        assert_eq!(ids("nical's"), vec![512]);
+        assert_eq!(ids("ni"), vec![490, 512]);
+        assert!(ids("kde").is_empty());
+        assert!(ids("").is_empty());
+
+        // phrases, within a text and ending or starting inside a word
+        assert_eq!(ids("arch disaster"), vec![490]);
+        assert_eq!(ids("the arch disas"), vec![490]);
+        assert_eq!(ids("he arch"), vec![490]);
+        assert_eq!(ids("unity on arch"), vec![490]);
+        assert_eq!(ids("nical's snaps"), vec![512]);
+        assert!(ids("disaster arch").is_empty());
+        assert!(ids("th arch").is_empty());
+        // the title and the chapter of 490 don't make a phrase together
+        assert!(ids("disaster unity").is_empty());
+        assert_eq!(
+            postings
+                .numbered(490)
+                .map(|ids| ids.iter().collect::<Vec<_>>()),
+            Some(vec![490])
+        );
+    }
+
+    #[test]
+    fn test_build_episodes_by_tag() {
+        let (mut by_id, by_tag) = index();
+        assert_eq!(build_episodes_by_tag(&by_id), by_tag);
+
+        let mut episode = by_id[&512].clone();
+        episode.id = 490;
+        episode.tags = vec![Tag::from(" Ubuntu"), Tag::from("ubuntu"), Tag::default()];
+        by_id.insert(490, episode);
+
+        assert_eq!(
+            build_episodes_by_tag(&by_id),
+            HashMap::from([(String::from("ubuntu"), vec![490, 512])])
+        );
+    }
+
+    #[test]
+    fn test_index_handle() {
+        let handle = IndexHandle::unavailable("missing file");
+        assert_eq!(
+            handle.status(),
+            IndexStatus::Unavailable(String::from("missing file"))
+        );
+        assert!(handle.get().episodes_by_id.is_empty());
+
+        let (by_id, by_tag) = index();
+        handle.swap(ShowIndex::new(by_id, by_tag, None));
+        assert!(handle.is_ready());
+        assert_eq!(handle.get().episodes_by_id.len(), 1);
+
+        // the last index is kept while rebuilding
+        handle.set_unavailable("rebuilding");
+        assert!(!handle.is_ready());
+        assert_eq!(handle.get().episodes_by_id.len(), 1);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{ArchiveIndexStore, Episode, EpisodesById, EpisodesByTag, ShowConfig};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where the index of a show is kept, the json files of the indexer (the default),
+/// an rkyv archive or a sqlite database updated in place
+pub trait IndexStore: Send {
+    /// Every episode and tag of the index, as stored, what the search indices are built from
+    fn read(&self) -> Result<(EpisodesById, EpisodesByTag), String>;
+
+    /// The episode of `id`, the stores that can't look it up alone read the whole index
+    fn episode(&self, id: usize) -> Result<Option<Episode>, String> {
+        Ok(self.read()?.0.remove(&id))
+    }
+
+    /// The episodes of `tag` in the order of the tag index
+    fn posting(&self, tag: &str) -> Result<Option<Vec<usize>>, String> {
+        Ok(self.read()?.1.remove(tag))
+    }
+
+    /// Replaces the whole index
+    fn replace(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String>;
+
+    /// Adds or replaces the episodes and the episodes of the tags, the others are kept,
+    /// an empty list of episodes drops its tag
+    fn upsert(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String>;
+}
+
+/// The store of the index of a show, its `archive` or its `database` when set,
+/// its json files otherwise
+pub fn index_store(show: &ShowConfig) -> Result<Box<dyn IndexStore>, String> {
+    if !show.archive.is_empty() {
+        Ok(Box::new(ArchiveIndexStore::new(&show.archive)))
+    } else if !show.database.is_empty() {
+        database_store(&show.database)
+    } else {
+        Ok(Box::new(JsonIndexStore::new(
+            &show.episodes_by_id,
+            &show.episodes_by_tag,
+        )))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn database_store(path: &str) -> Result<Box<dyn IndexStore>, String> {
+    Ok(Box::new(crate::SqliteIndexStore::new(path)))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn database_store(path: &str) -> Result<Box<dyn IndexStore>, String> {
+    Err(format!(
+        "Error at opening {}: built without the sqlite feature",
+        path
+    ))
+}
+
+/// The episodes and tags of an index that differ from `previous`, what an update upserts,
+/// the tags gone from the index with no episodes
+pub fn index_changes(
+    previous_by_id: &EpisodesById,
+    previous_by_tag: &EpisodesByTag,
+    by_id: &EpisodesById,
+    by_tag: &EpisodesByTag,
+) -> (EpisodesById, EpisodesByTag) {
+    let changed_by_id = by_id
+        .iter()
+        .filter(|(id, episode)| previous_by_id.get(id) != Some(episode))
+        .map(|(id, episode)| (*id, episode.clone()))
+        .collect();
+
+    let mut changed_by_tag: EpisodesByTag = by_tag
+        .iter()
+        .filter(|(tag, ids)| previous_by_tag.get(*tag) != Some(ids))
+        .map(|(tag, ids)| (tag.clone(), ids.clone()))
+        .collect();
+
+    for tag in previous_by_tag.keys() {
+        if !by_tag.contains_key(tag) {
+            changed_by_tag.insert(tag.clone(), Vec::new());
+        }
+    }
+
+    (changed_by_id, changed_by_tag)
+}
+
+/// Both index files of the indexer, read whole and rewritten whole
+pub struct JsonIndexStore {
+    episodes_by_id: PathBuf,
+    episodes_by_tag: PathBuf,
+}
+
+impl JsonIndexStore {
+    pub fn new(episodes_by_id: impl AsRef<Path>, episodes_by_tag: impl AsRef<Path>) -> Self {
+        Self {
+            episodes_by_id: episodes_by_id.as_ref().to_path_buf(),
+            episodes_by_tag: episodes_by_tag.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl IndexStore for JsonIndexStore {
+    // both files are read at once
+    fn read(&self) -> Result<(EpisodesById, EpisodesByTag), String> {
+        std::thread::scope(|scope| {
+            let by_tag = scope.spawn(|| read_json_file::<EpisodesByTag>(&self.episodes_by_tag));
+            let by_id = read_json_file::<EpisodesById>(&self.episodes_by_id)?;
+            let by_tag = by_tag
+                .join()
+                .map_err(|_| format!("Error at reading {}", self.episodes_by_tag.display()))??;
+
+            Ok((by_id, by_tag))
+        })
+    }
+
+    // sorted keys, like the files of the indexer
+    fn replace(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String> {
+        let by_id: BTreeMap<_, _> = by_id.iter().collect();
+        let by_tag: BTreeMap<_, _> = by_tag.iter().collect();
+
+        let to_json = |value: serde_json::Result<String>| {
+            value.map_err(|err| format!("Error at serializing the index: {}", err))
+        };
+        let files = [
+            (
+                &self.episodes_by_id,
+                to_json(serde_json::to_string_pretty(&by_id))?,
+            ),
+            (
+                &self.episodes_by_tag,
+                to_json(serde_json::to_string_pretty(&by_tag))?,
+            ),
+        ];
+
+        // both files are written before either is replaced
+        let tmp = |path: &Path| PathBuf::from(format!("{}.tmp", path.display()));
+
+        for (path, contents) in files.iter() {
+            std::fs::write(tmp(path), contents)
+                .map_err(|err| format!("Error at writing {}: {}", path.display(), err))?;
+        }
+
+        for (path, _) in files.iter() {
+            std::fs::rename(tmp(path), path)
+                .map_err(|err| format!("Error at writing {}: {}", path.display(), err))?;
+        }
+
+        Ok(())
+    }
+
+    fn upsert(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String> {
+        let (mut stored_by_id, mut stored_by_tag) = self.read()?;
+
+        stored_by_id.extend(by_id.iter().map(|(id, episode)| (*id, episode.clone())));
+
+        for (tag, ids) in by_tag.iter() {
+            if ids.is_empty() {
+                stored_by_tag.remove(tag);
+            } else {
+                stored_by_tag.insert(tag.clone(), ids.clone());
+            }
+        }
+
+        self.replace(&stored_by_id, &stored_by_tag)
+    }
+}
+
+/// Deserializes a json file while it's read instead of reading it into memory first
+pub fn read_json_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let reader = std::fs::File::open(path)
+        .map(std::io::BufReader::new)
+        .map_err(|err| format!("Error at reading {}: {}", path.display(), err))?;
+
+    serde_json::from_reader(reader)
+        .map_err(|err| format!("Error at parsing {}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{index_changes, IndexStore, JsonIndexStore};
+    use crate::{Episode, EpisodesById, EpisodesByTag};
+
+    fn episode(id: i64, title: &str) -> Episode {
+        Episode {
+            id,
+            title: String::from(title),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_index_changes() {
+        let previous_by_id =
+            EpisodesById::from([(490, episode(490, "Arch")), (500, episode(500, "ZFS"))]);
+        let previous_by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("gnome"), vec![500]),
+        ]);
+
+        let by_id = EpisodesById::from([
+            (490, episode(490, "Arch")),
+            (500, episode(500, "ZFS Forever")),
+            (501, episode(501, "Btrfs")),
+        ]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("btrfs"), vec![501]),
+        ]);
+
+        let (changed_by_id, changed_by_tag) =
+            index_changes(&previous_by_id, &previous_by_tag, &by_id, &by_tag);
+
+        let mut ids: Vec<_> = changed_by_id.keys().copied().collect();
+        ids.sort();
+        assert_eq!(ids, vec![500, 501]);
+        assert_eq!(
+            changed_by_tag,
+            EpisodesByTag::from([
+                (String::from("btrfs"), vec![501]),
+                (String::from("gnome"), vec![]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_json_index_store() {
+        let dir = std::env::temp_dir().join(format!("unplugged-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let store = JsonIndexStore::new(dir.join("by_id.json"), dir.join("by_tag.json"));
+        assert!(store.read().is_err());
+
+        let by_id = EpisodesById::from([(490, episode(490, "Arch")), (500, episode(500, "ZFS"))]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("zfs"), vec![500]),
+        ]);
+        store.replace(&by_id, &by_tag).unwrap();
+        assert_eq!(store.read().unwrap(), (by_id, by_tag));
+        assert_eq!(store.episode(490).unwrap(), Some(episode(490, "Arch")));
+        assert_eq!(store.posting("zfs").unwrap(), Some(vec![500]));
+
+        store
+            .upsert(
+                &EpisodesById::from([(501, episode(501, "Btrfs"))]),
+                &EpisodesByTag::from([
+                    (String::from("btrfs"), vec![501]),
+                    (String::from("zfs"), vec![]),
+                ]),
+            )
+            .unwrap();
+
+        let (by_id, by_tag) = store.read().unwrap();
+        assert_eq!(by_id.len(), 3);
+        assert_eq!(by_tag["btrfs"], vec![501]);
+        assert!(!by_tag.contains_key("zfs"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,348 @@
+use axum::body::Bytes;
+use axum::extract::{Multipart, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, info};
+use unplugged_engine::{
+    default_show, index_store, ingest_feed, parse_rss_feed, validate_index, EpisodesById,
+    EpisodesByTag, ShowIndex, Transcripts,
+};
+
+use crate::maintenance::set_index_metrics;
+use crate::shows::Shows;
+use crate::AppState;
+
+/// Largest upload of /admin/index, both files together
+pub const INDEX_UPLOAD_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    /// slug of the show, required in a multi-show archive
+    show: Option<String>,
+}
+
+// admin/index?show=lup, PUT with the multipart fields `episodes_by_id` and `episodes_by_tag`
+// replaces the index files of the show and swaps the index in memory,
+// nothing changes unless both files are valid
+pub async fn handle_index_upload(
+    Query(upload): Query<UploadQuery>,
+    State(shows): State<Shows>,
+    multipart: Multipart,
+) -> Response {
+    let state = match pick_show(&shows, upload.show.as_deref()) {
+        Ok(state) => state,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let (by_id_bytes, by_tag_bytes) = match read_index_files(multipart).await {
+        Ok(files) => files,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let (episodes_by_id, episodes_by_tag) = match parse_index(&by_id_bytes, &by_tag_bytes) {
+        Ok(index) => index,
+        Err(message) => return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response(),
+    };
+
+    let index = ParsedIndex {
+        episodes_by_id,
+        episodes_by_tag,
+        files: vec![
+            (state.show.episodes_by_id.clone(), by_id_bytes),
+            (state.show.episodes_by_tag.clone(), by_tag_bytes),
+        ],
+        // the transcripts aren't part of the upload, the ones loaded stay
+        transcripts: None,
+    };
+
+    match install_index(&shows, state, index).await {
+        Ok(summary) => {
+            info!(show = state.show.slug, "Swapped in the uploaded index");
+            Json(summary).into_response()
+        }
+        Err(message) => (StatusCode::INTERNAL_SERVER_ERROR, message).into_response(),
+    }
+}
+
+// the show of ?show=<slug>, optional in a single show archive
+fn pick_show<'a>(
+    shows: &'a Shows,
+    slug: Option<&str>,
+) -> Result<&'a Arc<AppState>, (StatusCode, &'static str)> {
+    let state = match slug {
+        Some(slug) => shows.iter().find(|show| show.show.slug == slug),
+        None if shows.len() == 1 => shows.first(),
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "the archive has several shows, pick one with ?show=<slug>",
+            ))
+        }
+    };
+
+    state.ok_or((StatusCode::NOT_FOUND, "unknown show"))
+}
+
+// admin/reindex?show=lup, POST, downloads `[podcast] feed_url` of the show and updates its index
+// with the new episodes, the episodes the feed no longer lists are kept
+pub async fn handle_reindex(
+    Query(upload): Query<UploadQuery>,
+    State(shows): State<Shows>,
+) -> Response {
+    let state = match pick_show(&shows, upload.show.as_deref()) {
+        Ok(state) => state,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let feed_url = &state.show.podcast.feed_url;
+
+    if feed_url.is_empty() {
+        return (StatusCode::NOT_FOUND, "the show has no podcast.feed_url").into_response();
+    }
+
+    let feed = match download_feed(feed_url).await {
+        Ok(xml) => parse_rss_feed(&xml),
+        Err(err) => {
+            error!("{}", err);
+            return (StatusCode::BAD_GATEWAY, err).into_response();
+        }
+    };
+
+    // the slug of the show is filled in while loading, the files are written without it
+    let mut previous = state.index().episodes_by_id.clone();
+    for episode in previous.values_mut() {
+        if episode.show == state.show.slug {
+            episode.show.clear();
+        }
+    }
+
+    let ingest = match feed.and_then(|feed| ingest_feed(feed, &previous)) {
+        Ok(ingest) => ingest,
+        Err(message) => return (StatusCode::UNPROCESSABLE_ENTITY, message).into_response(),
+    };
+
+    let files = match index_files(state, &ingest.episodes_by_id, &ingest.episodes_by_tag) {
+        Ok(files) => files,
+        Err(message) => return (StatusCode::INTERNAL_SERVER_ERROR, message).into_response(),
+    };
+
+    let index = ParsedIndex {
+        episodes_by_id: ingest.episodes_by_id,
+        episodes_by_tag: ingest.episodes_by_tag,
+        files,
+        transcripts: None,
+    };
+
+    match install_index(&shows, state, index).await {
+        Ok(mut summary) => {
+            info!(
+                show = state.show.slug,
+                added = ingest.added.len(),
+                "Updated the index from the feed"
+            );
+            summary["added"] = serde_json::json!(ingest.added);
+            summary["skipped"] = serde_json::json!(ingest.skipped);
+            Json(summary).into_response()
+        }
+        Err(message) => (StatusCode::INTERNAL_SERVER_ERROR, message).into_response(),
+    }
+}
+
+async fn download_feed(url: &str) -> Result<String, String> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Error at downloading the feed: {}", err))?;
+
+    if response.content_length().unwrap_or(0) > INDEX_UPLOAD_MAX_BYTES as u64 {
+        return Err(String::from(
+            "Error at downloading the feed: it is too large",
+        ));
+    }
+
+    response
+        .text()
+        .await
+        .map_err(|err| format!("Error at downloading the feed: {}", err))
+}
+
+// both index files with sorted keys, like the files of the indexer
+fn index_files(
+    state: &AppState,
+    by_id: &EpisodesById,
+    by_tag: &EpisodesByTag,
+) -> Result<Vec<(String, Bytes)>, String> {
+    let by_id: BTreeMap<_, _> = by_id.iter().collect();
+    let by_tag: BTreeMap<_, _> = by_tag.iter().collect();
+
+    let to_json = |value: serde_json::Result<String>| {
+        value
+            .map(Bytes::from)
+            .map_err(|err| format!("Error at serializing the index: {}", err))
+    };
+
+    Ok(vec![
+        (
+            state.show.episodes_by_id.clone(),
+            to_json(serde_json::to_string_pretty(&by_id))?,
+        ),
+        (
+            state.show.episodes_by_tag.clone(),
+            to_json(serde_json::to_string_pretty(&by_tag))?,
+        ),
+    ])
+}
+
+/// The episodes of the index files of an upload or a backup, once they are consistent
+pub fn parse_index(by_id: &[u8], by_tag: &[u8]) -> Result<(EpisodesById, EpisodesByTag), String> {
+    let episodes_by_id = serde_json::from_slice::<EpisodesById>(by_id)
+        .map_err(|err| format!("Error at parsing episodes_by_id: {}", err))?;
+    let episodes_by_tag = serde_json::from_slice::<EpisodesByTag>(by_tag)
+        .map_err(|err| format!("Error at parsing episodes_by_tag: {}", err))?;
+
+    validate_index(&episodes_by_id, &episodes_by_tag)?;
+
+    Ok((episodes_by_id, episodes_by_tag))
+}
+
+/// A validated index to install, with the files to write
+pub struct ParsedIndex {
+    pub episodes_by_id: EpisodesById,
+    pub episodes_by_tag: EpisodesByTag,
+    /// contents of the files by path
+    pub files: Vec<(String, Bytes)>,
+    /// replaces the transcripts loaded when set
+    pub transcripts: Option<Transcripts>,
+}
+
+/// Writes the files of `index`, rewrites the archive or the database of the show when it has one
+/// and swaps the index in memory, returns the summary of the new index
+pub async fn install_index(
+    shows: &Shows,
+    state: &AppState,
+    index: ParsedIndex,
+) -> Result<serde_json::Value, String> {
+    // one upload at a time, so the files always come from the same upload
+    let _upload = state.index_upload.lock().await;
+
+    let files: Vec<_> = index
+        .files
+        .iter()
+        .map(|(path, contents)| (path, contents.clone()))
+        .collect();
+
+    if let Err(err) = replace_files(&files).await {
+        error!("Error at writing the uploaded index: {}", err);
+        return Err(String::from("Error at writing the index files"));
+    }
+
+    // the archive or the database is loaded instead of the files on the next start,
+    // it's rewritten to match them
+    let (mut episodes_by_id, episodes_by_tag) =
+        if state.show.archive.is_empty() && state.show.database.is_empty() {
+            (index.episodes_by_id, index.episodes_by_tag)
+        } else {
+            let (by_id, by_tag) = (index.episodes_by_id, index.episodes_by_tag);
+
+            let written = match index_store(&state.show) {
+                Ok(store) => tokio::task::spawn_blocking(move || {
+                    store.replace(&by_id, &by_tag).map(|_| (by_id, by_tag))
+                })
+                .await
+                .map_err(|err| format!("Error at writing the index store: {}", err))
+                .and_then(|written| written),
+                Err(err) => Err(err),
+            };
+
+            match written {
+                Ok(index) => index,
+                Err(err) => {
+                    error!("{}", err);
+                    return Err(String::from("Error at writing the index store"));
+                }
+            }
+        };
+
+    default_show(&mut episodes_by_id, &state.show.slug);
+
+    let transcripts = match index.transcripts {
+        Some(transcripts) => Arc::new(transcripts),
+        None => state.index().transcripts.clone(),
+    };
+    let index = ShowIndex::new(episodes_by_id, episodes_by_tag, Some(Utc::now()))
+        .with_transcripts(transcripts);
+    let summary = serde_json::json!({
+        "show": state.show.slug,
+        "episodes": index.episodes_by_id.len(),
+        "tags": index.episodes_by_tag.len(),
+        "version": index.version,
+    });
+
+    state.index.swap(index);
+    state.page_cache.clear();
+    set_index_metrics(shows);
+
+    Ok(summary)
+}
+
+async fn read_index_files(mut multipart: Multipart) -> Result<(Bytes, Bytes), String> {
+    let mut by_id = None;
+    let mut by_tag = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| format!("Error at reading the upload: {}", err))?
+    {
+        let slot = match field.name() {
+            Some("episodes_by_id") => &mut by_id,
+            Some("episodes_by_tag") => &mut by_tag,
+            _ => continue,
+        };
+
+        *slot = Some(
+            field
+                .bytes()
+                .await
+                .map_err(|err| format!("Error at reading the upload: {}", err))?,
+        );
+    }
+
+    match (by_id, by_tag) {
+        (Some(by_id), Some(by_tag)) => Ok((by_id, by_tag)),
+        _ => Err(String::from(
+            "expected the episodes_by_id and episodes_by_tag fields",
+        )),
+    }
+}
+
+/// Every file is written next to its destination first and renamed once all of them
+// are on disk, a failed write leaves the previous files untouched
+pub async fn replace_files(files: &[(&String, Bytes)]) -> std::io::Result<()> {
+    let mut written = Vec::new();
+
+    for (path, contents) in files {
+        let tmp = PathBuf::from(format!("{}.upload", path));
+
+        if let Err(err) = tokio::fs::write(&tmp, contents).await {
+            for (tmp, _) in written {
+                let _ = tokio::fs::remove_file(tmp).await;
+            }
+            return Err(err);
+        }
+
+        written.push((tmp, path));
+    }
+
+    for (tmp, path) in written {
+        tokio::fs::rename(tmp, path).await?;
+    }
+
+    Ok(())
+}
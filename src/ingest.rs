@@ -0,0 +1,221 @@
+use crate::{
+    add_related, build_episodes_by_tag, validate_index, Episode, EpisodesById, EpisodesByTag,
+    FeedEpisodes,
+};
+use std::collections::HashSet;
+
+// query parameters of the links shared on social media, dropped from the episode urls
+const TRACKING_PARAMS: [&str; 6] = ["fbclid", "gclid", "mc_cid", "mc_eid", "ref", "source"];
+const TRACKING_PREFIXES: [&str; 1] = ["utm_"];
+
+/// Both index files built from the episodes of a feed
+#[derive(Debug)]
+pub struct Ingest {
+    pub episodes_by_id: EpisodesById,
+    pub episodes_by_tag: EpisodesByTag,
+    /// ids of the episodes that weren't in the previous index
+    pub added: Vec<usize>,
+    /// titles of the items without an episode number, they can't get an id
+    pub skipped: Vec<String>,
+    /// titles of the older versions of the episodes listed twice
+    pub duplicates: Vec<String>,
+}
+
+/// Builds both index files from the episodes of a feed and their related episodes, updating
+/// `previous`: its episodes missing from the feed are kept, feeds often list only the newest ones,
+/// and the fields the feed doesn't have (the video, the scraped show notes...) are carried over
+pub fn ingest_feed(feed: FeedEpisodes, previous: &EpisodesById) -> Result<Ingest, String> {
+    let mut by_id = previous.clone();
+    let mut seen = HashSet::new();
+    let mut added = Vec::new();
+    let mut duplicates = Vec::new();
+
+    // the feed lists the newest version of an episode first
+    for mut episode in feed.episodes {
+        let id = episode.id as usize;
+
+        if !seen.insert(id) {
+            duplicates.push(episode.title);
+            continue;
+        }
+
+        episode.url = normalize_url(&episode.url);
+
+        let episode = match by_id.remove(&id) {
+            Some(old) => carry_over(episode, old),
+            None => {
+                added.push(id);
+                episode
+            }
+        };
+
+        by_id.insert(id, episode);
+    }
+
+    let by_tag = build_episodes_by_tag(&by_id);
+    add_related(&mut by_id, &by_tag);
+    validate_index(&by_id, &by_tag)?;
+
+    added.sort();
+
+    Ok(Ingest {
+        episodes_by_id: by_id,
+        episodes_by_tag: by_tag,
+        added,
+        skipped: feed.skipped,
+        duplicates,
+    })
+}
+
+/// The url of an episode as the indexer writes it: https, lowercase host, no tracking parameters,
+/// fragment or trailing slash, so the feed and the scraped pages agree on it
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+
+    // not an absolute url, kept as is
+    let Some((_, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (host, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, ""),
+    };
+
+    let path = path.trim_end_matches('/');
+    let path = if path.is_empty() { "/" } else { path };
+
+    let query: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+    let query: Vec<_> = query
+        .into_iter()
+        .filter(|(key, _)| {
+            let key = key.to_lowercase();
+
+            !TRACKING_PARAMS.contains(&key.as_str())
+                && !TRACKING_PREFIXES
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix))
+        })
+        .collect();
+    let query = serde_urlencoded::to_string(&query).unwrap_or_default();
+
+    let mut normalized = format!("https://{}{}", host.to_lowercase(), path);
+
+    if !query.is_empty() {
+        normalized.push('?');
+        normalized.push_str(&query);
+    }
+
+    normalized
+}
+
+// the feed wins, the previous episode fills in what the feed leaves empty
+fn carry_over(mut episode: Episode, old: Episode) -> Episode {
+    episode.video = episode.video.or(old.video);
+    episode.description = episode.description.or(old.description);
+    episode.audio = episode.audio.or(old.audio);
+
+    if episode.tags.is_empty() {
+        episode.tags = old.tags;
+    }
+    if episode.guests.is_empty() {
+        episode.guests = old.guests;
+    }
+    if episode.hosts.is_empty() {
+        episode.hosts = old.hosts;
+    }
+    if episode.links.is_empty() {
+        episode.links = old.links;
+    }
+    if episode.chapters.is_empty() {
+        episode.chapters = old.chapters;
+    }
+    if episode.show.is_empty() {
+        episode.show = old.show;
+    }
+
+    episode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ingest_feed, normalize_url};
+    use crate::{Episode, EpisodesById, FeedEpisodes, Tag};
+
+    fn episode(id: i64, title: &str, tags: &[&str]) -> Episode {
+        Episode {
+            id,
+            title: String::from(title),
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ingest_feed() {
+        let mut scraped = episode(490, "490: The Arch Disaster", &["arch"]);
+        scraped.video = Some(String::from("https://youtu.be/490"));
+
+        let previous = EpisodesById::from([
+            (
+                489,
+                episode(489, "489: Older Than The Feed", &["arch", "kde"]),
+            ),
+            (490, scraped),
+        ]);
+
+        let mut feed = FeedEpisodes {
+            episodes: vec![
+                episode(512, "512: Unity Again", &["ubuntu", "arch"]),
+                episode(490, "490: The Arch Disaster", &["Arch", "pacman"]),
+                episode(490, "490: The Arch Disaster (draft)", &[]),
+            ],
+            skipped: vec![String::from("Bonus: Live Q&A")],
+        };
+        feed.episodes[0].url = String::from("http://linuxunplugged.com/512/?utm_source=rss");
+
+        let ingest = ingest_feed(feed, &previous).unwrap();
+        let by_id = &ingest.episodes_by_id;
+
+        assert_eq!(ingest.added, vec![512]);
+        assert_eq!(ingest.duplicates, vec!["490: The Arch Disaster (draft)"]);
+        assert_eq!(ingest.skipped, vec!["Bonus: Live Q&A"]);
+
+        assert_eq!(by_id.len(), 3);
+        assert_eq!(by_id[&490].tags.len(), 2);
+        assert_eq!(by_id[&490].video.as_deref(), Some("https://youtu.be/490"));
+        assert_eq!(ingest.episodes_by_tag["arch"], vec![489, 490, 512]);
+        assert_eq!(ingest.episodes_by_tag["pacman"], vec![490]);
+        assert!(!by_id[&512].related.is_empty());
+        assert_eq!(by_id[&512].url, "https://linuxunplugged.com/512");
+
+        let empty = FeedEpisodes::default();
+        assert!(ingest_feed(empty, &EpisodesById::new()).is_err());
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("http://LinuxUnplugged.com/512/"),
+            "https://linuxunplugged.com/512"
+        );
+        assert_eq!(
+            normalize_url(
+                " https://linuxunplugged.com/512?utm_source=mastodon&UTM_Medium=social&t=90#notes "
+            ),
+            "https://linuxunplugged.com/512?t=90"
+        );
+        assert_eq!(
+            normalize_url("https://linuxunplugged.com/512?fbclid=abc&ref=rss&source=feed"),
+            "https://linuxunplugged.com/512"
+        );
+        assert_eq!(
+            normalize_url("https://linuxunplugged.com"),
+            "https://linuxunplugged.com/"
+        );
+        assert_eq!(normalize_url("/512"), "/512");
+        assert_eq!(normalize_url(""), "");
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{parse_date, parse_duration, to_iso8601_duration, Episode};
+use serde_json::{json, Value};
+
+const SERIES_NAME: &str = "LINUX Unplugged";
+const SERIES_URL: &str = "https://linuxunplugged.com";
+
+/// Builds the schema.org `PodcastEpisode` structured data of an episode
+pub fn episode_json_ld(episode: &Episode) -> Value {
+    let mut json_ld = json!({
+        "@context": "https://schema.org",
+        "@type": "PodcastEpisode",
+        "name": episode.title,
+        "url": episode.url,
+        "episodeNumber": episode.number(),
+        "keywords": episode.tag_names().join(", "),
+        "partOfSeries": {
+            "@type": "PodcastSeries",
+            "name": SERIES_NAME,
+            "url": SERIES_URL,
+        },
+    });
+
+    if let Some(description) = &episode.description {
+        json_ld["description"] = json!(description);
+    }
+
+    if let Some(date) = parse_date(&episode.date) {
+        json_ld["datePublished"] = json!(date.format("%Y-%m-%d").to_string());
+    }
+
+    if let Some(seconds) = parse_duration(&episode.duration) {
+        json_ld["duration"] = json!(to_iso8601_duration(seconds));
+    }
+
+    json_ld
+}
+
+/// Serializes the structured data so it can be embedded in a `<script>` tag
+pub fn to_script_json(json_ld: &Value) -> String {
+    json_ld.to_string().replace("</", "<\\/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{episode_json_ld, to_script_json};
+    use crate::{Episode, Tag};
+
+    #[test]
+    fn test_episode_json_ld() {
+        let episode = Episode {
+            id: 512,
+            title: String::from("512: </script> Shocker"),
+            date: String::from("March 19th, 2023"),
+            duration: String::from("01:08:28"),
+            tags: vec![Tag::from("docker"), Tag::from("nixos")],
+            url: String::from("https://linuxunplugged.com/512"),
+            ..Default::default()
+        };
+
+        let json_ld = episode_json_ld(&episode);
+
+        assert_eq!(json_ld["@type"], "PodcastEpisode");
+        assert_eq!(json_ld["datePublished"], "2023-03-19");
+        assert_eq!(json_ld["duration"], "PT1H8M28S");
+        assert_eq!(json_ld["partOfSeries"]["@type"], "PodcastSeries");
+        assert!(!to_script_json(&json_ld).contains("</script>"));
+    }
+}
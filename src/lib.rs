@@ -1,16 +1,393 @@
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+mod alfred;
+mod analytics;
+mod analyzer;
+mod autocomplete;
+mod bench;
+mod bm25;
+mod config;
+mod date;
+mod discord;
+mod duration;
+mod engine;
+mod export;
+mod favorites;
+mod feed;
+mod fuzzy;
+mod grouping;
+mod highlight;
+mod i18n;
+mod index_archive;
+mod index_state;
+mod index_store;
+mod ingest;
+mod jsonld;
+mod log_file;
+mod matrix;
+mod oembed;
+mod on_this_day;
+mod opensearch;
+mod packed_index;
+mod page_cache;
+mod pagination;
 mod parser;
+mod permalink;
+mod playback;
+mod podcast_apps;
+mod related;
+mod rss;
+mod saved_search;
+mod search;
+mod sitemap;
+mod slug;
+mod sort;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+mod staleness;
+mod stats;
+mod symbols;
+mod tarball;
+mod telemetry;
+mod transcript;
+pub use alfred::*;
+pub use analytics::*;
+pub use analyzer::*;
+pub use autocomplete::*;
+pub use bench::*;
+pub use bm25::*;
+pub use config::*;
+pub use date::*;
+pub use discord::*;
+pub use duration::*;
+pub use engine::*;
+pub use export::*;
+pub use favorites::*;
+pub use feed::*;
+pub use fuzzy::*;
+pub use grouping::*;
+pub use highlight::*;
+pub use i18n::*;
+pub use index_archive::*;
+pub use index_state::*;
+pub use index_store::*;
+pub use ingest::*;
+pub use jsonld::*;
+pub use log_file::*;
+pub use matrix::*;
+pub use oembed::*;
+pub use on_this_day::*;
+pub use opensearch::*;
+pub use packed_index::*;
+pub use page_cache::*;
+pub use pagination::*;
 pub use parser::*;
+pub use permalink::*;
+pub use playback::*;
+pub use podcast_apps::*;
+pub use related::*;
+pub use rss::*;
+pub use saved_search::*;
+pub use search::*;
+pub use sitemap::*;
+pub use slug::*;
+pub use sort::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::*;
+pub use staleness::*;
+pub use stats::*;
+pub use symbols::*;
+pub use tarball::*;
+pub use telemetry::*;
+pub use transcript::*;
 
-#[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+)]
 pub struct Episode {
     pub id: i64,
+    /// the number of the episode in its show, the id stays the key of the index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<u32>,
     pub title: String,
     pub date: String,
     pub duration: String,
-    pub tags: Vec<String>,
+    pub tags: Vec<Tag>,
     pub url: String,
+    /// the video version of the episode, usually on YouTube
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video: Option<String>,
+    /// summary of the episode from the feed, in plain text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// people on the episode besides the hosts, searched with `guest:`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guests: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    /// slug of the show of the episode, the show of the index when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub show: String,
+    /// the audio file of the feed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<Enclosure>,
+    /// links of the show notes, searched with `domain:`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<Link>,
+    /// chapters in the order they're played, their titles are searched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapters: Vec<Chapter>,
+    /// ids of the episodes most alike, precomputed by `index build`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<i64>,
+}
+
+/// A tag of an episode, a plain string in the index files
+/// or `{"name": "nixos", "weight": 200, "category": "distro"}`
+#[derive(
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+)]
+#[serde(from = "TagEntry", into = "TagEntry")]
+pub struct Tag {
+    pub name: String,
+    /// percent of the points of `[scoring] tag`, 200 for what the episode is about,
+    /// 50 for a passing mention, 100 when missing
+    pub weight: Option<u32>,
+    pub category: Option<TagCategory>,
+}
+
+/// What a tag names, the episode pages group the tags by it
+#[derive(
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum TagCategory {
+    Distro,
+    App,
+    Topic,
+    Event,
+}
+
+impl TagCategory {
+    /// In the order the episode pages list them
+    pub const ALL: [TagCategory; 4] = [
+        TagCategory::Distro,
+        TagCategory::App,
+        TagCategory::Topic,
+        TagCategory::Event,
+    ];
+}
+
+// plain tags are written back as strings, so indices without weights keep their format
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TagEntry {
+    Name(String),
+    Tag {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weight: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        category: Option<TagCategory>,
+    },
+}
+
+impl From<TagEntry> for Tag {
+    fn from(entry: TagEntry) -> Self {
+        match entry {
+            TagEntry::Name(name) => Tag::from(name),
+            TagEntry::Tag {
+                name,
+                weight,
+                category,
+            } => Tag {
+                name,
+                weight,
+                category,
+            },
+        }
+    }
+}
+
+impl From<Tag> for TagEntry {
+    fn from(tag: Tag) -> Self {
+        match tag {
+            Tag {
+                name,
+                weight: None,
+                category: None,
+            } => TagEntry::Name(name),
+            Tag {
+                name,
+                weight,
+                category,
+            } => TagEntry::Tag {
+                name,
+                weight,
+                category,
+            },
+        }
+    }
+}
+
+impl From<String> for Tag {
+    fn from(name: String) -> Self {
+        Tag {
+            name,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(name: &str) -> Self {
+        Tag::from(name.to_string())
+    }
+}
+
+impl Tag {
+    /// The weight in percent, 100 for plain tags
+    pub fn weight(&self) -> u32 {
+        self.weight.unwrap_or(100)
+    }
+}
+
+/// A chapter of an episode and where it starts
+#[derive(
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+)]
+pub struct Chapter {
+    pub title: String,
+    pub start_secs: u64,
+}
+
+/// A link of the show notes of an episode
+#[derive(
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+)]
+pub struct Link {
+    pub url: String,
+    /// the text of the link, the url when it has none
+    #[serde(default)]
+    pub label: String,
+}
+
+impl Link {
+    /// The lowercase host of the url without `www.`,
+    /// "https://www.GitHub.com/nixos" -> "github.com"
+    pub fn domain(&self) -> Option<String> {
+        let (_, rest) = self.url.split_once("://")?;
+        let host = rest.split(['/', '?', '#']).next()?;
+        // user info and port
+        let host = host.rsplit('@').next()?.split(':').next()?;
+        let host = host.to_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+
+        (!host.is_empty()).then(|| host.to_string())
+    }
+}
+
+/// The enclosure of a feed item, the size and type as the feed states them
+#[derive(
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+)]
+pub struct Enclosure {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// "audio/mpeg"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+impl Episode {
+    /// The episode number, the id for the indices written before episodes had one
+    pub fn number(&self) -> Option<u32> {
+        self.number.or_else(|| u32::try_from(self.id).ok())
+    }
+
+    /// The chapters whose title contains any of the lowercase terms
+    pub fn matched_chapters(&self, terms: &[String]) -> Vec<&Chapter> {
+        self.chapters
+            .iter()
+            .filter(|chapter| {
+                let title = chapter.title.to_lowercase();
+                terms.iter().any(|term| title.contains(term.as_str()))
+            })
+            .collect()
+    }
+
+    /// The names of the tags, in the order of the index
+    pub fn tag_names(&self) -> Vec<&str> {
+        self.tags.iter().map(|tag| tag.name.as_str()).collect()
+    }
+
+    /// The duration in seconds, none when it doesn't parse
+    pub fn duration_seconds(&self) -> Option<u64> {
+        parse_duration(&self.duration)
+    }
 }
 
 impl From<Episode> for String {
@@ -19,49 +396,63 @@ impl From<Episode> for String {
     }
 }
 
-const EPISODES_BY_ID_FILE: &str = "episodes_by_id_index.json";
-const EPISODES_BY_TAG_FILE: &str = "episodes_by_tag_index.json";
-
 pub type EpisodesById = HashMap<usize, Episode>;
 pub type EpisodesByTag = HashMap<String, Vec<usize>>;
 
-pub async fn parse_episodes_by_tag() -> EpisodesByTag {
-    parse_json_file::<EpisodesByTag>(EPISODES_BY_TAG_FILE).await
+pub async fn parse_episodes_by_tag(file: &str) -> Result<EpisodesByTag, EngineError> {
+    parse_json_file::<EpisodesByTag>(file).await
 }
 
-pub async fn parse_episodes_by_id() -> EpisodesById {
-    parse_json_file::<EpisodesById>(EPISODES_BY_ID_FILE).await
+pub async fn parse_episodes_by_id(file: &str) -> Result<EpisodesById, EngineError> {
+    parse_json_file::<EpisodesById>(file).await
 }
 
-pub fn get_episodes_from_ids<'a>(ids: &[usize], by_id: &'a EpisodesById) -> Vec<&'a Episode> {
-    ids.iter()
-        .map(|id| by_id.get(id).expect("Error at getting episode by id"))
-        .collect()
+/// Fingerprint of the index contents, changes whenever the index files change
+pub fn index_version(by_id: &EpisodesById, by_tag: &EpisodesByTag) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    let mut episodes: Vec<_> = by_id.iter().collect();
+    episodes.sort_by_key(|(id, _)| **id);
+    episodes.hash(&mut hasher);
+
+    let mut tags: Vec<_> = by_tag.iter().collect();
+    tags.sort_by_key(|(tag, _)| *tag);
+    tags.hash(&mut hasher);
+
+    hasher.finish()
 }
 
-pub async fn parse_json_file<T>(file: &str) -> T
+pub async fn parse_json_file<T>(file: &str) -> Result<T, EngineError>
 where
     T: DeserializeOwned,
 {
     let contents = tokio::fs::read_to_string(file)
         .await
-        .unwrap_or_else(|_| panic!("Error at parsing {} file to json", &file));
+        .map_err(|source| EngineError::Io {
+            path: file.to_string(),
+            source,
+        })?;
 
-    serde_json::from_str::<T>(&contents).expect("Error at parsing to json file")
+    serde_json::from_str::<T>(&contents).map_err(|source| EngineError::Parse {
+        path: file.to_string(),
+        source,
+    })
 }
 
+/// The english stopwords, the analyzer of the archives without an `[analyzer]` section
 pub fn load_common_words() -> HashSet<String> {
-    let common_words = [
-        "the", "be", "is", "are", "to", "of", "and", "a", "an", "in", "that", "have", "i", "it",
-        "for", "not", "on", "with", "he", "as", "you", "do", "at", "this", "but", "his", "by",
-        "from", "they", "we", "say", "her", "she", "or", "an", "will", "my", "one", "all", "would",
-        "there", "their", "what", "so", "up", "out", "if", "about", "who", "get", "which", "me",
-        "when", "make", "can", "like", "time", "no", "just", "him", "know", "take", "people",
-        "into", "year", "your", "some", "could", "them", "see", "other", "than", "then", "now",
-        "look", "only", "come", "its", "it's", "over", "think", "also", "back", "after", "use",
-        "two", "how", "our", "work", "first", "well", "way", "even", "new", "want", "because",
-        "any", "these", "give", "day", "most", "us", "was", "from",
-    ];
+    stopwords("en", &[])
+}
 
-    common_words.iter().map(|s| s.to_string()).collect()
+// percent-encodes everything except unreserved characters (RFC 3986)
+pub fn encode_query_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
 }
@@ -0,0 +1,115 @@
+use crate::Rotation;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The log file of the server, rotated like the queries log once it reaches `max_bytes`,
+/// `&RotatingFile` is the writer of the tracing subscriber
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    /// the file and its size
+    file: Mutex<(File, u64)>,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, rotation: Rotation) -> std::io::Result<Self> {
+        let file = open_append(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            file: Mutex::new((file, size)),
+        })
+    }
+}
+
+impl Write for &RotatingFile {
+    // the subscriber writes a whole line at once
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut guard = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        let (file, size) = &mut *guard;
+
+        if *size > 0 && *size + buf.len() as u64 > self.rotation.max_bytes {
+            rotate(&self.path, self.rotation.keep)?;
+            *file = open_append(&self.path)?;
+            *size = 0;
+        }
+
+        file.write_all(buf)?;
+        *size += buf.len() as u64;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut guard = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        guard.0.flush()
+    }
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+// <path>.<keep - 1> -> <path>.<keep>, ..., <path> -> <path>.1
+fn rotate(path: &Path, keep: usize) -> std::io::Result<()> {
+    let rotated = |index: usize| PathBuf::from(format!("{}.{}", path.display(), index));
+
+    if keep == 0 {
+        return std::fs::remove_file(path);
+    }
+
+    for index in (1..keep).rev() {
+        let from = rotated(index);
+
+        if from.exists() {
+            std::fs::rename(&from, rotated(index + 1))?;
+        }
+    }
+
+    std::fs::rename(path, rotated(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RotatingFile;
+    use crate::Rotation;
+    use std::io::Write;
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("unplugged-log-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.log");
+
+        let rotation = Rotation {
+            max_bytes: 12,
+            keep: 2,
+        };
+        let log = RotatingFile::open(&path, rotation).unwrap();
+
+        // 8 bytes per line, a file holds a single line
+        for index in 0..4 {
+            (&log)
+                .write_all(format!("line {}\n", index).as_bytes())
+                .unwrap();
+        }
+
+        let lines = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+
+        assert_eq!(lines("server.log"), "line 3\n");
+        assert_eq!(lines("server.log.1"), "line 2\n");
+        assert_eq!(lines("server.log.2"), "line 1\n");
+        assert!(!dir.join("server.log.3").exists());
+
+        // appends to the existing file after a restart
+        let log = RotatingFile::open(&path, rotation).unwrap();
+        (&log).write_all(b"line\n").unwrap();
+        assert_eq!(lines("server.log"), "line 3\nline\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
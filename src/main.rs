@@ -1,173 +1,951 @@
-use axum::extract::{Query, State};
-use axum::response::{Html, IntoResponse};
-use axum::routing::{get, get_service};
-use axum::{Router, Server};
-use log::{debug, info};
-use tower_http::services::{ServeDir, ServeFile};
-
-use serde::Deserialize;
-use std::collections::{HashMap, HashSet};
+mod admin;
+mod alerts;
+mod api_keys;
+mod args;
+mod assets;
+mod backup;
+mod bookmarks;
+mod cli;
+mod csrf;
+mod daemon;
+mod dev;
+mod digests;
+mod doctor;
+mod error_pages;
+mod health;
+mod http_cache;
+mod index_upload;
+mod maintenance;
+mod matrix_bot;
+mod metrics;
+mod progress;
+mod rate_limit;
+mod reload;
+mod request_log;
+mod shows;
+mod static_site;
+mod systemd;
+mod timeouts;
+mod tls;
+mod tui;
+mod usage_reports;
+
+use axum::body::{Bytes, StreamBody};
+use axum::error_handling::HandleErrorLayer;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Host, Path, Query, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post, put};
+use axum::{BoxError, Json, Router, Server};
+use chrono::{Local, Utc};
+use clap::Parser;
+use futures::stream::{self, Stream, StreamExt};
+use futures::FutureExt;
+use tokio::sync::Semaphore;
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::load_shed::error::Overloaded;
+use tower::ServiceBuilder;
+use tower_http::catch_panic::CatchPanicLayer;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::trace::TraceLayer;
+use tracing::{debug, error, info, instrument, warn};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tera::{Context, Tera};
+use std::time::{Duration, Instant};
+use tera::Context;
+
+use admin::{
+    handle_admin_api_keys_json, handle_admin_queries, handle_admin_queries_json, require_admin,
+};
+use alerts::{handle_alert_feed, handle_save_search, handle_unsubscribe, handle_unsubscribe_page};
+use api_keys::{require_api_key, ApiKeys};
+use args::{Args, Command, LogFormat, ServeArgs};
+use assets::{bundled_translations, compile_templates, error_chain, StaticFiles, Templates};
+use backup::{handle_backup, handle_restore, RESTORE_MAX_BYTES};
+use bookmarks::{favorites_cookie, handle_favorites, handle_star, handle_unstar};
+use csrf::csrf_protect;
+use daemon::{reexec, stop_signal, PidFile, Stop};
+use dev::{watch_files, watch_indices, DevWatch};
+use digests::{send_digests, Mailer};
+use doctor::run_doctor;
+use error_pages::{bad_query_page, error_page, internal_error_page, panic_page, unknown_tag_page};
+use health::{handle_healthz, handle_readyz, stale_days};
+use http_cache::conditional_get;
+use index_upload::{handle_index_upload, handle_reindex, INDEX_UPLOAD_MAX_BYTES};
+use maintenance::{maintenance, reload_unavailable_indices, set_index_metrics};
+use matrix_bot::run_matrix_bot;
+use metrics::{handle_metrics, track_requests, Metrics};
+use progress::{
+    handle_delete_playback_position, handle_playback_position, handle_playback_positions,
+    handle_save_playback_position,
+};
+use rate_limit::{rate_limit, IpRateLimiter};
+use reload::{handle_reload, reload_on_hangup, ConfigHandle};
+use request_log::{log_response, make_request_span, REQUEST_ID_HEADER};
+use shows::{handle_search_all, Shows};
+use systemd::notify_ready;
+use timeouts::{search_budget_exceeded, search_was_cut_short, timeout_requests};
+use tls::{load_tls_config, reload_certificates};
 use unplugged_engine::{
-    get_episodes_from_ids, load_common_words, parse_episodes_by_id, parse_episodes_by_tag,
-    parse_query, Episode, EpisodesById, EpisodesByTag, ParseResult,
+    alfred_items, approximate_listening_time, atom_feed, discord_pong_response,
+    discord_search_response, duration_histogram, encode_query_component, episode_json_ld,
+    episode_oembed, episode_timeline, format_duration, group_by_year, highlight_terms, json_feed,
+    listening_time, normalize_query, oembed_episode_slug, on_this_day, opensearch_description,
+    parse_query_strict, podcast_app_links, popular_queries, query_terms, read_rotated_event_log,
+    related_to, search_index_version, search_permalink, tag_counts, tag_key, to_script_json,
+    transcript_hits, trending_tags, CachedPage, ClickEvent, Config, DiscordVerifier, Episode,
+    EventLog, ExportFormat, FavoritesSigner, FeedbackEvent, GroupBy, IndexHandle, Interaction,
+    InteractionRequest, Matches, PageCache, PageKey, Pagination, ParseResult, PlaybackPositions,
+    QueryError, QueryEvent, QueryVersion, ResultGroup, RotatingFile, Rotation, SavedSearches,
+    SearchParams, SearchResults, ShowConfig, ShowIndex, SortOrder, TagCategory, TagOrder,
+    Telemetry, TimelinePeriod, Transcripts, QUERY_FIELDS, QUERY_OPERATORS, RELATED_EPISODES,
+    SHOW_FIELD, TAG_FIELD,
 };
+use usage_reports::send_telemetry;
+
+const SEARCH_STREAM_DEBOUNCE: Duration = Duration::from_millis(250);
+// sentences of the transcript quoted per result
+const TRANSCRIPT_HITS: usize = 3;
+const WS_BATCH_SIZE: usize = 10;
+const SUGGESTIONS_LIMIT: usize = 8;
+// closest tags listed on the 404 of an unknown tag
+const TAG_SUGGESTIONS_LIMIT: usize = 5;
+// most episodes /related/<id> lists
+const RELATED_LIMIT: usize = 50;
+const DISCORD_RESULTS_LIMIT: usize = 3;
+const BATCH_QUERIES_LIMIT: usize = 20;
+// broad queries get the results page split by year
+const YEAR_GROUPS_MIN_RESULTS: usize = 20;
+const DURATION_BUCKET_MINUTES: u64 = 15;
+// newest episodes /api/stats/trending compares with the older ones
+const TRENDING_RECENT_EPISODES: usize = 20;
+// recent episodes a tag needs to be trending
+const TRENDING_MIN_EPISODES: usize = 2;
+const TRENDING_LIMIT: usize = 10;
+// trending tags listed on /stats
+const STATS_TRENDING_TAGS: usize = 5;
+// the popular queries are counted again from the queries log after this long
+const POPULAR_QUERIES_TTL: Duration = Duration::from_secs(10 * 60);
+// longer queries are cut in the analytics logs
+const LOGGED_QUERY_MAX_CHARS: usize = 200;
+const COMPRESSION_MIN_SIZE: u16 = 1024;
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+// templates recompiled by --dev when paths.templates isn't set
+const DEV_TEMPLATES_DIR: &str = "templates";
+// static files served from disk by --dev when paths.static_dir isn't set
+const DEV_STATIC_DIR: &str = "static";
+// server events and one line per request (method, path, status, latency)
+const DEFAULT_LOG_FILTER: &str = "unplugged_engine=info";
+
+async fn forget_rate_limits(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+
+    loop {
+        interval.tick().await;
+        state.rate_limiter.retain_recent();
+    }
+}
+
+// the search routes of a show, nested under its slug in a multi-show archive
+fn show_routes<S>(state: Arc<AppState>, static_files: &StaticFiles) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/", static_files.index(&state.mount_path))
+        .route("/search", get(handle_search)) // search?query=foo
+        .route("/s", get(handle_permalink)) // s?query=NixOS++Fedora
+        .route("/search/stream", get(handle_search_stream)) // search/stream?query=foo
+        .route("/search.atom", get(handle_search_feed)) // search.atom?query=foo
+        .route("/search.json", get(handle_search_json_feed)) // search.json?query=foo
+        .route("/search/export", get(handle_search_export)) // search/export?format=csv&query=foo
+        .route("/suggest", get(handle_suggest)) // suggest?q=foo
+        .route("/api/search", get(handle_search_api)) // api/search?query=foo
+        .route("/api/alfred", get(handle_alfred)) // api/alfred?query=foo
+        .route("/api/search/batch", post(handle_search_batch))
+        .route("/api/stats/timeline", get(handle_timeline)) // api/stats/timeline?query=btrfs&by=month
+        .route("/api/stats/durations", get(handle_durations)) // api/stats/durations?minutes=15
+        .route("/api/stats/trending", get(handle_trending)) // api/stats/trending?recent=20
+        .route("/api/tags", get(handle_tags_json)) // api/tags?sort=name
+        .route("/api/on-this-day", get(handle_on_this_day_json))
+        .route("/api/popular-queries", get(handle_popular_queries_json))
+        .route("/discord/interactions", post(handle_discord_interaction))
+        .route("/track/click", post(handle_track_click))
+        .route("/feedback", post(handle_feedback))
+        .route("/alerts", post(handle_save_search))
+        .route("/alerts/:file", get(handle_alert_feed)) // alerts/<token>.atom
+        .route(
+            "/alerts/unsubscribe/:key",
+            get(handle_unsubscribe_page).post(handle_unsubscribe),
+        )
+        .route("/ws", get(handle_ws))
+        .route("/opensearch.xml", get(handle_opensearch))
+        .route("/sitemap.xml", get(handle_sitemap))
+        .route("/episode/:slug", get(handle_episode)) // episode/490-the-arch-disaster
+        .route("/episode/:slug/chapters", get(handle_episode_chapters)) // episode/490/chapters
+        .route("/episode/:slug/transcript", get(handle_episode_transcript)) // episode/490/transcript
+        .route("/related/:slug", get(handle_related)) // related/490?limit=10
+        .route("/oembed", get(handle_oembed)) // oembed?url=https://.../episode/490-the-arch-disaster
+        .route("/tag/:tag", get(handle_tag)) // tag/nixos, tag/nixos.json
+        .route("/tags", get(handle_tags)) // tags?sort=name
+        .route("/stats", get(handle_stats))
+        .route("/help", get(handle_help))
+        .route("/on-this-day", get(handle_on_this_day))
+        .route("/popular", get(handle_popular_queries))
+        .route("/favorites", get(handle_favorites))
+        .route("/favorites/:id", post(handle_star).delete(handle_unstar))
+        .route("/playback/:token", get(handle_playback_positions))
+        .route(
+            "/playback/:token/:id",
+            get(handle_playback_position)
+                .put(handle_save_playback_position)
+                .delete(handle_delete_playback_position),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            timeout_requests,
+        ))
+        .route_layer(middleware::from_fn_with_state(state.clone(), maintenance))
+        .route_layer(middleware::from_fn_with_state(state.clone(), csrf_protect))
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            track_requests,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            conditional_get,
+        ))
+        .with_state(state)
+}
+
+// serves the routes under server.base_path for subdirectory deployments,
+// the bare prefixes (base path and show slugs) redirect to the trailing slash
+// so relative links resolve
+fn nest_under_base_path(base_path: &str, routes: Router, mount_paths: Vec<String>) -> Router {
+    let routes = if base_path.is_empty() {
+        routes
+    } else {
+        Router::new().nest(base_path, routes)
+    };
+
+    let mount_paths: Arc<[String]> = mount_paths
+        .into_iter()
+        .filter(|path| !path.is_empty())
+        .collect();
+
+    if mount_paths.is_empty() {
+        return routes;
+    }
+
+    routes.layer(middleware::from_fn_with_state(
+        mount_paths,
+        redirect_bare_mount_path,
+    ))
+}
+
+async fn redirect_bare_mount_path<B>(
+    State(mount_paths): State<Arc<[String]>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let path = request.uri().path();
+
+    if mount_paths.iter().any(|mount_path| mount_path == path) {
+        return Redirect::permanent(&format!("{}/", path)).into_response();
+    }
+
+    next.run(request).await
+}
+
+// the answer to the requests shed by the concurrency limit
+fn overloaded(metrics: &Metrics, err: BoxError) -> Response {
+    if !err.is::<Overloaded>() {
+        error!("Error at handling a request: {}", err);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    metrics.requests_shed.inc();
+
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, "1")],
+        "The server is busy, retry in a moment",
+    )
+        .into_response()
+}
 
-pub fn compile_templates() -> Tera {
-    Tera::new("templates/**/*.html").expect("Error at compiling templates")
+// skips small bodies and content that doesn't benefit from (or breaks with) compression
+fn compression_layer() -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(COMPRESSION_MIN_SIZE)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::const_new("text/event-stream"));
+
+    CompressionLayer::new().compress_when(predicate)
 }
 
-#[derive(Clone)]
+/// State of a show, the config, templates, rate limiter and metrics are shared by all shows
 pub struct AppState {
-    pub episodes_by_tag: EpisodesByTag,
-    pub episodes_by_id: EpisodesById,
-    pub common_words: HashSet<String>,
-    pub tera: Tera,
+    /// swapped when the config is reloaded, read with `config()`
+    pub config: Arc<ConfigHandle>,
+    pub show: ShowConfig,
+    /// url prefix of the show's pages, server.base_path plus the slug of the show
+    pub mount_path: String,
+    /// the index of the show, the show is in maintenance while it's unavailable
+    pub index: IndexHandle,
+    /// serializes the uploads of /admin/index
+    pub index_upload: tokio::sync::Mutex<()>,
+    /// set when discord.public_key is configured
+    pub discord: Option<DiscordVerifier>,
+    pub templates: Arc<Templates>,
+    /// set when alerts.file is configured
+    pub saved_searches: Option<Arc<SavedSearches>>,
+    /// set when playback.file is configured
+    pub playback: Option<Arc<PlaybackPositions>>,
+    /// set when analytics.queries_log is configured
+    pub query_log: Option<Arc<EventLog>>,
+    /// set when analytics.clicks_log is configured
+    pub click_log: Option<Arc<EventLog>>,
+    /// set when analytics.feedback_log is configured
+    pub feedback_log: Option<Arc<EventLog>>,
+    /// the popular queries of the homepage and when they were counted
+    pub popular_queries: tokio::sync::Mutex<Option<(Instant, Arc<Vec<String>>)>>,
+    /// signs the favorites cookie
+    pub favorites: FavoritesSigner,
+    pub page_cache: PageCache,
+    pub rate_limiter: Arc<IpRateLimiter>,
+    /// keys of the json apis, shared by all shows
+    pub api_keys: Arc<ApiKeys>,
+    pub metrics: Arc<Metrics>,
+    /// set when telemetry.endpoint is configured, shared by all shows
+    pub telemetry: Option<Arc<Telemetry>>,
+    pub started_at: Instant,
+}
+
+impl AppState {
+    /// The current index of the show, kept by the caller for the whole request
+    pub fn index(&self) -> Arc<ShowIndex> {
+        self.index.get()
+    }
+
+    /// The current config, kept by the caller for the whole request
+    pub fn config(&self) -> Arc<Config> {
+        self.config.get()
+    }
+
+    /// The stopwords of the current config
+    pub fn common_words(&self) -> Arc<HashSet<String>> {
+        self.config.stopwords()
+    }
+}
+
+fn init_tracing(format: LogFormat, file: Option<RotatingFile>) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+
+    // no colors in the log file
+    let ansi = file.is_none();
+    let writer = match file {
+        Some(file) => BoxMakeWriter::new(Arc::new(file)),
+        None => BoxMakeWriter::new(std::io::stdout),
+    };
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(ansi)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Text => subscriber.init(),
+        // one object per line, with the fields of the request span (id, method, path)
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .init(),
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    let args = Args::parse();
 
-    let episodes_by_tag = parse_episodes_by_tag().await;
-    let episodes_by_id = parse_episodes_by_id().await;
-    let common_words: HashSet<_> = load_common_words();
+    let config_path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+    let config = Config::load(&config_path, args.config.is_some());
 
-    let tera = compile_templates();
+    // the doctor reports a broken config along with everything else
+    if let Some(Command::Doctor) = args.command {
+        std::process::exit(run_doctor(&config_path, config).await);
+    }
 
-    let serve_dir = ServeDir::new("static");
+    let config = config.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
 
-    let app = Router::new()
-        .route("/", get_service(ServeFile::new("static/index.html")))
-        .route("/search", get(handle_search)) // search?query=foo
-        .fallback_service(serve_dir)
-        .with_state(Arc::new(AppState {
-            episodes_by_tag,
-            episodes_by_id,
-            common_words,
-            tera,
-        }));
+    // read again on reloads
+    let config_file = (config_path, args.config.is_some());
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    match args.command {
+        None => serve(config, config_file, args.serve).await,
+        Some(Command::Serve(serve_args)) => serve(config, config_file, serve_args).await,
+        Some(command) => std::process::exit(cli::run(config, command).await),
+    }
+}
 
-    info!(
-        "Web server listening on {} (http://localhost:{})",
-        addr,
-        addr.port()
-    );
+async fn serve(mut config: Config, (config_path, required): (PathBuf, bool), args: ServeArgs) {
+    if let Some(address) = args.address {
+        config.server.address = address.to_string();
+    }
+
+    let log_format = args
+        .log_format
+        .unwrap_or(match config.logging.format.as_str() {
+            "json" => LogFormat::Json,
+            _ => LogFormat::Text,
+        });
+
+    let log_file = args
+        .log_file
+        .or_else(|| (!config.logging.file.is_empty()).then(|| PathBuf::from(&config.logging.file)))
+        .map(|path| {
+            let rotation = Rotation {
+                max_bytes: config.logging.file_max_bytes,
+                keep: config.logging.file_keep,
+            };
+
+            RotatingFile::open(&path, rotation).unwrap_or_else(|err| {
+                eprintln!("Error at opening the log file {}: {}", path.display(), err);
+                std::process::exit(1);
+            })
+        });
+
+    init_tracing(log_format, log_file);
+
+    let _pid_file = args.pid_file.map(|path| {
+        PidFile::create(&path).unwrap_or_else(|err| {
+            error!("{}", err);
+            std::process::exit(1);
+        })
+    });
+
+    let discord = load_discord_verifier(&config.discord.public_key);
+    // dev mode recompiles the templates of the repo when they change
+    let templates_dir = if args.dev && config.paths.templates.is_empty() {
+        String::from(DEV_TEMPLATES_DIR)
+    } else {
+        config.paths.templates.clone()
+    };
+
+    let translations = Arc::new(bundled_translations());
+    let tera = compile_templates(&templates_dir, &translations)
+        .unwrap_or_else(|err| panic!("Error at compiling templates: {}", error_chain(&err)));
+    let templates = Arc::new(Templates::new(tera, translations, args.dev));
 
-    Server::bind(&addr)
-        .serve(app.into_make_service())
+    // and serves the static files of the repo from disk
+    let static_dir = if args.dev && config.paths.static_dir.is_empty() {
+        String::from(DEV_STATIC_DIR)
+    } else {
+        config.paths.static_dir.clone()
+    };
+
+    let static_files = StaticFiles::new(&static_dir, templates.clone());
+    let addr = config.socket_addr();
+    let tls_files = config.tls.files();
+    let reload_tls = !config.tls.dir.is_empty();
+    let base_path = config.server.base_path.clone();
+
+    let config = Arc::new(config);
+    let metrics = Arc::new(Metrics::new());
+    let rate_limiter = Arc::new(IpRateLimiter::new(
+        config.rate_limit.per_second,
+        config.rate_limit.burst,
+    ));
+
+    let analytics = &config.analytics;
+    let query_log = open_event_log(
+        "analytics.queries_log",
+        &analytics.queries_log,
+        Some(Rotation {
+            max_bytes: analytics.queries_log_max_bytes,
+            keep: analytics.queries_log_keep,
+        }),
+    )
+    .await;
+    let click_log = open_event_log("analytics.clicks_log", &analytics.clicks_log, None).await;
+    let feedback_log =
+        open_event_log("analytics.feedback_log", &analytics.feedback_log, None).await;
+
+    let saved_searches = if config.alerts.file.is_empty() {
+        None
+    } else {
+        let saved =
+            SavedSearches::load(std::path::Path::new(&config.alerts.file), config.alerts.max)
+                .await
+                .unwrap_or_else(|err| panic!("Error at loading alerts.file: {}", err));
+        Some(Arc::new(saved))
+    };
+
+    let playback = if config.playback.is_enabled() {
+        let positions = PlaybackPositions::load(
+            std::path::Path::new(&config.playback.file),
+            config.playback.max_listeners,
+        )
         .await
-        .unwrap();
+        .unwrap_or_else(|err| panic!("Error at loading playback.file: {}", err));
+        Some(Arc::new(positions))
+    } else {
+        None
+    };
+
+    let favorites = favorites_signer(&config.favorites.secret);
+    let telemetry = config
+        .telemetry
+        .is_enabled()
+        .then(|| Arc::new(Telemetry::default()));
+    let api_keys = Arc::new(ApiKeys::new(&config.api));
+
+    let config_handle = Arc::new(
+        ConfigHandle::new(config.clone(), config_path, required, templates_dir.clone())
+            .with_address(args.address),
+    );
+
+    let mut shows = Vec::new();
+
+    // the indices of every show are loaded at once
+    let show_configs = config.show_configs();
+    let indices = futures::future::join_all(show_configs.iter().map(ShowIndex::load)).await;
+
+    for (show, index) in show_configs.into_iter().zip(indices) {
+        // a missing or broken index puts the show in maintenance instead of failing to start
+        let index = match index {
+            Ok(index) => IndexHandle::ready(index),
+            Err(reason) => {
+                error!(show = show.slug, "{}, serving the maintenance page", reason);
+                IndexHandle::unavailable(&reason)
+            }
+        };
+
+        let mount_path = if show.slug.is_empty() {
+            base_path.clone()
+        } else {
+            format!("{}/{}", base_path, show.slug)
+        };
+
+        shows.push(Arc::new(AppState {
+            config: config_handle.clone(),
+            show,
+            mount_path,
+            index,
+            index_upload: tokio::sync::Mutex::new(()),
+            discord: discord.clone(),
+            templates: templates.clone(),
+            saved_searches: saved_searches.clone(),
+            playback: playback.clone(),
+            query_log: query_log.clone(),
+            click_log: click_log.clone(),
+            feedback_log: feedback_log.clone(),
+            popular_queries: tokio::sync::Mutex::new(None),
+            favorites: favorites.clone(),
+            page_cache: PageCache::new(config.cache.pages),
+            rate_limiter: rate_limiter.clone(),
+            api_keys: api_keys.clone(),
+            metrics: metrics.clone(),
+            telemetry: telemetry.clone(),
+            started_at: Instant::now(),
+        }));
+    }
+
+    let mut mount_paths: Vec<_> = shows.iter().map(|show| show.mount_path.clone()).collect();
+    mount_paths.push(base_path.clone());
+
+    let shows: Shows = Arc::new(shows);
+    let first = shows[0].clone();
+
+    set_index_metrics(&shows);
+
+    tokio::spawn(forget_rate_limits(first.clone()));
+    tokio::spawn(reload_unavailable_indices(shows.clone()));
+    tokio::spawn(reload_on_hangup(shows.clone()));
+
+    if config.digests.is_enabled() {
+        let mailer = Mailer::new(&config.digests).unwrap_or_else(|err| panic!("{}", err));
+        tokio::spawn(send_digests(shows.clone(), mailer));
+    }
+
+    if config.matrix.is_enabled() {
+        tokio::spawn(run_matrix_bot(first.clone(), config.matrix.clone()));
+    }
+
+    if let Some(telemetry) = telemetry {
+        tokio::spawn(send_telemetry(telemetry, config.telemetry.clone()));
+    }
+
+    if args.dev {
+        let watch = DevWatch {
+            templates_dir,
+            static_dir,
+        };
+        tokio::spawn(watch_files(watch, shows.clone()));
+    } else if config.paths.watch {
+        tokio::spawn(watch_indices(shows.clone()));
+    }
+
+    let request_permits = Arc::new(Semaphore::new(
+        match config.server.max_concurrent_requests {
+            0 => Semaphore::MAX_PERMITS,
+            max => max,
+        },
+    ));
+
+    let mut routes = Router::new();
+
+    if shows.len() == 1 {
+        routes = routes.merge(show_routes(first.clone(), &static_files));
+    } else {
+        routes = routes.route("/", static_files.index(&base_path)).route(
+            "/search",
+            get(handle_search_all)
+                .route_layer(middleware::from_fn_with_state(
+                    first.clone(),
+                    timeout_requests,
+                ))
+                .route_layer(middleware::from_fn_with_state(first.clone(), rate_limit)),
+        );
+
+        for show in shows.iter() {
+            let show_router =
+                static_files.fallback(show_routes(show.clone(), &static_files), &show.mount_path);
+
+            routes = routes.nest(&format!("/{}", show.show.slug), show_router);
+        }
+    }
+
+    // at most server.max_concurrent_requests requests are handled at once, the ones
+    // over the cap get a 503 right away instead of queueing behind a burst,
+    // the health checks, metrics and admin pages stay reachable under load
+    let overloaded_metrics = metrics.clone();
+    let routes = routes
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(move |err: BoxError| {
+                    let metrics = overloaded_metrics.clone();
+                    async move { overloaded(&metrics, err) }
+                }))
+                .load_shed()
+                .layer(GlobalConcurrencyLimitLayer::with_semaphore(request_permits)),
+        )
+        .route("/metrics", get(handle_metrics))
+        .route("/healthz", get(handle_healthz))
+        .route("/readyz", get(handle_readyz))
+        .merge(
+            Router::new()
+                .route("/admin/queries", get(handle_admin_queries)) // admin/queries?days=7
+                .route("/api/admin/queries", get(handle_admin_queries_json))
+                .route("/api/admin/api-keys", get(handle_admin_api_keys_json))
+                .route(
+                    "/admin/index", // admin/index?show=lup
+                    put(handle_index_upload).layer(DefaultBodyLimit::max(INDEX_UPLOAD_MAX_BYTES)),
+                )
+                .route("/admin/reindex", post(handle_reindex)) // admin/reindex?show=lup
+                .route("/admin/backup", get(handle_backup))
+                .route("/admin/reload", post(handle_reload))
+                .route(
+                    "/admin/restore",
+                    put(handle_restore).layer(DefaultBodyLimit::max(RESTORE_MAX_BYTES)),
+                )
+                .route_layer(middleware::from_fn_with_state(shows.clone(), require_admin)),
+        );
+
+    let routes = static_files
+        .fallback(routes, &base_path)
+        .layer(middleware::from_fn_with_state(first, track_requests))
+        .with_state(shows);
+
+    let app = nest_under_base_path(&base_path, routes, mount_paths)
+        .layer(compression_layer())
+        // the compressed body depends on the Accept-Encoding of the request
+        .layer(SetResponseHeaderLayer::appending(
+            header::VARY,
+            HeaderValue::from_static("accept-encoding"),
+        ))
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::new(
+                    HeaderName::from_static(REQUEST_ID_HEADER),
+                    MakeRequestUuid,
+                ))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(make_request_span)
+                        .on_response(log_response),
+                )
+                .layer(CatchPanicLayer::custom(move |panic| {
+                    panic_page(&templates, &base_path, panic)
+                }))
+                .layer(PropagateRequestIdLayer::new(HeaderName::from_static(
+                    REQUEST_ID_HEADER,
+                ))),
+        );
+
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let listener = systemd::listener(addr);
+    let addr = listener.local_addr().unwrap();
+    // kept open while the requests in flight finish, for the re-exec on SIGUSR2
+    let socket = listener
+        .try_clone()
+        .expect("Error at duplicating the socket");
+
+    // SIGTERM and SIGINT stop the server, SIGUSR2 starts it again on the same socket
+    // (SIGHUP reloads the config instead)
+    let stop = stop_signal().boxed().shared();
+
+    if let Some((cert, key)) = tls_files {
+        let tls_config = load_tls_config(&cert, &key).await;
+
+        if reload_tls {
+            tokio::spawn(reload_certificates(tls_config.clone(), cert, key));
+        }
+
+        info!(
+            "Web server listening on {} (https://localhost:{})",
+            addr,
+            addr.port()
+        );
+
+        let handle = axum_server::Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            let stop = stop.clone();
+            async move {
+                stop.await;
+                handle.graceful_shutdown(None);
+            }
+        });
+
+        notify_ready();
+
+        axum_server::from_tcp_rustls(listener, tls_config)
+            .handle(handle)
+            .serve(make_service)
+            .await
+            .unwrap();
+    } else {
+        info!(
+            "Web server listening on {} (http://localhost:{})",
+            addr,
+            addr.port()
+        );
+
+        let server = Server::from_tcp(listener)
+            .unwrap()
+            .serve(make_service)
+            .with_graceful_shutdown(stop.clone().map(|_| ()));
+        notify_ready();
+        server.await.unwrap();
+    }
+
+    if stop.await == Stop::Reexec {
+        reexec(socket);
+    }
+
+    info!("Web server stopped");
 }
 
 #[derive(Deserialize)]
 pub struct SearchQuery {
     pub query: String,
+    /// render only the results fragment (search?query=foo&partial=true)
+    #[serde(default)]
+    pub partial: bool,
+    /// sections per show in a multi-show archive (search?query=foo&group_by=show)
+    #[serde(default)]
+    pub group_by: Option<GroupBy>,
+    /// slug of the show to search (search?query=foo&show=lup), the same as `show:lup`
+    #[serde(default)]
+    pub show: Option<String>,
+    /// version of the query language (search?query=foo&qv=1), the latest by default
+    #[serde(default)]
+    pub qv: QueryVersion,
+    /// page of the results (search?query=foo&page=2), the first by default
+    #[serde(default)]
+    pub page: Option<usize>,
+    /// results per page (search?query=foo&per_page=20), `DEFAULT_PER_PAGE` by default
+    #[serde(default)]
+    pub per_page: Option<usize>,
+    /// order of the results (search?query=foo&sort=newest), by relevance by default
+    #[serde(default)]
+    pub sort: SortOrder,
 }
 
-async fn handle_search(
-    search: Query<SearchQuery>,
-    State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let query = search.query.clone();
-    let mut results: HashSet<Episode> = HashSet::new();
+impl SearchQuery {
+    /// Moves the show parameter into the query as a `show:` term
+    pub fn with_show_term(mut self) -> Self {
+        if let Some(slug) = self.show.take().filter(|slug| !slug.is_empty()) {
+            self.query = format!("{} {}{}", self.query.trim_end(), SHOW_FIELD, slug);
+        }
 
-    let ParseResult { terms, exclude } = parse_query(&query);
+        self
+    }
 
-    let terms: HashSet<_> = terms
-        .iter()
-        .map(|s| s.to_lowercase())
-        .filter(|s| !state.common_words.contains(s))
-        .collect();
+    /// The shareable state of the search, without how it's rendered
+    pub fn params(&self) -> SearchParams {
+        SearchParams {
+            qv: self.qv,
+            sort: self.sort,
+            ..SearchParams::new(&self.query)
+        }
+    }
+}
 
-    let exclude: HashSet<_> = HashSet::from_iter(exclude.into_iter());
+/// The search query of html pages, or the query to fill the search box with
+/// and what's wrong with it
+pub fn checked_search_query(
+    search: Result<Query<SearchQuery>, QueryRejection>,
+) -> Result<SearchQuery, (String, QueryError)> {
+    let Ok(Query(search)) = search else {
+        return Err((String::new(), QueryError::Empty));
+    };
 
-    let episodes_by_tag: HashMap<String, Vec<&Episode>> = state
-        .episodes_by_tag
-        .iter()
-        .map(|(tag, ids)| (tag, get_episodes_from_ids(ids, &state.episodes_by_id)))
-        .fold(HashMap::new(), |mut acc, (tag, episodes)| {
-            acc.insert(tag.to_string(), episodes);
-            acc
-        });
+    let search = search.with_show_term();
 
-    for (tag, episodes) in episodes_by_tag.iter() {
-        if terms
-            .iter()
-            .any(|term| tag.contains(term) || term.contains(tag))
-        {
-            results.extend(episodes.iter().map(|episode| (**episode).clone()));
-        }
+    match parse_query_strict(&search.query) {
+        Ok(_) => Ok(search),
+        Err(err) => Err((search.query, err)),
     }
+}
 
-    for (id, episode) in state.episodes_by_id.iter() {
-        // skip episode already seen
-        if results.contains(episode) {
-            continue;
-        }
+/// The searched and excluded terms of the query, shown by the results page
+pub fn search_filters(query: &str) -> serde_json::Value {
+    let ParseResult { terms, exclude } = query_terms(query);
+    serde_json::json!({ "terms": terms, "exclude": exclude })
+}
+
+/// An episode of the results page with its chapters and the sentences of its transcript
+/// matching the query
+#[derive(Serialize)]
+pub struct ResultEpisode<'a> {
+    #[serde(flatten)]
+    pub episode: &'a Episode,
+    /// the terms it matched, its title highlighted and its matching tags
+    pub matches: Matches,
+    pub matched_chapters: Vec<Moment>,
+    pub transcript_hits: Vec<Moment>,
+}
 
-        // if any of the search terms matches a word in the title
-        let episode_id = id.to_string();
+#[derive(Serialize)]
+/// A chapter title or a sentence of the transcript, linking to when it starts
+pub struct Moment {
+    pub text: String,
+    pub start_secs: u64,
+    /// "00:12:34"
+    pub start: String,
+}
 
-        if terms.contains(&episode_id)
-            || terms
-                .iter()
-                .any(|term| episode.title.to_lowercase().contains(term))
-        {
-            results.insert(episode.clone());
+impl Moment {
+    fn new(text: &str, start_secs: u64) -> Self {
+        Self {
+            text: text.to_string(),
+            start_secs,
+            start: format_duration(start_secs),
         }
     }
+}
 
-    // filtering the results
+impl<'a> ResultEpisode<'a> {
+    /// `terms` are the lowercase terms of the query, `highlights` the ones of `highlight_terms`
+    pub fn new(
+        episode: &'a Episode,
+        terms: &[String],
+        highlights: &[String],
+        transcripts: &Transcripts,
+    ) -> Self {
+        let matched_chapters = episode
+            .matched_chapters(terms)
+            .into_iter()
+            .map(|chapter| Moment::new(&chapter.title, chapter.start_secs))
+            .collect();
 
-    if !exclude.is_empty() {
-        results = results
+        let transcript_hits = usize::try_from(episode.id)
+            .ok()
+            .and_then(|id| transcripts.get(&id))
+            .map(|segments| transcript_hits(segments, terms, TRANSCRIPT_HITS))
+            .unwrap_or_default()
             .into_iter()
-            .filter(|episode| {
-                !episode
-                    .tags
-                    .iter()
-                    .any(|tag| exclude.iter().any(|excl_token| tag.contains(excl_token)))
-            })
+            .map(|hit| Moment::new(&hit.text, hit.start_secs))
             .collect();
-    }
 
-    // sorting results
+        Self {
+            episode,
+            matches: Matches::new(episode, highlights),
+            matched_chapters,
+            transcript_hits,
+        }
+    }
+}
 
-    let mut results_with_score: Vec<_> = results
+/// The lowercase terms of the query the chapters are matched with
+pub fn chapter_terms(query: &str) -> Vec<String> {
+    query_terms(query)
+        .terms
         .iter()
-        .map(|episode| {
-            let mut score = episode.tags.iter().fold(0, |acc, tag| {
-                // scores for tag
-                acc + if terms.contains(tag) || terms.iter().any(|term| tag.contains(term)) {
-                    50
-                } else {
-                    0
-                }
-            });
-
-            // scores for title
-            score += terms.iter().fold(0, |acc, term| {
-                acc + if episode.title.to_lowercase().contains(term) {
-                    100
-                } else {
-                    0
-                }
-            });
+        .map(|term| term.to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
 
-            (score, episode)
-        })
-        .collect();
+/// Runs the search pipeline, returns the matching episodes of `index` sorted by score,
+/// the caller keeps the index of the show (`state.index()`) while it renders them
+#[instrument(skip(state, index))]
+fn search_episodes<'a>(
+    state: &AppState,
+    index: &'a ShowIndex,
+    query: &str,
+    version: QueryVersion,
+) -> Vec<(i32, &'a Episode)> {
+    let start = Instant::now();
 
-    results_with_score.sort_by(|(a_score, _), (b_score, _)| b_score.cmp(a_score));
+    // out of time the search goes on with the episodes found so far
+    let SearchResults {
+        terms,
+        exclude,
+        corrections,
+        episodes: results_with_score,
+    } = search_index_version(
+        index,
+        query,
+        version,
+        &state.common_words(),
+        &state.config().scoring,
+        search_budget_exceeded,
+    );
 
     debug!(
-        "Query: {}, Search terms: {:?}, Exclude: {:?}",
-        &search.query, &terms, &exclude
+        "Query: {}, Search terms: {:?}, Exclude: {:?}, Corrections: {:?}",
+        query, &terms, &exclude, &corrections
     );
 
     debug!("score  | title");
@@ -177,21 +955,1516 @@ async fn handle_search(
     }
     debug!("{}", "-------".repeat(3));
 
-    let search_results: Vec<_> = results_with_score.iter().map(|(_, ep)| *ep).collect();
+    if search_was_cut_short() {
+        warn!(
+            query,
+            results = results_with_score.len(),
+            "search ran out of time, returning partial results"
+        );
+    } else {
+        info!(
+            query,
+            results = results_with_score.len(),
+            "search completed"
+        );
+    }
+
+    state
+        .metrics
+        .observe_search(start.elapsed(), results_with_score.len());
+
+    results_with_score
+}
+
+// counts the search for the telemetry and appends it to analytics.queries_log in the background,
+// only the search page and /api/search call it: the alerts, feeds, bots and live search
+// aren't people searching
+fn record_query(state: &AppState, query: &str, results: usize, took: Duration, cached: bool) {
+    if let Some(telemetry) = &state.telemetry {
+        telemetry.record(took, results);
+    }
+
+    let Some(query_log) = state.query_log.clone() else {
+        return;
+    };
+
+    let event = QueryEvent {
+        timestamp: Utc::now().timestamp(),
+        show: state.show.slug.clone(),
+        query: logged_query(query),
+        results,
+        zero_results: results == 0,
+        // microsecond precision is plenty
+        took_ms: (took.as_secs_f64() * 1_000_000.0).round() / 1000.0,
+        cached,
+    };
+
+    tokio::spawn(async move {
+        if let Err(err) = query_log.append(&event).await {
+            error!("Error at writing analytics.queries_log: {}", err);
+        }
+    });
+}
+
+async fn handle_search(
+    search: Result<Query<SearchQuery>, QueryRejection>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let search = match checked_search_query(search) {
+        Ok(search) => search,
+        Err((query, err)) => {
+            return bad_query_page(&state.templates, &state.mount_path, lang, &query, &err)
+        }
+    };
+
+    // htmx requests only need the results fragment
+    let fragment = search.partial || headers.contains_key("HX-Request");
+    let key = PageKey::new(&search.query, search.qv, fragment, lang)
+        .with_page(search.page, search.per_page)
+        .with_sort(search.sort);
+
+    let start = Instant::now();
+
+    let page = match state.page_cache.get(&key) {
+        Some(page) => {
+            state.metrics.page_cache_hits.inc();
+            record_query(&state, &key.query, page.results, start.elapsed(), true);
+            page
+        }
+        None => {
+            state.metrics.page_cache_misses.inc();
+
+            let page = match render_results(&state, &key) {
+                Ok(page) => {
+                    record_query(&state, &key.query, page.results, start.elapsed(), false);
+                    page
+                }
+                Err(err) => {
+                    return internal_error_page(
+                        &state.templates,
+                        &state.mount_path,
+                        lang,
+                        Some(&search.query),
+                        &err,
+                    )
+                }
+            };
+
+            // partial results would stay around after the load passes
+            if !page.partial {
+                state.page_cache.insert(key, page.clone());
+            }
+
+            page
+        }
+    };
+
+    // the same url renders a full page or a fragment, in english or spanish,
+    // depending on the headers
+    (
+        [(header::VARY, "HX-Request, Accept-Language")],
+        Html(page.body.as_ref().to_string()),
+    )
+        .into_response()
+}
+
+// s?query=NixOS++Fedora, redirects to the canonical url of the search,
+// the one the copy link button of the results page shares
+async fn handle_permalink(
+    Query(params): Query<SearchParams>,
+    State(state): State<Arc<AppState>>,
+) -> Redirect {
+    Redirect::permanent(&search_permalink(&state.mount_path, &params))
+}
+
+// pages are rendered from the normalized query so cached pages
+// look the same no matter which spelling of the query filled the cache
+fn render_results(state: &AppState, key: &PageKey) -> Result<CachedPage, tera::Error> {
+    let start = Instant::now();
+    let index = state.index();
+    let mut results_with_score = search_episodes(state, &index, &key.query, key.version);
+    sort_results(&index, &mut results_with_score, key.sort);
+    let took_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let partial = search_was_cut_short();
+
+    let total_results = results_with_score.len();
+    let pagination = Pagination::new(key.page, key.per_page, total_results);
+
+    let terms = chapter_terms(&key.query);
+    let highlights = highlight_terms(&query_terms(&key.query), &state.common_words());
+    let search_results: Vec<_> = results_with_score[pagination.range()]
+        .iter()
+        .map(|(_, ep)| ResultEpisode::new(ep, &terms, &highlights, &index.transcripts))
+        .collect();
 
     // reply with a tera template
 
-    let query = &(search.query);
-    let episodes = search_results;
+    let query = &key.query;
+    let params = SearchParams {
+        qv: key.version,
+        sort: key.sort,
+        ..SearchParams::new(query)
+    };
+    let listening_seconds = listening_time(results_with_score.iter().map(|(_, ep)| *ep));
+    let (grouped, groups) = year_groups(search_results, key.sort, |result| &result.episode.date);
+    let groups = after_page_offset(groups, &pagination);
 
-    let html = state
-        .tera
-        .render(
-            "results.html",
-            &Context::from_serialize(&serde_json::json!({ "episodes": episodes, "query": query }))
-                .unwrap(),
-        )
-        .unwrap();
+    let template = if key.fragment {
+        "partials/results.html"
+    } else {
+        "results.html"
+    };
+
+    let html = state.templates.render(
+        template,
+        &Context::from_serialize(serde_json::json!({
+            "groups": groups,
+            "grouped": grouped,
+            "query": query,
+            "total_results": total_results,
+            "pagination": pagination,
+            "sort": key.sort,
+            "sort_orders": SortOrder::ALL,
+            "listening_seconds": approximate_listening_time(listening_seconds),
+            "took_ms": took_ms,
+            "partial": partial,
+            "filters": search_filters(query),
+            "search_params": params.to_query_string(),
+            "permalink": search_permalink(&state.mount_path, &params),
+            "export": true,
+            "track_clicks": state.click_log.is_some(),
+            "feedback": state.feedback_log.is_some(),
+            "alerts": state.saved_searches.is_some(),
+            "app_links": podcast_app_links(&state.show.podcast),
+            "digests": state.saved_searches.is_some() && state.config().digests.is_enabled(),
+            "favorites_cookie": favorites_cookie(state),
+            "stale_days": stale_days(state, &index),
+            "base_path": state.mount_path,
+            "lang": key.lang,
+        }))?,
+    )?;
+
+    Ok(CachedPage {
+        body: html.into(),
+        results: total_results,
+        partial,
+    })
+}
+
+/// The sections of a page of the results, ranked after the results of the previous pages
+pub fn after_page_offset<T>(
+    mut groups: Vec<ResultGroup<T>>,
+    pagination: &Pagination,
+) -> Vec<ResultGroup<T>> {
+    for group in groups.iter_mut() {
+        group.offset += pagination.offset;
+    }
+
+    groups
+}
+
+/// The sections of the results page, one per year for broad queries ranked by relevance
+/// and a single one without heading otherwise, the dates already order the other sorts
+pub fn year_groups<T, F>(episodes: Vec<T>, sort: SortOrder, date: F) -> (bool, Vec<ResultGroup<T>>)
+where
+    F: Fn(&T) -> &str,
+{
+    if sort == SortOrder::Relevance && episodes.len() >= YEAR_GROUPS_MIN_RESULTS {
+        (true, group_by_year(episodes, date))
+    } else {
+        (false, ResultGroup::ungrouped(episodes))
+    }
+}
+
+/// Reorders the results of a search ranked by relevance
+pub fn sort_results(index: &ShowIndex, results: &mut [(i32, &Episode)], sort: SortOrder) {
+    sort.sort(results, |(_, episode)| {
+        index.search_fields.get(&(episode.id as usize))
+    });
+}
+
+// search/stream?query=foo
+// the client reopens the event source whenever the query is refined,
+// which drops the previous stream before it reaches the search.
+// the keystrokes aren't recorded, the query is when it's submitted to the search page.
+// the stream ends after the results, the client closes the event source on them
+// so the browser doesn't reconnect and search again
+async fn handle_search_stream(
+    Query(search): Query<SearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let search = search.with_show_term();
+    let results = stream::once(async move {
+        tokio::time::sleep(SEARCH_STREAM_DEBOUNCE).await;
+
+        // the same query typed again is served from the cache of the results pages
+        let key = PageKey::new(&search.query, search.qv, false, "").with_json();
+
+        let results = match state.page_cache.get(&key) {
+            Some(page) => {
+                state.metrics.page_cache_hits.inc();
+                page.body
+            }
+            None => {
+                state.metrics.page_cache_misses.inc();
+
+                let page = stream_results(&state, &key);
+
+                if !page.partial {
+                    state.page_cache.insert(key, page.clone());
+                }
+
+                page.body
+            }
+        };
+
+        let query = serde_json::to_string(&search.query).expect("Error at serializing the query");
+        let event = Event::default()
+            .event("results")
+            .data(format!(r#"{{"query":{},"results":{}}}"#, query, results));
+
+        Ok(event)
+    });
+
+    Sse::new(results).keep_alive(KeepAlive::default())
+}
+
+// the json of the results of /search/stream
+fn stream_results(state: &AppState, key: &PageKey) -> CachedPage {
+    let index = state.index();
+    let results_with_score = search_episodes(state, &index, &key.query, key.version);
+    let partial = search_was_cut_short();
+
+    let results: Vec<_> = results_with_score
+        .iter()
+        .map(|(score, episode)| serde_json::json!({ "score": score, "episode": episode }))
+        .collect();
+    let body = serde_json::to_string(&results).expect("Error at serializing search results");
+
+    CachedPage {
+        body: body.into(),
+        results: results.len(),
+        partial,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum WsMessage {
+    /// a new or refined query
+    Query(String),
+    /// restricts the results to a tag, `null` clears it
+    Facet(Option<String>),
+}
+
+/// Per connection state of an interactive search session
+#[derive(Default)]
+struct WsSession {
+    query: String,
+    facet: Option<String>,
+    /// results of the last query and how long it took, what the session records when it ends
+    results: usize,
+    took: Duration,
+}
+
+async fn handle_ws(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
+    ws.on_upgrade(|socket| ws_session(socket, state))
+}
+
+// clients send `{"query": "nixos"}` or `{"facet": "docker"}`, plain text frames are queries.
+// every keystroke is searched, only the last query is recorded, once the session ends
+async fn ws_session(mut socket: WebSocket, state: Arc<AppState>) {
+    let mut session = WsSession::default();
+
+    'session: while let Some(Ok(message)) = socket.recv().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+
+        match serde_json::from_str::<WsMessage>(&text) {
+            Ok(WsMessage::Query(query)) => session.query = query,
+            Ok(WsMessage::Facet(facet)) => session.facet = facet,
+            Err(_) => session.query = text,
+        }
+
+        for reply in ws_replies(&state, &mut session) {
+            if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                break 'session;
+            }
+        }
+    }
+
+    if !session.query.trim().is_empty() {
+        record_query(&state, &session.query, session.results, session.took, false);
+    }
+}
+
+// results in batches of `WS_BATCH_SIZE`, then suggestions for the last term
+fn ws_replies(state: &AppState, session: &mut WsSession) -> Vec<serde_json::Value> {
+    let start = Instant::now();
+    let index = state.index();
+    let mut results_with_score =
+        search_episodes(state, &index, &session.query, QueryVersion::LATEST);
+    session.results = results_with_score.len();
+    session.took = start.elapsed();
+
+    // a facet matches its tag whatever the case, like a `tag:` term
+    if let Some(facet) = &session.facet {
+        let facet = facet.to_lowercase();
+        results_with_score.retain(|(_, episode)| {
+            episode
+                .tag_names()
+                .iter()
+                .any(|tag| tag.to_lowercase() == facet)
+        });
+    }
+
+    let mut replies: Vec<_> = results_with_score
+        .chunks(WS_BATCH_SIZE)
+        .enumerate()
+        .map(|(batch, chunk)| {
+            let results: Vec<_> = chunk
+                .iter()
+                .map(|(score, episode)| serde_json::json!({ "score": score, "episode": episode }))
+                .collect();
+
+            serde_json::json!({ "type": "results", "batch": batch, "results": results })
+        })
+        .collect();
+
+    let last_term = session.query.split_whitespace().last().unwrap_or_default();
+
+    replies.push(serde_json::json!({
+        "type": "done",
+        "query": session.query,
+        "facet": session.facet,
+        "total": results_with_score.len(),
+        "suggestions": index.autocomplete.suggest(last_term, SUGGESTIONS_LIMIT),
+    }));
+
+    replies
+}
+
+// api/alfred?query=foo
+async fn handle_alfred(
+    Query(search): Query<SearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let search = search.with_show_term();
+    let index = state.index();
+    let results_with_score = search_episodes(&state, &index, &search.query, search.qv);
+    let episodes: Vec<_> = results_with_score.iter().map(|(_, ep)| *ep).collect();
+
+    Json(alfred_items(&episodes))
+}
+
+// api/search?query=foo, the results of the search page as json
+async fn handle_search_api(
+    search: Result<Query<SearchQuery>, QueryRejection>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let search = match checked_search_query(search) {
+        Ok(search) => search,
+        Err((_, err)) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    };
+
+    let start = Instant::now();
+    let index = state.index();
+    let mut results_with_score = search_episodes(&state, &index, &search.query, search.qv);
+    record_query(
+        &state,
+        &search.query,
+        results_with_score.len(),
+        start.elapsed(),
+        false,
+    );
+    sort_results(&index, &mut results_with_score, search.sort);
+    let pagination = Pagination::new(search.page, search.per_page, results_with_score.len());
+    let highlights = highlight_terms(&query_terms(&search.query), &state.common_words());
+    let episodes: Vec<_> = results_with_score[pagination.range()]
+        .iter()
+        .map(|(score, episode)| {
+            serde_json::json!({
+                "score": score,
+                "episode": episode,
+                "matches": Matches::new(episode, &highlights),
+            })
+        })
+        .collect();
+
+    let mut body = search_filters(&search.query);
+    body["query"] = serde_json::json!(search.query);
+    body["total"] = serde_json::json!(pagination.total);
+    body["page"] = serde_json::json!(pagination.page);
+    body["per_page"] = serde_json::json!(pagination.per_page);
+    body["pages"] = serde_json::json!(pagination.pages);
+    body["sort"] = serde_json::json!(search.sort);
+    body["episodes"] = serde_json::json!(episodes);
+
+    Json(body).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct BatchSearch {
+    pub queries: Vec<String>,
+}
+
+// api/search/batch, body: {"queries": ["nixos", "fedora -gnome"]}
+async fn handle_search_batch(
+    State(state): State<Arc<AppState>>,
+    Json(batch): Json<BatchSearch>,
+) -> Response {
+    if batch.queries.len() > BATCH_QUERIES_LIMIT {
+        let message = format!("a batch accepts up to {} queries", BATCH_QUERIES_LIMIT);
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    // every query of the batch searches the same index
+    let index = state.index();
+    let results: Vec<_> = batch
+        .queries
+        .iter()
+        .map(|query| {
+            let results_with_score = search_episodes(&state, &index, query, QueryVersion::LATEST);
+            let episodes: Vec<_> = results_with_score
+                .iter()
+                .map(|(score, episode)| serde_json::json!({ "score": score, "episode": episode }))
+                .collect();
+
+            serde_json::json!({
+                "query": query,
+                "total": episodes.len(),
+                "listening_seconds": listening_time(results_with_score.iter().map(|(_, episode)| *episode)),
+                "episodes": episodes,
+            })
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "results": results,
+        "app_links": podcast_app_links(&state.show.podcast),
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct TimelineQuery {
+    /// counts every episode when empty
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub by: TimelinePeriod,
+}
+
+// api/stats/timeline?query=btrfs&by=month, how often a topic comes up over time
+async fn handle_timeline(
+    Query(timeline): Query<TimelineQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let result = if timeline.query.trim().is_empty() {
+        episode_timeline(state.index().episodes_by_id.values(), timeline.by)
+    } else {
+        let index = state.index();
+        let results_with_score =
+            search_episodes(&state, &index, &timeline.query, QueryVersion::LATEST);
+        episode_timeline(results_with_score.iter().map(|(_, ep)| *ep), timeline.by)
+    };
+
+    Json(serde_json::json!({
+        "query": timeline.query,
+        "total": result.buckets.iter().map(|bucket| bucket.episodes).sum::<usize>() + result.undated,
+        "timeline": result.buckets,
+        "undated": result.undated,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct DurationsQuery {
+    /// size of the buckets
+    #[serde(default = "default_bucket_minutes")]
+    pub minutes: u64,
+}
+
+fn default_bucket_minutes() -> u64 {
+    DURATION_BUCKET_MINUTES
+}
+
+// api/stats/durations?minutes=15, episodes per length, the durations
+// the parser rejected are listed under "unparsed"
+async fn handle_durations(
+    Query(durations): Query<DurationsQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    if durations.minutes == 0 {
+        return (StatusCode::BAD_REQUEST, "minutes must be at least 1").into_response();
+    }
+
+    let histogram = duration_histogram(state.index().episodes_by_id.values(), durations.minutes);
+
+    Json(serde_json::json!({
+        "minutes": durations.minutes,
+        "histogram": histogram.buckets,
+        "unparsed": histogram.unparsed,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct TrendingQuery {
+    /// newest episodes compared with the older ones
+    #[serde(default = "default_trending_recent")]
+    pub recent: usize,
+    #[serde(default = "default_trending_limit")]
+    pub limit: usize,
+}
+
+fn default_trending_recent() -> usize {
+    TRENDING_RECENT_EPISODES
+}
+
+fn default_trending_limit() -> usize {
+    TRENDING_LIMIT
+}
+
+// api/stats/trending?recent=20, the tags of the newest episodes more frequent than before
+async fn handle_trending(
+    Query(trending): Query<TrendingQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    if trending.recent == 0 {
+        return (StatusCode::BAD_REQUEST, "recent must be at least 1").into_response();
+    }
+
+    let mut tags = trending_tags(
+        state.index().episodes_by_id.values(),
+        trending.recent,
+        TRENDING_MIN_EPISODES,
+    );
+    tags.truncate(trending.limit);
+
+    Json(serde_json::json!({
+        "recent": trending.recent,
+        "tags": tags,
+    }))
+    .into_response()
+}
+
+async fn open_event_log(
+    key: &str,
+    path: &str,
+    rotation: Option<Rotation>,
+) -> Option<Arc<EventLog>> {
+    if path.is_empty() {
+        return None;
+    }
+
+    let log = EventLog::open_rotating(std::path::Path::new(path), rotation)
+        .await
+        .unwrap_or_else(|err| panic!("Error at opening {} {}: {}", key, path, err));
+
+    Some(Arc::new(log))
+}
+
+#[derive(Deserialize)]
+pub struct Click {
+    pub episode_id: usize,
+    /// 1-based position of the episode in the results
+    pub rank: usize,
+    #[serde(default)]
+    pub query: String,
+}
+
+// track/click, body: {"episode_id": 512, "rank": 1, "query": "nixos"}
+// sent by the results page when a result is opened
+async fn handle_track_click(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<Arc<AppState>>,
+    Json(click): Json<Click>,
+) -> Response {
+    let Some(click_log) = &state.click_log else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    if click.rank == 0 || !state.index().episodes_by_id.contains_key(&click.episode_id) {
+        return (StatusCode::BAD_REQUEST, "unknown episode or rank").into_response();
+    }
+
+    let event = ClickEvent {
+        timestamp: Utc::now().timestamp(),
+        show: state.show.slug.clone(),
+        query: logged_query(&click.query),
+        episode_id: click.episode_id,
+        rank: click.rank,
+        ip: state
+            .config()
+            .analytics
+            .store_ip
+            .then(|| addr.ip().to_string()),
+    };
+
+    if let Err(err) = click_log.append(&event).await {
+        error!("Error at writing analytics.clicks_log: {}", err);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[derive(Deserialize)]
+pub struct Feedback {
+    pub episode_id: usize,
+    pub query: String,
+    pub relevant: bool,
+}
+
+// feedback, body: {"episode_id": 512, "query": "nixos", "relevant": false}
+// human judgments to evaluate ranking changes against
+async fn handle_feedback(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<Arc<AppState>>,
+    Json(feedback): Json<Feedback>,
+) -> Response {
+    let Some(feedback_log) = &state.feedback_log else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let query = logged_query(&feedback.query);
+
+    if query.is_empty()
+        || !state
+            .index()
+            .episodes_by_id
+            .contains_key(&feedback.episode_id)
+    {
+        return (StatusCode::BAD_REQUEST, "unknown episode or empty query").into_response();
+    }
+
+    let event = FeedbackEvent {
+        timestamp: Utc::now().timestamp(),
+        show: state.show.slug.clone(),
+        query,
+        episode_id: feedback.episode_id,
+        relevant: feedback.relevant,
+        ip: state
+            .config()
+            .analytics
+            .store_ip
+            .then(|| addr.ip().to_string()),
+    };
+
+    if let Err(err) = feedback_log.append(&event).await {
+        error!("Error at writing analytics.feedback_log: {}", err);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+// normalized query of the analytics logs, longer queries are cut
+fn logged_query(query: &str) -> String {
+    normalize_query(query)
+        .chars()
+        .take(LOGGED_QUERY_MAX_CHARS)
+        .collect()
+}
+
+// a random secret forgets the favorites on restarts, better than a known default one
+fn favorites_signer(secret: &str) -> FavoritesSigner {
+    if !secret.is_empty() {
+        return FavoritesSigner::new(secret.as_bytes());
+    }
+
+    info!("favorites.secret is empty, favorites are signed with a random key until restart");
+
+    let random = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    FavoritesSigner::new(random.as_bytes())
+}
+
+fn load_discord_verifier(public_key: &str) -> Option<DiscordVerifier> {
+    if public_key.is_empty() {
+        return None;
+    }
+
+    let verifier = DiscordVerifier::new(public_key);
+
+    if verifier.is_none() {
+        warn!("discord.public_key is not a valid ed25519 public key, discord interactions are disabled");
+    }
+
+    verifier
+}
+
+// discord/interactions, the interactions endpoint url of the Discord application
+async fn handle_discord_interaction(
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> Response {
+    let Some(verifier) = &state.discord else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+
+    let signature = header_value("X-Signature-Ed25519");
+    let timestamp = header_value("X-Signature-Timestamp");
+
+    if !verifier.verify(signature, timestamp, &body) {
+        return (StatusCode::UNAUTHORIZED, "invalid request signature").into_response();
+    }
+
+    let Ok(interaction) = serde_json::from_slice::<Interaction>(&body) else {
+        return (StatusCode::BAD_REQUEST, "invalid interaction").into_response();
+    };
+
+    match interaction.request() {
+        InteractionRequest::Ping => Json(discord_pong_response()).into_response(),
+        InteractionRequest::Search(query) => {
+            let index = state.index();
+            let results_with_score = search_episodes(&state, &index, &query, QueryVersion::LATEST);
+            let episodes: Vec<_> = results_with_score
+                .iter()
+                .take(DISCORD_RESULTS_LIMIT)
+                .map(|(_, ep)| *ep)
+                .collect();
+
+            Json(discord_search_response(&query, &episodes)).into_response()
+        }
+        InteractionRequest::Unsupported => {
+            (StatusCode::BAD_REQUEST, "unsupported interaction").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    pub q: String,
+}
+
+// suggest?q=nix, replies in the OpenSearch suggestions format: ["nix", ["nixos", "nixpkgs"]]
+async fn handle_suggest(
+    suggest: Query<SuggestQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let index = state.index();
+    let suggestions = index.autocomplete.suggest(&suggest.q, SUGGESTIONS_LIMIT);
+
+    (
+        [(header::CONTENT_TYPE, "application/x-suggestions+json")],
+        serde_json::json!([suggest.q, suggestions]).to_string(),
+    )
+}
+
+// search.atom?query=foo
+async fn handle_search_feed(
+    Query(search): Query<SearchQuery>,
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let search = search.with_show_term();
+    let index = state.index();
+    let mut results_with_score = search_episodes(&state, &index, &search.query, search.qv);
+    sort_results(&index, &mut results_with_score, search.sort);
+    let episodes: Vec<_> = results_with_score.iter().map(|(_, ep)| *ep).collect();
+
+    let feed_url = format!(
+        "{}/search.atom?{}",
+        base_url(&state, host),
+        search.params().to_query_string()
+    );
+
+    let feed = atom_feed(
+        &format!("Unplugged search: {}", search.query),
+        &feed_url,
+        &episodes,
+    );
+
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        feed,
+    )
+}
+
+// search.json?query=foo, the same results as a JSON Feed
+async fn handle_search_json_feed(
+    Query(search): Query<SearchQuery>,
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let search = search.with_show_term();
+    let index = state.index();
+    let mut results_with_score = search_episodes(&state, &index, &search.query, search.qv);
+    sort_results(&index, &mut results_with_score, search.sort);
+    let episodes: Vec<_> = results_with_score.iter().map(|(_, ep)| *ep).collect();
+
+    let base_url = base_url(&state, host);
+    let query_string = search.params().to_query_string();
+
+    let feed = json_feed(
+        &format!("Unplugged search: {}", search.query),
+        &format!("{}/search?{}", base_url, query_string),
+        &format!("{}/search.json?{}", base_url, query_string),
+        &episodes,
+    );
+
+    (
+        [(header::CONTENT_TYPE, "application/feed+json")],
+        feed.to_string(),
+    )
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    query: String,
+    format: ExportFormat,
+    #[serde(default)]
+    qv: QueryVersion,
+}
+
+// search/export?format=csv&query=foo, every result as csv or a markdown table
+async fn handle_search_export(
+    Query(export): Query<ExportQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    if let Err(err) = parse_query_strict(&export.query) {
+        return (StatusCode::BAD_REQUEST, err.to_string()).into_response();
+    }
+
+    let format = export.format;
+    let index = state.index();
+    let rows: Vec<_> = search_episodes(&state, &index, &export.query, export.qv)
+        .iter()
+        .map(|(_, episode)| format.row(episode))
+        .collect();
+
+    let lines = stream::once(async move { format.header() })
+        .chain(stream::iter(rows))
+        .map(Ok::<_, Infallible>);
+
+    let filename = format!("unplugged-search.{}", format.extension());
+
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        StreamBody::new(lines),
+    )
+        .into_response()
+}
+
+async fn handle_opensearch(
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    (
+        [(
+            header::CONTENT_TYPE,
+            "application/opensearchdescription+xml; charset=utf-8",
+        )],
+        opensearch_description(&base_url(&state, host)),
+    )
+}
+
+async fn handle_sitemap(
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        state.index().sitemap.to_xml(&base_url(&state, host)),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct EpisodePage {
+    /// seconds into the episode the player starts at (episode/512?t=754)
+    t: Option<u64>,
+}
+
+// episode/490-the-arch-disaster, bare ids (episode/490) and the slugs of renamed episodes
+// redirect to the current slug
+async fn handle_episode(
+    Path(slug): Path<String>,
+    Query(page): Query<EpisodePage>,
+    headers: HeaderMap,
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let index = state.index();
+    let episode = index
+        .slugs
+        .id(&slug)
+        .and_then(|id| index.episodes_by_id.get(&id));
+
+    let Some(episode) = episode else {
+        let current = slug
+            .split('-')
+            .next()
+            .and_then(|id| id.parse::<usize>().ok())
+            .and_then(|id| index.slugs.slug(id));
+
+        if let Some(current) = current {
+            let mut path = format!("{}/episode/{}", state.mount_path, current);
+
+            if let Some(start) = page.t {
+                path.push_str(&format!("?t={}", start));
+            }
+
+            return Redirect::permanent(&path).into_response();
+        }
+
+        return error_page(
+            &state.templates,
+            &state.mount_path,
+            lang,
+            StatusCode::NOT_FOUND,
+            None,
+            None,
+        );
+    };
+
+    let mut context = episode_page_context(&index, episode, &slug);
+    context["start"] = page.t.into();
+    context["base_url"] = base_url(&state, host).into();
+    context["favorites_cookie"] = favorites_cookie(&state).into();
+    context["playback"] = state.playback.is_some().into();
+    context["stale_days"] = stale_days(&state, &index).into();
+    context["base_path"] = state.mount_path.clone().into();
+    context["lang"] = lang.into();
+
+    match Context::from_serialize(context)
+        .and_then(|context| state.templates.render("episode.html", &context))
+    {
+        Ok(html) => ([(header::VARY, "Accept-Language")], Html(html)).into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+// the id of an episode by its slug or its number, `490-the-arch-disaster` or `490`,
+// none when the index doesn't have it
+fn episode_id(index: &ShowIndex, slug: &str) -> Option<usize> {
+    let id = index.slugs.id(slug).or_else(|| {
+        slug.split('-')
+            .next()
+            .and_then(|id| id.parse::<usize>().ok())
+    })?;
+
+    index.episodes_by_id.contains_key(&id).then_some(id)
+}
+
+// episode/<id>/chapters, the chapters of the episode and when they start
+async fn handle_episode_chapters(
+    Path(slug): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let index = state.index();
+
+    let Some(id) = episode_id(&index, &slug) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    Json(serde_json::json!({
+        "id": id,
+        "chapters": index.episodes_by_id[&id].chapters,
+    }))
+    .into_response()
+}
+
+// episode/<id>/transcript, the segments of the transcript and when they're said,
+// a 404 for the episodes without one
+async fn handle_episode_transcript(
+    Path(slug): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let index = state.index();
+
+    let Some(id) = episode_id(&index, &slug) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let Some(segments) = index.transcripts.get(&id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    Json(serde_json::json!({
+        "id": id,
+        "segments": segments,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct RelatedQuery {
+    #[serde(default = "default_related_limit")]
+    pub limit: usize,
+}
+
+fn default_related_limit() -> usize {
+    RELATED_EPISODES
+}
+
+// related/<id>?limit=10, the episodes most like an episode by their tags and titles,
+// scored from the current index rather than stored by `index build`
+async fn handle_related(
+    Path(slug): Path<String>,
+    Query(related): Query<RelatedQuery>,
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let index = state.index();
+
+    let Some(id) = episode_id(&index, &slug) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let limit = related.limit.min(RELATED_LIMIT);
+    let ids =
+        related_to(&index.episodes_by_id, &index.episodes_by_tag, id, limit).unwrap_or_default();
+    let base_url = base_url(&state, host);
+
+    let episodes: Vec<_> = ids
+        .into_iter()
+        .filter_map(|id| {
+            let id = usize::try_from(id).ok()?;
+            let episode = index.episodes_by_id.get(&id)?;
+            let slug = index.slugs.slug(id)?;
+
+            Some(serde_json::json!({
+                "episode": episode,
+                "page": format!("{}/episode/{}", base_url, slug),
+            }))
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "id": id,
+        "episodes": episodes,
+    }))
+    .into_response()
+}
+
+/// The context of episode.html shared by the server and export-site: the episode,
+/// its related episodes and its tags by category
+pub fn episode_page_context(index: &ShowIndex, episode: &Episode, slug: &str) -> serde_json::Value {
+    // the indices built before the related episodes were stored get them scored now
+    let related_ids = if episode.related.is_empty() {
+        usize::try_from(episode.id)
+            .ok()
+            .and_then(|id| {
+                related_to(
+                    &index.episodes_by_id,
+                    &index.episodes_by_tag,
+                    id,
+                    RELATED_EPISODES,
+                )
+            })
+            .unwrap_or_default()
+    } else {
+        episode.related.clone()
+    };
+
+    // ids of episodes no longer in the index are left out
+    let related: Vec<_> = related_ids
+        .iter()
+        .filter_map(|id| {
+            let id = usize::try_from(*id).ok()?;
+            let related = index.episodes_by_id.get(&id)?;
+            let slug = index.slugs.slug(id)?;
+            Some(serde_json::json!({ "slug": slug, "title": related.title }))
+        })
+        .collect();
+
+    // by category, the tags without one last
+    let tag_groups: Vec<_> = TagCategory::ALL
+        .into_iter()
+        .map(Some)
+        .chain([None])
+        .filter_map(|category| {
+            let tags: Vec<_> = episode
+                .tags
+                .iter()
+                .filter(|tag| tag.category == category)
+                .map(|tag| tag.name.as_str())
+                .collect();
+            (!tags.is_empty()).then(|| serde_json::json!({ "category": category, "tags": tags }))
+        })
+        .collect();
+
+    serde_json::json!({
+        "episode": episode,
+        "slug": slug,
+        "related": related,
+        "tag_groups": tag_groups,
+        "json_ld": to_script_json(&episode_json_ld(episode)),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct OembedQuery {
+    url: String,
+    maxwidth: Option<u32>,
+    /// only "json" is served
+    format: Option<String>,
+}
+
+// oembed?url=<episode permalink>, the card forums and blogs embed when the link is pasted
+async fn handle_oembed(
+    Query(oembed): Query<OembedQuery>,
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    if oembed
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "json")
+    {
+        return StatusCode::NOT_IMPLEMENTED.into_response();
+    }
+
+    let index = state.index();
+    let episode = oembed_episode_slug(&oembed.url, &state.mount_path)
+        .and_then(|slug| slug.split('-').next())
+        .and_then(|id| id.parse::<usize>().ok())
+        .and_then(|id| Some((index.episodes_by_id.get(&id)?, index.slugs.slug(id)?)));
+
+    let Some((episode, slug)) = episode else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let base_url = base_url(&state, host);
+    let page_url = format!("{}/episode/{}", base_url, slug);
+
+    Json(episode_oembed(
+        episode,
+        &page_url,
+        &state.show.name,
+        &base_url,
+        &state.show.podcast,
+        oembed.maxwidth,
+    ))
+    .into_response()
+}
+
+// tag/nixos searches the tag, misspelled tags (tag/nix-os) redirect to the tag with
+// the same letters or list the closest ones, tag/nixos.json is the JSON Feed of the tag
+async fn handle_tag(
+    Path(tag): Path<String>,
+    headers: HeaderMap,
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let index = state.index();
+
+    // the episodes tagged with it, like the links of the tags page
+    if index.episodes_by_tag.contains_key(&tag) {
+        let query = encode_query_component(&format!("{}\"{}\"", TAG_FIELD, tag));
+        let path = format!("{}/search?query={}", state.mount_path, query);
+        return Redirect::permanent(&path).into_response();
+    }
+
+    if let Some(tag) = tag.strip_suffix(".json") {
+        if !index.episodes_by_tag.contains_key(tag) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+
+        let query = format!("{}\"{}\"", TAG_FIELD, tag);
+        let results_with_score = search_episodes(&state, &index, &query, QueryVersion::LATEST);
+        let episodes: Vec<_> = results_with_score.iter().map(|(_, ep)| *ep).collect();
+
+        let base_url = base_url(&state, host);
+        let tag_url = format!("{}/tag/{}", base_url, encode_query_component(tag));
+
+        let feed = json_feed(
+            &format!("Unplugged search: {}", tag),
+            &tag_url,
+            &format!("{}.json", tag_url),
+            &episodes,
+        );
+
+        return (
+            [(header::CONTENT_TYPE, "application/feed+json")],
+            feed.to_string(),
+        )
+            .into_response();
+    }
+
+    let suggestions = index.autocomplete.nearest(&tag, TAG_SUGGESTIONS_LIMIT);
+
+    match suggestions.first() {
+        Some(nearest) if tag_key(nearest) == tag_key(&tag) => {
+            let path = format!(
+                "{}/tag/{}",
+                state.mount_path,
+                encode_query_component(nearest)
+            );
+            Redirect::permanent(&path).into_response()
+        }
+        _ => {
+            let lang = state.templates.negotiate(&headers);
+            unknown_tag_page(
+                &state.templates,
+                &state.mount_path,
+                lang,
+                &tag,
+                &suggestions,
+            )
+        }
+    }
+}
+
+// the episodes released on today's month and day in the previous years, with the slug
+// of their page, today is the date of the server
+fn anniversaries(index: &ShowIndex) -> Vec<serde_json::Value> {
+    on_this_day(&index.episodes_by_id, Local::now().date_naive())
+        .into_iter()
+        .filter_map(|anniversary| {
+            let mut episode = serde_json::to_value(anniversary.episode).ok()?;
+            episode["years_ago"] = anniversary.years_ago.into();
+            episode["slug"] = index.slugs.slug(anniversary.episode.id as usize).into();
+            Some(episode)
+        })
+        .collect()
+}
+
+// on-this-day, the widget of the homepage, an html fragment that's empty
+// when no episode was released on this day
+async fn handle_on_this_day(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let context = Context::from_serialize(serde_json::json!({
+        "episodes": anniversaries(&state.index()),
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| {
+        state
+            .templates
+            .render("partials/on_this_day.html", &context)
+    }) {
+        Ok(html) => ([(header::VARY, "Accept-Language")], Html(html)).into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+// api/on-this-day, the same episodes for bots, with the url of their page
+async fn handle_on_this_day_json(
+    host: Option<Host>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let base_url = base_url(&state, host);
+    let mut episodes = anniversaries(&state.index());
+
+    for episode in episodes.iter_mut() {
+        if let Some(slug) = episode["slug"].as_str() {
+            episode["page"] = format!("{}/episode/{}", base_url, slug).into();
+        }
+    }
+
+    Json(serde_json::json!({
+        "date": Local::now().date_naive().format("%Y-%m-%d").to_string(),
+        "episodes": episodes,
+    }))
+}
+
+// the most searched queries of the show, counted from the queries log at most once per
+// POPULAR_QUERIES_TTL, concurrent requests wait for the same count
+async fn popular_queries_of(state: &AppState) -> Arc<Vec<String>> {
+    let analytics = &state.config().analytics;
+
+    if analytics.queries_log.is_empty() || analytics.popular_queries == 0 {
+        return Arc::default();
+    }
+
+    let mut cached = state.popular_queries.lock().await;
+
+    if let Some((counted_at, queries)) = cached.as_ref() {
+        if counted_at.elapsed() < POPULAR_QUERIES_TTL {
+            return queries.clone();
+        }
+    }
+
+    let events: Vec<QueryEvent> = read_rotated_event_log(
+        std::path::Path::new(&analytics.queries_log),
+        analytics.queries_log_keep,
+    )
+    .await;
+    let since = Utc::now().timestamp() - analytics.popular_days.max(0) * 24 * 60 * 60;
+
+    let queries = Arc::new(popular_queries(
+        &events,
+        since,
+        Some(&state.show.slug),
+        analytics.popular_min_searches,
+        analytics.popular_queries,
+    ));
+    *cached = Some((Instant::now(), queries.clone()));
+
+    queries
+}
+
+// popular, the suggestions of the homepage, an html fragment that's empty without any
+async fn handle_popular_queries(
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let context = Context::from_serialize(serde_json::json!({
+        "queries": *popular_queries_of(&state).await,
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| state.templates.render("partials/popular.html", &context)) {
+        Ok(html) => ([(header::VARY, "Accept-Language")], Html(html)).into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+// api/popular-queries
+async fn handle_popular_queries_json(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(serde_json::json!({ "queries": *popular_queries_of(&state).await }))
+}
+
+#[derive(Deserialize)]
+pub struct TagsQuery {
+    #[serde(default)]
+    pub sort: TagOrder,
+}
+
+// tags?sort=name, every tag with its number of episodes, linking its search
+async fn handle_tags(
+    Query(tags): Query<TagsQuery>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let context = Context::from_serialize(serde_json::json!({
+        "tags": tag_counts(&state.index().episodes_by_tag, tags.sort),
+        "sort": tags.sort,
+        "show": state.show.name,
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| state.templates.render("tags.html", &context)) {
+        Ok(html) => ([(header::VARY, "Accept-Language")], Html(html)).into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+// api/tags?sort=name
+async fn handle_tags_json(
+    Query(tags): Query<TagsQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "sort": tags.sort,
+        "tags": tag_counts(&state.index().episodes_by_tag, tags.sort),
+    }))
+}
+
+async fn handle_stats(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let index = state.index();
+
+    let mut trending = trending_tags(
+        index.episodes_by_id.values(),
+        TRENDING_RECENT_EPISODES,
+        TRENDING_MIN_EPISODES,
+    );
+    trending.truncate(STATS_TRENDING_TAGS);
+
+    let context = Context::from_serialize(serde_json::json!({
+        "stats": index.stats,
+        "trending": trending,
+        "trending_recent": TRENDING_RECENT_EPISODES,
+        "show": state.show.name,
+        "updated_at": index
+            .updated_at
+            .map(|time| time.format("%Y-%m-%d").to_string()),
+        "stale_days": stale_days(&state, &index),
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| state.templates.render("stats.html", &context)) {
+        Ok(html) => ([(header::VARY, "Accept-Language")], Html(html)).into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+// the operators and fields of the query language, from the registry of the parser
+async fn handle_help(headers: HeaderMap, State(state): State<Arc<AppState>>) -> Response {
+    let lang = state.templates.negotiate(&headers);
+
+    let context = Context::from_serialize(serde_json::json!({
+        "operators": QUERY_OPERATORS,
+        "fields": QUERY_FIELDS,
+        "base_path": state.mount_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| state.templates.render("help.html", &context)) {
+        Ok(html) => ([(header::VARY, "Accept-Language")], Html(html)).into_response(),
+        Err(err) => internal_error_page(&state.templates, &state.mount_path, lang, None, &err),
+    }
+}
+
+// the attributes every cookie of the site shares, the whole base path
+// and only over https when it's served over https
+fn cookie_attributes(config: &Config) -> String {
+    let path = if config.server.base_path.is_empty() {
+        "/"
+    } else {
+        &config.server.base_path
+    };
+
+    if config.tls.is_enabled() || config.server.public_url.starts_with("https://") {
+        format!("Path={}; Secure", path)
+    } else {
+        format!("Path={}", path)
+    }
+}
+
+// absolute url of the show, server.public_url or derived from the request host
+// (the Host header, or the authority of http/2 requests), plus the mount path
+fn base_url(state: &AppState, host: Option<Host>) -> String {
+    let config = state.config();
+
+    if !config.server.public_url.is_empty() {
+        let show_path = &state.mount_path[config.server.base_path.len()..];
+        return format!(
+            "{}{}",
+            config.server.public_url.trim_end_matches('/'),
+            show_path
+        );
+    }
+
+    let host = host.map_or_else(|| String::from("localhost:3000"), |Host(host)| host);
+
+    let scheme = if config.tls.is_enabled() {
+        "https"
+    } else {
+        "http"
+    };
 
-    Html(html)
+    format!("{}://{}{}", scheme, host, state.mount_path)
 }
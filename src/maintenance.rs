@@ -0,0 +1,89 @@
+use axum::extract::State;
+use axum::http::{header, HeaderValue, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use unplugged_engine::ShowIndex;
+
+use crate::assets::Templates;
+use crate::error_pages::error_page;
+use crate::shows::Shows;
+use crate::AppState;
+
+// how often the index of a show in maintenance is loaded again,
+// also the Retry-After of the maintenance page
+const INDEX_RETRY_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The 503 page of a show whose index is missing or being rebuilt
+pub fn maintenance_page(templates: &Templates, base_path: &str, lang: &str) -> Response {
+    let mut response = error_page(
+        templates,
+        base_path,
+        lang,
+        StatusCode::SERVICE_UNAVAILABLE,
+        None,
+        None,
+    );
+
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(INDEX_RETRY_INTERVAL.as_secs()),
+    );
+    response
+}
+
+/// Serves the maintenance page instead of the routes of a show without an index
+pub async fn maintenance<B>(
+    State(state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if state.index.is_ready() {
+        return next.run(request).await;
+    }
+
+    let lang = state.templates.negotiate(request.headers());
+    maintenance_page(&state.templates, &state.mount_path, lang)
+}
+
+/// Episodes and tags of every show, for the index gauges
+pub fn set_index_metrics(shows: &Shows) {
+    let indices: Vec<_> = shows.iter().map(|show| show.index()).collect();
+
+    shows[0].metrics.set_index(
+        indices.iter().map(|index| index.episodes_by_id.len()).sum(),
+        indices
+            .iter()
+            .map(|index| index.episodes_by_tag.len())
+            .sum(),
+    );
+}
+
+/// Loads the index of the shows in maintenance again until it succeeds,
+/// e.g. once the indexer is done rewriting the files
+pub async fn reload_unavailable_indices(shows: Shows) {
+    let mut interval = tokio::time::interval(INDEX_RETRY_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        for show in shows.iter().filter(|show| !show.index.is_ready()) {
+            match ShowIndex::load(&show.show).await {
+                Ok(index) => {
+                    info!(
+                        show = show.show.slug,
+                        episodes = index.episodes_by_id.len(),
+                        "Loaded the index, leaving maintenance"
+                    );
+
+                    show.index.swap(index);
+                    show.page_cache.clear();
+                    set_index_metrics(&shows);
+                }
+                Err(reason) => warn!(show = show.show.slug, "Index still unavailable: {}", reason),
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{escape_xml, Episode};
+use serde_json::{json, Value};
+
+const SEARCH_COMMAND: &str = "!search";
+
+/// A `!search <query>` message in one of the rooms of the bot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatrixCommand {
+    pub room_id: String,
+    /// the message, the reply quotes it
+    pub event_id: String,
+    pub query: String,
+}
+
+/// The `!search` commands among the new messages of a `/sync` response,
+/// only in `rooms` and never the messages of the bot (`own_user`)
+pub fn matrix_commands(sync: &Value, rooms: &[String], own_user: &str) -> Vec<MatrixCommand> {
+    let Some(joined) = sync["rooms"]["join"].as_object() else {
+        return Vec::new();
+    };
+
+    let mut commands = Vec::new();
+
+    for (room_id, room) in joined.iter().filter(|(id, _)| rooms.contains(id)) {
+        let Some(events) = room["timeline"]["events"].as_array() else {
+            continue;
+        };
+
+        for event in events {
+            if event["type"] != "m.room.message"
+                || event["sender"] == own_user
+                || event["content"]["msgtype"] != "m.text"
+            {
+                continue;
+            }
+
+            let query = event["content"]["body"]
+                .as_str()
+                .and_then(|body| body.strip_prefix(SEARCH_COMMAND))
+                .filter(|rest| rest.starts_with(char::is_whitespace))
+                .map(str::trim)
+                .filter(|query| !query.is_empty());
+
+            let (Some(query), Some(event_id)) = (query, event["event_id"].as_str()) else {
+                continue;
+            };
+
+            commands.push(MatrixCommand {
+                room_id: room_id.clone(),
+                event_id: event_id.to_string(),
+                query: query.to_string(),
+            });
+        }
+    }
+
+    commands
+}
+
+/// The reply to a command, the episodes as plain text and as an html list,
+/// sent as a notice so other bots don't answer it
+pub fn matrix_search_reply(command: &MatrixCommand, episodes: &[&Episode]) -> Value {
+    let (body, html) = if episodes.is_empty() {
+        let body = format!("No episodes found for \"{}\"", command.query);
+        let html = escape_xml(&body);
+        (body, html)
+    } else {
+        let mut body = format!("Top results for \"{}\"", command.query);
+        let mut html = format!(
+            "Top results for <code>{}</code><ul>",
+            escape_xml(&command.query)
+        );
+
+        for episode in episodes {
+            body.push_str(&format!(
+                "\n- {} ({}, {}) {}",
+                episode.title, episode.date, episode.duration, episode.url
+            ));
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> <em>{} | {}</em></li>",
+                escape_xml(&episode.url),
+                escape_xml(&episode.title),
+                escape_xml(&episode.date),
+                escape_xml(&episode.duration)
+            ));
+        }
+
+        html.push_str("</ul>");
+        (body, html)
+    };
+
+    json!({
+        "msgtype": "m.notice",
+        "body": body,
+        "format": "org.matrix.custom.html",
+        "formatted_body": html,
+        "m.relates_to": { "m.in_reply_to": { "event_id": command.event_id } },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matrix_commands, matrix_search_reply, MatrixCommand};
+    use crate::Episode;
+    use serde_json::json;
+
+    #[test]
+    fn test_matrix_commands() {
+        let message = |event_id: &str, sender: &str, body: &str| {
+            json!({
+                "type": "m.room.message",
+                "event_id": event_id,
+                "sender": sender,
+                "content": { "msgtype": "m.text", "body": body },
+            })
+        };
+
+        let sync = json!({
+            "next_batch": "s2",
+            "rooms": { "join": {
+                "!lup:example.org": { "timeline": { "events": [
+                    message("$1", "@alice:example.org", "!search  nixos btrfs "),
+                    message("$2", "@bot:example.org", "!search nixos"),
+                    message("$3", "@alice:example.org", "!searching"),
+                    message("$4", "@alice:example.org", "!search"),
+                    { "type": "m.room.member", "event_id": "$5", "sender": "@bob:example.org" },
+                ] } },
+                "!other:example.org": { "timeline": { "events": [
+                    message("$6", "@alice:example.org", "!search zfs"),
+                ] } },
+            } },
+        });
+
+        let rooms = vec![String::from("!lup:example.org")];
+
+        assert_eq!(
+            matrix_commands(&sync, &rooms, "@bot:example.org"),
+            vec![MatrixCommand {
+                room_id: String::from("!lup:example.org"),
+                event_id: String::from("$1"),
+                query: String::from("nixos btrfs"),
+            }]
+        );
+        assert!(matrix_commands(&json!({}), &rooms, "@bot:example.org").is_empty());
+    }
+
+    #[test]
+    fn test_matrix_search_reply() {
+        let command = MatrixCommand {
+            room_id: String::from("!lup:example.org"),
+            event_id: String::from("$1"),
+            query: String::from("<arch>"),
+        };
+        let episode = Episode {
+            id: 490,
+            title: String::from("490: The Arch Disaster"),
+            date: String::from("January 1st, 2022"),
+            duration: String::from("01:16:02"),
+            url: String::from("https://linuxunplugged.com/490"),
+            ..Default::default()
+        };
+
+        let reply = matrix_search_reply(&command, &[&episode]);
+
+        assert_eq!(reply["msgtype"], "m.notice");
+        assert_eq!(reply["m.relates_to"]["m.in_reply_to"]["event_id"], "$1");
+        assert_eq!(
+            reply["body"],
+            "Top results for \"<arch>\"\n- 490: The Arch Disaster (January 1st, 2022, 01:16:02) https://linuxunplugged.com/490"
+        );
+        assert!(reply["formatted_body"]
+            .as_str()
+            .unwrap()
+            .starts_with("Top results for <code>&lt;arch&gt;</code><ul><li><a href="));
+
+        let empty = matrix_search_reply(&command, &[]);
+
+        assert_eq!(
+            empty["formatted_body"],
+            "No episodes found for &quot;&lt;arch&gt;&quot;"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use unplugged_engine::{
+    encode_query_component, matrix_commands, matrix_search_reply, MatrixConfig, QueryVersion,
+};
+use uuid::Uuid;
+
+use crate::{search_episodes, AppState};
+
+const MATRIX_RESULTS_LIMIT: usize = 3;
+
+// how long a /sync waits for new messages, the requests may take a bit longer
+const MATRIX_SYNC_TIMEOUT_MS: u64 = 30_000;
+const MATRIX_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+// pause after a failed sync, so a homeserver down isn't hammered
+const MATRIX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Answers the `!search` messages of the configured rooms with the top results of
+/// the show, searched right here rather than over http
+pub async fn run_matrix_bot(state: Arc<AppState>, config: MatrixConfig) {
+    let client = Client::new();
+    let homeserver = config.homeserver.trim_end_matches('/').to_string();
+    let api = |path: &str| format!("{}/_matrix/client/v3{}", homeserver, path);
+
+    let token = if config.access_token.is_empty() {
+        match login(&client, &api("/login"), &config).await {
+            Ok(token) => token,
+            Err(err) => {
+                error!("{}, the matrix bot is off", err);
+                return;
+            }
+        }
+    } else {
+        config.access_token.clone()
+    };
+
+    for room in config.rooms.iter() {
+        let join = client.post(api(&format!("/join/{}", encode_query_component(room))));
+
+        if let Err(err) = send(join.bearer_auth(&token).body("{}")).await {
+            warn!(room, "Error at joining the matrix room: {}", err);
+        }
+    }
+
+    // the messages sent before the start aren't answered
+    let skip_history = encode_query_component(r#"{"room":{"timeline":{"limit":0}}}"#);
+    let mut since: Option<String> = None;
+
+    info!(
+        user = config.user,
+        rooms = config.rooms.len(),
+        "Matrix bot listening"
+    );
+
+    loop {
+        let url = match &since {
+            Some(since) => format!(
+                "{}?timeout={}&since={}",
+                api("/sync"),
+                MATRIX_SYNC_TIMEOUT_MS,
+                encode_query_component(since)
+            ),
+            None => format!("{}?timeout=0&filter={}", api("/sync"), skip_history),
+        };
+
+        let sync = match send(client.get(url).bearer_auth(&token)).await {
+            Ok(sync) => sync,
+            Err(err) => {
+                error!("Error at syncing with the matrix homeserver: {}", err);
+                tokio::time::sleep(MATRIX_RETRY_INTERVAL).await;
+                continue;
+            }
+        };
+
+        if since.is_some() {
+            for command in matrix_commands(&sync, &config.rooms, &config.user) {
+                let reply = {
+                    let index = state.index();
+                    let results_with_score =
+                        search_episodes(&state, &index, &command.query, QueryVersion::LATEST);
+                    let episodes: Vec<_> = results_with_score
+                        .iter()
+                        .take(MATRIX_RESULTS_LIMIT)
+                        .map(|(_, ep)| *ep)
+                        .collect();
+
+                    matrix_search_reply(&command, &episodes)
+                };
+
+                let path = format!(
+                    "/rooms/{}/send/m.room.message/{}",
+                    encode_query_component(&command.room_id),
+                    Uuid::new_v4()
+                );
+                let request = client.put(api(&path)).bearer_auth(&token);
+
+                if let Err(err) = send(request.body(reply.to_string())).await {
+                    error!(
+                        room = command.room_id,
+                        "Error at replying in matrix: {}", err
+                    );
+                }
+            }
+        }
+
+        since = sync["next_batch"].as_str().map(String::from).or(since);
+    }
+}
+
+// logs in with the password of matrix.user, returns the access token
+async fn login(client: &Client, url: &str, config: &MatrixConfig) -> Result<String, String> {
+    let body = json!({
+        "type": "m.login.password",
+        "identifier": { "type": "m.id.user", "user": config.user },
+        "password": config.password,
+        "initial_device_display_name": "Unplugged search",
+    });
+
+    let response = send(client.post(url).body(body.to_string()))
+        .await
+        .map_err(|err| format!("Error at logging in to matrix: {}", err))?;
+
+    response["access_token"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| String::from("Error at logging in to matrix: no access token"))
+}
+
+async fn send(request: RequestBuilder) -> Result<Value, String> {
+    let response = request
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .timeout(MATRIX_REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?;
+
+    let body = response.text().await.map_err(|err| err.to_string())?;
+
+    serde_json::from_str(&body).map_err(|err| err.to_string())
+}
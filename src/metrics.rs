@@ -0,0 +1,257 @@
+use axum::extract::{MatchedPath, State};
+use axum::http::{header, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use unplugged_engine::IndexStaleness;
+
+use crate::shows::Shows;
+use crate::AppState;
+
+const RESULT_COUNT_BUCKETS: [f64; 8] = [0.0, 1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0];
+
+/// Prometheus metrics of the instance, rendered by `/metrics`
+pub struct Metrics {
+    registry: Registry,
+    pub requests: IntCounterVec,
+    pub request_duration: HistogramVec,
+    pub search_duration: Histogram,
+    pub search_results: Histogram,
+    pub page_cache_hits: IntCounter,
+    pub page_cache_misses: IntCounter,
+    pub requests_shed: IntCounter,
+    pub index_episodes: IntGauge,
+    pub index_tags: IntGauge,
+    pub index_last_reload: IntGauge,
+    pub index_newest_episode: IntGaugeVec,
+    pub index_refreshed: IntGaugeVec,
+    pub index_stale: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some(String::from("unplugged")), None)
+            .expect("Error at creating metrics registry");
+
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency"),
+            &["route"],
+        )
+        .unwrap();
+        let search_duration = Histogram::with_opts(HistogramOpts::new(
+            "search_duration_seconds",
+            "Time spent in the search pipeline",
+        ))
+        .unwrap();
+        let search_results = Histogram::with_opts(
+            HistogramOpts::new("search_results", "Number of results per search")
+                .buckets(RESULT_COUNT_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        let page_cache_hits = IntCounter::new(
+            "page_cache_hits_total",
+            "Results pages served from the cache",
+        )
+        .unwrap();
+        let page_cache_misses =
+            IntCounter::new("page_cache_misses_total", "Results pages rendered").unwrap();
+        let requests_shed = IntCounter::new(
+            "http_requests_shed_total",
+            "Requests answered with a 503 over server.max_concurrent_requests",
+        )
+        .unwrap();
+        let index_episodes =
+            IntGauge::new("index_episodes", "Episodes in the loaded index").unwrap();
+        let index_tags = IntGauge::new("index_tags", "Tags in the loaded index").unwrap();
+        let index_last_reload = IntGauge::new(
+            "index_last_reload_timestamp_seconds",
+            "Unix time of the last index load",
+        )
+        .unwrap();
+        let index_newest_episode = IntGaugeVec::new(
+            Opts::new(
+                "index_newest_episode_timestamp_seconds",
+                "Unix time of the date of the newest episode of the show",
+            ),
+            &["show"],
+        )
+        .unwrap();
+        let index_refreshed = IntGaugeVec::new(
+            Opts::new(
+                "index_refreshed_timestamp_seconds",
+                "Unix time of the last successful refresh of the index of the show",
+            ),
+            &["show"],
+        )
+        .unwrap();
+        let index_stale = IntGaugeVec::new(
+            Opts::new(
+                "index_stale",
+                "1 when the newest episode of the show is older than staleness.max_days",
+            ),
+            &["show"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry
+            .register(Box::new(request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(search_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(search_results.clone())).unwrap();
+        registry
+            .register(Box::new(page_cache_hits.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(page_cache_misses.clone()))
+            .unwrap();
+        registry.register(Box::new(requests_shed.clone())).unwrap();
+        registry.register(Box::new(index_episodes.clone())).unwrap();
+        registry.register(Box::new(index_tags.clone())).unwrap();
+        registry
+            .register(Box::new(index_last_reload.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(index_newest_episode.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(index_refreshed.clone()))
+            .unwrap();
+        registry.register(Box::new(index_stale.clone())).unwrap();
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            search_duration,
+            search_results,
+            page_cache_hits,
+            page_cache_misses,
+            requests_shed,
+            index_episodes,
+            index_tags,
+            index_last_reload,
+            index_newest_episode,
+            index_refreshed,
+            index_stale,
+        }
+    }
+
+    pub fn observe_search(&self, duration: Duration, results: usize) {
+        self.search_duration.observe(duration.as_secs_f64());
+        self.search_results.observe(results as f64);
+    }
+
+    pub fn set_index(&self, episodes: usize, tags: usize) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+
+        self.index_episodes.set(episodes as i64);
+        self.index_tags.set(tags as i64);
+        self.index_last_reload.set(now as i64);
+    }
+
+    pub fn set_staleness(&self, show: &str, staleness: &IndexStaleness) {
+        let newest = staleness
+            .newest_episode
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map_or(0, |date| date.timestamp());
+
+        self.index_newest_episode
+            .with_label_values(&[show])
+            .set(newest);
+        self.index_refreshed
+            .with_label_values(&[show])
+            .set(staleness.refreshed_at.timestamp());
+        self.index_stale
+            .with_label_values(&[show])
+            .set(staleness.stale as i64);
+    }
+
+    /// Metrics in the prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Error at encoding metrics");
+
+        String::from_utf8(buffer).expect("Error at encoding metrics")
+    }
+}
+
+// set on responses already counted, the routes of a show are tracked inside its
+// nested router (where the route template is known) and again by the root router
+#[derive(Clone, Copy)]
+struct Tracked;
+
+/// Counts every request by its route template (not the raw path, to keep the label set small)
+pub async fn track_requests<B>(
+    State(state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| String::from("static"));
+    let method = request.method().to_string();
+
+    let start = Instant::now();
+    let mut response = next.run(request).await;
+
+    if response.extensions().get::<Tracked>().is_some() {
+        return response;
+    }
+
+    response.extensions_mut().insert(Tracked);
+
+    state
+        .metrics
+        .requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    state
+        .metrics
+        .request_duration
+        .with_label_values(&[&route])
+        .observe(start.elapsed().as_secs_f64());
+
+    response
+}
+
+pub async fn handle_metrics(State(shows): State<Shows>) -> impl IntoResponse {
+    let state = &shows[0];
+    let now = Utc::now();
+
+    // the age of the newest episode grows without a reload, it's computed when scraped
+    for show in shows.iter() {
+        let staleness = IndexStaleness::new(&show.index(), now, state.config().staleness.max_days);
+        state.metrics.set_staleness(&show.show.slug, &staleness);
+    }
+
+    (
+        [(
+            header::CONTENT_TYPE,
+            TextEncoder::new().format_type().to_string(),
+        )],
+        state.metrics.render(),
+    )
+}
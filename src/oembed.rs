@@ -0,0 +1,154 @@
+use crate::{escape_xml, format_date, parse_date, parse_duration, Episode, PodcastConfig};
+use serde_json::{json, Value};
+
+/// Width of the embedded card when the consumer doesn't ask for a `maxwidth`
+pub const OEMBED_WIDTH: u32 = 480;
+const OEMBED_HEIGHT: u32 = 160;
+
+// the card quotes the start of the description
+const OEMBED_DESCRIPTION_CHARS: usize = 200;
+
+/// The slug of an episode permalink served under `mount_path`,
+/// `https://search.example.com/lup/episode/490-the-arch-disaster?t=754` -> `490-the-arch-disaster`
+pub fn oembed_episode_slug<'a>(url: &'a str, mount_path: &str) -> Option<&'a str> {
+    let (_, rest) = url.split_once("://")?;
+    let path = &rest[rest.find('/')?..];
+    let path = path.split(['?', '#']).next()?;
+    let slug = path.strip_prefix(mount_path)?.strip_prefix("/episode/")?;
+
+    (!slug.is_empty() && !slug.contains('/')).then_some(slug)
+}
+
+/// The oEmbed `rich` response of an episode, a card linking to `page_url`,
+/// `provider_url` is the homepage of the archive
+pub fn episode_oembed(
+    episode: &Episode,
+    page_url: &str,
+    provider_name: &str,
+    provider_url: &str,
+    podcast: &PodcastConfig,
+    max_width: Option<u32>,
+) -> Value {
+    let width = max_width.map_or(OEMBED_WIDTH, |max| max.min(OEMBED_WIDTH));
+
+    let mut details = Vec::new();
+
+    if let Some(date) = parse_date(&episode.date) {
+        details.push(format_date(date));
+    }
+
+    if let Some(seconds) = parse_duration(&episode.duration) {
+        details.push(format!("{} min", (seconds + 30) / 60));
+    }
+
+    let mut html = format!(
+        "<blockquote class=\"unplugged-episode\" style=\"max-width: {}px\"><a href=\"{}\"><strong>{}</strong></a>",
+        width,
+        escape_xml(page_url),
+        escape_xml(&episode.title)
+    );
+
+    if !details.is_empty() {
+        html.push_str(&format!("<br><small>{}</small>", details.join(" · ")));
+    }
+
+    if let Some(description) = &episode.description {
+        let mut quote: String = description.chars().take(OEMBED_DESCRIPTION_CHARS).collect();
+
+        if quote.len() < description.len() {
+            quote.push('…');
+        }
+
+        html.push_str(&format!("<p>{}</p>", escape_xml(&quote)));
+    }
+
+    html.push_str("</blockquote>");
+
+    let mut oembed = json!({
+        "version": "1.0",
+        "type": "rich",
+        "title": episode.title,
+        "provider_name": provider_name,
+        "provider_url": provider_url,
+        "html": html,
+        "width": width,
+        "height": OEMBED_HEIGHT,
+    });
+
+    if !podcast.artwork_url.is_empty() {
+        oembed["thumbnail_url"] = json!(podcast.artwork_url);
+        oembed["thumbnail_width"] = json!(podcast.artwork_size);
+        oembed["thumbnail_height"] = json!(podcast.artwork_size);
+    }
+
+    oembed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{episode_oembed, oembed_episode_slug};
+    use crate::{Episode, PodcastConfig};
+
+    #[test]
+    fn test_oembed_episode_slug() {
+        let url = "https://search.example.com/lup/episode/490-the-arch-disaster?t=754";
+
+        assert_eq!(
+            oembed_episode_slug(url, "/lup"),
+            Some("490-the-arch-disaster")
+        );
+        assert_eq!(
+            oembed_episode_slug("http://localhost:3000/episode/490", ""),
+            Some("490")
+        );
+        assert_eq!(oembed_episode_slug(url, ""), None);
+        assert_eq!(oembed_episode_slug("/episode/490", ""), None);
+        assert_eq!(
+            oembed_episode_slug("https://example.com/episode/", ""),
+            None
+        );
+    }
+
+    #[test]
+    fn test_episode_oembed() {
+        let episode = Episode {
+            id: 490,
+            title: String::from("490: The <Arch> Disaster"),
+            date: String::from("January 1st, 2022"),
+            duration: String::from("01:16:02"),
+            description: Some(String::from("Arch broke.")),
+            ..Default::default()
+        };
+        let podcast = PodcastConfig {
+            artwork_url: String::from("https://example.com/cover.jpg"),
+            ..Default::default()
+        };
+        let page = "https://search.example.com/episode/490-the-arch-disaster";
+
+        let oembed = episode_oembed(
+            &episode,
+            page,
+            "Linux Unplugged",
+            "https://search.example.com",
+            &podcast,
+            Some(320),
+        );
+
+        assert_eq!(oembed["type"], "rich");
+        assert_eq!(oembed["title"], "490: The <Arch> Disaster");
+        assert_eq!(oembed["width"], 320);
+        assert_eq!(oembed["thumbnail_width"], 1400);
+        assert_eq!(
+            oembed["html"],
+            "<blockquote class=\"unplugged-episode\" style=\"max-width: 320px\">\
+             <a href=\"https://search.example.com/episode/490-the-arch-disaster\">\
+             <strong>490: The &lt;Arch&gt; Disaster</strong></a>\
+             <br><small>January 1st, 2022 · 76 min</small><p>Arch broke.</p></blockquote>"
+        );
+
+        let plain = episode_oembed(&episode, page, "", "", &PodcastConfig::default(), None);
+
+        assert_eq!(plain["width"], 480);
+        assert!(plain.get("thumbnail_url").is_none());
+    }
+}
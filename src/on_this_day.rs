@@ -0,0 +1,87 @@
+use crate::{parse_date, Episode, EpisodesById};
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+/// An episode released on the month and day of `today`
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Anniversary<'a> {
+    /// 0 for the episodes released today
+    pub years_ago: i32,
+    pub episode: &'a Episode,
+}
+
+/// The episodes released on this day across all years, the most recent first,
+/// on the 28th of February of common years the ones of the 29th show up too
+pub fn on_this_day(by_id: &EpisodesById, today: NaiveDate) -> Vec<Anniversary<'_>> {
+    let leap_day_missing = today.month() == 2
+        && today.day() == 28
+        && NaiveDate::from_ymd_opt(today.year(), 2, 29).is_none();
+
+    let mut anniversaries: Vec<_> = by_id
+        .values()
+        .filter_map(|episode| {
+            let date = parse_date(&episode.date)?;
+            let same_day = (date.month(), date.day()) == (today.month(), today.day())
+                || (leap_day_missing && (date.month(), date.day()) == (2, 29));
+
+            (same_day && date.year() <= today.year()).then(|| Anniversary {
+                years_ago: today.year() - date.year(),
+                episode,
+            })
+        })
+        .collect();
+
+    anniversaries.sort_by(|a, b| {
+        a.years_ago
+            .cmp(&b.years_ago)
+            .then(b.episode.id.cmp(&a.episode.id))
+    });
+    anniversaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::on_this_day;
+    use crate::{Episode, EpisodesById};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_on_this_day() {
+        let episode = |id: i64, date: &str| Episode {
+            id,
+            title: format!("{}: Episode", id),
+            date: String::from(date),
+            duration: String::new(),
+            tags: vec![],
+            url: String::new(),
+            ..Default::default()
+        };
+
+        let by_id = EpisodesById::from([
+            (300, episode(300, "October 15th, 2019")),
+            (400, episode(400, "October 15th, 2021")),
+            (401, episode(401, "October 16th, 2021")),
+            (500, episode(500, "October 15th, 2027")),
+            (600, episode(600, "February 29th, 2024")),
+            (601, episode(601, "February 28th, 2025")),
+            (0, episode(0, "someday")),
+        ]);
+
+        let years = |today| -> Vec<_> {
+            on_this_day(&by_id, today)
+                .iter()
+                .map(|day| (day.episode.id, day.years_ago))
+                .collect()
+        };
+
+        let today = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        assert_eq!(years(today), vec![(400, 5), (300, 7)]);
+
+        let today = NaiveDate::from_ymd_opt(2025, 2, 28).unwrap();
+        assert_eq!(years(today), vec![(601, 0), (600, 1)]);
+
+        // leap year, the 29th waits for its own day
+        let today = NaiveDate::from_ymd_opt(2028, 2, 28).unwrap();
+        assert_eq!(years(today), vec![(601, 3)]);
+    }
+}
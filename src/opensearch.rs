@@ -0,0 +1,47 @@
+use crate::escape_xml;
+
+/// Builds the OpenSearch description document,
+/// `base_url` is the absolute url of the instance without trailing slash
+pub fn opensearch_description(base_url: &str) -> String {
+    let base_url = escape_xml(base_url.trim_end_matches('/'));
+
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n");
+    xml.push_str("  <ShortName>Unplugged search</ShortName>\n");
+    xml.push_str("  <Description>Search the Linux Unplugged episode archive</Description>\n");
+    xml.push_str("  <InputEncoding>UTF-8</InputEncoding>\n");
+    xml.push_str(&format!(
+        "  <Url type=\"text/html\" method=\"get\" template=\"{}/search?query={{searchTerms}}\"/>\n",
+        base_url
+    ));
+    xml.push_str(&format!(
+        "  <Url type=\"application/atom+xml\" method=\"get\" template=\"{}/search.atom?query={{searchTerms}}\"/>\n",
+        base_url
+    ));
+    xml.push_str(&format!(
+        "  <Url type=\"application/x-suggestions+json\" method=\"get\" template=\"{}/suggest?q={{searchTerms}}\"/>\n",
+        base_url
+    ));
+    xml.push_str(&format!(
+        "  <Url type=\"application/opensearchdescription+xml\" rel=\"self\" template=\"{}/opensearch.xml\"/>\n",
+        base_url
+    ));
+    xml.push_str("</OpenSearchDescription>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::opensearch_description;
+
+    #[test]
+    fn test_opensearch_description() {
+        let xml = opensearch_description("http://localhost:3000/");
+
+        assert!(xml.contains("template=\"http://localhost:3000/search?query={searchTerms}\""));
+        assert!(xml.contains("template=\"http://localhost:3000/suggest?q={searchTerms}\""));
+        assert!(xml.contains("<ShortName>Unplugged search</ShortName>"));
+    }
+}
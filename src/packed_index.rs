@@ -0,0 +1,326 @@
+use crate::{Episode, EpisodesById, EpisodesByTag};
+use memmap2::Mmap;
+use roaring::RoaringBitmap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// the file starts with the magic, the episode and tag counts, then the tables and the data
+const MAGIC: &[u8; 8] = b"UPIDX001";
+const HEADER_BYTES: usize = 16;
+// id (u32), title offset (u64) and length (u32), document offset (u64) and length (u32)
+const EPISODE_ENTRY_BYTES: usize = 28;
+// name offset (u64) and length (u32), posting list offset (u64) and length (u32)
+const TAG_ENTRY_BYTES: usize = 24;
+
+/// Writes the index as a packed file read in place by `PackedIndex`: the episodes sorted
+/// by id with their lowercase title and json document, the tags sorted by name with their
+/// posting list as a serialized roaring bitmap, all offsets little endian
+pub fn write_packed_index(
+    by_id: &EpisodesById,
+    by_tag: &EpisodesByTag,
+    path: &Path,
+) -> Result<(), String> {
+    let mut ids: Vec<_> = by_id.keys().copied().collect();
+    ids.sort();
+
+    let mut tags: Vec<_> = by_tag.keys().collect();
+    tags.sort();
+
+    let tables = HEADER_BYTES + ids.len() * EPISODE_ENTRY_BYTES + tags.len() * TAG_ENTRY_BYTES;
+    let mut table = Vec::with_capacity(tables);
+    let mut data = Vec::new();
+
+    table.extend_from_slice(MAGIC);
+    table.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+    table.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+
+    // appends a blob to the data section, returns its offset in the file and its length
+    let append = |data: &mut Vec<u8>, bytes: &[u8]| -> Result<(u64, u32), String> {
+        let offset = (tables + data.len()) as u64;
+        let len = u32::try_from(bytes.len())
+            .map_err(|_| String::from("Error at packing the index: a document is over 4 GiB"))?;
+        data.extend_from_slice(bytes);
+        Ok((offset, len))
+    };
+
+    for id in ids.iter() {
+        let episode = &by_id[id];
+        let id = u32::try_from(*id)
+            .map_err(|_| format!("Error at packing the index: the id {} is too large", id))?;
+
+        let document = serde_json::to_vec(episode)
+            .map_err(|err| format!("Error at packing the episode {}: {}", id, err))?;
+
+        let (title_offset, title_len) = append(&mut data, episode.title.to_lowercase().as_bytes())?;
+        let (document_offset, document_len) = append(&mut data, &document)?;
+
+        table.extend_from_slice(&id.to_le_bytes());
+        table.extend_from_slice(&title_offset.to_le_bytes());
+        table.extend_from_slice(&title_len.to_le_bytes());
+        table.extend_from_slice(&document_offset.to_le_bytes());
+        table.extend_from_slice(&document_len.to_le_bytes());
+    }
+
+    for tag in tags {
+        let posting: RoaringBitmap = by_tag[tag]
+            .iter()
+            .filter_map(|id| u32::try_from(*id).ok())
+            .collect();
+
+        let mut bytes = Vec::with_capacity(posting.serialized_size());
+        posting
+            .serialize_into(&mut bytes)
+            .map_err(|err| format!("Error at packing the tag {}: {}", tag, err))?;
+
+        let (name_offset, name_len) = append(&mut data, tag.as_bytes())?;
+        let (posting_offset, posting_len) = append(&mut data, &bytes)?;
+
+        table.extend_from_slice(&name_offset.to_le_bytes());
+        table.extend_from_slice(&name_len.to_le_bytes());
+        table.extend_from_slice(&posting_offset.to_le_bytes());
+        table.extend_from_slice(&posting_len.to_le_bytes());
+    }
+
+    // written next to the file then renamed, a server mapping the old file keeps reading it
+    let tmp = PathBuf::from(format!("{}.tmp", path.display()));
+    let write = |tmp: &Path| -> std::io::Result<()> {
+        let mut file = std::fs::File::create(tmp)?;
+        file.write_all(&table)?;
+        file.write_all(&data)?;
+        file.sync_all()
+    };
+
+    write(&tmp)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|err| format!("Error at writing {}: {}", path.display(), err))
+}
+
+/// An index in the packed format memory mapped, the posting lists and the episodes
+/// are decoded on demand instead of being deserialized at startup
+pub struct PackedIndex {
+    mmap: Mmap,
+    episodes: usize,
+    tags: usize,
+}
+
+impl PackedIndex {
+    /// Maps the file and checks every entry points inside it,
+    /// the file must be replaced by a rename (as `write_packed_index` does), never truncated
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|err| format!("Error at reading {}: {}", path.display(), err))?;
+
+        // safe as long as the file isn't truncated while it's mapped
+        let mmap = unsafe { Mmap::map(&file) }
+            .map_err(|err| format!("Error at mapping {}: {}", path.display(), err))?;
+
+        let invalid = |reason: &str| format!("Error at reading {}: {}", path.display(), reason);
+
+        if mmap.len() < HEADER_BYTES || &mmap[..MAGIC.len()] != MAGIC {
+            return Err(invalid("not a packed index"));
+        }
+
+        let index = Self {
+            episodes: read_u32(&mmap, 8) as usize,
+            tags: read_u32(&mmap, 12) as usize,
+            mmap,
+        };
+
+        let tables = index.episodes * EPISODE_ENTRY_BYTES + index.tags * TAG_ENTRY_BYTES;
+
+        if HEADER_BYTES + tables > index.mmap.len() {
+            return Err(invalid("the file is truncated"));
+        }
+
+        let in_bounds = |(offset, len): (usize, usize)| {
+            offset
+                .checked_add(len)
+                .is_some_and(|end| end <= index.mmap.len())
+        };
+        let episodes_in_bounds = (0..index.episodes).all(|i| {
+            in_bounds(index.blob(index.episode_entry(i) + 4))
+                && in_bounds(index.blob(index.episode_entry(i) + 16))
+        });
+        let tags_in_bounds = (0..index.tags).all(|i| {
+            in_bounds(index.blob(index.tag_entry(i)))
+                && in_bounds(index.blob(index.tag_entry(i) + 12))
+        });
+
+        if !episodes_in_bounds || !tags_in_bounds {
+            return Err(invalid("an entry points past the end of the file"));
+        }
+
+        Ok(index)
+    }
+
+    pub fn episode_count(&self) -> usize {
+        self.episodes
+    }
+
+    pub fn tag_count(&self) -> usize {
+        self.tags
+    }
+
+    /// Decodes the episode, None when the index doesn't have it
+    pub fn episode(&self, id: usize) -> Option<Episode> {
+        let id = u32::try_from(id).ok()?;
+        let i = self.find(self.episodes, |i| {
+            read_u32(&self.mmap, self.episode_entry(i)).cmp(&id)
+        })?;
+
+        serde_json::from_slice(self.bytes(self.episode_entry(i) + 16)).ok()
+    }
+
+    /// The posting list of a tag, None when the index doesn't have it
+    pub fn posting(&self, tag: &str) -> Option<RoaringBitmap> {
+        let i = self.find(self.tags, |i| self.tag_name(i).cmp(tag))?;
+        self.tag_posting(i)
+    }
+
+    /// The tags in alphabetical order
+    pub fn tag_names(&self) -> impl Iterator<Item = &str> {
+        (0..self.tags).map(|i| self.tag_name(i))
+    }
+
+    /// Every episode and tag of the index, decoded to be searched like the index files
+    pub fn read_index(&self) -> (EpisodesById, EpisodesByTag) {
+        let by_id = (0..self.episodes)
+            .filter_map(|i| {
+                let document = self.bytes(self.episode_entry(i) + 16);
+                serde_json::from_slice::<Episode>(document).ok()
+            })
+            .map(|episode| (episode.id as usize, episode))
+            .collect();
+
+        let by_tag = (0..self.tags)
+            .map(|i| {
+                let ids = self.tag_posting(i).unwrap_or_default();
+                (
+                    self.tag_name(i).to_string(),
+                    ids.iter().map(|id| id as usize).collect(),
+                )
+            })
+            .collect();
+
+        (by_id, by_tag)
+    }
+
+    fn episode_entry(&self, i: usize) -> usize {
+        HEADER_BYTES + i * EPISODE_ENTRY_BYTES
+    }
+
+    fn tags_table(&self) -> usize {
+        HEADER_BYTES + self.episodes * EPISODE_ENTRY_BYTES
+    }
+
+    fn tag_entry(&self, i: usize) -> usize {
+        self.tags_table() + i * TAG_ENTRY_BYTES
+    }
+
+    fn tag_name(&self, i: usize) -> &str {
+        std::str::from_utf8(self.bytes(self.tag_entry(i))).unwrap_or_default()
+    }
+
+    fn tag_posting(&self, i: usize) -> Option<RoaringBitmap> {
+        RoaringBitmap::deserialize_from(self.bytes(self.tag_entry(i) + 12)).ok()
+    }
+
+    // offset and length of the blob described at `pos`
+    fn blob(&self, pos: usize) -> (usize, usize) {
+        (
+            read_u64(&self.mmap, pos) as usize,
+            read_u32(&self.mmap, pos + 8) as usize,
+        )
+    }
+
+    fn bytes(&self, pos: usize) -> &[u8] {
+        let (offset, len) = self.blob(pos);
+        &self.mmap[offset..offset + len]
+    }
+
+    // binary search over the sorted entries of a table
+    fn find<F>(&self, len: usize, compare: F) -> Option<usize>
+    where
+        F: Fn(usize) -> std::cmp::Ordering,
+    {
+        let (mut low, mut high) = (0, len);
+
+        while low < high {
+            let mid = (low + high) / 2;
+
+            match compare(mid) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+
+        None
+    }
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_packed_index, PackedIndex};
+    use crate::{Episode, EpisodesById, EpisodesByTag, Tag};
+
+    #[test]
+    fn test_packed_index() {
+        let episode = |id: i64, title: &str, tags: &[&str]| Episode {
+            id,
+            title: String::from(title),
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..Default::default()
+        };
+
+        let by_id = EpisodesById::from([
+            (
+                490,
+                episode(490, "490: The Arch Disaster", &["arch", "btrfs"]),
+            ),
+            (500, episode(500, "500: ZFS Forever", &["zfs"])),
+            (501, episode(501, "501: Filesystems", &["zfs", "btrfs"])),
+        ]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("btrfs"), vec![490, 501]),
+            (String::from("zfs"), vec![500, 501]),
+        ]);
+
+        let path =
+            std::env::temp_dir().join(format!("unplugged-packed-{}.upidx", std::process::id()));
+        write_packed_index(&by_id, &by_tag, &path).unwrap();
+
+        let packed = PackedIndex::open(&path).unwrap();
+        assert_eq!(packed.episode_count(), 3);
+        assert_eq!(
+            packed.tag_names().collect::<Vec<_>>(),
+            vec!["arch", "btrfs", "zfs"]
+        );
+        assert_eq!(packed.episode(500), Some(by_id[&500].clone()));
+        assert_eq!(packed.episode(499), None);
+        assert_eq!(
+            packed.posting("btrfs").unwrap().iter().collect::<Vec<_>>(),
+            vec![490, 501]
+        );
+        assert_eq!(packed.posting("gnome"), None);
+
+        // decoded whole, searched by the same pipeline as the index files
+        let (packed_by_id, packed_by_tag) = packed.read_index();
+        assert_eq!(packed_by_id, by_id);
+        assert_eq!(packed_by_tag, by_tag);
+
+        std::fs::write(&path, b"UPIDX001\xff\xff\xff\xff").unwrap();
+        assert!(PackedIndex::open(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{normalize_query, QueryVersion, SortOrder};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+/// What a rendered results page depends on besides the index
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct PageKey {
+    pub query: String,
+    /// the version of the query language the query is parsed with
+    pub version: QueryVersion,
+    /// results fragment (htmx) instead of the full page
+    pub fragment: bool,
+    /// language negotiated from the `Accept-Language` of the request
+    pub lang: String,
+    /// the `page` and `per_page` of the request, the first page of the default size when none
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    /// the order of the results
+    pub sort: SortOrder,
+    /// the results as the json of `/search/stream` instead of html
+    pub json: bool,
+}
+
+impl PageKey {
+    pub fn new(query: &str, version: QueryVersion, fragment: bool, lang: &str) -> Self {
+        Self {
+            query: normalize_query(query),
+            version,
+            fragment,
+            lang: lang.to_string(),
+            page: None,
+            per_page: None,
+            sort: SortOrder::Relevance,
+            json: false,
+        }
+    }
+
+    /// The key of another page of the results
+    pub fn with_page(self, page: Option<usize>, per_page: Option<usize>) -> Self {
+        Self {
+            page,
+            per_page,
+            ..self
+        }
+    }
+
+    /// The key of the results in another order
+    pub fn with_sort(self, sort: SortOrder) -> Self {
+        Self { sort, ..self }
+    }
+
+    /// The key of the results as json
+    pub fn with_json(self) -> Self {
+        Self { json: true, ..self }
+    }
+}
+
+/// A rendered results page and its number of results
+#[derive(Clone, Debug)]
+pub struct CachedPage {
+    /// the html of the page, or the json of the results
+    pub body: Arc<str>,
+    pub results: usize,
+    /// the search ran out of time, such pages aren't cached
+    pub partial: bool,
+}
+
+/// Bounded cache of rendered html for hot queries,
+/// must be cleared whenever the index changes
+pub struct PageCache {
+    pages: Mutex<LruCache<PageKey, CachedPage>>,
+}
+
+impl PageCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+
+        Self {
+            pages: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    pub fn get(&self, key: &PageKey) -> Option<CachedPage> {
+        self.pages
+            .lock()
+            .expect("Error at locking page cache")
+            .get(key)
+            .cloned()
+    }
+
+    pub fn insert(&self, key: PageKey, page: CachedPage) {
+        self.pages
+            .lock()
+            .expect("Error at locking page cache")
+            .put(key, page);
+    }
+
+    pub fn clear(&self) {
+        self.pages
+            .lock()
+            .expect("Error at locking page cache")
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedPage, PageCache, PageKey};
+    use crate::{QueryVersion, SortOrder};
+
+    #[test]
+    fn test_page_cache() {
+        let cache = PageCache::new(2);
+        let page = |body: &str| CachedPage {
+            body: body.into(),
+            results: 1,
+            partial: false,
+        };
+
+        cache.insert(
+            PageKey::new("NixOS", QueryVersion::LATEST, false, "en"),
+            page("nixos"),
+        );
+        cache.insert(
+            PageKey::new("fedora", QueryVersion::LATEST, false, "en"),
+            page("fedora"),
+        );
+
+        // same normalized query
+        assert_eq!(
+            cache
+                .get(&PageKey::new("  nixos ", QueryVersion::LATEST, false, "en"))
+                .map(|page| page.body)
+                .as_deref(),
+            Some("nixos")
+        );
+        assert!(cache
+            .get(&PageKey::new("nixos", QueryVersion::LATEST, true, "en"))
+            .is_none());
+        assert!(cache
+            .get(&PageKey::new("nixos", QueryVersion::LATEST, false, "es"))
+            .is_none());
+        assert!(cache
+            .get(&PageKey::new("nixos", QueryVersion::V1, false, "en"))
+            .is_none());
+
+        // other pages and orders of the results
+        let nixos = PageKey::new("nixos", QueryVersion::LATEST, false, "en");
+        assert!(cache.get(&nixos.clone().with_page(Some(2), None)).is_none());
+        assert!(cache
+            .get(&nixos.clone().with_sort(SortOrder::Oldest))
+            .is_none());
+        assert!(cache.get(&nixos.with_json()).is_none());
+
+        // evicts the least recently used page
+        cache.insert(
+            PageKey::new("arch", QueryVersion::LATEST, false, "en"),
+            page("arch"),
+        );
+        assert!(cache
+            .get(&PageKey::new("fedora", QueryVersion::LATEST, false, "en"))
+            .is_none());
+
+        cache.clear();
+        assert!(cache
+            .get(&PageKey::new("nixos", QueryVersion::LATEST, false, "en"))
+            .is_none());
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Serialize;
+use std::ops::Range;
+
+/// Results listed per page when the request doesn't say, `per_page`
+pub const DEFAULT_PER_PAGE: usize = 50;
+/// The most results a page lists, larger `per_page` are capped to it
+pub const MAX_PER_PAGE: usize = 200;
+
+/// A page of the results of a search, the pages count from 1
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: usize,
+    pub per_page: usize,
+    /// results of the search, on every page
+    pub total: usize,
+    /// 1 for a search without results
+    pub pages: usize,
+    /// results listed on the pages before this one
+    pub offset: usize,
+    pub previous: Option<usize>,
+    pub next: Option<usize>,
+}
+
+impl Pagination {
+    /// The page `page` of `total` results, the pages past the last one are the last one
+    pub fn new(page: Option<usize>, per_page: Option<usize>, total: usize) -> Self {
+        let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        let pages = total.div_ceil(per_page).max(1);
+        let page = page.unwrap_or(1).clamp(1, pages);
+
+        Self {
+            page,
+            per_page,
+            total,
+            pages,
+            offset: (page - 1) * per_page,
+            previous: (page > 1).then(|| page - 1),
+            next: (page < pages).then(|| page + 1),
+        }
+    }
+
+    /// The indices of the results listed on the page
+    pub fn range(&self) -> Range<usize> {
+        self.offset..(self.offset + self.per_page).min(self.total)
+    }
+
+    /// The results listed on the page, out of every result
+    pub fn slice<T>(&self, mut results: Vec<T>) -> Vec<T> {
+        let range = self.range();
+        results.truncate(range.end);
+        results.split_off(range.start.min(results.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pagination, MAX_PER_PAGE};
+
+    #[test]
+    fn test_pagination() {
+        let first = Pagination::new(None, Some(20), 45);
+        assert_eq!((first.page, first.pages, first.offset), (1, 3, 0));
+        assert_eq!((first.previous, first.next), (None, Some(2)));
+        assert_eq!(first.range(), 0..20);
+
+        let last = Pagination::new(Some(3), Some(20), 45);
+        assert_eq!((last.previous, last.next), (Some(2), None));
+        assert_eq!(last.slice((0..45).collect()), (40..45).collect::<Vec<_>>());
+
+        // out of range pages and sizes are brought back in range
+        assert_eq!(Pagination::new(Some(9), Some(20), 45), last);
+        assert_eq!(Pagination::new(Some(0), Some(20), 45), first);
+        assert_eq!(Pagination::new(None, Some(0), 45).per_page, 1);
+        assert_eq!(Pagination::new(None, Some(1000), 45).per_page, MAX_PER_PAGE);
+
+        let empty = Pagination::new(Some(2), None, 0);
+        assert_eq!((empty.page, empty.pages, empty.next), (1, 1, None));
+        assert!(empty.slice(Vec::<u32>::new()).is_empty());
+    }
+}
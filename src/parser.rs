@@ -1,3 +1,275 @@
+use crate::{parse_date_bound, parse_duration_bound};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Prefix of the query terms searching the guests of the episodes, `guest:"alan pope"`
+pub const GUEST_FIELD: &str = "guest:";
+/// Prefix of the query terms keeping the episodes of a show, `show:lup`
+pub const SHOW_FIELD: &str = "show:";
+/// Prefix of the query terms searching the links of the show notes, `domain:github.com`
+pub const DOMAIN_FIELD: &str = "domain:";
+/// Prefix of the query terms only matching the tags, `tag:docker`
+pub const TAG_FIELD: &str = "tag:";
+/// Prefix of the query terms only matching the titles, `title:"self hosting"`
+pub const TITLE_FIELD: &str = "title:";
+/// Prefix of the query terms finding an episode by its id in the index, `id:512`
+pub const ID_FIELD: &str = "id:";
+/// Prefix of the query terms keeping the episodes published on or after a day, month or year,
+/// `after:2022-01-01`
+pub const AFTER_FIELD: &str = "after:";
+/// Prefix of the query terms keeping the episodes published before a day, month or year,
+/// `before:2023-06`
+pub const BEFORE_FIELD: &str = "before:";
+/// Prefix of the query terms keeping the episodes lasting at least a duration, `longer:60m`
+pub const LONGER_FIELD: &str = "longer:";
+/// Prefix of the query terms keeping the episodes lasting less than a duration, `shorter:45m`
+pub const SHORTER_FIELD: &str = "shorter:";
+
+/// The field a term of a query is scoped to, by its prefix
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryField {
+    Guest,
+    Show,
+    Domain,
+    Tag,
+    Title,
+    Id,
+    After,
+    Before,
+    Longer,
+    Shorter,
+}
+
+impl QueryField {
+    pub const ALL: [QueryField; 10] = [
+        QueryField::Guest,
+        QueryField::Show,
+        QueryField::Domain,
+        QueryField::Tag,
+        QueryField::Title,
+        QueryField::Id,
+        QueryField::After,
+        QueryField::Before,
+        QueryField::Longer,
+        QueryField::Shorter,
+    ];
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            QueryField::Guest => GUEST_FIELD,
+            QueryField::Show => SHOW_FIELD,
+            QueryField::Domain => DOMAIN_FIELD,
+            QueryField::Tag => TAG_FIELD,
+            QueryField::Title => TITLE_FIELD,
+            QueryField::Id => ID_FIELD,
+            QueryField::After => AFTER_FIELD,
+            QueryField::Before => BEFORE_FIELD,
+            QueryField::Longer => LONGER_FIELD,
+            QueryField::Shorter => SHORTER_FIELD,
+        }
+    }
+
+    /// Whether the value is a date of `after:` and `before:` or a duration of `longer:`
+    /// and `shorter:`, the other fields take any value
+    pub fn accepts(self, value: &str) -> bool {
+        match self {
+            QueryField::After | QueryField::Before => parse_date_bound(value).is_some(),
+            QueryField::Longer | QueryField::Shorter => parse_duration_bound(value).is_some(),
+            _ => true,
+        }
+    }
+
+    /// The field of a term and its value, `Title:self hosting` -> `(Title, "self hosting")`,
+    /// none for a plain term
+    pub fn split(term: &str) -> Option<(QueryField, &str)> {
+        QueryField::ALL.into_iter().find_map(|field| {
+            let prefix = field.prefix();
+            let value = term.get(prefix.len()..)?;
+
+            term[..prefix.len()]
+                .eq_ignore_ascii_case(prefix)
+                .then_some((field, value))
+        })
+    }
+}
+
+/// Version of the query language, the `qv` parameter of the search urls, so searches saved
+/// before an operator was added keep returning the same results
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum QueryVersion {
+    /// terms, quoted terms and `-` exclusions, `guest:nixos` and `#512` are plain terms
+    V1,
+    /// the `guest:`, `show:` and `domain:` fields and `#512` episode numbers
+    V2,
+    /// the `tag:`, `title:` and `id:` fields
+    V3,
+    /// `AND`, `OR` and parentheses
+    V4,
+    /// the `after:`, `before:`, `longer:` and `shorter:` fields
+    #[default]
+    V5,
+}
+
+impl QueryVersion {
+    pub const LATEST: QueryVersion = QueryVersion::V5;
+
+    pub fn number(self) -> u32 {
+        match self {
+            QueryVersion::V1 => 1,
+            QueryVersion::V2 => 2,
+            QueryVersion::V3 => 3,
+            QueryVersion::V4 => 4,
+            QueryVersion::V5 => 5,
+        }
+    }
+
+    /// Whether `field:value` terms and `#512` are operators rather than plain terms
+    pub fn has_fields(self) -> bool {
+        self >= QueryVersion::V2
+    }
+
+    /// Whether `tag:`, `title:` and `id:` scope a term to a field rather than being plain terms
+    pub fn has_scoped_terms(self) -> bool {
+        self >= QueryVersion::V3
+    }
+
+    /// Whether `AND`, `OR` and parentheses are operators rather than plain terms
+    pub fn has_boolean_operators(self) -> bool {
+        self >= QueryVersion::V4
+    }
+
+    /// Whether `after:`, `before:`, `longer:` and `shorter:` filter the dates and durations
+    /// rather than being plain terms
+    pub fn has_range_filters(self) -> bool {
+        self >= QueryVersion::V5
+    }
+}
+
+impl TryFrom<u32> for QueryVersion {
+    type Error = String;
+
+    fn try_from(number: u32) -> Result<Self, Self::Error> {
+        match number {
+            1 => Ok(QueryVersion::V1),
+            2 => Ok(QueryVersion::V2),
+            3 => Ok(QueryVersion::V3),
+            4 => Ok(QueryVersion::V4),
+            5 => Ok(QueryVersion::V5),
+            _ => Err(format!("unknown query language version {}", number)),
+        }
+    }
+}
+
+impl From<QueryVersion> for u32 {
+    fn from(version: QueryVersion) -> Self {
+        version.number()
+    }
+}
+
+/// An operator or a field of the query language, `/help` is generated from them
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct QuerySyntax {
+    /// how it's written
+    pub syntax: &'static str,
+    /// key of the message of the locales describing it
+    pub help: &'static str,
+    /// a query using it, linked from `/help`
+    pub example: &'static str,
+}
+
+/// The operators the parser and the search understand
+pub const QUERY_OPERATORS: [QuerySyntax; 6] = [
+    QuerySyntax {
+        syntax: "term",
+        help: "help-term",
+        example: "nixos",
+    },
+    QuerySyntax {
+        syntax: "\"two words\"",
+        help: "help-phrase",
+        example: "\"docker compose\"",
+    },
+    QuerySyntax {
+        syntax: "-term",
+        help: "help-exclude",
+        example: "linux -nixos -\"windows server\"",
+    },
+    QuerySyntax {
+        syntax: "#512",
+        help: "help-number",
+        example: "#512",
+    },
+    QuerySyntax {
+        syntax: "a AND b",
+        help: "help-and",
+        example: "nixos AND \"steam deck\"",
+    },
+    QuerySyntax {
+        syntax: "(a OR b)",
+        help: "help-group",
+        example: "(nixos OR fedora) AND gaming -flatpak",
+    },
+];
+
+/// The fields a term can be prefixed with to search a single field of the episodes
+pub const QUERY_FIELDS: [QuerySyntax; 10] = [
+    QuerySyntax {
+        syntax: GUEST_FIELD,
+        help: "help-guest",
+        example: "\"guest:alan pope\"",
+    },
+    QuerySyntax {
+        syntax: SHOW_FIELD,
+        help: "help-show",
+        example: "zfs show:lup",
+    },
+    QuerySyntax {
+        syntax: DOMAIN_FIELD,
+        help: "help-domain",
+        example: "domain:github.com",
+    },
+    QuerySyntax {
+        syntax: TAG_FIELD,
+        help: "help-tag",
+        example: "tag:docker",
+    },
+    QuerySyntax {
+        syntax: TITLE_FIELD,
+        help: "help-title",
+        example: "title:\"self hosting\"",
+    },
+    QuerySyntax {
+        syntax: ID_FIELD,
+        help: "help-id",
+        example: "id:512",
+    },
+    QuerySyntax {
+        syntax: AFTER_FIELD,
+        help: "help-after",
+        example: "nixos after:2022-01-01",
+    },
+    QuerySyntax {
+        syntax: BEFORE_FIELD,
+        help: "help-before",
+        example: "docker before:2023-06",
+    },
+    QuerySyntax {
+        syntax: LONGER_FIELD,
+        help: "help-longer",
+        example: "\"home lab\" longer:60m",
+    },
+    QuerySyntax {
+        syntax: SHORTER_FIELD,
+        help: "help-shorter",
+        example: "gaming shorter:45m",
+    },
+];
+
 pub struct QueryParser {
     pub index: usize,
     pub source: Vec<char>,
@@ -82,12 +354,11 @@ impl QueryParser {
 
         if ch == '-' {
             self.trim_while(|ch| ch == '-');
-            if self.peek_next().is_some() {
-                return self.get_token().map(|mut s| {
-                    s.insert(0, '-');
-                    s
-                });
-            }
+            // a dangling `-` at the end excludes nothing
+            return self.get_token().map(|mut s| {
+                s.insert(0, '-');
+                s
+            });
         } else if ch == '"' {
             // skip '"'
             self.advance();
@@ -101,6 +372,18 @@ impl QueryParser {
         } else if !ch.is_whitespace() {
             // accept all that isn't whitespace
             while self.is_not_end() && self.is_not_whitespace() {
+                // the quoted value of a field, `title:"self hosting"`
+                if self.peek() == '"' && token.ends_with(':') {
+                    self.advance();
+
+                    while self.is_not_end() && self.peek() != '"' {
+                        token.push(self.peek_advance());
+                    }
+
+                    self.advance();
+                    continue;
+                }
+
                 token.push(self.peek_advance());
             }
         }
@@ -132,16 +415,525 @@ pub struct ParseResult {
     pub exclude: Vec<String>,
 }
 
+impl ParseResult {
+    /// The terms with the field they're scoped to and their value, none for the plain terms
+    pub fn scoped_terms(&self) -> Vec<(Option<QueryField>, &str)> {
+        self.terms
+            .iter()
+            .map(|term| match QueryField::split(term) {
+                Some((field, value)) => (Some(field), value),
+                None => (None, term.as_str()),
+            })
+            .collect()
+    }
+}
+
+/// The canonical form of a query: lowercase, the whitespace collapsed, the terms then the
+/// exclusions sorted and without duplicates, `Backup  ZFS zfs` -> `backup zfs`,
+/// queries with the same canonical form return the same results, share their cached page
+/// and are counted together by the analytics
+pub fn normalize_query(query: &str) -> String {
+    // the order of the operators matters, only the terms are normalized
+    if is_boolean_query(query) {
+        return parse_query_expr(query)
+            .map(|expr| expr.to_string())
+            .unwrap_or_default();
+    }
+
+    let ParseResult { terms, exclude } = parse_query(&query.to_lowercase());
+
+    let canonical = |terms: Vec<String>| -> BTreeSet<String> {
+        terms
+            .iter()
+            .map(|term| term.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect()
+    };
+
+    // phrases keep their quotes, so does the empty phrase which matches every title
+    let quoted = |term: &String| {
+        if term.is_empty() || term.contains(' ') {
+            format!("\"{}\"", term)
+        } else {
+            term.clone()
+        }
+    };
+
+    canonical(terms)
+        .iter()
+        .map(quoted)
+        .chain(
+            canonical(exclude)
+                .iter()
+                .map(|term| format!("-{}", quoted(term))),
+        )
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A query with `AND`, `OR` and parentheses, `(nixos OR fedora) -flatpak`. Like in the other
+/// queries the terms next to each other match any of them and the `-` terms leave episodes out,
+/// `AND` binds tighter than `OR`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryExpr {
+    /// a term, a phrase or a `field:value`, matched like the terms of the other queries
+    Term(String),
+    /// `-term` or `-(...)`
+    Not(Box<QueryExpr>),
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+}
+
+impl QueryExpr {
+    /// The terms of the expression and the excluded ones, what the results are scored by
+    pub fn terms(&self) -> (Vec<String>, Vec<String>) {
+        let mut terms = (Vec::new(), Vec::new());
+        self.collect_terms(false, &mut terms);
+        terms
+    }
+
+    fn collect_terms(&self, negated: bool, terms: &mut (Vec<String>, Vec<String>)) {
+        match self {
+            QueryExpr::Term(term) if negated => terms.1.push(term.clone()),
+            QueryExpr::Term(term) => terms.0.push(term.clone()),
+            QueryExpr::Not(inner) => inner.collect_terms(!negated, terms),
+            QueryExpr::And(items) | QueryExpr::Or(items) => {
+                for item in items {
+                    item.collect_terms(negated, terms);
+                }
+            }
+        }
+    }
+}
+
+/// The canonical form of the expression, lowercase terms and uppercase operators
+impl fmt::Display for QueryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nested = |expr: &QueryExpr| match expr {
+            QueryExpr::And(_) | QueryExpr::Or(_) => format!("({})", expr),
+            _ => expr.to_string(),
+        };
+
+        match self {
+            QueryExpr::Term(term) => {
+                let term = term.split_whitespace().collect::<Vec<_>>().join(" ");
+                let term = term.to_lowercase();
+
+                if term.is_empty() || term.contains([' ', '(', ')']) {
+                    write!(f, "\"{}\"", term)
+                } else {
+                    write!(f, "{}", term)
+                }
+            }
+            QueryExpr::Not(inner) => write!(f, "-{}", nested(inner)),
+            QueryExpr::And(items) => {
+                let items: Vec<_> = items.iter().map(nested).collect();
+                write!(f, "{}", items.join(" AND "))
+            }
+            QueryExpr::Or(items) => {
+                let items: Vec<_> = items.iter().map(nested).collect();
+                write!(f, "{}", items.join(" OR "))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ExprToken {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+impl QueryParser {
+    // like `get_token`, the parentheses and the operators are tokens of their own
+    fn get_expr_token(&mut self) -> Option<ExprToken> {
+        self.trim_left();
+
+        if self.is_end() {
+            return None;
+        }
+
+        match self.peek() {
+            '(' => {
+                self.advance();
+                return Some(ExprToken::Open);
+            }
+            ')' => {
+                self.advance();
+                return Some(ExprToken::Close);
+            }
+            '-' => {
+                self.trim_while(|ch| ch == '-');
+                return Some(ExprToken::Not);
+            }
+            _ => {}
+        }
+
+        let mut token = String::new();
+        let mut quoted = false;
+
+        while self.is_not_end() && (quoted || !self.is_whitespace()) {
+            let ch = self.peek();
+
+            // a phrase, or the quoted value of a field, `title:"self hosting"`
+            if ch == '"' && (quoted || token.is_empty() || token.ends_with(':')) {
+                self.advance();
+                quoted = !quoted;
+                continue;
+            }
+
+            if !quoted && (ch == '(' || ch == ')') {
+                break;
+            }
+
+            token.push(self.peek_advance());
+        }
+
+        Some(match token.as_str() {
+            "AND" => ExprToken::And,
+            "OR" => ExprToken::Or,
+            _ => ExprToken::Term(token.trim().to_string()),
+        })
+    }
+}
+
+/// Whether the query uses `AND`, `OR` or parentheses
+pub fn is_boolean_query(query: &str) -> bool {
+    let mut parser = QueryParser::new(query);
+
+    std::iter::from_fn(|| parser.get_expr_token()).any(|token| {
+        matches!(
+            token,
+            ExprToken::Open | ExprToken::Close | ExprToken::And | ExprToken::Or
+        )
+    })
+}
+
+/// Parses a query with `AND`, `OR` and parentheses, none without terms,
+/// unclosed parentheses are closed at the end and the extra `)` are dropped
+pub fn parse_query_expr(query: &str) -> Option<QueryExpr> {
+    let mut parser = QueryParser::new(query);
+    let tokens = std::iter::from_fn(|| parser.get_expr_token()).collect();
+
+    ExprParser { tokens, index: 0 }.parse()
+}
+
+struct ExprParser {
+    tokens: Vec<ExprToken>,
+    index: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&ExprToken> {
+        self.tokens.get(self.index)
+    }
+
+    fn parse(&mut self) -> Option<QueryExpr> {
+        let mut items = Vec::new();
+
+        loop {
+            items.extend(self.any());
+
+            if self.peek().is_none() {
+                break;
+            }
+
+            // an extra `)`
+            self.index += 1;
+        }
+
+        group(items)
+    }
+
+    // terms next to each other or joined by `OR`, until the end of the group
+    fn any(&mut self) -> Option<QueryExpr> {
+        let mut items = Vec::new();
+
+        loop {
+            match self.peek() {
+                None | Some(ExprToken::Close) => break,
+                // `OR` is what the terms next to each other do, a leading `AND` joins nothing
+                Some(ExprToken::Or) | Some(ExprToken::And) => self.index += 1,
+                Some(_) => items.extend(self.all()),
+            }
+        }
+
+        group(items)
+    }
+
+    // terms joined by `AND`
+    fn all(&mut self) -> Option<QueryExpr> {
+        let mut items: Vec<_> = self.unary().into_iter().collect();
+
+        while self.peek() == Some(&ExprToken::And) {
+            self.index += 1;
+
+            match self.unary() {
+                Some(expr) => items.push(expr),
+                None => break,
+            }
+        }
+
+        match items.len() {
+            0 | 1 => items.pop(),
+            _ => Some(QueryExpr::And(items)),
+        }
+    }
+
+    fn unary(&mut self) -> Option<QueryExpr> {
+        match self.peek()?.clone() {
+            ExprToken::Not => {
+                self.index += 1;
+                self.unary().map(|expr| QueryExpr::Not(Box::new(expr)))
+            }
+            ExprToken::Open => {
+                self.index += 1;
+                let expr = self.any();
+
+                if self.peek() == Some(&ExprToken::Close) {
+                    self.index += 1;
+                }
+
+                expr
+            }
+            ExprToken::Term(term) => {
+                self.index += 1;
+                Some(QueryExpr::Term(term))
+            }
+            ExprToken::Close | ExprToken::And | ExprToken::Or => None,
+        }
+    }
+}
+
+// the terms of a group match any of them, its exclusions apply to all of them
+fn group(items: Vec<QueryExpr>) -> Option<QueryExpr> {
+    let (mut all, mut any): (Vec<_>, Vec<_>) = items
+        .into_iter()
+        .partition(|expr| matches!(expr, QueryExpr::Not(_)));
+
+    match any.len() {
+        0 => {}
+        1 => all.insert(0, any.remove(0)),
+        _ => all.insert(0, QueryExpr::Or(any)),
+    }
+
+    match all.len() {
+        0 | 1 => all.pop(),
+        _ => Some(QueryExpr::And(all)),
+    }
+}
+
 pub fn parse_query(query: &str) -> ParseResult {
     let mut parser = QueryParser::new(query);
     parser.parse()
 }
 
+/// The terms and the exclusions of a query, those of its expression when it uses
+/// `AND`, `OR` or parentheses
+pub fn query_terms(query: &str) -> ParseResult {
+    if !is_boolean_query(query) {
+        return parse_query(query);
+    }
+
+    let (terms, exclude) = parse_query_expr(query)
+        .map(|expr| expr.terms())
+        .unwrap_or_default();
+
+    ParseResult { terms, exclude }
+}
+
+/// Mistakes in a query that the lenient `parse_query` silently works around
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryError {
+    Empty,
+    /// a `"` at this char index is never closed
+    UnterminatedQuote(usize),
+    /// every term is excluded, e.g. `-nixos -fedora`
+    OnlyExclusions,
+    /// a `(` at this char index is never closed, or a `)` closes nothing
+    UnbalancedParenthesis(usize),
+    /// the date or the duration of a filter doesn't parse, e.g. `after:yesterday`
+    InvalidFilter(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Empty => write!(f, "The query is empty, type a tag or a title to search"),
+            QueryError::UnterminatedQuote(index) => write!(
+                f,
+                "The quote at character {} is never closed, e.g. \"docker compose\"",
+                index + 1
+            ),
+            QueryError::OnlyExclusions => write!(
+                f,
+                "The query only excludes tags, add a term to search, e.g. linux -nixos"
+            ),
+            QueryError::UnbalancedParenthesis(index) => write!(
+                f,
+                "The parenthesis at character {} has no match, e.g. (nixos OR fedora) -flatpak",
+                index + 1
+            ),
+            QueryError::InvalidFilter(term) => write!(
+                f,
+                "{} isn't a date or a duration, e.g. after:2022-01-01 or longer:60m",
+                term
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Parses the query reporting the mistakes instead of guessing what was meant
+pub fn parse_query_strict(query: &str) -> Result<ParseResult, QueryError> {
+    if query.trim().is_empty() {
+        return Err(QueryError::Empty);
+    }
+
+    let quotes: Vec<_> = query
+        .chars()
+        .enumerate()
+        .filter(|(_, ch)| *ch == '"')
+        .map(|(index, _)| index)
+        .collect();
+
+    if quotes.len() % 2 == 1 {
+        return Err(QueryError::UnterminatedQuote(quotes[quotes.len() - 1]));
+    }
+
+    if is_boolean_query(query) {
+        if let Some(index) = unbalanced_parenthesis(query) {
+            return Err(QueryError::UnbalancedParenthesis(index));
+        }
+
+        let (terms, _) = parse_query_expr(query)
+            .map(|expr| expr.terms())
+            .unwrap_or_default();
+
+        if terms.iter().all(|term| term.is_empty()) {
+            return Err(QueryError::OnlyExclusions);
+        }
+    }
+
+    let result = parse_query(query);
+
+    let invalid = result
+        .terms
+        .iter()
+        .chain(result.exclude.iter())
+        .find(|term| QueryField::split(term).is_some_and(|(field, value)| !field.accepts(value)));
+
+    if let Some(term) = invalid {
+        return Err(QueryError::InvalidFilter(term.clone()));
+    }
+
+    if result.terms.iter().all(|term| term.is_empty()) {
+        return Err(QueryError::OnlyExclusions);
+    }
+
+    Ok(result)
+}
+
+// the char index of the first parenthesis without a match, outside the quotes
+fn unbalanced_parenthesis(query: &str) -> Option<usize> {
+    let mut open = Vec::new();
+    let mut quoted = false;
+
+    for (index, ch) in query.chars().enumerate() {
+        match ch {
+            '"' => quoted = !quoted,
+            '(' if !quoted => open.push(index),
+            ')' if !quoted && open.pop().is_none() => return Some(index),
+            _ => {}
+        }
+    }
+
+    open.first().copied()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::ParseResult;
 
-    use super::QueryParser;
+    use super::{
+        is_boolean_query, normalize_query, parse_query, parse_query_expr, parse_query_strict,
+        QueryError, QueryExpr, QueryField, QueryParser, QUERY_FIELDS, QUERY_OPERATORS,
+    };
+
+    #[test]
+    fn test_query_syntax_examples() {
+        for syntax in QUERY_OPERATORS.iter().chain(QUERY_FIELDS.iter()) {
+            assert!(
+                parse_query_strict(syntax.example).is_ok(),
+                "{}",
+                syntax.example
+            );
+        }
+
+        // the examples of the fields search with the field
+        for field in QUERY_FIELDS {
+            let ParseResult { terms, .. } = parse_query(field.example);
+            assert!(
+                terms.iter().any(|term| term.starts_with(field.syntax)),
+                "{}",
+                field.example
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_query_strict() {
+        assert_eq!(parse_query_strict("  ").err(), Some(QueryError::Empty));
+        assert_eq!(
+            parse_query_strict("docker \"remote desktop").err(),
+            Some(QueryError::UnterminatedQuote(7))
+        );
+        assert_eq!(
+            parse_query_strict("-nixos -\"windows server\"").err(),
+            Some(QueryError::OnlyExclusions)
+        );
+        assert!(parse_query_strict("docker -nixos").is_ok());
+        assert_eq!(
+            parse_query_strict("nixos After:yesterday").err(),
+            Some(QueryError::InvalidFilter(String::from("After:yesterday")))
+        );
+        assert_eq!(
+            parse_query_strict("nixos -longer:forever").err(),
+            Some(QueryError::InvalidFilter(String::from("longer:forever")))
+        );
+        assert!(parse_query_strict("nixos after:2022 shorter:1h30m").is_ok());
+    }
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(
+            normalize_query("  Docker   \"Remote  Desktop\" -NixOS "),
+            "docker \"remote desktop\" -nixos"
+        );
+        assert_eq!(
+            normalize_query("ZFS backup"),
+            normalize_query("backup zfs zfs")
+        );
+        assert_eq!(
+            normalize_query("-\"windows  server\" linux -nixos -nixos guest:wendell"),
+            "guest:wendell linux -nixos -\"windows server\""
+        );
+        assert_eq!(
+            normalize_query("docker \"remote desktop"),
+            "docker \"remote desktop\""
+        );
+        assert_eq!(normalize_query("  "), "");
+
+        // the canonical form is its own canonical form
+        for query in ["\"\" zfs", "#512 -\"a b\" \"c\"", "- -x y"] {
+            let canonical = normalize_query(query);
+            assert_eq!(normalize_query(&canonical), canonical, "{}", query);
+        }
+    }
 
     #[test]
     fn test_get_token() {
@@ -158,6 +950,103 @@ mod tests {
         assert_eq!(parser.get_token(), None);
     }
 
+    #[test]
+    fn test_scoped_terms() {
+        let result = parse_query("tag:docker Title:\"self hosting\" id:512 zfs -tag:nixos");
+
+        assert_eq!(
+            result.scoped_terms(),
+            vec![
+                (Some(QueryField::Tag), "docker"),
+                (Some(QueryField::Title), "self hosting"),
+                (Some(QueryField::Id), "512"),
+                (None, "zfs"),
+            ]
+        );
+        assert_eq!(result.exclude, vec!["tag:nixos"]);
+        assert_eq!(QueryField::split("guest:"), Some((QueryField::Guest, "")));
+        assert_eq!(QueryField::split("id"), None);
+    }
+
+    #[test]
+    fn test_parse_query_expr() {
+        use QueryExpr::{And, Not, Or};
+        let term = |term: &str| QueryExpr::Term(String::from(term));
+        let not = |expr| Not(Box::new(expr));
+
+        assert_eq!(
+            parse_query_expr("(nixos OR fedora) -flatpak"),
+            Some(And(vec![
+                Or(vec![term("nixos"), term("fedora")]),
+                not(term("flatpak"))
+            ]))
+        );
+        assert_eq!(
+            parse_query_expr("zfs AND \"steam deck\" OR arch"),
+            Some(Or(vec![
+                And(vec![term("zfs"), term("steam deck")]),
+                term("arch")
+            ]))
+        );
+        assert_eq!(
+            parse_query_expr("-(gnome OR kde) title:\"self hosting\""),
+            Some(And(vec![
+                term("title:self hosting"),
+                not(Or(vec![term("gnome"), term("kde")]))
+            ]))
+        );
+
+        // unclosed groups end with the query, extra parentheses and operators are dropped
+        assert_eq!(
+            parse_query_expr("(nixos AND (zfs OR btrfs"),
+            parse_query_expr("(nixos AND (zfs OR btrfs))")
+        );
+        assert_eq!(parse_query_expr(") AND nixos OR"), Some(term("nixos")));
+        assert_eq!(parse_query_expr("( )"), None);
+
+        assert_eq!(
+            parse_query_expr("(a OR -b) AND c").unwrap().terms(),
+            (
+                vec![String::from("a"), String::from("c")],
+                vec![String::from("b")]
+            )
+        );
+        assert!(is_boolean_query("(nixos)"));
+        assert!(!is_boolean_query("nixos and fedora \"(draft)\""));
+    }
+
+    #[test]
+    fn test_boolean_query_errors_and_canonical_form() {
+        assert_eq!(
+            parse_query_strict("(nixos OR fedora").err(),
+            Some(QueryError::UnbalancedParenthesis(0))
+        );
+        assert_eq!(
+            parse_query_strict("nixos) OR fedora").err(),
+            Some(QueryError::UnbalancedParenthesis(5))
+        );
+        assert_eq!(
+            parse_query_strict("-(nixos OR fedora)").err(),
+            Some(QueryError::OnlyExclusions)
+        );
+        assert!(parse_query_strict("\"(draft\" nixos").is_ok());
+
+        assert_eq!(
+            normalize_query("(NixOS  OR \"Steam  Deck\") AND -flatpak"),
+            "(nixos OR \"steam deck\") AND -flatpak"
+        );
+        // the operators keep their order, only the terms are normalized
+        assert_ne!(
+            normalize_query("(a OR b) AND c"),
+            normalize_query("(a AND b) OR c")
+        );
+
+        for query in ["(a OR b) -c", "a AND (b OR -\"c d\")", "-(a) b"] {
+            let canonical = normalize_query(query);
+            assert_eq!(normalize_query(&canonical), canonical, "{}", query);
+        }
+    }
+
     // cargo test -- parser::tests::test_parse --exact --nocapture
     #[test]
     fn test_parse() {
@@ -170,15 +1059,30 @@ mod tests {
 
         println!("results\nterms: {:?}\nexclude: {:?}", &terms, &exclude);
 
-        assert!(terms.len() > 0);
-        assert!(exclude.len() == 2);
+        assert_eq!(
+            terms,
+            vec![
+                String::from("docker compose"),
+                String::from("chocolate cupcakes"),
+                String::from("😸😸😸"),
+            ]
+        );
+        assert_eq!(
+            exclude,
+            vec![String::from("dotnet"), String::from("windows server")]
+        );
+    }
 
-        assert_eq!(terms[0], String::from("docker compose"));
-        assert_eq!(terms[1], String::from("chocolate cupcakes"));
-        assert_eq!(terms[2], String::from("😸😸😸"));
+    #[test]
+    fn test_parse_blank() {
+        // the query of a feed or a page without `?query=`
+        for query in ["", "   ", " - -- "] {
+            let mut parser = QueryParser::new(query);
+            let ParseResult { terms, exclude } = parser.parse();
 
-        assert_eq!(exclude[0], String::from("dotnet"));
-        assert_eq!(exclude[1], String::from("windows server"));
+            assert!(terms.is_empty(), "{:?}", query);
+            assert!(exclude.is_empty(), "{:?}", query);
+        }
     }
 
     #[test]
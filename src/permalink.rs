@@ -0,0 +1,112 @@
+use crate::{encode_query_component, normalize_query, QueryVersion, SortOrder};
+use serde::Deserialize;
+
+/// The state of a search that round-trips through its url so results can be shared,
+/// the terms, exclusions and quoted tags all live in the query
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct SearchParams {
+    pub query: String,
+    /// the version of the query language the query was written in, the latest by default
+    pub qv: QueryVersion,
+    /// the order of the results, by relevance by default
+    pub sort: SortOrder,
+}
+
+impl SearchParams {
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            qv: QueryVersion::LATEST,
+            sort: SortOrder::Relevance,
+        }
+    }
+
+    /// The same search with the parameters in their normalized form,
+    /// searches returning the same results share it
+    pub fn canonical(&self) -> Self {
+        Self {
+            query: normalize_query(&self.query),
+            qv: self.qv,
+            sort: self.sort,
+        }
+    }
+
+    /// `query=nixos%20-fedora`, the canonical parameters in a fixed order,
+    /// the ones with their default value left out
+    pub fn to_query_string(&self) -> String {
+        let canonical = self.canonical();
+        let mut params = Vec::new();
+
+        if !canonical.query.is_empty() {
+            params.push(format!(
+                "query={}",
+                encode_query_component(&canonical.query)
+            ));
+        }
+
+        if canonical.qv != QueryVersion::LATEST {
+            params.push(format!("qv={}", canonical.qv.number()));
+        }
+
+        if canonical.sort != SortOrder::Relevance {
+            params.push(format!("sort={}", canonical.sort.name()));
+        }
+
+        params.join("&")
+    }
+}
+
+/// Url of the results page of a search, relative to `base` (the mount path of the show)
+pub fn search_permalink(base: &str, params: &SearchParams) -> String {
+    let query_string = params.to_query_string();
+
+    if query_string.is_empty() {
+        format!("{}/", base)
+    } else {
+        format!("{}/search?{}", base, query_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{search_permalink, SearchParams};
+    use crate::{QueryVersion, SortOrder};
+
+    #[test]
+    fn test_canonical_query_string() {
+        let params: SearchParams =
+            serde_urlencoded::from_str("query=NixOS++%22Docker%20Compose%22&partial=true").unwrap();
+
+        assert_eq!(params.query, "NixOS  \"Docker Compose\"");
+        assert_eq!(
+            params.to_query_string(),
+            "query=%22docker%20compose%22%20nixos"
+        );
+
+        // the spelling of the query doesn't change the link
+        assert_eq!(
+            search_permalink("/lup", &params),
+            search_permalink("/lup", &SearchParams::new("nixos \"docker compose\""))
+        );
+        assert_eq!(search_permalink("", &SearchParams::new("  ")), "/");
+
+        // searches of an older query language keep their version
+        let legacy: SearchParams = serde_urlencoded::from_str("query=guest%3Ajim&qv=1").unwrap();
+        assert_eq!(legacy.qv, QueryVersion::V1);
+        assert_eq!(legacy.to_query_string(), "query=guest%3Ajim&qv=1");
+        assert_eq!(
+            serde_urlencoded::from_str::<SearchParams>("query=nixos&qv=5")
+                .unwrap()
+                .to_query_string(),
+            "query=nixos"
+        );
+        assert!(serde_urlencoded::from_str::<SearchParams>("query=nixos&qv=9").is_err());
+
+        // the order of the results is part of the link
+        let newest: SearchParams =
+            serde_urlencoded::from_str("sort=newest&qv=1&query=nixos").unwrap();
+        assert_eq!(newest.sort, SortOrder::Newest);
+        assert_eq!(newest.to_query_string(), "query=nixos&qv=1&sort=newest");
+    }
+}
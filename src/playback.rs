@@ -0,0 +1,285 @@
+use crate::SaveError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tokio::sync::Mutex;
+
+// the least played episodes of a listener are forgotten past it
+const PLAYBACK_MAX_EPISODES: usize = 1_000;
+
+/// Where a listener stopped an episode
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaybackPosition {
+    /// seconds from the start
+    pub position: u32,
+    /// unix seconds
+    pub updated_at: i64,
+}
+
+/// Start of the keys of the episodes of a show in the playback positions, `lup/`,
+/// empty in a single show archive
+pub fn playback_prefix(show: &str) -> String {
+    if show.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", show)
+    }
+}
+
+/// Key of an episode of a show in the playback positions, `lup/512`, `512` in a single show archive
+pub fn playback_key(show: &str, id: usize) -> String {
+    format!("{}{}", playback_prefix(show), id)
+}
+
+/// The anonymous tokens the players generate and share between devices,
+/// 16 to 64 letters, digits, `-` or `_`
+pub fn is_valid_playback_token(token: &str) -> bool {
+    (16..=64).contains(&token.len())
+        && token
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+type Listeners = HashMap<String, HashMap<String, PlaybackPosition>>;
+
+/// Playback positions by listener token and episode, persisted as a json file
+pub struct PlaybackPositions {
+    path: PathBuf,
+    max_listeners: usize,
+    listeners: RwLock<Listeners>,
+    /// one write of the file at a time
+    write: Mutex<()>,
+}
+
+impl PlaybackPositions {
+    /// Loads the positions of `path`, none when the file doesn't exist yet
+    pub async fn load(path: &Path, max_listeners: usize) -> std::io::Result<Self> {
+        let listeners = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_listeners,
+            listeners: RwLock::new(listeners),
+            write: Mutex::new(()),
+        })
+    }
+
+    pub fn get(&self, token: &str, key: &str) -> Option<PlaybackPosition> {
+        self.listeners.read().unwrap().get(token)?.get(key).copied()
+    }
+
+    /// The positions of a listener whose keys start with `prefix`, by key
+    pub fn listener(&self, token: &str, prefix: &str) -> HashMap<String, PlaybackPosition> {
+        self.listeners
+            .read()
+            .unwrap()
+            .get(token)
+            .map(|positions| {
+                positions
+                    .iter()
+                    .filter(|(key, _)| key.starts_with(prefix))
+                    .map(|(key, position)| (key.clone(), *position))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.listeners.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub async fn set(
+        &self,
+        token: &str,
+        key: String,
+        position: PlaybackPosition,
+    ) -> Result<(), SaveError> {
+        let _write = self.write.lock().await;
+
+        let contents = {
+            let mut listeners = self.listeners.write().unwrap();
+
+            if !listeners.contains_key(token) && listeners.len() >= self.max_listeners {
+                return Err(SaveError::Full);
+            }
+
+            let positions = listeners.entry(token.to_string()).or_default();
+            positions.insert(key, position);
+
+            if positions.len() > PLAYBACK_MAX_EPISODES {
+                let oldest = positions
+                    .iter()
+                    .min_by_key(|(_, position)| position.updated_at)
+                    .map(|(key, _)| key.clone());
+                positions.remove(&oldest.unwrap_or_default());
+            }
+
+            serde_json::to_string(&*listeners).map_err(|err| SaveError::Io(err.into()))?
+        };
+
+        self.persist(contents).await
+    }
+
+    /// Forgets the position of an episode, false when there was none
+    pub async fn remove(&self, token: &str, key: &str) -> Result<bool, SaveError> {
+        let _write = self.write.lock().await;
+
+        let contents = {
+            let mut listeners = self.listeners.write().unwrap();
+
+            let Some(positions) = listeners.get_mut(token) else {
+                return Ok(false);
+            };
+
+            if positions.remove(key).is_none() {
+                return Ok(false);
+            }
+
+            if positions.is_empty() {
+                listeners.remove(token);
+            }
+
+            serde_json::to_string(&*listeners).map_err(|err| SaveError::Io(err.into()))?
+        };
+
+        self.persist(contents).await.map(|_| true)
+    }
+
+    /// Every position by listener token, as the file stores them
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&*self.listeners.read().unwrap())
+    }
+
+    /// Replaces every position, e.g. with the ones of a backup
+    pub async fn replace_all(
+        &self,
+        listeners: HashMap<String, HashMap<String, PlaybackPosition>>,
+    ) -> Result<(), SaveError> {
+        if listeners.len() > self.max_listeners {
+            return Err(SaveError::Full);
+        }
+
+        let _write = self.write.lock().await;
+        let contents =
+            serde_json::to_string(&listeners).map_err(|err| SaveError::Io(err.into()))?;
+
+        self.persist(contents).await?;
+        *self.listeners.write().unwrap() = listeners;
+
+        Ok(())
+    }
+
+    async fn persist(&self, contents: String) -> Result<(), SaveError> {
+        // written next to the file and renamed, a crash never leaves half a file
+        let tmp = PathBuf::from(format!("{}.tmp", self.path.display()));
+        tokio::fs::write(&tmp, contents)
+            .await
+            .map_err(SaveError::Io)?;
+        tokio::fs::rename(&tmp, &self.path)
+            .await
+            .map_err(SaveError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_valid_playback_token, playback_key, playback_prefix, PlaybackPosition, PlaybackPositions,
+    };
+    use crate::SaveError;
+
+    #[tokio::test]
+    async fn test_playback_positions() {
+        let dir = std::env::temp_dir().join(format!("unplugged-playback-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("playback.json");
+
+        let position = |position, updated_at| PlaybackPosition {
+            position,
+            updated_at,
+        };
+        let (alice, bob) = ("0123456789abcdef", "fedcba9876543210");
+
+        let positions = PlaybackPositions::load(&path, 1).await.unwrap();
+        assert!(positions.is_empty());
+
+        positions
+            .set(alice, playback_key("lup", 512), position(754, 1680000000))
+            .await
+            .unwrap();
+        positions
+            .set(alice, playback_key("coder", 512), position(60, 1680000001))
+            .await
+            .unwrap();
+        positions
+            .set(alice, playback_key("lup", 512), position(900, 1680000002))
+            .await
+            .unwrap();
+        assert!(matches!(
+            positions
+                .set(bob, playback_key("lup", 512), position(1, 1680000003))
+                .await,
+            Err(SaveError::Full)
+        ));
+
+        // persisted
+        let reloaded = PlaybackPositions::load(&path, 1).await.unwrap();
+        assert_eq!(
+            reloaded.get(alice, "lup/512"),
+            Some(position(900, 1680000002))
+        );
+        assert_eq!(reloaded.get(bob, "lup/512"), None);
+        assert_eq!(
+            reloaded
+                .listener(alice, &playback_prefix("lup"))
+                .into_keys()
+                .collect::<Vec<_>>(),
+            vec!["lup/512"]
+        );
+        assert_eq!(reloaded.listener(alice, "").len(), 2);
+
+        assert!(positions.remove(alice, "lup/512").await.unwrap());
+        assert!(!positions.remove(alice, "lup/512").await.unwrap());
+        assert!(positions.remove(alice, "coder/512").await.unwrap());
+        assert!(PlaybackPositions::load(&path, 1).await.unwrap().is_empty());
+
+        // restored from a backup
+        let backup = reloaded.to_json().unwrap();
+        positions
+            .replace_all(serde_json::from_str(&backup).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            PlaybackPositions::load(&path, 1)
+                .await
+                .unwrap()
+                .get(alice, "coder/512"),
+            Some(position(60, 1680000001))
+        );
+        let two = serde_json::from_str(r#"{"a": {}, "b": {}}"#).unwrap();
+        assert!(matches!(
+            positions.replace_all(two).await,
+            Err(SaveError::Full)
+        ));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_playback_token() {
+        assert_eq!(playback_key("", 512), "512");
+        assert!(is_valid_playback_token("4f9c2a1e-7b3d-4c55"));
+        assert!(!is_valid_playback_token("short"));
+        assert!(!is_valid_playback_token("0123456789abcdef/.."));
+        assert!(!is_valid_playback_token(&"a".repeat(65)));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{encode_query_component, PodcastConfig};
+use serde::Serialize;
+
+/// An "open in" link of a podcast app
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct AppLink {
+    pub app: &'static str,
+    pub url: String,
+}
+
+/// The podcast apps that can open the show, Pocket Casts and Overcast resolve
+/// the Apple Podcasts id and subscribe to the feed without it, `podcast://`
+/// opens the default podcast app of the device
+pub fn podcast_app_links(podcast: &PodcastConfig) -> Vec<AppLink> {
+    let apple_id = &podcast.apple_podcasts_id;
+    let feed = podcast
+        .feed_url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+
+    let mut links = Vec::new();
+
+    if !apple_id.is_empty() {
+        links.push(AppLink {
+            app: "Apple Podcasts",
+            url: format!("https://podcasts.apple.com/podcast/id{}", apple_id),
+        });
+        links.push(AppLink {
+            app: "Pocket Casts",
+            url: format!("https://pca.st/itunes/{}", apple_id),
+        });
+        links.push(AppLink {
+            app: "Overcast",
+            url: format!("https://overcast.fm/itunes{}", apple_id),
+        });
+    } else if !feed.is_empty() {
+        links.push(AppLink {
+            app: "Pocket Casts",
+            url: format!("pktc://subscribe/{}", feed),
+        });
+        links.push(AppLink {
+            app: "Overcast",
+            url: format!(
+                "overcast://x-callback-url/add?url={}",
+                encode_query_component(&podcast.feed_url)
+            ),
+        });
+    }
+
+    if !feed.is_empty() {
+        links.push(AppLink {
+            app: "podcast",
+            url: format!("podcast://{}", feed),
+        });
+    }
+
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::podcast_app_links;
+    use crate::PodcastConfig;
+
+    #[test]
+    fn test_podcast_app_links() {
+        let links = |feed_url: &str, apple_podcasts_id: &str| -> Vec<String> {
+            let podcast = PodcastConfig {
+                feed_url: feed_url.to_string(),
+                apple_podcasts_id: apple_podcasts_id.to_string(),
+                ..Default::default()
+            };
+            podcast_app_links(&podcast)
+                .into_iter()
+                .map(|link| link.url)
+                .collect()
+        };
+
+        assert_eq!(
+            links("https://feeds.example.com/lup", "687598126"),
+            vec![
+                "https://podcasts.apple.com/podcast/id687598126",
+                "https://pca.st/itunes/687598126",
+                "https://overcast.fm/itunes687598126",
+                "podcast://feeds.example.com/lup",
+            ]
+        );
+        assert_eq!(
+            links("https://feeds.example.com/lup", ""),
+            vec![
+                "pktc://subscribe/feeds.example.com/lup",
+                "overcast://x-callback-url/add?url=https%3A%2F%2Ffeeds.example.com%2Flup",
+                "podcast://feeds.example.com/lup",
+            ]
+        );
+        assert!(links("", "").is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::error;
+use unplugged_engine::{
+    is_valid_playback_token, playback_key, playback_prefix, PlaybackPosition, PlaybackPositions,
+    SaveError,
+};
+
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct Progress {
+    /// seconds from the start
+    pub position: u32,
+}
+
+// the store of the positions, none when playback.file isn't set or the token is malformed
+fn positions<'a>(
+    state: &'a AppState,
+    token: &str,
+) -> Result<&'a Arc<PlaybackPositions>, (StatusCode, &'static str)> {
+    let Some(positions) = &state.playback else {
+        return Err((StatusCode::NOT_FOUND, "playback positions are disabled"));
+    };
+
+    if !is_valid_playback_token(token) {
+        return Err((StatusCode::BAD_REQUEST, "malformed playback token"));
+    }
+
+    Ok(positions)
+}
+
+// playback/<token>, the positions of the listener in the episodes of the show by id
+pub async fn handle_playback_positions(
+    Path(token): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let positions = match positions(&state, &token) {
+        Ok(positions) => positions,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let prefix = playback_prefix(&state.show.slug);
+
+    let by_id: HashMap<_, _> = positions
+        .listener(&token, &prefix)
+        .into_iter()
+        .map(|(key, position)| (key[prefix.len()..].to_string(), position))
+        .collect();
+
+    Json(by_id).into_response()
+}
+
+// playback/<token>/<id>, where the listener stopped the episode
+pub async fn handle_playback_position(
+    Path((token, id)): Path<(String, usize)>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let positions = match positions(&state, &token) {
+        Ok(positions) => positions,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    match positions.get(&token, &playback_key(&state.show.slug, id)) {
+        Some(position) => Json(position).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// PUT playback/<token>/<id>, json: {"position": 754}
+pub async fn handle_save_playback_position(
+    Path((token, id)): Path<(String, usize)>,
+    State(state): State<Arc<AppState>>,
+    Json(progress): Json<Progress>,
+) -> Response {
+    let positions = match positions(&state, &token) {
+        Ok(positions) => positions,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    if !state.index().episodes_by_id.contains_key(&id) {
+        return (StatusCode::BAD_REQUEST, "unknown episode").into_response();
+    }
+
+    let position = PlaybackPosition {
+        position: progress.position,
+        updated_at: Utc::now().timestamp(),
+    };
+
+    match positions
+        .set(&token, playback_key(&state.show.slug, id), position)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(SaveError::Full) => {
+            error!("Error at saving a playback position: playback.max_listeners reached");
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+        Err(err) => {
+            error!("Error at writing playback.file: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// DELETE playback/<token>/<id>, forgets the position once the episode is over
+pub async fn handle_delete_playback_position(
+    Path((token, id)): Path<(String, usize)>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let positions = match positions(&state, &token) {
+        Ok(positions) => positions,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    match positions
+        .remove(&token, &playback_key(&state.show.slug, id))
+        .await
+    {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            error!("Error at writing playback.file: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use axum::extract::{ConnectInfo, State};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Response};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, RwLock};
+use tera::Context;
+use tracing::{error, warn};
+
+use crate::api_keys::ApiClient;
+use crate::assets::error_chain;
+use crate::request_log::request_id;
+use crate::AppState;
+
+/// Token bucket per client ip
+pub struct IpRateLimiter {
+    /// replaced when the config is reloaded
+    limiter: RwLock<DefaultKeyedRateLimiter<IpAddr>>,
+}
+
+fn keyed_limiter(requests_per_second: u32, burst: u32) -> DefaultKeyedRateLimiter<IpAddr> {
+    let per_second = NonZeroU32::new(requests_per_second).unwrap_or(NonZeroU32::MIN);
+    let burst = NonZeroU32::new(burst).unwrap_or(per_second);
+
+    RateLimiter::keyed(Quota::per_second(per_second).allow_burst(burst))
+}
+
+impl IpRateLimiter {
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        Self {
+            limiter: RwLock::new(keyed_limiter(requests_per_second, burst)),
+        }
+    }
+
+    /// Applies a new quota, every ip starts again with a full bucket
+    pub fn set_quota(&self, requests_per_second: u32, burst: u32) {
+        *self.limiter.write().unwrap() = keyed_limiter(requests_per_second, burst);
+    }
+
+    /// Takes a token for the ip, or returns how many seconds until the next one
+    pub fn check(&self, ip: IpAddr) -> Result<(), u64> {
+        self.limiter
+            .read()
+            .unwrap()
+            .check_key(&ip)
+            .map_err(|not_until| {
+                let wait = not_until.wait_time_from(DefaultClock::default().now());
+                wait.as_secs().max(1)
+            })
+    }
+
+    /// Forgets the buckets that are full again, keeps memory bounded
+    pub fn retain_recent(&self) {
+        self.limiter.read().unwrap().retain_recent();
+    }
+}
+
+pub async fn rate_limit<B>(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // api requests with a key have the rate limit of the key
+    if request.extensions().get::<ApiClient>().is_some() {
+        return next.run(request).await;
+    }
+
+    match state.rate_limiter.check(addr.ip()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            warn!("Rate limited {} ({})", addr.ip(), request.uri());
+
+            let lang = state.templates.negotiate(request.headers());
+
+            let context = Context::from_serialize(serde_json::json!({
+                "retry_after": retry_after,
+                "request_id": request_id(&request),
+                "base_path": state.mount_path,
+                "lang": lang,
+            }));
+
+            let html = context
+                .and_then(|context| state.templates.render("rate_limited.html", &context))
+                .unwrap_or_else(|err| {
+                    error!(
+                        "Error at rendering rate_limited.html: {}",
+                        error_chain(&err)
+                    );
+                    String::from("Too many requests")
+                });
+
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Html(html),
+            )
+                .into_response()
+        }
+    }
+}
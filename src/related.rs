@@ -0,0 +1,182 @@
+use crate::{Episode, EpisodesById, EpisodesByTag};
+use std::collections::{HashMap, HashSet};
+
+/// How many related episodes `index build` stores per episode
+pub const RELATED_EPISODES: usize = 5;
+
+// a word shared by two titles is worth half a tag shared as rare
+const TITLE_WORD_WEIGHT: f64 = 0.5;
+// shorter words of the titles ("the", "on") say nothing about the episodes
+const MIN_TITLE_WORD_LEN: usize = 4;
+
+// the lowercase words of a title worth comparing, without the numbers
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TITLE_WORD_LEN)
+        .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+        .map(String::from)
+        .collect()
+}
+
+// the episodes of every word of the titles
+fn title_postings(by_id: &EpisodesById) -> HashMap<String, Vec<usize>> {
+    let mut postings: HashMap<String, Vec<usize>> = HashMap::new();
+
+    for (id, episode) in by_id.iter() {
+        for word in title_words(&episode.title) {
+            postings.entry(word).or_default().push(*id);
+        }
+    }
+
+    postings
+}
+
+// the ids of the episodes most like `episode`, by the tags and the words of the titles
+// they share, the rarer ones weigh more, ties go to the newest episode
+fn most_alike(
+    id: usize,
+    episode: &Episode,
+    by_tag: &EpisodesByTag,
+    titles: &HashMap<String, Vec<usize>>,
+    limit: usize,
+) -> Vec<i64> {
+    let mut scores: HashMap<usize, f64> = HashMap::new();
+
+    let tags = episode
+        .tags
+        .iter()
+        .filter_map(|tag| by_tag.get(&tag.name.trim().to_lowercase()))
+        .map(|ids| (ids, 1.0));
+    let words = title_words(&episode.title)
+        .into_iter()
+        .filter_map(|word| titles.get(&word))
+        .map(|ids| (ids, TITLE_WORD_WEIGHT));
+
+    for (ids, weight) in tags.chain(words) {
+        // a tag of every episode says nothing about them
+        let weight = weight / ids.len() as f64;
+
+        for other in ids.iter().filter(|other| **other != id) {
+            *scores.entry(*other).or_default() += weight;
+        }
+    }
+
+    let mut scores: Vec<_> = scores.into_iter().collect();
+    scores.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then(b_id.cmp(a_id)));
+
+    scores
+        .into_iter()
+        .take(limit)
+        .map(|(other, _)| other as i64)
+        .collect()
+}
+
+/// The ids of the episodes sharing the most tags and words of the title with each episode,
+/// rarer tags and words weigh more, ties go to the newest episode
+pub fn related_episodes(
+    by_id: &EpisodesById,
+    by_tag: &EpisodesByTag,
+    limit: usize,
+) -> HashMap<usize, Vec<i64>> {
+    let titles = title_postings(by_id);
+
+    by_id
+        .iter()
+        .map(|(id, episode)| (*id, most_alike(*id, episode, by_tag, &titles, limit)))
+        .collect()
+}
+
+/// The related episodes of a single episode, scored like `related_episodes`,
+/// none when the index doesn't have it
+pub fn related_to(
+    by_id: &EpisodesById,
+    by_tag: &EpisodesByTag,
+    id: usize,
+    limit: usize,
+) -> Option<Vec<i64>> {
+    let episode = by_id.get(&id)?;
+
+    Some(most_alike(
+        id,
+        episode,
+        by_tag,
+        &title_postings(by_id),
+        limit,
+    ))
+}
+
+/// Stores the related episodes in the episodes, replacing the ones of an earlier build
+pub fn add_related(by_id: &mut EpisodesById, by_tag: &EpisodesByTag) {
+    let mut related = related_episodes(by_id, by_tag, RELATED_EPISODES);
+
+    for (id, episode) in by_id.iter_mut() {
+        episode.related = related.remove(id).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{related_episodes, related_to};
+    use crate::{build_episodes_by_tag, Episode, EpisodesById, Tag};
+
+    fn episode(id: i64, tags: &[&str]) -> Episode {
+        Episode {
+            id,
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_related_episodes() {
+        let by_id: EpisodesById = [
+            episode(1, &["linux", "zfs", "nas"]),
+            episode(2, &["Linux", "zfs"]),
+            episode(3, &["linux", "nas"]),
+            episode(4, &["linux"]),
+            episode(5, &["gaming"]),
+        ]
+        .into_iter()
+        .map(|episode| (episode.id as usize, episode))
+        .collect();
+        let by_tag = build_episodes_by_tag(&by_id);
+
+        let related = related_episodes(&by_id, &by_tag, 2);
+
+        // zfs and nas are rarer than linux
+        assert_eq!(related[&1], vec![3, 2]);
+        assert_eq!(related[&2], vec![1, 4]);
+        assert_eq!(related[&4], vec![3, 2]);
+        assert!(related[&5].is_empty());
+    }
+
+    #[test]
+    fn test_related_to() {
+        let titled = |id: i64, title: &str, tags: &[&str]| Episode {
+            title: String::from(title),
+            ..episode(id, tags)
+        };
+        let by_id: EpisodesById = [
+            titled(1, "490: Steam Deck Review", &["gaming"]),
+            titled(2, "491: The Steam Deck, a Year Later", &[]),
+            titled(3, "492: Gaming on Linux", &["gaming"]),
+            titled(4, "493: The Review of the Year", &[]),
+        ]
+        .into_iter()
+        .map(|episode| (episode.id as usize, episode))
+        .collect();
+        let by_tag = build_episodes_by_tag(&by_id);
+
+        // two shared words are worth a shared tag, the ties go to the newest episode,
+        // the numbers and the short words ("the", "of") aren't compared
+        assert_eq!(related_to(&by_id, &by_tag, 1, 5), Some(vec![3, 2, 4]));
+        assert_eq!(related_to(&by_id, &by_tag, 2, 1), Some(vec![1]));
+        assert_eq!(related_to(&by_id, &by_tag, 9, 5), None);
+        assert_eq!(
+            related_to(&by_id, &by_tag, 1, 5).unwrap(),
+            related_episodes(&by_id, &by_tag, 5)[&1]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, warn};
+use unplugged_engine::Config;
+
+use crate::assets::{compile_templates, error_chain};
+use crate::shows::Shows;
+use crate::systemd::notify_ready;
+
+/// The running config shared by the shows, swapped as a whole when the file is read again,
+/// requests keep the config they started with
+pub struct ConfigHandle {
+    path: PathBuf,
+    /// the file was passed with --config, a missing one is an error
+    required: bool,
+    /// recompiled on a reload, empty for the bundled templates
+    templates_dir: String,
+    /// `serve --address`, overrides server.address of the file on every reload
+    address: Option<SocketAddr>,
+    current: RwLock<(Arc<Config>, Arc<HashSet<String>>)>,
+    /// changes with every reload, part of the ETags of the pages rendered with the config
+    version: AtomicU64,
+}
+
+// the settings and the reloads before, the templates may change without the config
+fn config_version(previous: u64, config: &Config) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    previous.hash(&mut hasher);
+    serde_json::to_string(config)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+
+    hasher.finish()
+}
+
+impl ConfigHandle {
+    pub fn new(config: Arc<Config>, path: PathBuf, required: bool, templates_dir: String) -> Self {
+        let stopwords = config.analyzer.stopwords();
+        let version = config_version(0, &config);
+
+        Self {
+            path,
+            required,
+            templates_dir,
+            address: None,
+            current: RwLock::new((config, Arc::new(stopwords))),
+            version: AtomicU64::new(version),
+        }
+    }
+
+    /// Keeps the address given on the command line when the file is read again
+    pub fn with_address(mut self, address: Option<SocketAddr>) -> Self {
+        self.address = address;
+        self
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.current.read().unwrap().0.clone()
+    }
+
+    /// The stopwords of the current `[analyzer]`
+    pub fn stopwords(&self) -> Arc<HashSet<String>> {
+        self.current.read().unwrap().1.clone()
+    }
+
+    /// Fingerprint of the current config and templates
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Relaxed)
+    }
+}
+
+/// What a reload changed
+#[derive(Serialize, Debug)]
+pub struct ReloadReport {
+    /// sections of the config applied
+    pub applied: Vec<String>,
+    /// sections of the config that changed but need a restart, they keep their current values
+    pub restart_required: Vec<String>,
+    /// the template overrides don't compile, the current ones are kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub templates_error: Option<String>,
+}
+
+/// Reads the config file and the template overrides again and applies what can change
+/// while running, a config that doesn't load changes nothing
+pub fn reload(shows: &Shows) -> Result<ReloadReport, String> {
+    let first = &shows[0];
+    let handle = &first.config;
+
+    let mut new = Config::load(&handle.path, handle.required).map_err(|err| err.to_string())?;
+
+    if let Some(address) = handle.address {
+        new.server.address = address.to_string();
+    }
+
+    let reload = handle.get().reload(new);
+    let config = reload.config;
+
+    if reload.applied.iter().any(|section| section == "rate_limit") {
+        first
+            .rate_limiter
+            .set_quota(config.rate_limit.per_second, config.rate_limit.burst);
+    }
+
+    let templates = &first.templates;
+    let templates_error = match compile_templates(&handle.templates_dir, templates.translations()) {
+        Ok(tera) => {
+            templates.replace(Ok(tera));
+            None
+        }
+        Err(err) => Some(error_chain(&err)),
+    };
+
+    let stopwords = config.analyzer.stopwords();
+    let version = config_version(handle.version(), &config);
+    *handle.current.write().unwrap() = (Arc::new(config), Arc::new(stopwords));
+    handle.version.store(version, Ordering::Relaxed);
+
+    // the cached pages were ranked and rendered with the previous settings
+    for show in shows.iter() {
+        show.page_cache.clear();
+    }
+
+    Ok(ReloadReport {
+        applied: reload.applied,
+        restart_required: reload.restart_required,
+        templates_error,
+    })
+}
+
+fn log_reload(shows: &Shows) -> Result<ReloadReport, String> {
+    let report = reload(shows);
+
+    match &report {
+        Ok(report) => {
+            info!(
+                applied = report.applied.join(", "),
+                "Reloaded the config and the templates"
+            );
+
+            if !report.restart_required.is_empty() {
+                warn!(
+                    sections = report.restart_required.join(", "),
+                    "Config changes kept until the next restart"
+                );
+            }
+
+            if let Some(err) = &report.templates_error {
+                warn!(
+                    "Error at compiling the templates, keeping the current ones: {}",
+                    err
+                );
+            }
+        }
+        Err(err) => error!("Error at reloading, keeping the current config: {}", err),
+    }
+
+    report
+}
+
+/// Reloads on every SIGHUP
+pub async fn reload_on_hangup(shows: Shows) {
+    let mut hangup = signal(SignalKind::hangup()).expect("Error at listening to SIGHUP");
+
+    while hangup.recv().await.is_some() {
+        if let Err(err) = sd_notify::notify(false, &[sd_notify::NotifyState::Reloading]) {
+            warn!("Error at notifying systemd: {}", err);
+        }
+
+        let _ = log_reload(&shows);
+
+        notify_ready();
+    }
+}
+
+// admin/reload, POST, what SIGHUP does, answers with what changed
+pub async fn handle_reload(State(shows): State<Shows>) -> Response {
+    match log_reload(&shows) {
+        Ok(report) => Json(report).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
@@ -0,0 +1,34 @@
+use axum::http::{Request, Response};
+use std::time::Duration;
+use tracing::{info, info_span, Span};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+pub fn request_id<B>(request: &Request<B>) -> &str {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or("-")
+}
+
+/// Span of a request, every event logged while handling it (engine included) carries the id,
+/// and the name of the api key of the request when it has one
+pub fn make_request_span<B>(request: &Request<B>) -> Span {
+    info_span!(
+        "request",
+        id = %request_id(request),
+        method = %request.method(),
+        path = %request.uri().path(),
+        api_key = tracing::field::Empty,
+    )
+}
+
+/// One line per response, latency in milliseconds so log collectors can aggregate it
+pub fn log_response<B>(response: &Response<B>, latency: Duration, _span: &Span) {
+    info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_secs_f64() * 1000.0,
+        "request completed"
+    );
+}
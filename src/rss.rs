@@ -0,0 +1,256 @@
+use crate::{format_date, format_duration, parse_duration, Enclosure, Episode, Tag};
+use chrono::DateTime;
+use roxmltree::{Document, Node};
+
+const ITUNES_NAMESPACE: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+const PODCAST_NAMESPACE: &str = "https://podcastindex.org/namespace/1.0";
+
+/// The episodes of a podcast feed, in the order of the feed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeedEpisodes {
+    pub episodes: Vec<Episode>,
+    /// titles of the items without an episode number, they can't get an id
+    pub skipped: Vec<String>,
+}
+
+/// Reads the episodes of an RSS feed, the id is the `itunes:episode` (or `podcast:episode`)
+/// number or the number the title starts with, tags come from the categories and `itunes:keywords`
+/// and the guests and hosts from the `podcast:person` tags
+pub fn parse_rss_feed(xml: &str) -> Result<FeedEpisodes, String> {
+    let document =
+        Document::parse(xml).map_err(|err| format!("Error at parsing the feed: {}", err))?;
+
+    let channel = document
+        .root_element()
+        .children()
+        .find(|node| is_element(node, None, "channel"))
+        .ok_or_else(|| String::from("Error at parsing the feed: expected an rss channel"))?;
+
+    let mut feed = FeedEpisodes::default();
+
+    for item in channel
+        .children()
+        .filter(|node| is_element(node, None, "item"))
+    {
+        let title = child_text(&item, None, "title").unwrap_or_default();
+
+        let id = child_text(&item, Some(ITUNES_NAMESPACE), "episode")
+            .or_else(|| child_text(&item, Some(PODCAST_NAMESPACE), "episode"))
+            .and_then(|number| number.parse::<i64>().ok())
+            .or_else(|| title_number(&title));
+
+        let Some(id) = id else {
+            feed.skipped.push(title);
+            continue;
+        };
+
+        let date = child_text(&item, None, "pubDate")
+            .and_then(|date| DateTime::parse_from_rfc2822(&date).ok())
+            .map(|date| format_date(date.date_naive()))
+            .unwrap_or_default();
+
+        let duration = child_text(&item, Some(ITUNES_NAMESPACE), "duration")
+            .and_then(|duration| parse_duration(&duration))
+            .map(format_duration)
+            .unwrap_or_default();
+
+        let keywords = child_text(&item, Some(ITUNES_NAMESPACE), "keywords").unwrap_or_default();
+        let mut tags: Vec<String> = Vec::new();
+
+        let categories = item
+            .children()
+            .filter(|node| is_element(node, None, "category"))
+            .filter_map(|node| node.text().map(String::from));
+
+        for tag in categories.chain(keywords.split(',').map(String::from)) {
+            let tag = tag.trim().to_lowercase();
+
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+
+        let url = child_text(&item, None, "link").unwrap_or_default();
+
+        let description = child_text(&item, Some(ITUNES_NAMESPACE), "summary")
+            .or_else(|| child_text(&item, None, "description"))
+            .map(|summary| strip_html(&summary))
+            .filter(|summary| !summary.is_empty());
+
+        let audio = item
+            .children()
+            .find(|node| is_element(node, None, "enclosure"))
+            .and_then(|enclosure| {
+                let url = enclosure.attribute("url")?.trim();
+
+                (!url.is_empty()).then(|| Enclosure {
+                    url: url.to_string(),
+                    bytes: enclosure
+                        .attribute("length")
+                        .and_then(|length| length.trim().parse().ok())
+                        .filter(|bytes| *bytes > 0),
+                    mime_type: enclosure
+                        .attribute("type")
+                        .map(|mime_type| mime_type.trim().to_string())
+                        .filter(|mime_type| !mime_type.is_empty()),
+                })
+            });
+
+        // podcast:person, the role is host unless it says otherwise
+        let mut guests = Vec::new();
+        let mut hosts = Vec::new();
+
+        for person in item
+            .children()
+            .filter(|node| is_element(node, Some(PODCAST_NAMESPACE), "person"))
+        {
+            let Some(name) = person.text().map(str::trim).filter(|name| !name.is_empty()) else {
+                continue;
+            };
+
+            match person.attribute("role").map(str::to_lowercase).as_deref() {
+                Some("guest") => guests.push(name.to_string()),
+                None | Some("host") => hosts.push(name.to_string()),
+                _ => {}
+            }
+        }
+
+        feed.episodes.push(Episode {
+            id,
+            number: u32::try_from(id).ok(),
+            title,
+            date,
+            duration,
+            tags: tags.into_iter().map(Tag::from).collect(),
+            url,
+            description,
+            guests,
+            hosts,
+            audio,
+            ..Default::default()
+        });
+    }
+
+    Ok(feed)
+}
+
+// "490: The Arch Disaster" -> 490
+fn title_number(title: &str) -> Option<i64> {
+    let (number, _) = title.split_once(':')?;
+    number.trim().parse().ok()
+}
+
+// show notes are usually html, "<p>Arch <b>broke</b></p>" -> "Arch broke"
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for char in html.chars() {
+        match char {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(char),
+            _ => {}
+        }
+    }
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_element(node: &Node, namespace: Option<&str>, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name && node.tag_name().namespace() == namespace
+}
+
+fn child_text(node: &Node, namespace: Option<&str>, name: &str) -> Option<String> {
+    node.children()
+        .find(|child| is_element(child, namespace, name))
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_rss_feed;
+    use crate::Enclosure;
+
+    #[test]
+    fn test_parse_rss_feed() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd"
+  xmlns:podcast="https://podcastindex.org/namespace/1.0">
+  <channel>
+    <title>LINUX Unplugged</title>
+    <item>
+      <title>512: Ubuntu Unity</title>
+      <itunes:title>Ubuntu Unity</itunes:title>
+      <link>https://linuxunplugged.com/512</link>
+      <pubDate>Mon, 13 Nov 2023 19:15:00 -0800</pubDate>
+      <itunes:duration>4974</itunes:duration>
+      <category>Ubuntu</category>
+      <itunes:keywords>ubuntu, Unity,gnome</itunes:keywords>
+      <description><![CDATA[<p>Unity is <b>back</b> &amp; better</p>]]></description>
+      <podcast:person>Chris Fisher</podcast:person>
+      <enclosure url="https://aphid.fireside.fm/512.mp3" length="79636423" type="audio/mpeg"/>
+      <podcast:person role="Guest">Rudra Saraswat</podcast:person>
+      <podcast:person role="producer">Jeff</podcast:person>
+    </item>
+    <item>
+      <title><![CDATA[Bonus: The Arch Disaster]]></title>
+      <itunes:episode>490</itunes:episode>
+      <pubDate>Sun, 2 Jan 2022 10:00:00 +0000</pubDate>
+      <itunes:duration>1:08:28</itunes:duration>
+      <enclosure url="https://aphid.fireside.fm/490.mp3" length="0"/>
+    </item>
+    <item>
+      <title>Trailer</title>
+    </item>
+  </channel>
+</rss>"#;
+
+        let feed = parse_rss_feed(xml).unwrap();
+        assert_eq!(feed.skipped, vec![String::from("Trailer")]);
+
+        let unity = &feed.episodes[0];
+        assert_eq!(unity.id, 512);
+        assert_eq!(unity.number, Some(512));
+        assert_eq!(unity.title, "512: Ubuntu Unity");
+        assert_eq!(unity.date, "November 13th, 2023");
+        assert_eq!(unity.duration, "01:22:54");
+        assert_eq!(unity.tag_names(), vec!["ubuntu", "unity", "gnome"]);
+        assert_eq!(unity.url, "https://linuxunplugged.com/512");
+        assert_eq!(unity.description.as_deref(), Some("Unity is back & better"));
+        assert_eq!(unity.guests, vec!["Rudra Saraswat"]);
+        assert_eq!(unity.hosts, vec!["Chris Fisher"]);
+        assert_eq!(
+            unity.audio,
+            Some(Enclosure {
+                url: String::from("https://aphid.fireside.fm/512.mp3"),
+                bytes: Some(79636423),
+                mime_type: Some(String::from("audio/mpeg")),
+            })
+        );
+
+        let arch = &feed.episodes[1];
+        assert_eq!(arch.id, 490);
+        assert_eq!(arch.title, "Bonus: The Arch Disaster");
+        assert_eq!(arch.date, "January 2nd, 2022");
+        assert_eq!(arch.duration, "01:08:28");
+        assert_eq!(arch.description, None);
+        assert_eq!(arch.audio.as_ref().and_then(|audio| audio.bytes), None);
+
+        assert!(parse_rss_feed("<html></html>").is_err());
+        assert!(parse_rss_feed("not xml").is_err());
+    }
+}
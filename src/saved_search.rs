@@ -0,0 +1,376 @@
+use crate::Episode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tokio::sync::Mutex;
+
+/// A query saved as a topic subscription, its feed only has the episodes
+/// that started matching after it was saved
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SavedSearch {
+    pub query: String,
+    /// slug of the show, empty in a single show archive
+    #[serde(default)]
+    pub show: String,
+    /// unix seconds
+    pub created_at: i64,
+    /// ids of the episodes matching when the search was saved
+    pub known: Vec<usize>,
+    /// email digest of the new matches, none for the searches followed by feed only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<Digest>,
+}
+
+/// How often a digest is emailed, when there's something new
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Daily,
+    #[default]
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn interval_secs(&self) -> i64 {
+        match self {
+            DigestFrequency::Daily => 24 * 60 * 60,
+            DigestFrequency::Weekly => 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// An email subscription to the new matches of a saved search
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Digest {
+    pub email: String,
+    pub frequency: DigestFrequency,
+    /// language of the emails, the one of the page the search was saved from
+    pub lang: String,
+    /// secret of the unsubscribe link, apart from the token of the feed
+    pub unsubscribe: String,
+    /// unix seconds of the last digest, or of the subscription
+    pub last_sent_at: i64,
+    /// ids of the episodes already emailed
+    pub sent: Vec<usize>,
+}
+
+impl Digest {
+    pub fn is_due(&self, now: i64) -> bool {
+        now - self.last_sent_at >= self.frequency.interval_secs()
+    }
+
+    /// The new matches that weren't in a previous digest
+    pub fn unsent<'a>(&self, new_matches: Vec<&'a Episode>) -> Vec<&'a Episode> {
+        new_matches
+            .into_iter()
+            .filter(|episode| !self.sent.contains(&(episode.id as usize)))
+            .collect()
+    }
+}
+
+impl SavedSearch {
+    /// The `matches` of the query that weren't known when it was saved, newest first
+    pub fn new_matches<'a>(&self, matches: &[&'a Episode]) -> Vec<&'a Episode> {
+        let mut episodes: Vec<_> = matches
+            .iter()
+            .filter(|episode| !self.known.contains(&(episode.id as usize)))
+            .copied()
+            .collect();
+
+        episodes.sort_by_key(|episode| std::cmp::Reverse(episode.id));
+        episodes
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// the store holds its maximum of saved searches, or of listeners for the playback positions
+    Full,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Full => write!(f, "the store is full"),
+            SaveError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// Saved searches by token, persisted as a json file
+pub struct SavedSearches {
+    path: PathBuf,
+    max: usize,
+    searches: RwLock<HashMap<String, SavedSearch>>,
+    /// one write of the file at a time
+    write: Mutex<()>,
+}
+
+impl SavedSearches {
+    /// Loads the saved searches of `path`, none when the file doesn't exist yet
+    pub async fn load(path: &Path, max: usize) -> std::io::Result<Self> {
+        let searches = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            max,
+            searches: RwLock::new(searches),
+            write: Mutex::new(()),
+        })
+    }
+
+    pub fn get(&self, token: &str) -> Option<SavedSearch> {
+        self.searches.read().unwrap().get(token).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.searches.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The saved searches with an email digest, by token
+    pub fn with_digest(&self) -> Vec<(String, SavedSearch)> {
+        self.searches
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, search)| search.digest.is_some())
+            .map(|(token, search)| (token.clone(), search.clone()))
+            .collect()
+    }
+
+    /// Token of the saved search whose digest has the `unsubscribe` secret
+    pub fn find_unsubscribe(&self, unsubscribe: &str) -> Option<String> {
+        self.searches
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, search)| {
+                search
+                    .digest
+                    .as_ref()
+                    .is_some_and(|digest| digest.unsubscribe == unsubscribe)
+            })
+            .map(|(token, _)| token.clone())
+    }
+
+    pub async fn insert(&self, token: String, search: SavedSearch) -> Result<(), SaveError> {
+        let _write = self.write.lock().await;
+
+        let contents = {
+            let mut searches = self.searches.write().unwrap();
+
+            if searches.len() >= self.max {
+                return Err(SaveError::Full);
+            }
+
+            searches.insert(token, search);
+            serde_json::to_string(&*searches).map_err(|err| SaveError::Io(err.into()))?
+        };
+
+        self.persist(contents).await
+    }
+
+    /// Changes the saved search of `token` with `update`, false when there's no such search
+    pub async fn update<F>(&self, token: &str, update: F) -> Result<bool, SaveError>
+    where
+        F: FnOnce(&mut SavedSearch),
+    {
+        let _write = self.write.lock().await;
+
+        let contents = {
+            let mut searches = self.searches.write().unwrap();
+
+            let Some(search) = searches.get_mut(token) else {
+                return Ok(false);
+            };
+
+            update(search);
+            serde_json::to_string(&*searches).map_err(|err| SaveError::Io(err.into()))?
+        };
+
+        self.persist(contents).await.map(|_| true)
+    }
+
+    /// Every saved search by token, as the file stores them
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&*self.searches.read().unwrap())
+    }
+
+    /// Replaces every saved search, e.g. with the ones of a backup
+    pub async fn replace_all(
+        &self,
+        searches: HashMap<String, SavedSearch>,
+    ) -> Result<(), SaveError> {
+        if searches.len() > self.max {
+            return Err(SaveError::Full);
+        }
+
+        let _write = self.write.lock().await;
+        let contents = serde_json::to_string(&searches).map_err(|err| SaveError::Io(err.into()))?;
+
+        self.persist(contents).await?;
+        *self.searches.write().unwrap() = searches;
+
+        Ok(())
+    }
+
+    async fn persist(&self, contents: String) -> Result<(), SaveError> {
+        // written next to the file and renamed, a crash never leaves half a file
+        let tmp = PathBuf::from(format!("{}.tmp", self.path.display()));
+        tokio::fs::write(&tmp, contents)
+            .await
+            .map_err(SaveError::Io)?;
+        tokio::fs::rename(&tmp, &self.path)
+            .await
+            .map_err(SaveError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Digest, DigestFrequency, SaveError, SavedSearch, SavedSearches};
+    use crate::{Episode, Tag};
+    use std::collections::HashMap;
+
+    fn episode(id: i64) -> Episode {
+        Episode {
+            id,
+            title: format!("{}: Episode", id),
+            date: String::from("March 19th, 2023"),
+            duration: String::from("01:00:00"),
+            tags: vec![Tag::from("btrfs")],
+            url: format!("https://linuxunplugged.com/{}", id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_new_matches() {
+        let search = SavedSearch {
+            query: String::from("btrfs"),
+            show: String::new(),
+            created_at: 1680000000,
+            known: vec![1, 2],
+            digest: None,
+        };
+
+        let episodes = [episode(1), episode(3), episode(2), episode(4)];
+        let matches: Vec<_> = episodes.iter().collect();
+
+        let ids: Vec<_> = search
+            .new_matches(&matches)
+            .iter()
+            .map(|episode| episode.id)
+            .collect();
+
+        assert_eq!(ids, vec![4, 3]);
+    }
+
+    #[test]
+    fn test_digest() {
+        let digest = Digest {
+            email: String::from("listener@example.com"),
+            frequency: DigestFrequency::Daily,
+            lang: String::from("en"),
+            unsubscribe: String::from("secret"),
+            last_sent_at: 1680000000,
+            sent: vec![3],
+        };
+
+        assert!(!digest.is_due(1680000000 + 3600));
+        assert!(digest.is_due(1680000000 + 86400));
+
+        let episodes = [episode(4), episode(3)];
+        let ids: Vec<_> = digest
+            .unsent(episodes.iter().collect())
+            .iter()
+            .map(|episode| episode.id)
+            .collect();
+
+        assert_eq!(ids, vec![4]);
+    }
+
+    #[tokio::test]
+    async fn test_saved_searches() {
+        let dir = std::env::temp_dir().join(format!("unplugged-saved-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("alerts.json");
+
+        let search = SavedSearch {
+            query: String::from("btrfs"),
+            show: String::new(),
+            created_at: 1680000000,
+            known: vec![1],
+            digest: None,
+        };
+
+        let saved = SavedSearches::load(&path, 1).await.unwrap();
+        assert!(saved.is_empty());
+
+        saved
+            .insert(String::from("abc"), search.clone())
+            .await
+            .unwrap();
+        assert!(matches!(
+            saved.insert(String::from("def"), search.clone()).await,
+            Err(SaveError::Full)
+        ));
+
+        // persisted
+        let reloaded = SavedSearches::load(&path, 1).await.unwrap();
+        assert_eq!(reloaded.get("abc"), Some(search.clone()));
+        assert!(reloaded.get("def").is_none());
+
+        let digest = Digest {
+            email: String::from("listener@example.com"),
+            frequency: DigestFrequency::Daily,
+            lang: String::from("en"),
+            unsubscribe: String::from("secret"),
+            last_sent_at: 1680000000,
+            sent: vec![],
+        };
+
+        assert!(saved
+            .update("abc", |search| search.digest = Some(digest.clone()))
+            .await
+            .unwrap());
+        assert!(!saved.update("def", |_| {}).await.unwrap());
+
+        let reloaded = SavedSearches::load(&path, 1).await.unwrap();
+        assert_eq!(reloaded.with_digest().len(), 1);
+        assert_eq!(reloaded.find_unsubscribe("secret").as_deref(), Some("abc"));
+        assert!(reloaded.find_unsubscribe("abc").is_none());
+
+        let backup: HashMap<String, SavedSearch> =
+            serde_json::from_str(&reloaded.to_json().unwrap()).unwrap();
+        saved.replace_all(HashMap::new()).await.unwrap();
+        assert!(SavedSearches::load(&path, 1).await.unwrap().is_empty());
+
+        saved.replace_all(backup.clone()).await.unwrap();
+        assert_eq!(saved.with_digest().len(), 1);
+        assert!(matches!(
+            saved
+                .replace_all(HashMap::from([
+                    (String::from("abc"), search.clone()),
+                    (String::from("def"), search.clone()),
+                ]))
+                .await,
+            Err(SaveError::Full)
+        ));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,1312 @@
+use crate::{
+    analyze_term, closest_words, is_boolean_query, parse_date_bound, parse_duration_bound,
+    parse_query, parse_query_expr, stem, Bm25, Episode, ParseResult, QueryExpr, QueryVersion,
+    ScoringConfig, SearchFields, ShowIndex, Symbols, TermWeights, AFTER_FIELD, BEFORE_FIELD,
+    DOMAIN_FIELD, GUEST_FIELD, ID_FIELD, LONGER_FIELD, SHORTER_FIELD, SHOW_FIELD, TAG_FIELD,
+    TITLE_FIELD,
+};
+use chrono::NaiveDate;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use roaring::RoaringBitmap;
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+
+// candidates scored on the rayon thread pool from this many on,
+// fewer are scored faster on the current thread
+#[cfg(feature = "parallel")]
+const PARALLEL_SCORING_MIN: usize = 1024;
+
+/// The episodes matching a query, best score first, and the terms they were matched with
+#[derive(Clone, Debug, Default)]
+pub struct SearchResults<'a> {
+    /// lowercase search terms without the common words
+    pub terms: HashSet<String>,
+    /// tags excluded with `-`
+    pub exclude: HashSet<String>,
+    /// the words of the index closest to the terms matching nothing, "kubernetes" for "kuberntes"
+    pub corrections: HashSet<String>,
+    /// borrowed from the index, cloned only by the callers that keep them
+    pub episodes: Vec<(i32, &'a Episode)>,
+}
+
+/// Searches the tags and titles of an index, shared by the web server and the cli,
+/// once `out_of_time` returns true the search goes on with the episodes found so far
+pub fn search_index<'a, F>(
+    index: &'a ShowIndex,
+    query: &str,
+    common_words: &HashSet<String>,
+    scoring: &ScoringConfig,
+    out_of_time: F,
+) -> SearchResults<'a>
+where
+    F: Fn() -> bool,
+{
+    search_index_version(
+        index,
+        query,
+        QueryVersion::LATEST,
+        common_words,
+        scoring,
+        out_of_time,
+    )
+}
+
+/// `search_index` with the query parsed as an older version of the query language
+pub fn search_index_version<'a, F>(
+    index: &'a ShowIndex,
+    query: &str,
+    version: QueryVersion,
+    common_words: &HashSet<String>,
+    scoring: &ScoringConfig,
+    out_of_time: F,
+) -> SearchResults<'a>
+where
+    F: Fn() -> bool,
+{
+    let analyze =
+        |terms, exclude| AnalyzedQuery::new(index, terms, exclude, version, common_words, scoring);
+
+    let (query, results) = if version.has_boolean_operators() && is_boolean_query(query) {
+        let expr = parse_query_expr(query);
+        let (terms, exclude) = expr.as_ref().map(QueryExpr::terms).unwrap_or_default();
+
+        // a query of only exclusions finds nothing, like the other queries
+        let results = match &expr {
+            Some(expr) if !terms.is_empty() => evaluate(expr, index, &analyze, &out_of_time),
+            _ => RoaringBitmap::new(),
+        };
+
+        // the results are scored by every term of the expression
+        (analyze(terms, exclude), results)
+    } else {
+        let ParseResult { terms, exclude } = parse_query(query);
+        let query = analyze(terms, exclude);
+        let results = query.keep(index, query.matching(index, &out_of_time));
+
+        (query, results)
+    };
+
+    // sorting results
+
+    let AnalyzedQuery {
+        terms,
+        exclude,
+        numbers,
+        scoped,
+        corrections,
+        stems,
+        ..
+    } = query;
+
+    let symbols = index.tag_join.symbols();
+    let scored = scored_tags(symbols, &terms);
+    // the tags of an exact term aren't scored twice
+    let scored_corrections = scored_tags(symbols, &corrections) - &scored;
+    let stem_terms: HashSet<String> = stems.keys().cloned().collect();
+    let scored_stems = scored_tags(symbols, &stem_terms) - &scored;
+    // `tag:` terms score their tags, `title:` terms the titles
+    let scored_scoped = scoped
+        .tags
+        .iter()
+        .filter_map(|tag| symbols.get(tag))
+        .collect::<RoaringBitmap>()
+        - &scored;
+    let scoped_scoring = ScoringConfig {
+        chapter: 0,
+        description: 0,
+        transcript: 0,
+        ..scoring.clone()
+    };
+
+    let candidates: Vec<u32> = results.iter().collect();
+    let weights = index.bm25.weights(&terms);
+    let correction_weights = index.bm25.weights(&corrections);
+    let stem_weights = index.bm25.weights(&stem_terms);
+    let scoped_weights = index.bm25.weights(&scoped.titles);
+
+    let score = |posting: &u32| {
+        let id = *posting as usize;
+        let episode = index.episodes_by_id.get(&id)?;
+        let fields = &index.search_fields[&id];
+        let exact = score_fields(fields, &terms, symbols, &scored, scoring, &weights);
+        let mut score = exact.total;
+
+        if !stems.is_empty() {
+            let stemmed = score_fields(
+                fields,
+                &stem_terms,
+                symbols,
+                &scored_stems,
+                scoring,
+                &stem_weights,
+            );
+            score += stem_points(&exact, &stemmed, &stems).saturating_mul(scoring.stem) / 100;
+        }
+
+        if !corrections.is_empty() {
+            let fuzzy = score_fields(
+                fields,
+                &corrections,
+                symbols,
+                &scored_corrections,
+                scoring,
+                &correction_weights,
+            );
+            score += fuzzy.total.saturating_mul(scoring.fuzzy) / 100;
+        }
+
+        if !scoped.is_empty() {
+            let points = score_fields(
+                fields,
+                &scoped.titles,
+                symbols,
+                &scored_scoped,
+                &scoped_scoring,
+                &scoped_weights,
+            );
+            score += points.total;
+        }
+
+        Some((score, episode))
+    };
+
+    #[cfg(feature = "parallel")]
+    let mut episodes: Vec<_> = if candidates.len() >= PARALLEL_SCORING_MIN {
+        candidates.par_iter().filter_map(score).collect()
+    } else {
+        candidates.iter().filter_map(score).collect()
+    };
+
+    #[cfg(not(feature = "parallel"))]
+    let mut episodes: Vec<_> = candidates.iter().filter_map(score).collect();
+
+    rank_episodes(&mut episodes, &numbers);
+
+    SearchResults {
+        terms,
+        exclude,
+        corrections,
+        episodes,
+    }
+}
+
+// the episodes matching an expression, every term is matched like a query of its own
+fn evaluate<A, F>(
+    expr: &QueryExpr,
+    index: &ShowIndex,
+    analyze: &A,
+    out_of_time: &F,
+) -> RoaringBitmap
+where
+    A: Fn(Vec<String>, Vec<String>) -> AnalyzedQuery,
+    F: Fn() -> bool,
+{
+    let every_episode = || -> RoaringBitmap {
+        index
+            .search_fields
+            .keys()
+            .filter_map(|id| u32::try_from(*id).ok())
+            .collect()
+    };
+
+    match expr {
+        QueryExpr::Term(term) => {
+            let query = analyze(vec![term.clone()], Vec::new());
+            query.keep(index, query.matching(index, out_of_time))
+        }
+        // `-term` leaves out what it leaves out of the other queries, the episodes with the tag
+        QueryExpr::Not(inner) => match inner.as_ref() {
+            QueryExpr::Term(term) => {
+                analyze(Vec::new(), vec![term.clone()]).keep(index, every_episode())
+            }
+            inner => every_episode() - evaluate(inner, index, analyze, out_of_time),
+        },
+        QueryExpr::And(items) => items
+            .iter()
+            .map(|item| evaluate(item, index, analyze, out_of_time))
+            .reduce(|all, ids| all & ids)
+            .unwrap_or_default(),
+        QueryExpr::Or(items) => items
+            .iter()
+            .map(|item| evaluate(item, index, analyze, out_of_time))
+            .fold(RoaringBitmap::new(), |any, ids| any | ids),
+    }
+}
+
+// the terms of a query once analyzed, what they match and leave out
+struct AnalyzedQuery {
+    /// lowercase search terms without the common words
+    terms: HashSet<String>,
+    exclude: HashSet<String>,
+    /// episode numbers searched for
+    numbers: HashSet<u32>,
+    filters: FieldFilters,
+    ranges: RangeFilters,
+    scoped: ScopedTerms,
+    corrections: HashSet<String>,
+    /// the stems of the inflected terms and the term of each, `container` of `containers`
+    stems: HashMap<String, String>,
+}
+
+impl AnalyzedQuery {
+    fn new(
+        index: &ShowIndex,
+        mut terms: Vec<String>,
+        mut exclude: Vec<String>,
+        version: QueryVersion,
+        common_words: &HashSet<String>,
+        scoring: &ScoringConfig,
+    ) -> Self {
+        let fields = version.has_fields();
+
+        let filters = if fields {
+            FieldFilters::take(&mut terms, &mut exclude)
+        } else {
+            FieldFilters::default()
+        };
+
+        let ranges = if version.has_range_filters() {
+            RangeFilters::take(&mut terms, &mut exclude)
+        } else {
+            RangeFilters::default()
+        };
+
+        let scoped = if version.has_scoped_terms() {
+            ScopedTerms::take(&mut terms, &mut exclude)
+        } else {
+            ScopedTerms::default()
+        };
+
+        // episode numbers searched for, "493" or "#493" finds episode number 493,
+        // "#493" isn't matched against the tags and titles
+        let numbers: HashSet<u32> = terms
+            .iter()
+            .filter(|_| fields)
+            .filter_map(|term| parse_episode_number(term))
+            .collect();
+
+        let terms: HashSet<_> = terms
+            .iter()
+            .flat_map(|s| analyze_term(s, common_words))
+            .filter(|s| !fields || !s.starts_with('#') || parse_episode_number(s).is_none())
+            .collect();
+
+        // the inflected terms are also searched by their stem
+        let stems: HashMap<_, _> = if scoring.stem > 0 {
+            terms
+                .iter()
+                .filter_map(|term| Some((stem(term)?, term.clone())))
+                .filter(|(stem, _)| !terms.contains(stem) && !common_words.contains(stem))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        // the misspelled terms are searched with the closest words, for fewer points,
+        // a stem isn't a misspelling of its term
+        let corrections = if scoring.fuzzy > 0 {
+            let mut corrections = typo_corrections(index, &terms);
+            corrections.retain(|correction| !stems.contains_key(correction));
+            corrections
+        } else {
+            HashSet::new()
+        };
+
+        Self {
+            terms,
+            exclude: HashSet::from_iter(exclude),
+            numbers,
+            filters,
+            ranges,
+            scoped,
+            corrections,
+            stems,
+        }
+    }
+
+    // the ids of the episodes matching a term, once `out_of_time` returns true
+    // the episodes found so far
+    fn matching<F>(&self, index: &ShowIndex, out_of_time: &F) -> RoaringBitmap
+    where
+        F: Fn() -> bool,
+    {
+        let Self {
+            terms,
+            numbers,
+            filters,
+            ranges,
+            scoped,
+            corrections,
+            stems,
+            ..
+        } = self;
+
+        let mut results = RoaringBitmap::new();
+
+        // a query of only `guest:`, `show:`, `domain:` or date and duration filters
+        // lists every episode they keep
+        let narrows = filters.narrows() || ranges.narrows();
+
+        if terms.is_empty() && numbers.is_empty() && scoped.is_empty() && narrows {
+            results = index
+                .search_fields
+                .keys()
+                .filter_map(|id| u32::try_from(*id).ok())
+                .collect();
+        }
+
+        for (tag, ids) in index.tag_join.iter() {
+            if out_of_time() {
+                break;
+            }
+
+            if terms
+                .iter()
+                .chain(corrections.iter())
+                .chain(stems.keys())
+                .any(|term| tag.contains(term) || term.contains(tag))
+            {
+                results |= ids;
+            }
+        }
+
+        // `tag:` terms are looked up in the posting lists, they match the whole tag
+        for tag in scoped.tags.iter() {
+            if let Some(ids) = index.tag_join.posting(tag) {
+                results |= ids;
+            }
+        }
+
+        // the terms and the phrases are looked up in the words of the titles, chapters,
+        // show notes and transcripts
+        for term in terms.iter().chain(corrections.iter()).chain(stems.keys()) {
+            if out_of_time() {
+                break;
+            }
+
+            results |= index.word_postings.matching(term);
+        }
+
+        for number in numbers.iter() {
+            if let Some(ids) = index.word_postings.numbered(*number) {
+                results |= ids;
+            }
+        }
+
+        // `title:` terms are looked up in the words, then only the episodes with them
+        // in the title are kept, they don't match the tags, chapters, show notes and transcripts
+        for term in scoped.titles.iter() {
+            let in_title = index
+                .word_postings
+                .matching(term)
+                .into_iter()
+                .filter(|posting| {
+                    index
+                        .search_fields
+                        .get(&(*posting as usize))
+                        .is_some_and(|fields| fields.title.contains(term.as_str()))
+                });
+
+            results.extend(in_title);
+        }
+
+        for id in scoped.ids.iter() {
+            if let (true, Ok(posting)) = (index.episodes_by_id.contains_key(id), u32::try_from(*id))
+            {
+                results.insert(posting);
+            }
+        }
+
+        results
+    }
+
+    // the episodes of `results` the fields and the exclusions keep
+    fn keep(&self, index: &ShowIndex, mut results: RoaringBitmap) -> RoaringBitmap {
+        let Self {
+            exclude,
+            filters,
+            ranges,
+            scoped,
+            ..
+        } = self;
+
+        if !filters.is_empty() || ranges.narrows() || scoped.excludes() {
+            results = results
+                .iter()
+                .filter(|posting| {
+                    let id = *posting as usize;
+
+                    index.search_fields.get(&id).is_some_and(|fields| {
+                        filters.matches(fields)
+                            && ranges.matches(fields)
+                            && scoped.keeps(id, fields)
+                    })
+                })
+                .collect();
+        }
+
+        if !exclude.is_empty() {
+            results -= index
+                .tag_join
+                .union(|tag| exclude.iter().any(|excl_token| tag.contains(excl_token)));
+        }
+
+        results
+    }
+}
+
+// shorter words are left alone, a single edit turns them into too many other words
+const FUZZY_MIN_CHARS: usize = 4;
+
+// the closest words of the tags, titles, chapters, show notes and transcripts to the terms matching none,
+// the phrases and the numbers aren't corrected
+fn typo_corrections(index: &ShowIndex, terms: &HashSet<String>) -> HashSet<String> {
+    let symbols = index.tag_join.symbols();
+
+    let misspelled = terms.iter().filter(|term| {
+        term.chars().count() >= FUZZY_MIN_CHARS
+            && term.chars().all(char::is_alphabetic)
+            && !symbols
+                .iter()
+                .any(|(_, tag)| tag.contains(term.as_str()) || term.contains(tag))
+            && index.word_postings.matching(term).is_empty()
+    });
+
+    misspelled
+        .flat_map(|term| {
+            let tags = symbols.iter().map(|(_, tag)| tag);
+            closest_words(term, tags.chain(index.word_postings.words()))
+        })
+        .map(String::from)
+        .filter(|word| !terms.contains(word))
+        .collect()
+}
+
+/// Orders the results, the episodes numbered like a number of the query come first
+/// ("512" is episode 512, then the titles saying 512), then the highest scores,
+/// ties by id so every path scoring them gives the same order
+pub(crate) fn rank_episodes<E: Borrow<Episode>>(episodes: &mut [(i32, E)], numbers: &HashSet<u32>) {
+    let numbered = |episode: &E| {
+        episode
+            .borrow()
+            .number()
+            .is_some_and(|number| numbers.contains(&number))
+    };
+
+    episodes.sort_by(|(a_score, a), (b_score, b)| {
+        numbered(b)
+            .cmp(&numbered(a))
+            .then(b_score.cmp(a_score))
+            .then(a.borrow().id.cmp(&b.borrow().id))
+    });
+}
+
+// "493" or "#493" -> 493, "0493" and "493a" aren't numbers
+pub(crate) fn parse_episode_number(term: &str) -> Option<u32> {
+    let digits = term.strip_prefix('#').unwrap_or(term);
+    let number: u32 = digits.parse().ok()?;
+
+    (number.to_string() == digits).then_some(number)
+}
+
+// the `field:value` terms of a query, they narrow the results instead of matching tags and titles
+#[derive(Clone, Debug, Default)]
+struct FieldFilters {
+    /// every one of them is a guest of the episode
+    guests: Vec<String>,
+    exclude_guests: Vec<String>,
+    /// the episode is of any of them
+    shows: Vec<String>,
+    exclude_shows: Vec<String>,
+    /// the show notes link to every one of them or their subdomains
+    domains: Vec<String>,
+    exclude_domains: Vec<String>,
+}
+
+impl FieldFilters {
+    fn take(terms: &mut Vec<String>, exclude: &mut Vec<String>) -> Self {
+        Self {
+            guests: take_field(terms, GUEST_FIELD),
+            exclude_guests: take_field(exclude, GUEST_FIELD),
+            shows: take_field(terms, SHOW_FIELD),
+            exclude_shows: take_field(exclude, SHOW_FIELD),
+            domains: take_field(terms, DOMAIN_FIELD),
+            exclude_domains: take_field(exclude, DOMAIN_FIELD),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.narrows()
+            && self.exclude_guests.is_empty()
+            && self.exclude_shows.is_empty()
+            && self.exclude_domains.is_empty()
+    }
+
+    // filters listing episodes on their own, without other terms
+    fn narrows(&self) -> bool {
+        !self.guests.is_empty() || !self.shows.is_empty() || !self.domains.is_empty()
+    }
+
+    fn matches(&self, fields: &SearchFields) -> bool {
+        let has_guest = |name: &String| {
+            fields
+                .guests
+                .iter()
+                .any(|guest| guest.contains(name.as_str()))
+        };
+
+        // "github.com" also matches "gist.github.com"
+        let links_to = |domain: &String| {
+            fields.domains.iter().any(|linked| {
+                linked == domain
+                    || linked
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|sub| sub.ends_with('.'))
+            })
+        };
+
+        self.guests.iter().all(has_guest)
+            && !self.exclude_guests.iter().any(has_guest)
+            && (self.shows.is_empty() || self.shows.contains(&fields.show))
+            && !self.exclude_shows.contains(&fields.show)
+            && self.domains.iter().all(links_to)
+            && !self.exclude_domains.iter().any(links_to)
+    }
+}
+
+// the `after:`, `before:`, `longer:` and `shorter:` terms of a query, the episodes without
+// a date or a duration that parses are left out by the bounds of the field
+#[derive(Clone, Debug, Default)]
+struct RangeFilters {
+    /// published on or after this day
+    after: Option<NaiveDate>,
+    /// published before this day
+    before: Option<NaiveDate>,
+    /// lasting at least this many seconds
+    longer: Option<u64>,
+    /// lasting less than this many seconds
+    shorter: Option<u64>,
+}
+
+impl RangeFilters {
+    fn take(terms: &mut Vec<String>, exclude: &mut Vec<String>) -> Self {
+        let dates = |values: Vec<String>| -> Vec<NaiveDate> {
+            values.iter().filter_map(|v| parse_date_bound(v)).collect()
+        };
+        let durations = |values: Vec<String>| -> Vec<u64> {
+            values
+                .iter()
+                .filter_map(|v| parse_duration_bound(v))
+                .collect()
+        };
+
+        // `-after:2022` is `before:2022`, `-longer:1h` is `shorter:1h`,
+        // the tightest of several bounds wins
+        let mut after = dates(take_field(terms, AFTER_FIELD));
+        let mut before = dates(take_field(terms, BEFORE_FIELD));
+        after.extend(dates(take_field(exclude, BEFORE_FIELD)));
+        before.extend(dates(take_field(exclude, AFTER_FIELD)));
+
+        let mut longer = durations(take_field(terms, LONGER_FIELD));
+        let mut shorter = durations(take_field(terms, SHORTER_FIELD));
+        longer.extend(durations(take_field(exclude, SHORTER_FIELD)));
+        shorter.extend(durations(take_field(exclude, LONGER_FIELD)));
+
+        Self {
+            after: after.into_iter().max(),
+            before: before.into_iter().min(),
+            longer: longer.into_iter().max(),
+            shorter: shorter.into_iter().min(),
+        }
+    }
+
+    fn narrows(&self) -> bool {
+        self.after.is_some()
+            || self.before.is_some()
+            || self.longer.is_some()
+            || self.shorter.is_some()
+    }
+
+    fn matches(&self, fields: &SearchFields) -> bool {
+        within(fields.date, self.after, self.before)
+            && within(fields.duration, self.longer, self.shorter)
+    }
+}
+
+// `min <= value < max`, a missing value is out of any bound
+fn within<T: PartialOrd>(value: Option<T>, min: Option<T>, max: Option<T>) -> bool {
+    match value {
+        Some(value) => min.is_none_or(|min| value >= min) && max.is_none_or(|max| value < max),
+        None => min.is_none() && max.is_none(),
+    }
+}
+
+// the `tag:`, `title:` and `id:` terms of a query, they match a single field of the episodes
+#[derive(Clone, Debug, Default)]
+struct ScopedTerms {
+    tags: HashSet<String>,
+    titles: HashSet<String>,
+    /// ids of the index, `id:512`
+    ids: Vec<usize>,
+    exclude_titles: Vec<String>,
+    exclude_ids: Vec<usize>,
+}
+
+impl ScopedTerms {
+    fn take(terms: &mut Vec<String>, exclude: &mut Vec<String>) -> Self {
+        let ids = |values: Vec<String>| values.iter().filter_map(|id| id.parse().ok()).collect();
+
+        // `-tag:nixos` excludes the tag like `-nixos`
+        let exclude_tags = take_field(exclude, TAG_FIELD);
+        exclude.extend(exclude_tags);
+
+        Self {
+            tags: take_field(terms, TAG_FIELD).into_iter().collect(),
+            titles: take_field(terms, TITLE_FIELD).into_iter().collect(),
+            ids: ids(take_field(terms, ID_FIELD)),
+            exclude_titles: take_field(exclude, TITLE_FIELD),
+            exclude_ids: ids(take_field(exclude, ID_FIELD)),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.titles.is_empty() && self.ids.is_empty()
+    }
+
+    fn excludes(&self) -> bool {
+        !self.exclude_titles.is_empty() || !self.exclude_ids.is_empty()
+    }
+
+    fn keeps(&self, id: usize, fields: &SearchFields) -> bool {
+        !self.exclude_ids.contains(&id)
+            && !self
+                .exclude_titles
+                .iter()
+                .any(|term| fields.title.contains(term.as_str()))
+    }
+}
+
+// `guest:name` terms of a query, lowercase and without the prefix,
+// they filter the results instead of matching tags and titles
+fn take_field(terms: &mut Vec<String>, field: &str) -> Vec<String> {
+    let mut values = Vec::new();
+
+    terms.retain(|term| {
+        let term = term.to_lowercase();
+
+        match term.strip_prefix(field) {
+            Some(value) => {
+                if !value.is_empty() {
+                    values.push(value.to_string());
+                }
+                false
+            }
+            None => true,
+        }
+    });
+
+    values
+}
+
+/// Points of an episode per matched field, the score `search_index` ranks by
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    /// tags matching a search term and their points
+    pub tags: Vec<(String, i32)>,
+    /// search terms found in the title and their points
+    pub title: Vec<(String, i32)>,
+    /// search terms found in the chapter titles and their points
+    pub chapters: Vec<(String, i32)>,
+    /// search terms found in the show notes and their points
+    pub description: Vec<(String, i32)>,
+    /// search terms said in the transcript and their points
+    pub transcript: Vec<(String, i32)>,
+    pub total: i32,
+}
+
+/// Scores an episode against the search terms of a query
+pub fn score_episode(
+    episode: &Episode,
+    terms: &HashSet<String>,
+    scoring: &ScoringConfig,
+) -> ScoreBreakdown {
+    let mut symbols = Symbols::default();
+
+    for tag in episode.tags.iter() {
+        symbols.intern(&tag.name.to_lowercase());
+    }
+
+    let fields = SearchFields::new(episode, &symbols);
+    let scored = scored_tags(&symbols, terms);
+
+    // without the rest of the index every match is worth its flat points
+    let bm25 = Bm25::default();
+    score_fields(
+        &fields,
+        terms,
+        &symbols,
+        &scored,
+        scoring,
+        &bm25.weights(terms),
+    )
+}
+
+/// Scores an episode of an index by its id, its transcript included
+pub fn score_indexed_episode(
+    index: &ShowIndex,
+    id: usize,
+    terms: &HashSet<String>,
+    scoring: &ScoringConfig,
+) -> Option<ScoreBreakdown> {
+    let fields = index.search_fields.get(&id)?;
+    let symbols = index.tag_join.symbols();
+    let scored = scored_tags(symbols, terms);
+
+    Some(score_fields(
+        fields,
+        terms,
+        symbols,
+        &scored,
+        scoring,
+        &index.bm25.weights(terms),
+    ))
+}
+
+// the points of the stems in the fields their term isn't found in, "containers" scores the tag
+// "container" and the titles saying "container" without adding to the exact matches
+fn stem_points(
+    exact: &ScoreBreakdown,
+    stemmed: &ScoreBreakdown,
+    stems: &HashMap<String, String>,
+) -> i32 {
+    let missed = |found: &[(String, i32)], matches: &[(String, i32)]| -> i32 {
+        matches
+            .iter()
+            .filter(|(stem, _)| !found.iter().any(|(term, _)| stems.get(stem) == Some(term)))
+            .map(|(_, points)| points)
+            .sum()
+    };
+
+    // the tags of the exact terms are already left out of the scored ones
+    let tags: i32 = stemmed.tags.iter().map(|(_, points)| points).sum();
+
+    tags + missed(&exact.title, &stemmed.title)
+        + missed(&exact.chapters, &stemmed.chapters)
+        + missed(&exact.description, &stemmed.description)
+        + missed(&exact.transcript, &stemmed.transcript)
+}
+
+// the tags worth points, matched against the terms once per query instead of once per result
+fn scored_tags(symbols: &Symbols, terms: &HashSet<String>) -> RoaringBitmap {
+    symbols
+        .iter()
+        .filter(|(_, tag)| {
+            terms.contains(*tag) || terms.iter().any(|term| tag.contains(term.as_str()))
+        })
+        .map(|(symbol, _)| symbol)
+        .collect()
+}
+
+fn score_fields(
+    fields: &SearchFields,
+    terms: &HashSet<String>,
+    symbols: &Symbols,
+    scored: &RoaringBitmap,
+    scoring: &ScoringConfig,
+    weights: &TermWeights,
+) -> ScoreBreakdown {
+    let mut breakdown = ScoreBreakdown::default();
+
+    // BM25 weighs the points by how rare the term is, the flat model gives them as they are
+    let weigh = |points: i32, weight: f64| {
+        if scoring.is_bm25() {
+            (points as f64 * weight).round() as i32
+        } else {
+            points
+        }
+    };
+
+    // scores for tag
+    for (tag, weight) in fields.tags.iter() {
+        if scored.contains(*tag) {
+            let weight = i32::try_from(*weight).unwrap_or(i32::MAX);
+            let points = weigh(scoring.tag.saturating_mul(weight) / 100, weights.tag(*tag));
+            breakdown
+                .tags
+                .push((symbols.name(*tag).to_string(), points));
+        }
+    }
+
+    // scores for title
+    let mut terms: Vec<_> = terms.iter().collect();
+    terms.sort();
+
+    let title_words = fields.title.split_whitespace().count();
+
+    for term in terms {
+        let count = fields.title.matches(term.as_str()).count();
+
+        if count > 0 {
+            let weight = weights.title(term, count, title_words);
+            breakdown
+                .title
+                .push((term.clone(), weigh(scoring.title, weight)));
+        }
+
+        // once per term, however many chapters it's in
+        if fields
+            .chapters
+            .iter()
+            .any(|chapter| chapter.contains(term.as_str()))
+        {
+            breakdown.chapters.push((term.clone(), scoring.chapter));
+        }
+
+        if fields.description.contains(term.as_str()) {
+            breakdown
+                .description
+                .push((term.clone(), scoring.description));
+        }
+
+        if fields.transcript.contains(term.as_str()) {
+            breakdown
+                .transcript
+                .push((term.clone(), scoring.transcript));
+        }
+    }
+
+    breakdown.total = breakdown
+        .tags
+        .iter()
+        .chain(breakdown.title.iter())
+        .chain(breakdown.chapters.iter())
+        .chain(breakdown.description.iter())
+        .chain(breakdown.transcript.iter())
+        .map(|(_, points)| points)
+        .sum();
+
+    breakdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{score_episode, search_index, search_index_version, ScoreBreakdown};
+    use crate::{
+        load_common_words, Chapter, Episode, EpisodesById, EpisodesByTag, Link, QueryVersion,
+        ScoringConfig, ShowIndex, Tag, TagCategory, TranscriptSegment, Transcripts,
+    };
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    // the points of the flat model, the same whatever the rest of the index
+    fn flat() -> ScoringConfig {
+        ScoringConfig {
+            model: String::from("flat"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_search_index() {
+        let episode = |id: i64, title: &str, tags: &[&str]| Episode {
+            id,
+            title: String::from(title),
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..Default::default()
+        };
+
+        let link = |url: &str| Link {
+            url: String::from(url),
+            label: String::new(),
+        };
+
+        let by_id = EpisodesById::from([
+            (
+                490,
+                Episode {
+                    number: Some(12),
+                    guests: vec![String::from("Wendell Wilson"), String::from("Jim")],
+                    links: vec![link("https://www.NixOS.org/download")],
+                    description: Some(String::from("Rebuilding a storage pool after an update.")),
+                    ..episode(490, "490: The Arch Disaster", &["arch", "btrfs"])
+                },
+            ),
+            (
+                500,
+                Episode {
+                    guests: vec![String::from("Wendell Wilson")],
+                    links: vec![
+                        link("https://gist.github.com/wendell"),
+                        link("https://nixos.org"),
+                    ],
+                    ..episode(500, "500: ZFS Forever", &["zfs"])
+                },
+            ),
+            (
+                501,
+                Episode {
+                    show: String::from("ssh"),
+                    chapters: vec![Chapter {
+                        title: String::from("Bcachefs merged"),
+                        start_secs: 754,
+                    }],
+                    ..episode(501, "501: Filesystems", &["zfs", "btrfs"])
+                },
+            ),
+        ]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("btrfs"), vec![490, 501]),
+            (String::from("zfs"), vec![500, 501]),
+        ]);
+        let transcripts = Transcripts::from([(
+            500,
+            vec![TranscriptSegment {
+                start_secs: 90,
+                text: String::from("We moved the pool to Bcachefs."),
+            }],
+        )]);
+        let index = ShowIndex::new(by_id, by_tag, None).with_transcripts(Arc::new(transcripts));
+
+        let search = |query: &str| -> Vec<(i32, i64)> {
+            search_index(&index, query, &load_common_words(), &flat(), || false)
+                .episodes
+                .iter()
+                .map(|(score, episode)| (*score, episode.id))
+                .collect()
+        };
+
+        assert_eq!(search("zfs -btrfs"), vec![(150, 500)]);
+        assert_eq!(search("the arch"), vec![(150, 490)]);
+        assert_eq!(search("501"), vec![(100, 501)]);
+        assert_eq!(search("#501"), vec![(0, 501)]);
+        assert_eq!(search("#12"), vec![(0, 490)]);
+        assert_eq!(search("490"), vec![(100, 490)]);
+
+        assert_eq!(search("guest:wendell"), vec![(0, 490), (0, 500)]);
+        assert_eq!(search("zfs guest:Wendell"), vec![(150, 500)]);
+        assert_eq!(search("guest:wendell -guest:jim"), vec![(0, 500)]);
+
+        assert_eq!(search("show:ssh"), vec![(0, 501)]);
+        assert_eq!(search("zfs -show:ssh"), vec![(150, 500)]);
+        assert_eq!(search("#501 show:SSH"), vec![(0, 501)]);
+        assert_eq!(search("#500 show:ssh"), vec![]);
+
+        assert_eq!(search("domain:github.com"), vec![(0, 500)]);
+        assert_eq!(search("domain:nixos.org"), vec![(0, 490), (0, 500)]);
+        assert_eq!(
+            search("domain:nixos.org -domain:github.com"),
+            vec![(0, 490)]
+        );
+        assert_eq!(search("domain:hub.com"), vec![]);
+
+        assert_eq!(search("bcachefs"), vec![(25, 501), (10, 500)]);
+        assert_eq!(search("pool"), vec![(15, 490), (10, 500)]);
+
+        // scoped terms only match and score their field
+        assert_eq!(search("tag:arch"), vec![(50, 490)]);
+        assert_eq!(search("title:\"the arch\""), vec![(100, 490)]);
+        assert_eq!(search("Title:bcachefs"), vec![]);
+        assert_eq!(search("id:501"), vec![(0, 501)]);
+        assert_eq!(search("tag:btrfs -id:490"), vec![(50, 501)]);
+        assert_eq!(search("zfs -title:forever"), vec![(50, 501)]);
+        assert_eq!(search("btrfs -tag:arch"), vec![(50, 501)]);
+        assert_eq!(search("tag:ar"), vec![]);
+        assert_eq!(search("title:disaster"), vec![(100, 490)]);
+        assert_eq!(search("title:pool"), vec![]);
+
+        // boolean operators, a term next to a group matches either
+        assert_eq!(search("zfs AND btrfs"), vec![(100, 501)]);
+        assert_eq!(search("(arch OR zfs) -btrfs"), vec![(150, 500)]);
+        assert_eq!(search("btrfs AND -(show:ssh)"), vec![(50, 490)]);
+        assert_eq!(search("(zfs AND btrfs) arch"), vec![(200, 490), (100, 501)]);
+        assert_eq!(search("-(zfs OR arch)"), vec![]);
+
+        // misspelled terms match the closest words for half the points
+        assert_eq!(search("bcachfs"), vec![(12, 501), (5, 500)]);
+        assert_eq!(search("filesytems"), vec![(50, 501)]);
+        assert_eq!(search("arch disastr"), vec![(200, 490)]);
+        assert_eq!(search("btrf"), vec![(50, 490), (50, 501)]);
+
+        // inflected terms match their stem where they aren't found themselves
+        assert_eq!(search("pools"), vec![(15, 490), (10, 500)]);
+        assert_eq!(search("arches"), vec![(150, 490)]);
+        assert_eq!(search("disasters"), vec![(100, 490)]);
+
+        // compounds also match their parts
+        assert_eq!(search("arch-disaster"), vec![(250, 490)]);
+        assert_eq!(search("zfs/forever"), vec![(250, 500), (50, 501)]);
+
+        // the first version of the query language has no fields nor episode numbers
+        let legacy = |query: &str| -> Vec<i64> {
+            search_index_version(
+                &index,
+                query,
+                QueryVersion::V1,
+                &load_common_words(),
+                &ScoringConfig::default(),
+                || false,
+            )
+            .episodes
+            .iter()
+            .map(|(_, episode)| episode.id)
+            .collect()
+        };
+
+        assert!(legacy("guest:wendell").is_empty());
+        assert!(legacy("#12").is_empty());
+        assert_eq!(legacy("zfs -btrfs"), vec![500]);
+
+        let out_of_time = search_index(
+            &index,
+            "zfs",
+            &load_common_words(),
+            &ScoringConfig::default(),
+            || true,
+        );
+        assert!(out_of_time.episodes.is_empty());
+    }
+
+    #[test]
+    fn test_search_index_numbers() {
+        let episode = |id: i64, title: &str, tags: &[&str]| Episode {
+            id,
+            title: String::from(title),
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..Default::default()
+        };
+
+        let by_id = EpisodesById::from([
+            (512, episode(512, "512: Arch Again", &["arch"])),
+            (530, episode(530, "530: 512GB of RAM", &["512gb", "ram"])),
+            (540, episode(540, "540: Fedora", &["fedora"])),
+        ]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![512]),
+            (String::from("512gb"), vec![530]),
+            (String::from("ram"), vec![530]),
+            (String::from("fedora"), vec![540]),
+        ]);
+        let index = ShowIndex::new(by_id, by_tag, None);
+
+        let search = |query: &str, version: QueryVersion| -> Vec<(i32, i64)> {
+            search_index_version(
+                &index,
+                query,
+                version,
+                &load_common_words(),
+                &flat(),
+                || false,
+            )
+            .episodes
+            .iter()
+            .map(|(score, episode)| (*score, episode.id))
+            .collect()
+        };
+
+        // the episode numbered 512 first, even though the title saying 512 scores higher
+        assert_eq!(
+            search("512", QueryVersion::LATEST),
+            vec![(100, 512), (150, 530)]
+        );
+        assert_eq!(search("#512", QueryVersion::LATEST), vec![(0, 512)]);
+        assert_eq!(
+            search("fedora 512", QueryVersion::LATEST),
+            vec![(100, 512), (150, 530), (150, 540)]
+        );
+
+        // the first version of the query language ranks by score alone
+        assert_eq!(
+            search("512", QueryVersion::V1),
+            vec![(150, 530), (100, 512)]
+        );
+    }
+
+    #[test]
+    fn test_search_index_ranges() {
+        let episode = |id: i64, date: &str, duration: &str| Episode {
+            id,
+            title: format!("{}: Linux", id),
+            date: String::from(date),
+            duration: String::from(duration),
+            ..Default::default()
+        };
+
+        let by_id = EpisodesById::from([
+            (490, episode(490, "December 21st, 2021", "45:10")),
+            (500, episode(500, "June 1st, 2023", "1:02:00")),
+            (510, episode(510, "March 19th, 2024", "30:00")),
+            (520, episode(520, "soon", "")),
+        ]);
+        let index = ShowIndex::new(by_id, EpisodesByTag::new(), None);
+
+        let search = |query: &str, version: QueryVersion| -> Vec<i64> {
+            let mut ids: Vec<_> = search_index_version(
+                &index,
+                query,
+                version,
+                &load_common_words(),
+                &ScoringConfig::default(),
+                || false,
+            )
+            .episodes
+            .iter()
+            .map(|(_, episode)| episode.id)
+            .collect();
+            ids.sort();
+            ids
+        };
+
+        let latest = QueryVersion::LATEST;
+        assert_eq!(search("linux after:2022", latest), vec![500, 510]);
+        assert_eq!(search("linux before:2023-06", latest), vec![490]);
+        assert_eq!(search("after:2023-06-01 before:2024", latest), vec![500]);
+        assert_eq!(search("linux -after:2023-06", latest), vec![490]);
+        assert_eq!(search("longer:45m", latest), vec![490, 500]);
+        assert_eq!(search("linux shorter:1h", latest), vec![490, 510]);
+        assert_eq!(search("linux -longer:45m", latest), vec![510]);
+        assert_eq!(
+            search("(after:2024 OR longer:1h) AND linux", latest),
+            vec![500, 510]
+        );
+
+        // an unparseable bound is dropped, older versions search the words
+        assert_eq!(
+            search("linux after:tomorrow", latest),
+            vec![490, 500, 510, 520]
+        );
+        assert!(search("after:2022", QueryVersion::V4).is_empty());
+    }
+
+    #[test]
+    fn test_search_index_bm25() {
+        let episode = |id: i64, title: &str, tags: &[&str]| Episode {
+            id,
+            title: String::from(title),
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..Default::default()
+        };
+
+        let by_id: EpisodesById = [
+            episode(1, "Steam Deck Gaming on Fedora", &["linux", "gaming"]),
+            episode(2, "Fedora Workstation Review", &["linux", "fedora"]),
+            episode(3, "Arch Gaming Rig", &["linux"]),
+            episode(4, "Kernel News", &["linux"]),
+            episode(5, "Ubuntu Again", &["linux"]),
+        ]
+        .into_iter()
+        .map(|episode| (episode.id as usize, episode))
+        .collect();
+        let by_tag = EpisodesByTag::from([
+            (String::from("linux"), vec![1, 2, 3, 4, 5]),
+            (String::from("gaming"), vec![1]),
+            (String::from("fedora"), vec![2]),
+        ]);
+        let index = ShowIndex::new(by_id, by_tag, None);
+
+        let search = |query: &str, scoring: &ScoringConfig| -> Vec<(i32, i64)> {
+            search_index(&index, query, &load_common_words(), scoring, || false)
+                .episodes
+                .iter()
+                .map(|(score, episode)| (*score, episode.id))
+                .collect()
+        };
+
+        assert_eq!(search("steam fedora", &flat()), vec![(200, 1), (150, 2)]);
+        assert!(search("linux", &flat())
+            .iter()
+            .all(|(score, _)| *score == 50));
+
+        // the rare words of a title outrank a tag, the tag of every episode is worth little
+        let bm25 = search("steam fedora", &ScoringConfig::default());
+        assert_eq!(bm25[0].1, 1);
+        assert!(bm25[0].0 > bm25[1].0);
+
+        let linux = search("linux", &ScoringConfig::default());
+        assert_eq!(linux.len(), 5);
+        assert!(linux.iter().all(|(score, _)| *score < 50));
+    }
+
+    #[test]
+    fn test_search_index_order() {
+        // enough candidates for the parallel scoring
+        let by_id: EpisodesById = (1..=3000)
+            .map(|id| {
+                let episode = Episode {
+                    id: id as i64,
+                    title: format!("{}: Linux", id),
+                    tags: if id % 3 == 0 {
+                        vec![Tag::from("linux")]
+                    } else {
+                        Vec::new()
+                    },
+                    ..Default::default()
+                };
+                (id, episode)
+            })
+            .collect();
+        let by_tag =
+            EpisodesByTag::from([(String::from("linux"), (3..=3000).step_by(3).collect())]);
+        let index = ShowIndex::new(by_id, by_tag, None);
+
+        let results = search_index(&index, "linux", &load_common_words(), &flat(), || false);
+        let order: Vec<_> = results
+            .episodes
+            .iter()
+            .map(|(score, episode)| (*score, episode.id))
+            .collect();
+
+        let mut expected: Vec<_> = (1..=3000)
+            .map(|id| (if id % 3 == 0 { 150 } else { 100 }, id))
+            .collect();
+        expected.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(a.cmp(b)));
+
+        assert_eq!(order, expected);
+    }
+
+    #[test]
+    fn test_score_episode() {
+        // plain tags and weighted ones mixed
+        let tags = r#"["zfs",{"name":"zfs on root","weight":50,"category":"topic"},"btrfs"]"#;
+        let episode = Episode {
+            title: String::from("496: ZFS All The Things"),
+            tags: serde_json::from_str(tags).unwrap(),
+            chapters: vec![
+                Chapter {
+                    title: String::from("ZFS send"),
+                    start_secs: 0,
+                },
+                Chapter {
+                    title: String::from("ZFS receive"),
+                    start_secs: 600,
+                },
+            ],
+            description: Some(String::from("Moving all the things to a new pool.")),
+            ..Default::default()
+        };
+        let terms = HashSet::from([String::from("zfs"), String::from("things")]);
+
+        assert_eq!(
+            episode.tags[1],
+            Tag {
+                name: String::from("zfs on root"),
+                weight: Some(50),
+                category: Some(TagCategory::Topic),
+            }
+        );
+        assert_eq!(serde_json::to_string(&episode.tags).unwrap(), tags);
+
+        assert_eq!(
+            score_episode(&episode, &terms, &ScoringConfig::default()),
+            ScoreBreakdown {
+                tags: vec![(String::from("zfs"), 50), (String::from("zfs on root"), 25)],
+                title: vec![(String::from("things"), 100), (String::from("zfs"), 100)],
+                chapters: vec![(String::from("zfs"), 25)],
+                description: vec![(String::from("things"), 15)],
+                transcript: Vec::new(),
+                total: 315,
+            }
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{Html, IntoResponse, Response};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Instant;
+use tera::Context;
+use unplugged_engine::{
+    approximate_listening_time, group_by_name, highlight_terms, listening_time, podcast_app_links,
+    query_terms, search_permalink, GroupBy, Pagination, SortOrder,
+};
+
+use crate::bookmarks::favorites_cookie;
+use crate::error_pages::{bad_query_page, internal_error_page};
+use crate::maintenance::maintenance_page;
+use crate::timeouts::search_was_cut_short;
+use crate::{
+    after_page_offset, chapter_terms, checked_search_query, record_query, search_episodes,
+    search_filters, year_groups, AppState, ResultEpisode, SearchQuery,
+};
+
+// episodes of each show when the results are grouped by show
+const SHOW_GROUP_LIMIT: usize = 5;
+
+/// The state of every show, a single one with an empty slug
+/// unless the config lists `[[shows]]`
+pub type Shows = Arc<Vec<Arc<AppState>>>;
+
+// search?query=foo at the root of a multi-show archive,
+// searches every show and merges the results by score
+pub async fn handle_search_all(
+    search: Result<Query<SearchQuery>, QueryRejection>,
+    headers: HeaderMap,
+    State(shows): State<Shows>,
+) -> Response {
+    let base_path = &shows[0].config().server.base_path;
+    let templates = &shows[0].templates;
+    let lang = templates.negotiate(&headers);
+
+    let search = match checked_search_query(search) {
+        Ok(search) => search,
+        Err((query, err)) => return bad_query_page(templates, base_path, lang, &query, &err),
+    };
+
+    // the shows in maintenance just have no results, unless all of them are
+    if shows.iter().all(|show| !show.index.is_ready()) {
+        return maintenance_page(templates, base_path, lang);
+    }
+
+    let fragment = search.partial || headers.contains_key("HX-Request");
+
+    let start = Instant::now();
+
+    let indices: Vec<_> = shows.iter().map(|show| show.index()).collect();
+
+    let mut results: Vec<_> = shows
+        .iter()
+        .zip(indices.iter())
+        .flat_map(|(show, index)| {
+            let results = search_episodes(show, index, &search.query, search.qv);
+            record_query(show, &search.query, results.len(), start.elapsed(), false);
+
+            results
+                .into_iter()
+                .map(move |(score, episode)| (score, show, index, episode))
+        })
+        .collect();
+
+    results.sort_by(|(a_score, ..), (b_score, ..)| b_score.cmp(a_score));
+    search.sort.sort(&mut results, |(.., index, episode)| {
+        index.search_fields.get(&(episode.id as usize))
+    });
+    let took_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let listening_seconds = listening_time(results.iter().map(|(.., episode)| *episode));
+
+    let total_results = results.len();
+    let group_by_show = search.group_by == Some(GroupBy::Show);
+
+    // the sections per show list their first episodes and link to the rest, they aren't paged
+    let pagination =
+        (!group_by_show).then(|| Pagination::new(search.page, search.per_page, total_results));
+
+    if let Some(pagination) = &pagination {
+        results = pagination.slice(results);
+    }
+
+    let terms = chapter_terms(&search.query);
+    let highlights = highlight_terms(&query_terms(&search.query), &shows[0].common_words());
+    let episodes: Vec<Value> = results
+        .iter()
+        .map(|(_, show, index, episode)| {
+            let result = ResultEpisode::new(episode, &terms, &highlights, &index.transcripts);
+            let mut value = serde_json::to_value(result).unwrap();
+            value["show"] = json!({
+                "slug": show.show.slug,
+                "name": show.show.name,
+                "path": show.mount_path,
+                "favorites_cookie": favorites_cookie(show),
+                "app_links": podcast_app_links(&show.show.podcast),
+            });
+            value
+        })
+        .collect();
+
+    let (grouped, groups) = match &pagination {
+        None => {
+            let show_name = |episode: &Value| {
+                episode["show"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            (true, group_by_name(episodes, show_name, SHOW_GROUP_LIMIT))
+        }
+        Some(pagination) => {
+            let (grouped, groups) = year_groups(episodes, search.sort, |episode| {
+                episode["date"].as_str().unwrap_or_default()
+            });
+            (grouped, after_page_offset(groups, pagination))
+        }
+    };
+
+    let template = if fragment {
+        "partials/results.html"
+    } else {
+        "results.html"
+    };
+
+    let context = Context::from_serialize(json!({
+        "groups": groups,
+        "grouped": grouped,
+        "group_by_show": group_by_show,
+        "shows_search": true,
+        "query": search.query,
+        "total_results": total_results,
+        "pagination": pagination,
+        "sort": search.sort,
+        "sort_orders": SortOrder::ALL,
+        "listening_seconds": approximate_listening_time(listening_seconds),
+        "took_ms": took_ms,
+        "partial": search_was_cut_short(),
+        "filters": search_filters(&search.query),
+        "search_params": search.params().to_query_string(),
+        "permalink": search_permalink(base_path, &search.params()),
+        "track_clicks": shows[0].click_log.is_some(),
+        "feedback": shows[0].feedback_log.is_some(),
+        "base_path": base_path,
+        "lang": lang,
+    }));
+
+    match context.and_then(|context| templates.render(template, &context)) {
+        Ok(html) => ([(header::VARY, "HX-Request, Accept-Language")], Html(html)).into_response(),
+        Err(err) => internal_error_page(templates, base_path, lang, Some(&search.query), &err),
+    }
+}
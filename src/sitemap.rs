@@ -0,0 +1,127 @@
+use crate::{
+    encode_query_component, escape_xml, parse_date, EpisodeSlugs, EpisodesById, EpisodesByTag,
+};
+use chrono::NaiveDate;
+
+#[derive(Clone, Debug)]
+pub struct SitemapEntry {
+    /// path relative to the root of the instance, e.g. "/episode/512-docker-shocker"
+    pub path: String,
+    pub lastmod: Option<NaiveDate>,
+}
+
+/// Pages of the instance worth crawling, built once from the in-memory index
+#[derive(Clone, Debug)]
+pub struct Sitemap {
+    pub entries: Vec<SitemapEntry>,
+}
+
+impl Sitemap {
+    pub fn new(by_id: &EpisodesById, by_tag: &EpisodesByTag, slugs: &EpisodeSlugs) -> Self {
+        let mut entries = Vec::new();
+
+        let newest = by_id
+            .values()
+            .filter_map(|episode| parse_date(&episode.date))
+            .max();
+
+        entries.push(SitemapEntry {
+            path: String::from("/"),
+            lastmod: newest,
+        });
+
+        let mut ids: Vec<_> = by_id.keys().collect();
+        ids.sort();
+
+        for id in ids {
+            entries.push(SitemapEntry {
+                path: format!("/episode/{}", slugs.slug(*id).unwrap_or_default()),
+                lastmod: parse_date(&by_id[id].date),
+            });
+        }
+
+        let mut tags: Vec<_> = by_tag.iter().collect();
+        tags.sort_by_key(|(tag, _)| *tag);
+
+        for (tag, ids) in tags {
+            // the tag page changes whenever a new episode gets the tag
+            let lastmod = ids
+                .iter()
+                .filter_map(|id| by_id.get(id))
+                .filter_map(|episode| parse_date(&episode.date))
+                .max();
+
+            entries.push(SitemapEntry {
+                path: format!("/search?query={}", encode_tag(tag)),
+                lastmod,
+            });
+        }
+
+        Self { entries }
+    }
+
+    /// Renders the sitemap as xml, `base_url` is the absolute url of the instance
+    pub fn to_xml(&self, base_url: &str) -> String {
+        let base_url = base_url.trim_end_matches('/');
+        let mut xml = String::new();
+
+        xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+
+        for entry in &self.entries {
+            xml.push_str("  <url>\n");
+            xml.push_str(&format!(
+                "    <loc>{}</loc>\n",
+                escape_xml(&format!("{}{}", base_url, entry.path))
+            ));
+
+            if let Some(lastmod) = entry.lastmod {
+                xml.push_str(&format!(
+                    "    <lastmod>{}</lastmod>\n",
+                    lastmod.format("%Y-%m-%d")
+                ));
+            }
+
+            xml.push_str("  </url>\n");
+        }
+
+        xml.push_str("</urlset>\n");
+        xml
+    }
+}
+
+// tag pages are exact searches, i.e. the tag wrapped in quotes
+fn encode_tag(tag: &str) -> String {
+    format!("%22{}%22", encode_query_component(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sitemap;
+    use crate::{Episode, EpisodeSlugs, EpisodesById, EpisodesByTag, Tag};
+
+    #[test]
+    fn test_sitemap() {
+        let episode = Episode {
+            id: 512,
+            title: String::from("512: Docker Shocker"),
+            date: String::from("March 19th, 2023"),
+            duration: String::from("01:08:28"),
+            tags: vec![Tag::from("docker compose")],
+            url: String::from("https://linuxunplugged.com/512"),
+            ..Default::default()
+        };
+
+        let by_id = EpisodesById::from([(512, episode)]);
+        let by_tag = EpisodesByTag::from([(String::from("docker compose"), vec![512])]);
+
+        let slugs = EpisodeSlugs::new(&by_id);
+        let xml = Sitemap::new(&by_id, &by_tag, &slugs).to_xml("http://localhost:3000");
+
+        assert!(xml.contains("<loc>http://localhost:3000/episode/512-docker-shocker</loc>"));
+        assert!(
+            xml.contains("<loc>http://localhost:3000/search?query=%22docker%20compose%22</loc>")
+        );
+        assert_eq!(xml.matches("<lastmod>2023-03-19</lastmod>").count(), 3);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::EpisodesById;
+use std::collections::HashMap;
+
+/// `490: The Arch Disaster!` -> `the-arch-disaster`, lowercase ascii letters and digits
+/// joined by dashes, the episode number the titles start with is left out
+pub fn slugify(title: &str) -> String {
+    let title = match title.split_once(':') {
+        Some((number, rest)) if number.trim().chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => title,
+    };
+
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The url slugs of the episodes (`490-the-arch-disaster`) and the reverse lookup,
+/// built when the index is loaded
+#[derive(Clone, Debug, Default)]
+pub struct EpisodeSlugs {
+    slugs: HashMap<usize, String>,
+    ids: HashMap<String, usize>,
+}
+
+impl EpisodeSlugs {
+    pub fn new(by_id: &EpisodesById) -> Self {
+        let mut ids: Vec<_> = by_id.keys().copied().collect();
+        ids.sort();
+
+        let mut episode_slugs = Self::default();
+
+        for id in ids {
+            let title = slugify(&by_id[&id].title);
+            let base = if title.is_empty() {
+                id.to_string()
+            } else {
+                format!("{}-{}", id, title)
+            };
+
+            // the id keeps slugs apart, a suffix settles whatever still collides
+            let mut slug = base.clone();
+            let mut n = 2;
+
+            while episode_slugs.ids.contains_key(&slug) {
+                slug = format!("{}-{}", base, n);
+                n += 1;
+            }
+
+            episode_slugs.ids.insert(slug.clone(), id);
+            episode_slugs.slugs.insert(id, slug);
+        }
+
+        episode_slugs
+    }
+
+    pub fn slug(&self, id: usize) -> Option<&str> {
+        self.slugs.get(&id).map(String::as_str)
+    }
+
+    pub fn id(&self, slug: &str) -> Option<usize> {
+        self.ids.get(slug).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slugify, EpisodeSlugs};
+    use crate::{Episode, EpisodesById};
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("490: The Arch Disaster"), "the-arch-disaster");
+        assert_eq!(slugify("DNF or Die!"), "dnf-or-die");
+        assert_eq!(slugify("  Rust: 2024 -- Édition "), "rust-2024-dition");
+        assert_eq!(slugify("512: ???"), "");
+    }
+
+    #[test]
+    fn test_episode_slugs() {
+        let episode = |id: i64, title: &str| Episode {
+            id,
+            title: String::from(title),
+            date: String::new(),
+            duration: String::new(),
+            tags: vec![],
+            url: String::new(),
+            ..Default::default()
+        };
+
+        let by_id = EpisodesById::from([
+            (490, episode(490, "490: The Arch Disaster")),
+            (512, episode(512, "512: ???")),
+        ]);
+        let slugs = EpisodeSlugs::new(&by_id);
+
+        assert_eq!(slugs.slug(490), Some("490-the-arch-disaster"));
+        assert_eq!(slugs.id("490-the-arch-disaster"), Some(490));
+        assert_eq!(slugs.slug(512), Some("512"));
+        assert_eq!(slugs.id("the-arch-disaster"), None);
+    }
+}
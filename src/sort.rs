@@ -0,0 +1,105 @@
+use crate::SearchFields;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// The order of the results of a search, `sort=newest`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// best score first
+    #[default]
+    Relevance,
+    Newest,
+    Oldest,
+    Longest,
+    Shortest,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 5] = [
+        SortOrder::Relevance,
+        SortOrder::Newest,
+        SortOrder::Oldest,
+        SortOrder::Longest,
+        SortOrder::Shortest,
+    ];
+
+    /// How it's written in the urls, `newest`
+    pub fn name(self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "relevance",
+            SortOrder::Newest => "newest",
+            SortOrder::Oldest => "oldest",
+            SortOrder::Longest => "longest",
+            SortOrder::Shortest => "shortest",
+        }
+    }
+
+    /// Reorders results ranked by relevance, the episodes without a date or a duration
+    /// that parses go last and the ties keep their rank
+    pub fn sort<'a, T, F>(self, results: &mut [T], fields: F)
+    where
+        F: Fn(&T) -> Option<&'a SearchFields>,
+    {
+        let date = |result: &T| fields(result).and_then(|fields| fields.date);
+        let duration = |result: &T| fields(result).and_then(|fields| fields.duration);
+
+        match self {
+            SortOrder::Relevance => {}
+            SortOrder::Newest => results.sort_by(|a, b| compare(date(a), date(b), true)),
+            SortOrder::Oldest => results.sort_by(|a, b| compare(date(a), date(b), false)),
+            SortOrder::Longest => results.sort_by(|a, b| compare(duration(a), duration(b), true)),
+            SortOrder::Shortest => results.sort_by(|a, b| compare(duration(a), duration(b), false)),
+        }
+    }
+}
+
+// the missing values after the others, whichever the direction
+fn compare<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SortOrder;
+    use crate::SearchFields;
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_sort_order() {
+        let fields = |day: Option<u32>, duration: Option<u64>| SearchFields {
+            date: day.and_then(|day| NaiveDate::from_ymd_opt(2023, 6, day)),
+            duration,
+            ..Default::default()
+        };
+
+        let index = HashMap::from([
+            (1, fields(Some(10), Some(3600))),
+            (2, fields(None, Some(1800))),
+            (3, fields(Some(20), None)),
+            (4, fields(Some(10), Some(5400))),
+        ]);
+
+        let sorted = |order: SortOrder| -> Vec<usize> {
+            let mut ranked = vec![1, 2, 3, 4];
+            order.sort(&mut ranked, |id| index.get(id));
+            ranked
+        };
+
+        assert_eq!(sorted(SortOrder::Relevance), vec![1, 2, 3, 4]);
+        assert_eq!(sorted(SortOrder::Newest), vec![3, 1, 4, 2]);
+        assert_eq!(sorted(SortOrder::Oldest), vec![1, 4, 3, 2]);
+        assert_eq!(sorted(SortOrder::Longest), vec![4, 1, 2, 3]);
+        assert_eq!(sorted(SortOrder::Shortest), vec![2, 1, 4, 3]);
+
+        assert_eq!(
+            serde_urlencoded::from_str::<Vec<(String, SortOrder)>>("sort=oldest").unwrap(),
+            vec![(String::from("sort"), SortOrder::Oldest)]
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{Episode, EpisodesById, EpisodesByTag, IndexStore};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+// the episodes as json, the tags and their episodes in the order of the tag index
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS episodes (
+    id INTEGER PRIMARY KEY,
+    episode TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS postings (
+    tag_id INTEGER NOT NULL REFERENCES tags (id),
+    position INTEGER NOT NULL,
+    episode_id INTEGER NOT NULL,
+    PRIMARY KEY (tag_id, position)
+);
+";
+
+/// The index of a show in a sqlite database, the episodes of an update are upserted
+/// instead of rewriting the whole index, and the server reads it while it's written.
+/// `read` loads every row for the search indices, `episode` and `posting` query a single one
+pub struct SqliteIndexStore {
+    path: PathBuf,
+}
+
+impl SqliteIndexStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    fn open_read_only(&self) -> rusqlite::Result<Connection> {
+        Connection::open_with_flags(
+            &self.path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+    }
+
+    fn read_tables(&self) -> rusqlite::Result<(EpisodesById, EpisodesByTag)> {
+        let connection = self.open_read_only()?;
+
+        let mut by_id = EpisodesById::new();
+        let mut episodes = connection.prepare("SELECT id, episode FROM episodes")?;
+        let mut rows = episodes.query([])?;
+
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            by_id.insert(id as usize, parse_episode(&row.get::<_, String>(1)?)?);
+        }
+
+        let mut by_tag = EpisodesByTag::new();
+        let mut postings = connection.prepare(
+            "SELECT tags.name, postings.episode_id FROM postings
+             JOIN tags ON tags.id = postings.tag_id
+             ORDER BY postings.tag_id, postings.position",
+        )?;
+        let mut rows = postings.query([])?;
+
+        while let Some(row) = rows.next()? {
+            let tag: String = row.get(0)?;
+            let id: i64 = row.get(1)?;
+
+            by_tag.entry(tag).or_default().push(id as usize);
+        }
+
+        Ok((by_id, by_tag))
+    }
+
+    fn read_episode(&self, id: usize) -> rusqlite::Result<Option<Episode>> {
+        let episode: Option<String> = self
+            .open_read_only()?
+            .query_row(
+                "SELECT episode FROM episodes WHERE id = ?1",
+                params![id as i64],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        episode.as_deref().map(parse_episode).transpose()
+    }
+
+    fn read_posting(&self, tag: &str) -> rusqlite::Result<Option<Vec<usize>>> {
+        let connection = self.open_read_only()?;
+        let mut posting = connection.prepare(
+            "SELECT postings.episode_id FROM postings
+             JOIN tags ON tags.id = postings.tag_id
+             WHERE tags.name = ?1 ORDER BY postings.position",
+        )?;
+
+        let ids = posting
+            .query_map(params![tag], |row| row.get::<_, i64>(0))?
+            .map(|id| id.map(|id| id as usize))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // the tags without episodes are dropped
+        Ok(Some(ids).filter(|ids| !ids.is_empty()))
+    }
+
+    // a single transaction, the readers see the index before or after it,
+    // only the rows that changed are written, returns how many
+    fn write_tables(
+        &self,
+        by_id: &EpisodesById,
+        by_tag: &EpisodesByTag,
+        replace: bool,
+    ) -> rusqlite::Result<usize> {
+        let mut connection = Connection::open(&self.path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+
+        let transaction = connection.transaction()?;
+        let mut written = 0;
+
+        if replace {
+            transaction
+                .execute_batch("DELETE FROM postings; DELETE FROM tags; DELETE FROM episodes;")?;
+        }
+
+        {
+            let mut episode = transaction.prepare(
+                "INSERT INTO episodes (id, episode) VALUES (?1, ?2)
+                 ON CONFLICT (id) DO UPDATE SET episode = excluded.episode
+                 WHERE episode != excluded.episode",
+            )?;
+
+            for (id, value) in by_id.iter() {
+                let value = serde_json::to_string(value)
+                    .map_err(|err| rusqlite::Error::ToSqlConversionFailure(err.into()))?;
+
+                written += episode.execute(params![*id as i64, value])?;
+            }
+
+            let mut add_tag = transaction
+                .prepare("INSERT INTO tags (name) VALUES (?1) ON CONFLICT DO NOTHING")?;
+            let mut tag = transaction.prepare("SELECT id FROM tags WHERE name = ?1")?;
+            let mut truncate =
+                transaction.prepare("DELETE FROM postings WHERE tag_id = ?1 AND position >= ?2")?;
+            let mut drop_tag = transaction.prepare("DELETE FROM tags WHERE id = ?1")?;
+            let mut posting = transaction.prepare(
+                "INSERT INTO postings (tag_id, position, episode_id) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (tag_id, position) DO UPDATE SET episode_id = excluded.episode_id
+                 WHERE episode_id != excluded.episode_id",
+            )?;
+
+            for (name, ids) in by_tag.iter() {
+                if ids.is_empty() {
+                    let tag_id: Option<i64> =
+                        tag.query_row(params![name], |row| row.get(0)).optional()?;
+
+                    if let Some(tag_id) = tag_id {
+                        written += truncate.execute(params![tag_id, 0])?;
+                        written += drop_tag.execute(params![tag_id])?;
+                    }
+                    continue;
+                }
+
+                written += add_tag.execute(params![name])?;
+                let tag_id: i64 = tag.query_row(params![name], |row| row.get(0))?;
+
+                for (position, id) in ids.iter().enumerate() {
+                    written += posting.execute(params![tag_id, position as i64, *id as i64])?;
+                }
+
+                written += truncate.execute(params![tag_id, ids.len() as i64])?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(written)
+    }
+}
+
+fn parse_episode(episode: &str) -> rusqlite::Result<Episode> {
+    serde_json::from_str(episode)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, err.into()))
+}
+
+impl IndexStore for SqliteIndexStore {
+    fn read(&self) -> Result<(EpisodesById, EpisodesByTag), String> {
+        self.read_tables()
+            .map_err(|err| format!("Error at reading {}: {}", self.path.display(), err))
+    }
+
+    fn episode(&self, id: usize) -> Result<Option<Episode>, String> {
+        self.read_episode(id)
+            .map_err(|err| format!("Error at reading {}: {}", self.path.display(), err))
+    }
+
+    fn posting(&self, tag: &str) -> Result<Option<Vec<usize>>, String> {
+        self.read_posting(tag)
+            .map_err(|err| format!("Error at reading {}: {}", self.path.display(), err))
+    }
+
+    fn replace(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String> {
+        self.write_tables(by_id, by_tag, true)
+            .map(|_| ())
+            .map_err(|err| format!("Error at writing {}: {}", self.path.display(), err))
+    }
+
+    fn upsert(&self, by_id: &EpisodesById, by_tag: &EpisodesByTag) -> Result<(), String> {
+        self.write_tables(by_id, by_tag, false)
+            .map(|_| ())
+            .map_err(|err| format!("Error at writing {}: {}", self.path.display(), err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqliteIndexStore;
+    use crate::{Episode, EpisodesById, EpisodesByTag, IndexStore, Tag};
+
+    #[test]
+    fn test_sqlite_index_store() {
+        let path =
+            std::env::temp_dir().join(format!("unplugged-store-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let store = SqliteIndexStore::new(&path);
+        assert!(store.read().is_err());
+
+        let episode = |id: i64, title: &str, tags: &[&str]| Episode {
+            id,
+            title: String::from(title),
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..Default::default()
+        };
+
+        let by_id = EpisodesById::from([
+            (
+                490,
+                episode(490, "490: The Arch Disaster", &["arch", "btrfs"]),
+            ),
+            (500, episode(500, "500: ZFS Forever", &["zfs"])),
+            (501, episode(501, "501: Filesystems", &["zfs", "btrfs"])),
+        ]);
+        let by_tag = EpisodesByTag::from([
+            (String::from("arch"), vec![490]),
+            (String::from("btrfs"), vec![501, 490]),
+            (String::from("zfs"), vec![500, 501]),
+        ]);
+
+        store.replace(&by_id, &by_tag).unwrap();
+        assert_eq!(store.read().unwrap(), (by_id.clone(), by_tag.clone()));
+
+        // looked up without reading the rest
+        assert_eq!(store.episode(500).unwrap(), Some(by_id[&500].clone()));
+        assert_eq!(store.episode(499).unwrap(), None);
+        assert_eq!(store.posting("btrfs").unwrap(), Some(vec![501, 490]));
+        assert_eq!(store.posting("gnome").unwrap(), None);
+
+        // upserting what's stored writes nothing, a changed posting only its changed rows
+        assert_eq!(store.write_tables(&by_id, &by_tag, false).unwrap(), 0);
+        let reordered = EpisodesByTag::from([(String::from("btrfs"), vec![490, 501])]);
+        assert_eq!(
+            store
+                .write_tables(&EpisodesById::new(), &reordered, false)
+                .unwrap(),
+            2
+        );
+        store
+            .write_tables(&EpisodesById::new(), &by_tag, false)
+            .unwrap();
+
+        // an update only writes what changed, the rest is kept
+        store
+            .upsert(
+                &EpisodesById::from([(502, episode(502, "502: Bcachefs", &["bcachefs"]))]),
+                &EpisodesByTag::from([
+                    (String::from("bcachefs"), vec![502]),
+                    (String::from("arch"), vec![]),
+                    (String::from("zfs"), vec![501]),
+                ]),
+            )
+            .unwrap();
+
+        let (stored_by_id, stored_by_tag) = store.read().unwrap();
+        assert_eq!(stored_by_id.len(), 4);
+        assert_eq!(stored_by_id[&490], by_id[&490]);
+        assert_eq!(stored_by_tag["btrfs"], vec![501, 490]);
+        assert_eq!(stored_by_tag["bcachefs"], vec![502]);
+        assert_eq!(stored_by_tag["zfs"], vec![501]);
+        assert!(!stored_by_tag.contains_key("arch"));
+        assert_eq!(store.posting("arch").unwrap(), None);
+
+        // replacing drops the episodes of the previous index
+        store
+            .replace(
+                &EpisodesById::from([(500, by_id[&500].clone())]),
+                &EpisodesByTag::new(),
+            )
+            .unwrap();
+        assert_eq!(store.read().unwrap().0.len(), 1);
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
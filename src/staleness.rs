@@ -0,0 +1,70 @@
+use crate::{parse_date, ShowIndex};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// How out of date the index of a show is, by the date of its newest episode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexStaleness {
+    /// date of the newest episode, none when no date can be parsed
+    pub newest_episode: Option<NaiveDate>,
+    /// days since the newest episode
+    pub age_days: Option<i64>,
+    /// last successful refresh of the index
+    pub refreshed_at: DateTime<Utc>,
+    /// the newest episode is older than `max_days`, never with `max_days` 0
+    pub stale: bool,
+}
+
+impl IndexStaleness {
+    pub fn new(index: &ShowIndex, now: DateTime<Utc>, max_days: u32) -> Self {
+        let newest_episode = index.stats.last_date.as_deref().and_then(parse_date);
+        let age_days = newest_episode.map(|date| (now.date_naive() - date).num_days().max(0));
+
+        Self {
+            newest_episode,
+            age_days,
+            refreshed_at: index.loaded_at,
+            stale: max_days > 0 && age_days.is_some_and(|age| age > max_days as i64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexStaleness;
+    use crate::{Episode, EpisodesById, EpisodesByTag, ShowIndex};
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn test_index_staleness() {
+        let episode = |id: i64, date: &str| Episode {
+            id,
+            date: String::from(date),
+            ..Default::default()
+        };
+        let by_id = EpisodesById::from([
+            (1, episode(1, "March 5th, 2023")),
+            (2, episode(2, "March 19th, 2023")),
+            (3, episode(3, "")),
+        ]);
+        let index = ShowIndex::new(by_id, EpisodesByTag::new(), None);
+        let now = Utc.with_ymd_and_hms(2023, 4, 2, 12, 0, 0).unwrap();
+
+        let staleness = IndexStaleness::new(&index, now, 14);
+
+        assert_eq!(
+            staleness.newest_episode,
+            NaiveDate::from_ymd_opt(2023, 3, 19)
+        );
+        assert_eq!(staleness.age_days, Some(14));
+        assert_eq!(staleness.refreshed_at, index.loaded_at);
+        assert!(!staleness.stale);
+
+        assert!(IndexStaleness::new(&index, now, 13).stale);
+        assert!(!IndexStaleness::new(&index, now, 0).stale);
+
+        let undated = IndexStaleness::new(&ShowIndex::empty(), now, 1);
+
+        assert_eq!(undated.age_days, None);
+        assert!(!undated.stale);
+    }
+}
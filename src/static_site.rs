@@ -0,0 +1,229 @@
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use tera::{Context, Tera};
+use unplugged_engine::{group_by_year, parse_date, slugify, Config, Episode, ShowIndex};
+
+use crate::args::ExportSiteArgs;
+use crate::assets::{bundled_translations, compile_templates, error_chain, read_static_file};
+use crate::cli::select_shows;
+use crate::episode_page_context;
+
+// static files every page links to
+const SITE_STATIC_FILES: [&str; 1] = ["index.css"];
+
+/// Writes the archive of every show as static html: the list of the episodes by year
+/// (index.html), a page per episode (episode/<slug>/) and per tag (tag/<tag>/),
+/// the pages link each other instead of searching, which needs the server
+pub async fn export_site(config: &Config, args: ExportSiteArgs) -> Result<(), String> {
+    let base_path = args.base_path.trim_end_matches('/');
+
+    if !base_path.is_empty() && !base_path.starts_with('/') {
+        return Err(String::from(
+            "Error at --base-path: expected a path starting with a slash, e.g. \"/archive\"",
+        ));
+    }
+
+    let translations = Arc::new(bundled_translations());
+    let tera = compile_templates(&config.paths.templates, &translations)
+        .map_err(|err| format!("Error at compiling the templates: {}", error_chain(&err)))?;
+
+    for show in select_shows(config, args.show.as_deref())? {
+        let index = ShowIndex::load(&show).await?;
+
+        let (dir, show_path) = if show.slug.is_empty() {
+            (args.out.clone(), base_path.to_string())
+        } else {
+            (
+                args.out.join(&show.slug),
+                format!("{}/{}", base_path, show.slug),
+            )
+        };
+
+        let site = Site {
+            tera: &tera,
+            dir: &dir,
+            base_path: &show_path,
+            lang: &args.lang,
+        };
+
+        let pages = site.write_show(&index)?;
+
+        for name in SITE_STATIC_FILES {
+            let contents = read_static_file(&config.paths.static_dir, name)
+                .ok_or_else(|| format!("Error at reading the static file {}", name))?;
+            write_file(&dir.join(name), &contents)?;
+        }
+
+        println!("Wrote {} pages to {}", pages, dir.display());
+    }
+
+    Ok(())
+}
+
+struct Site<'a> {
+    tera: &'a Tera,
+    dir: &'a Path,
+    base_path: &'a str,
+    lang: &'a str,
+}
+
+impl Site<'_> {
+    // the pages of a show, returns how many were written
+    fn write_show(&self, index: &ShowIndex) -> Result<usize, String> {
+        let tag_pages = tag_pages(index);
+        let mut pages = 0;
+
+        let mut episodes: Vec<_> = index.episodes_by_id.values().collect();
+        newest_first(&mut episodes);
+
+        for episode in episodes.iter() {
+            let Some(slug) = index.slugs.slug(episode.id as usize) else {
+                continue;
+            };
+
+            let mut context = episode_page_context(index, episode, slug);
+            context["static_site"] = true.into();
+            context["start"] = serde_json::Value::Null;
+            context["tag_pages"] = json!(tag_pages);
+
+            let html = self.render("episode.html", context)?;
+            write_file(
+                &self.dir.join("episode").join(slug).join("index.html"),
+                html.as_bytes(),
+            )?;
+            pages += 1;
+        }
+
+        let mut tags: Vec<_> = index.episodes_by_tag.iter().collect();
+        tags.sort_by(|(a, a_ids), (b, b_ids)| b_ids.len().cmp(&a_ids.len()).then(a.cmp(b)));
+
+        for (tag, ids) in tags.iter() {
+            let mut tagged: Vec<_> = ids
+                .iter()
+                .filter_map(|id| index.episodes_by_id.get(id))
+                .collect();
+            newest_first(&mut tagged);
+
+            let html = self.render_archive(index, &tagged, Some(tag), &[], &tag_pages)?;
+            let page = &tag_pages[tag.as_str()];
+            write_file(
+                &self.dir.join("tag").join(page).join("index.html"),
+                html.as_bytes(),
+            )?;
+            pages += 1;
+        }
+
+        let tag_list: Vec<_> = tags
+            .iter()
+            .map(|(tag, ids)| {
+                json!({ "name": tag, "page": tag_pages[tag.as_str()], "count": ids.len() })
+            })
+            .collect();
+
+        let html = self.render_archive(index, &episodes, None, &tag_list, &tag_pages)?;
+        write_file(&self.dir.join("index.html"), html.as_bytes())?;
+
+        Ok(pages + 1)
+    }
+
+    fn render_archive(
+        &self,
+        index: &ShowIndex,
+        episodes: &[&Episode],
+        tag: Option<&str>,
+        tags: &[serde_json::Value],
+        tag_pages: &HashMap<String, String>,
+    ) -> Result<String, String> {
+        let episodes: Vec<_> = episodes
+            .iter()
+            .map(|episode| {
+                let mut value = json!(episode);
+                value["slug"] = index.slugs.slug(episode.id as usize).into();
+                value
+            })
+            .collect();
+
+        let total = episodes.len();
+        let groups = group_by_year(episodes, |episode| {
+            episode["date"].as_str().unwrap_or_default()
+        });
+
+        self.render(
+            "archive.html",
+            json!({
+                "tag": tag,
+                "total": total,
+                "groups": groups,
+                "tags": tags,
+                "tag_pages": tag_pages,
+            }),
+        )
+    }
+
+    fn render(&self, template: &str, mut context: serde_json::Value) -> Result<String, String> {
+        context["base_path"] = self.base_path.into();
+        context["lang"] = self.lang.into();
+
+        Context::from_serialize(context)
+            .and_then(|context| self.tera.render(template, &context))
+            .map_err(|err| format!("Error at rendering {}: {}", template, error_chain(&err)))
+    }
+}
+
+// the directory of the page of every tag, by the tags of the index and the names
+// the episodes spell them with, slugs taken by another tag get a number
+fn tag_pages(index: &ShowIndex) -> HashMap<String, String> {
+    let mut tags: Vec<_> = index.episodes_by_tag.keys().collect();
+    tags.sort();
+
+    let mut pages: HashMap<String, String> = HashMap::new();
+    let mut taken = HashSet::new();
+
+    for tag in tags {
+        let base = match slugify(tag) {
+            slug if slug.is_empty() => String::from("tag"),
+            slug => slug,
+        };
+
+        let mut page = base.clone();
+        let mut n = 2;
+
+        while !taken.insert(page.clone()) {
+            page = format!("{}-{}", base, n);
+            n += 1;
+        }
+
+        pages.insert(tag.clone(), page);
+    }
+
+    for episode in index.episodes_by_id.values() {
+        for tag in episode.tag_names() {
+            if let Some(page) = pages.get(&tag.trim().to_lowercase()).cloned() {
+                pages.entry(tag.to_string()).or_insert(page);
+            }
+        }
+    }
+
+    pages
+}
+
+// by date, the newest episode first and the ones without a date last
+fn newest_first(episodes: &mut [&Episode]) {
+    episodes.sort_by(|a, b| {
+        parse_date(&b.date)
+            .cmp(&parse_date(&a.date))
+            .then(b.id.cmp(&a.id))
+    });
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| format!("Error at creating {}: {}", parent.display(), err))?;
+    }
+
+    std::fs::write(path, contents)
+        .map_err(|err| format!("Error at writing {}: {}", path.display(), err))
+}
@@ -0,0 +1,526 @@
+use crate::{format_duration, parse_date, parse_duration, Episode, EpisodesById, EpisodesByTag};
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: String,
+    pub episodes: usize,
+}
+
+/// The order of the tags of the /tags page, `sort=name`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagOrder {
+    /// most episodes first
+    #[default]
+    Episodes,
+    /// alphabetical, ignoring the case
+    Name,
+}
+
+/// Every tag with its number of episodes, the ties by name
+pub fn tag_counts(by_tag: &EpisodesByTag, order: TagOrder) -> Vec<TagCount> {
+    let mut tags: Vec<_> = by_tag
+        .iter()
+        .map(|(tag, ids)| TagCount {
+            tag: tag.clone(),
+            episodes: ids.len(),
+        })
+        .collect();
+
+    match order {
+        TagOrder::Episodes => {
+            tags.sort_by(|a, b| b.episodes.cmp(&a.episodes).then(a.tag.cmp(&b.tag)))
+        }
+        TagOrder::Name => tags.sort_by(|a, b| {
+            a.tag
+                .to_lowercase()
+                .cmp(&b.tag.to_lowercase())
+                .then(a.tag.cmp(&b.tag))
+        }),
+    }
+
+    tags
+}
+
+/// Summary of the in-memory index for the /stats page, built once at startup
+#[derive(Serialize, Clone, Debug)]
+pub struct IndexStats {
+    pub episodes: usize,
+    pub tags: usize,
+    /// most used tags, most episodes first
+    pub top_tags: Vec<TagCount>,
+    /// dates of the oldest and newest episodes as written in the index
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    /// average of the parseable durations, "01:08:28"
+    pub average_duration: Option<String>,
+    /// total size of the audio files whose size the feed states
+    pub audio_bytes: Option<u64>,
+    /// episodes with an audio file
+    pub audio_episodes: usize,
+}
+
+impl IndexStats {
+    pub fn new(by_id: &EpisodesById, by_tag: &EpisodesByTag, top: usize) -> Self {
+        let mut top_tags = tag_counts(by_tag, TagOrder::Episodes);
+        top_tags.truncate(top);
+
+        let dated: Vec<_> = by_id
+            .values()
+            .filter_map(|episode| parse_date(&episode.date).map(|date| (date, &episode.date)))
+            .collect();
+
+        let first_date = dated.iter().min().map(|(_, date)| date.to_string());
+        let last_date = dated.iter().max().map(|(_, date)| date.to_string());
+
+        let durations: Vec<_> = by_id
+            .values()
+            .filter_map(|episode| parse_duration(&episode.duration))
+            .collect();
+
+        let average_duration = (!durations.is_empty())
+            .then(|| format_duration(durations.iter().sum::<u64>() / durations.len() as u64));
+
+        let audio: Vec<_> = by_id
+            .values()
+            .filter_map(|episode| episode.audio.as_ref())
+            .collect();
+        let sizes: Vec<_> = audio.iter().filter_map(|audio| audio.bytes).collect();
+        let audio_bytes = (!sizes.is_empty()).then(|| sizes.iter().sum());
+
+        Self {
+            episodes: by_id.len(),
+            tags: by_tag.len(),
+            top_tags,
+            first_date,
+            last_date,
+            average_duration,
+            audio_bytes,
+            audio_episodes: audio.len(),
+        }
+    }
+}
+
+/// Size of the buckets of a timeline
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelinePeriod {
+    #[default]
+    Year,
+    Month,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TimelineBucket {
+    /// "2023" or "2023-03"
+    pub period: String,
+    pub episodes: usize,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Timeline {
+    /// every period from the oldest to the newest episode, empty periods included
+    /// so charts don't skip them
+    pub buckets: Vec<TimelineBucket>,
+    /// episodes whose date can't be parsed
+    pub undated: usize,
+}
+
+/// Counts the episodes per year or month
+pub fn episode_timeline<'a>(
+    episodes: impl IntoIterator<Item = &'a Episode>,
+    period: TimelinePeriod,
+) -> Timeline {
+    let mut counts = BTreeMap::new();
+    let mut undated = 0;
+
+    for episode in episodes {
+        match parse_date(&episode.date) {
+            Some(date) => {
+                let month = match period {
+                    TimelinePeriod::Year => 1,
+                    TimelinePeriod::Month => date.month(),
+                };
+                *counts.entry((date.year(), month)).or_insert(0) += 1;
+            }
+            None => undated += 1,
+        }
+    }
+
+    let (Some(&first), Some(&last)) = (counts.keys().next(), counts.keys().next_back()) else {
+        return Timeline {
+            buckets: Vec::new(),
+            undated,
+        };
+    };
+
+    let mut buckets = Vec::new();
+    let (mut year, mut month) = first;
+
+    while (year, month) <= last {
+        buckets.push(TimelineBucket {
+            period: match period {
+                TimelinePeriod::Year => year.to_string(),
+                TimelinePeriod::Month => format!("{}-{:02}", year, month),
+            },
+            episodes: counts.get(&(year, month)).copied().unwrap_or(0),
+        });
+
+        if period == TimelinePeriod::Year || month == 12 {
+            year += 1;
+            month = 1;
+        } else {
+            month += 1;
+        }
+    }
+
+    Timeline { buckets, undated }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DurationBucket {
+    /// minutes, the bucket holds `from <= duration < to`
+    pub from: u64,
+    pub to: u64,
+    pub episodes: usize,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct UnparsedDuration {
+    pub id: i64,
+    pub duration: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct DurationHistogram {
+    /// from 0 to the longest episode, empty buckets included
+    pub buckets: Vec<DurationBucket>,
+    /// episodes the duration parser rejected, sorted by id
+    pub unparsed: Vec<UnparsedDuration>,
+}
+
+/// Counts the episodes per `minutes` long bucket of duration
+pub fn duration_histogram<'a>(
+    episodes: impl IntoIterator<Item = &'a Episode>,
+    minutes: u64,
+) -> DurationHistogram {
+    let bucket_size = minutes.max(1) * 60;
+    let mut counts: Vec<usize> = Vec::new();
+    let mut unparsed = Vec::new();
+
+    for episode in episodes {
+        let Some(seconds) = parse_duration(&episode.duration) else {
+            unparsed.push(UnparsedDuration {
+                id: episode.id,
+                duration: episode.duration.clone(),
+            });
+            continue;
+        };
+
+        let index = (seconds / bucket_size) as usize;
+
+        if counts.len() <= index {
+            counts.resize(index + 1, 0);
+        }
+
+        counts[index] += 1;
+    }
+
+    unparsed.sort_by_key(|episode| episode.id);
+
+    let minutes = bucket_size / 60;
+    let buckets = counts
+        .into_iter()
+        .enumerate()
+        .map(|(index, episodes)| DurationBucket {
+            from: index as u64 * minutes,
+            to: (index as u64 + 1) * minutes,
+            episodes,
+        })
+        .collect();
+
+    DurationHistogram { buckets, unparsed }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TrendingTag {
+    pub tag: String,
+    /// recent episodes with the tag
+    pub recent: usize,
+    /// older episodes with the tag
+    pub before: usize,
+    /// how many times more often the recent episodes have the tag than the older ones
+    pub lift: f64,
+}
+
+/// The tags of the `recent` newest episodes more frequent than in the older ones, the biggest
+/// rise first, tags of fewer than `min_episodes` recent episodes are left out as noise
+pub fn trending_tags<'a>(
+    episodes: impl IntoIterator<Item = &'a Episode>,
+    recent: usize,
+    min_episodes: usize,
+) -> Vec<TrendingTag> {
+    let mut dated: Vec<_> = episodes
+        .into_iter()
+        .filter_map(|episode| parse_date(&episode.date).map(|date| (date, episode)))
+        .collect();
+    dated.sort_by_key(|(date, episode)| std::cmp::Reverse((*date, episode.id)));
+
+    let recent = recent.min(dated.len());
+    let older = dated.len() - recent;
+
+    // (recent, before) episodes per tag
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for (position, (_, episode)) in dated.iter().enumerate() {
+        let mut tags: Vec<_> = episode
+            .tags
+            .iter()
+            .map(|tag| tag.name.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+
+        for tag in tags {
+            let count = counts.entry(tag).or_default();
+
+            if position < recent {
+                count.0 += 1;
+            } else {
+                count.1 += 1;
+            }
+        }
+    }
+
+    let mut trending: Vec<_> = counts
+        .into_iter()
+        .filter(|(_, (recent_count, _))| *recent_count >= min_episodes.max(1))
+        .map(|(tag, (recent_count, before))| {
+            // one more older episode with and without the tag, so new tags don't divide by zero
+            let recent_share = recent_count as f64 / recent as f64;
+            let before_share = (before + 1) as f64 / (older + 1) as f64;
+            let lift = (recent_share / before_share * 100.0).round() / 100.0;
+
+            TrendingTag {
+                tag,
+                recent: recent_count,
+                before,
+                lift,
+            }
+        })
+        .filter(|tag| tag.lift > 1.0)
+        .collect();
+
+    trending.sort_by(|a, b| {
+        b.lift
+            .total_cmp(&a.lift)
+            .then(b.recent.cmp(&a.recent))
+            .then(a.tag.cmp(&b.tag))
+    });
+
+    trending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        duration_histogram, episode_timeline, tag_counts, trending_tags, DurationBucket,
+        IndexStats, TagCount, TagOrder, TimelineBucket, TimelinePeriod,
+    };
+    use crate::{Enclosure, Episode, EpisodesById, EpisodesByTag, Tag};
+
+    fn episode(id: i64, date: &str, duration: &str) -> Episode {
+        Episode {
+            id,
+            title: format!("{}: Episode", id),
+            date: date.to_string(),
+            duration: duration.to_string(),
+            tags: Vec::new(),
+            url: format!("https://linuxunplugged.com/{}", id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_index_stats() {
+        let by_id = EpisodesById::from([
+            (1, episode(1, "March 19th, 2023", "01:00:00")),
+            (2, episode(2, "January 1st, 2022", "00:30:00")),
+            (3, episode(3, "not a date", "unknown")),
+            (
+                4,
+                Episode {
+                    audio: Some(Enclosure {
+                        url: String::from("https://aphid.fireside.fm/4.mp3"),
+                        bytes: Some(1024),
+                        mime_type: None,
+                    }),
+                    ..episode(4, "not a date", "unknown")
+                },
+            ),
+            (
+                5,
+                Episode {
+                    audio: Some(Enclosure {
+                        url: String::from("https://aphid.fireside.fm/5.mp3"),
+                        ..Default::default()
+                    }),
+                    ..episode(5, "not a date", "unknown")
+                },
+            ),
+        ]);
+
+        let by_tag = EpisodesByTag::from([
+            (String::from("nixos"), vec![1, 2]),
+            (String::from("fedora"), vec![3]),
+            (String::from("arch"), vec![1]),
+        ]);
+
+        let stats = IndexStats::new(&by_id, &by_tag, 2);
+
+        assert_eq!(stats.episodes, 5);
+        assert_eq!(stats.tags, 3);
+        assert_eq!(
+            stats.top_tags,
+            vec![
+                TagCount {
+                    tag: String::from("nixos"),
+                    episodes: 2
+                },
+                TagCount {
+                    tag: String::from("arch"),
+                    episodes: 1
+                },
+            ]
+        );
+        assert_eq!(stats.first_date.as_deref(), Some("January 1st, 2022"));
+        assert_eq!(stats.last_date.as_deref(), Some("March 19th, 2023"));
+        assert_eq!(stats.average_duration.as_deref(), Some("00:45:00"));
+        assert_eq!(stats.audio_bytes, Some(1024));
+        assert_eq!(stats.audio_episodes, 2);
+
+        let empty = IndexStats::new(&EpisodesById::new(), &EpisodesByTag::new(), 10);
+        assert!(empty.first_date.is_none());
+        assert!(empty.average_duration.is_none());
+        assert!(empty.audio_bytes.is_none());
+    }
+
+    #[test]
+    fn test_tag_counts() {
+        let by_tag = EpisodesByTag::from([
+            (String::from("nixos"), vec![1, 2]),
+            (String::from("Fedora"), vec![3]),
+            (String::from("arch"), vec![1]),
+        ]);
+
+        let names = |order| -> Vec<String> {
+            tag_counts(&by_tag, order)
+                .into_iter()
+                .map(|count| count.tag)
+                .collect()
+        };
+
+        assert_eq!(names(TagOrder::Episodes), vec!["nixos", "Fedora", "arch"]);
+        assert_eq!(names(TagOrder::Name), vec!["arch", "Fedora", "nixos"]);
+        assert_eq!(tag_counts(&by_tag, TagOrder::Episodes)[0].episodes, 2);
+    }
+
+    #[test]
+    fn test_episode_timeline() {
+        let episodes = [
+            episode(1, "November 20th, 2022", "01:00:00"),
+            episode(2, "January 1st, 2023", "01:00:00"),
+            episode(3, "January 22nd, 2023", "01:00:00"),
+            episode(4, "not a date", "01:00:00"),
+        ];
+
+        let bucket = |period: &str, episodes| TimelineBucket {
+            period: period.to_string(),
+            episodes,
+        };
+
+        let by_year = episode_timeline(&episodes, TimelinePeriod::Year);
+        assert_eq!(by_year.buckets, vec![bucket("2022", 1), bucket("2023", 2)]);
+        assert_eq!(by_year.undated, 1);
+
+        // empty months are part of the timeline
+        let by_month = episode_timeline(&episodes, TimelinePeriod::Month);
+        assert_eq!(
+            by_month.buckets,
+            vec![
+                bucket("2022-11", 1),
+                bucket("2022-12", 0),
+                bucket("2023-01", 2)
+            ]
+        );
+
+        assert!(episode_timeline(&[], TimelinePeriod::Year)
+            .buckets
+            .is_empty());
+    }
+
+    #[test]
+    fn test_duration_histogram() {
+        let episodes = [
+            episode(1, "", "00:10:00"),
+            episode(2, "", "14:59"),
+            episode(3, "", "00:45:00"),
+            episode(4, "", "1 hr"),
+        ];
+
+        let bucket = |from, to, episodes| DurationBucket { from, to, episodes };
+
+        let histogram = duration_histogram(&episodes, 15);
+        assert_eq!(
+            histogram.buckets,
+            vec![
+                bucket(0, 15, 2),
+                bucket(15, 30, 0),
+                bucket(30, 45, 0),
+                bucket(45, 60, 1)
+            ]
+        );
+        assert_eq!(histogram.unparsed.len(), 1);
+        assert_eq!(histogram.unparsed[0].duration, "1 hr");
+
+        assert!(duration_histogram(&[], 15).buckets.is_empty());
+    }
+
+    #[test]
+    fn test_trending_tags() {
+        let tagged = |id, date: &str, tags: &[&str]| Episode {
+            tags: tags.iter().map(|tag| Tag::from(*tag)).collect(),
+            ..episode(id, date, "01:00:00")
+        };
+
+        let episodes = [
+            tagged(1, "January 1st, 2022", &["linux", "gnome"]),
+            tagged(2, "February 1st, 2022", &["linux", "gnome"]),
+            tagged(3, "March 1st, 2022", &["linux", "kde"]),
+            tagged(4, "April 1st, 2022", &["linux", "nixos"]),
+            tagged(5, "May 1st, 2022", &["linux", "NixOS", "Steam Deck"]),
+            tagged(6, "June 1st, 2022", &["linux", "nixos", "kde"]),
+            tagged(7, "not a date", &["nixos", "steam deck"]),
+        ];
+
+        let trending = trending_tags(&episodes, 3, 2);
+
+        // nixos is in 3/3 recent episodes, and 0 of 3 before it (1/4 with the smoothing)
+        assert_eq!(
+            trending
+                .iter()
+                .map(|tag| (tag.tag.as_str(), tag.recent, tag.before, tag.lift))
+                .collect::<Vec<_>>(),
+            vec![("nixos", 3, 0, 4.0)]
+        );
+
+        // linux is as frequent as before, steam deck too rare
+        assert_eq!(trending_tags(&episodes, 3, 1)[1].tag, "steam deck");
+        assert!(trending_tags(&episodes, 10, 1).is_empty());
+        assert!(trending_tags(&[], 3, 1).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+/// The id of an interned string, an index into its `Symbols` table
+pub type Symbol = u32;
+
+/// Symbol table interning the tags of an index, each distinct tag is stored once
+/// and compared as an integer
+#[derive(Clone, Debug, Default)]
+pub struct Symbols {
+    ids: HashMap<String, Symbol>,
+    names: Vec<String>,
+}
+
+impl Symbols {
+    /// The symbol of `name`, added to the table the first time
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.ids.get(name) {
+            return *symbol;
+        }
+
+        let symbol = self.names.len() as Symbol;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), symbol);
+
+        symbol
+    }
+
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.ids.get(name).copied()
+    }
+
+    /// The string of a symbol of this table
+    pub fn name(&self, symbol: Symbol) -> &str {
+        &self.names[symbol as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Symbols in the order they were interned
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(symbol, name)| (symbol as Symbol, name.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Symbols;
+
+    #[test]
+    fn test_symbols() {
+        let mut symbols = Symbols::default();
+
+        let zfs = symbols.intern("zfs");
+        let btrfs = symbols.intern("btrfs");
+
+        assert_eq!(symbols.intern("zfs"), zfs);
+        assert_ne!(zfs, btrfs);
+        assert_eq!(symbols.len(), 2);
+
+        assert_eq!(symbols.get("btrfs"), Some(btrfs));
+        assert_eq!(symbols.get("arch"), None);
+        assert_eq!(symbols.name(zfs), "zfs");
+        assert_eq!(
+            symbols.iter().collect::<Vec<_>>(),
+            vec![(zfs, "zfs"), (btrfs, "btrfs")]
+        );
+    }
+}
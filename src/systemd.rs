@@ -0,0 +1,36 @@
+use listenfd::ListenFd;
+use std::net::{SocketAddr, TcpListener};
+use tracing::{info, warn};
+
+/// The socket passed by systemd socket activation (`LISTEN_FDS`),
+/// otherwise a new socket bound to `addr`
+pub fn listener(addr: SocketAddr) -> TcpListener {
+    let inherited = ListenFd::from_env()
+        .take_tcp_listener(0)
+        .unwrap_or_else(|err| {
+            warn!("Error at taking the systemd socket: {}", err);
+            None
+        });
+
+    let listener = match inherited {
+        Some(listener) => {
+            info!("Using the socket passed by systemd or the previous process (LISTEN_FDS)");
+            listener
+        }
+        None => TcpListener::bind(addr)
+            .unwrap_or_else(|err| panic!("Error at binding {}: {}", addr, err)),
+    };
+
+    listener
+        .set_nonblocking(true)
+        .expect("Error at setting the socket as non blocking");
+
+    listener
+}
+
+/// Tells systemd the server is ready (`Type=notify` units), no-op when not run by systemd
+pub fn notify_ready() {
+    if let Err(err) = sd_notify::notify(false, &[sd_notify::NotifyState::Ready]) {
+        warn!("Error at notifying systemd: {}", err);
+    }
+}
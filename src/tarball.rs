@@ -0,0 +1,164 @@
+// ustar archives, enough for the backups: regular files with short names, no directories
+
+const BLOCK: usize = 512;
+
+/// A file of a tarball
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TarEntry {
+    /// path inside the archive, e.g. "index/episodes_by_id_index.json"
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+/// The header, contents and padding of a file of a tarball, `mtime` in unix seconds,
+/// names are limited to 100 bytes
+pub fn tar_entry(name: &str, contents: &[u8], mtime: i64) -> Vec<u8> {
+    let mut header = [0u8; BLOCK];
+
+    let name = name.as_bytes();
+    let name = &name[..name.len().min(100)];
+    header[..name.len()].copy_from_slice(name);
+
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], contents.len() as u64);
+    write_octal(&mut header[136..148], mtime.max(0) as u64);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // the checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|byte| *byte as u64).sum();
+    write_octal(&mut header[148..155], checksum);
+    header[155] = b' ';
+
+    let mut entry = Vec::with_capacity(BLOCK + contents.len() + BLOCK);
+    entry.extend_from_slice(&header);
+    entry.extend_from_slice(contents);
+    entry.resize(entry.len() + padding(contents.len()), 0);
+    entry
+}
+
+/// The two empty blocks closing a tarball
+pub fn tar_end() -> Vec<u8> {
+    vec![0; 2 * BLOCK]
+}
+
+/// The regular files of a tarball, the other entries (directories, links) are skipped
+pub fn read_tarball(bytes: &[u8]) -> Result<Vec<TarEntry>, String> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+
+    while offset + BLOCK <= bytes.len() {
+        let header = &bytes[offset..offset + BLOCK];
+
+        if header.iter().all(|byte| *byte == 0) {
+            return Ok(entries);
+        }
+
+        let expected = read_octal(&header[148..156])
+            .ok_or_else(|| format!("Error at reading the header at byte {}", offset))?;
+        let checksum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| if (148..156).contains(&i) { b' ' } else { *byte } as u64)
+            .sum();
+
+        if checksum != expected {
+            return Err(format!("Error at reading the header at byte {}", offset));
+        }
+
+        let name = cstr(&header[..100]);
+        let prefix = cstr(&header[345..500]);
+        let size = read_octal(&header[124..136])
+            .ok_or_else(|| format!("Error at reading the size of {}", name))?
+            as usize;
+
+        let start = offset + BLOCK;
+        let end = start
+            .checked_add(size)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| format!("Error at reading {}: the tarball is truncated", name))?;
+
+        if matches!(header[156], b'0' | 0) {
+            entries.push(TarEntry {
+                name: if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", prefix, name)
+                },
+                contents: bytes[start..end].to_vec(),
+            });
+        }
+
+        offset = end + padding(size);
+    }
+
+    Err(String::from("Error at reading the tarball: it has no end"))
+}
+
+fn padding(size: usize) -> usize {
+    (BLOCK - size % BLOCK) % BLOCK
+}
+
+// zero padded octal digits and a NUL
+fn write_octal(field: &mut [u8], value: u64) {
+    let width = field.len() - 1;
+    let digits = format!("{:0width$o}", value, width = width);
+    field[..width].copy_from_slice(&digits.as_bytes()[digits.len() - width..]);
+    field[width] = 0;
+}
+
+fn read_octal(field: &[u8]) -> Option<u64> {
+    let digits = cstr(field);
+    let digits = digits.trim();
+
+    if digits.is_empty() {
+        return Some(0);
+    }
+
+    u64::from_str_radix(digits, 8).ok()
+}
+
+fn cstr(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_tarball, tar_end, tar_entry, TarEntry};
+
+    #[test]
+    fn test_tarball() {
+        let mut tarball = tar_entry("config.toml", b"[server]\n", 1_680_000_000);
+        tarball.extend(tar_entry("index/empty.json", b"", 0));
+        tarball.extend(tar_end());
+
+        assert_eq!(tarball.len() % 512, 0);
+        assert_eq!(
+            read_tarball(&tarball).unwrap(),
+            vec![
+                TarEntry {
+                    name: String::from("config.toml"),
+                    contents: b"[server]\n".to_vec(),
+                },
+                TarEntry {
+                    name: String::from("index/empty.json"),
+                    contents: Vec::new(),
+                },
+            ]
+        );
+
+        let mut corrupted = tarball.clone();
+        corrupted[0] = b'x';
+        assert!(read_tarball(&corrupted).is_err());
+
+        assert!(read_tarball(&tarball[..700]).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::percentile;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// latencies kept per report, later searches overwrite the oldest ones
+const TELEMETRY_MAX_LATENCIES: usize = 10_000;
+
+/// Aggregate counters of the searches since the last report, never the queries themselves
+#[derive(Debug, Default)]
+pub struct Telemetry {
+    window: Mutex<TelemetryWindow>,
+}
+
+#[derive(Debug, Default)]
+struct TelemetryWindow {
+    searches: u64,
+    zero_results: u64,
+    latencies: Vec<Duration>,
+}
+
+/// What is sent to telemetry.endpoint, one per interval
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TelemetryReport {
+    /// version of the engine
+    pub version: String,
+    pub period_secs: u64,
+    pub searches: u64,
+    pub zero_result_rate: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl Telemetry {
+    pub fn record(&self, took: Duration, results: usize) {
+        let mut window = self.window.lock().unwrap();
+
+        if window.latencies.len() < TELEMETRY_MAX_LATENCIES {
+            window.latencies.push(took);
+        } else {
+            let oldest = window.searches as usize % TELEMETRY_MAX_LATENCIES;
+            window.latencies[oldest] = took;
+        }
+
+        window.searches += 1;
+
+        if results == 0 {
+            window.zero_results += 1;
+        }
+    }
+
+    /// The report of the searches since the last one, starting a new period
+    pub fn take_report(&self, period: Duration) -> TelemetryReport {
+        let mut window = std::mem::take(&mut *self.window.lock().unwrap());
+        window.latencies.sort();
+
+        // microsecond precision is plenty
+        let ms = |duration: Duration| (duration.as_secs_f64() * 1_000_000.0).round() / 1000.0;
+
+        TelemetryReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            period_secs: period.as_secs(),
+            searches: window.searches,
+            zero_result_rate: if window.searches > 0 {
+                window.zero_results as f64 / window.searches as f64
+            } else {
+                0.0
+            },
+            p50_ms: ms(percentile(&window.latencies, 50.0)),
+            p90_ms: ms(percentile(&window.latencies, 90.0)),
+            p99_ms: ms(percentile(&window.latencies, 99.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Telemetry;
+    use std::time::Duration;
+
+    #[test]
+    fn test_take_report() {
+        let telemetry = Telemetry::default();
+
+        for millis in 1..=100 {
+            telemetry.record(Duration::from_millis(millis), (millis % 4) as usize);
+        }
+
+        let report = telemetry.take_report(Duration::from_secs(3600));
+
+        assert_eq!(report.period_secs, 3600);
+        assert_eq!(report.searches, 100);
+        assert_eq!(report.zero_result_rate, 0.25);
+        assert_eq!(report.p50_ms, 50.0);
+        assert_eq!(report.p90_ms, 90.0);
+        assert_eq!(report.p99_ms, 99.0);
+
+        // a new period starts empty
+        let report = telemetry.take_report(Duration::from_secs(3600));
+
+        assert_eq!(report.searches, 0);
+        assert_eq!(report.zero_result_rate, 0.0);
+        assert_eq!(report.p99_ms, 0.0);
+    }
+}
@@ -0,0 +1,88 @@
+use axum::extract::State;
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::cell::Cell;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::AppState;
+
+// the rest of the time is left for rendering the results
+const SEARCH_BUDGET_SHARE: f64 = 0.75;
+
+/// Deadline of the searches of a request, past it the search returns the episodes
+/// it found so far instead of running out the timeout of the route
+struct SearchBudget {
+    deadline: Instant,
+    exceeded: Cell<bool>,
+}
+
+tokio::task_local! {
+    static SEARCH_BUDGET: SearchBudget;
+}
+
+/// Whether the search of the current request ran out of time, searches outside of
+/// a request (websockets, event streams) have no budget
+pub fn search_budget_exceeded() -> bool {
+    SEARCH_BUDGET
+        .try_with(|budget| {
+            if !budget.exceeded.get() && Instant::now() >= budget.deadline {
+                budget.exceeded.set(true);
+            }
+
+            budget.exceeded.get()
+        })
+        .unwrap_or(false)
+}
+
+/// Whether a search of the current request returned partial results
+pub fn search_was_cut_short() -> bool {
+    SEARCH_BUDGET
+        .try_with(|budget| budget.exceeded.get())
+        .unwrap_or(false)
+}
+
+// suggestions are typed as you go, exports stream every matching episode
+fn route_timeout(state: &AppState, path: &str) -> Duration {
+    let timeouts = &state.config().timeouts;
+
+    let millis = match path {
+        "/suggest" => timeouts.suggest_ms,
+        "/search/export" => timeouts.export_ms,
+        _ => timeouts.default_ms,
+    };
+
+    Duration::from_millis(millis)
+}
+
+/// Answers the requests still running past the timeout of their route with a 503,
+/// the searches they run get a share of it as their budget
+pub async fn timeout_requests<B>(
+    State(state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let timeout = route_timeout(&state, &path);
+
+    let budget = SearchBudget {
+        deadline: Instant::now() + timeout.mul_f64(SEARCH_BUDGET_SHARE),
+        exceeded: Cell::new(false),
+    };
+
+    match tokio::time::timeout(timeout, SEARCH_BUDGET.scope(budget, next.run(request))).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("Timed out {} after {:?}", path, timeout);
+
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, "1")],
+                "The request took too long, retry in a moment",
+            )
+                .into_response()
+        }
+    }
+}
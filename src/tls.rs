@@ -0,0 +1,52 @@
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub async fn load_tls_config(cert: &Path, key: &Path) -> RustlsConfig {
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .unwrap_or_else(|err| {
+            panic!(
+                "Error at loading tls certificate {} and key {}: {}",
+                cert.display(),
+                key.display(),
+                err
+            )
+        })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Reloads the certificate and key when they change on disk (e.g. renewed by certbot),
+/// a failed reload keeps serving the previous certificate
+pub async fn reload_certificates(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    let mut interval = tokio::time::interval(RELOAD_CHECK_INTERVAL);
+    let mut last_modified = (modified(&cert), modified(&key));
+
+    loop {
+        interval.tick().await;
+
+        let current = (modified(&cert), modified(&key));
+
+        if current == last_modified {
+            continue;
+        }
+
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => {
+                info!(cert = %cert.display(), "Reloaded tls certificate");
+                last_modified = current;
+            }
+            Err(err) => {
+                warn!(cert = %cert.display(), "Error at reloading tls certificate: {}", err)
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::normalize_text;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A stretch of a transcript and when it's said
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TranscriptSegment {
+    pub start_secs: u64,
+    pub text: String,
+}
+
+/// The transcripts of the episodes by id, kept apart from the episodes
+/// so the results and the apis don't carry them around
+pub type Transcripts = HashMap<usize, Vec<TranscriptSegment>>;
+
+/// A sentence of a transcript matching the query, the results link to `start_secs`
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptHit {
+    pub start_secs: u64,
+    pub text: String,
+}
+
+/// The lowercase text of a transcript the search matches the terms against
+pub fn transcript_text(segments: &[TranscriptSegment]) -> String {
+    segments
+        .iter()
+        .map(|segment| normalize_text(&segment.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The first `limit` sentences containing any of the lowercase terms, in the order they're said
+pub fn transcript_hits(
+    segments: &[TranscriptSegment],
+    terms: &[String],
+    limit: usize,
+) -> Vec<TranscriptHit> {
+    let mut hits = Vec::new();
+
+    for segment in segments {
+        for sentence in sentences(&segment.text) {
+            if hits.len() >= limit {
+                return hits;
+            }
+
+            let lowercase = sentence.to_lowercase();
+
+            if terms.iter().any(|term| lowercase.contains(term.as_str())) {
+                hits.push(TranscriptHit {
+                    start_secs: segment.start_secs,
+                    text: sentence.to_string(),
+                });
+            }
+        }
+    }
+
+    hits
+}
+
+// "Arch broke. Again? Yes" -> "Arch broke.", "Again?", "Yes"
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    text.split_inclusive(['.', '?', '!'])
+        .map(str::trim)
+        .filter(|sentence| !sentence.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{transcript_hits, transcript_text, TranscriptHit, TranscriptSegment};
+
+    #[test]
+    fn test_transcript_hits() {
+        let segments = vec![
+            TranscriptSegment {
+                start_secs: 0,
+                text: String::from("Welcome to the show. We tried NixOS this week!"),
+            },
+            TranscriptSegment {
+                start_secs: 754,
+                text: String::from("Then nixos broke? It did not."),
+            },
+        ];
+
+        assert_eq!(
+            transcript_text(&segments),
+            "welcome to the show. we tried nixos this week!\nthen nixos broke? it did not."
+        );
+
+        let terms = vec![String::from("nixos")];
+
+        assert_eq!(
+            transcript_hits(&segments, &terms, 5),
+            vec![
+                TranscriptHit {
+                    start_secs: 0,
+                    text: String::from("We tried NixOS this week!"),
+                },
+                TranscriptHit {
+                    start_secs: 754,
+                    text: String::from("Then nixos broke?"),
+                },
+            ]
+        );
+        assert_eq!(transcript_hits(&segments, &terms, 1).len(), 1);
+        assert!(transcript_hits(&segments, &[String::from("arch")], 5).is_empty());
+    }
+}
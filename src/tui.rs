@@ -0,0 +1,187 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use unplugged_engine::{
+    parse_query_strict, search_index, Episode, QueryError, ScoringConfig, ShowConfig, ShowIndex,
+};
+
+// rows moved by page up and page down
+const PAGE_ROWS: usize = 10;
+
+/// The indices searched by the tui, the shows of the archive or the one picked with --show
+pub struct TuiShows {
+    pub shows: Vec<(ShowConfig, ShowIndex)>,
+    pub scoring: ScoringConfig,
+    /// the words of the queries the search ignores
+    pub stopwords: HashSet<String>,
+}
+
+struct App {
+    shows: TuiShows,
+    common_words: HashSet<String>,
+    query: String,
+    /// slug of the show and the episode, best score first within each show
+    results: Vec<(String, Episode)>,
+    list: ListState,
+    /// what's wrong with the query or what happened on enter
+    status: String,
+}
+
+impl App {
+    fn search(&mut self) {
+        self.results.clear();
+        self.status.clear();
+
+        match parse_query_strict(&self.query) {
+            Ok(_) => {}
+            Err(QueryError::Empty) => {}
+            Err(err) => self.status = err.to_string(),
+        }
+
+        for (show, index) in self.shows.shows.iter() {
+            let results = search_index(
+                index,
+                &self.query,
+                &self.common_words,
+                &self.shows.scoring,
+                || false,
+            );
+
+            self.results.extend(
+                results
+                    .episodes
+                    .into_iter()
+                    .map(|(_, episode)| (show.slug.clone(), episode.clone())),
+            );
+        }
+
+        self.list.select((!self.results.is_empty()).then_some(0));
+    }
+
+    fn move_selection(&mut self, rows: isize) {
+        let Some(selected) = self.list.selected() else {
+            return;
+        };
+
+        let last = self.results.len().saturating_sub(1) as isize;
+        self.list
+            .select(Some((selected as isize + rows).clamp(0, last) as usize));
+    }
+
+    fn open_selected(&mut self) {
+        let Some((_, episode)) = self.list.selected().and_then(|i| self.results.get(i)) else {
+            return;
+        };
+
+        self.status = match open::that_detached(&episode.url) {
+            Ok(()) => format!("Opened {}", episode.url),
+            Err(err) => format!("Error at opening {}: {}", episode.url, err),
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [input, results, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(
+            Paragraph::new(self.query.as_str()).block(Block::bordered().title(" Search ")),
+            input,
+        );
+        // after the last character, inside the border
+        frame.set_cursor_position((input.x + 1 + self.query.chars().count() as u16, input.y + 1));
+
+        let several_shows = self.shows.shows.len() > 1;
+        let items: Vec<_> = self
+            .results
+            .iter()
+            .map(|(show, episode)| {
+                let show = if several_shows {
+                    format!("{:<6} ", show)
+                } else {
+                    String::new()
+                };
+
+                ListItem::new(format!(
+                    "{}{:<20}  {}  {}",
+                    show,
+                    episode.date,
+                    episode.title,
+                    episode.tag_names().join(", ")
+                ))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(" {} results ", self.results.len())))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, results, &mut self.list);
+
+        let help = "↑↓ select · Enter open · Esc quit";
+        let footer_text = if self.status.is_empty() {
+            help.to_string()
+        } else {
+            format!("{} · {}", self.status, help)
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Up => self.move_selection(-1),
+                KeyCode::Down => self.move_selection(1),
+                KeyCode::PageUp => self.move_selection(-(PAGE_ROWS as isize)),
+                KeyCode::PageDown => self.move_selection(PAGE_ROWS as isize),
+                KeyCode::Enter => self.open_selected(),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.search();
+                }
+                KeyCode::Char(ch) => {
+                    self.query.push(ch);
+                    self.search();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Searches as you type in the terminal, enter opens the selected episode in the browser
+pub fn run_tui(shows: TuiShows) -> std::io::Result<()> {
+    let mut app = App {
+        common_words: shows.stopwords.clone(),
+        shows,
+        query: String::new(),
+        results: Vec::new(),
+        list: ListState::default(),
+        status: String::new(),
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+
+    result
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+use unplugged_engine::{Telemetry, TelemetryConfig};
+
+// a slow endpoint mustn't pile up reports
+const TELEMETRY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts a report to telemetry.endpoint every telemetry.interval_minutes,
+/// the periods without searches aren't reported
+pub async fn send_telemetry(telemetry: Arc<Telemetry>, config: TelemetryConfig) {
+    let period = Duration::from_secs(config.interval_minutes * 60);
+    let client = reqwest::Client::new();
+
+    info!(
+        "Reporting aggregate search counters to {} every {} minutes",
+        config.endpoint, config.interval_minutes
+    );
+
+    let mut interval = tokio::time::interval(period);
+    // the first tick is immediate, there's nothing to report yet
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let report = telemetry.take_report(period);
+
+        if report.searches == 0 {
+            continue;
+        }
+
+        let body = serde_json::to_string(&report).expect("Error at serializing the report");
+
+        let sent = client
+            .post(&config.endpoint)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .timeout(TELEMETRY_TIMEOUT)
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        if let Err(err) = sent {
+            error!("Error at sending the telemetry report: {}", err);
+        }
+    }
+}